@@ -24,8 +24,11 @@ typedef unsigned __int128 uint128_t;
 #define GW_MAX_L2TX_ARGS_SIZE (128 * 1024)
 /* 128KB + 4KB */
 #define GW_MAX_L2TX_SIZE (132 * 1024)
-/* MAX kv state pairs in a tx */
+/* MAX kv state pairs in a tx, override it by -DGW_MAX_KV_PAIRS=<n>,
+ * keep in sync with gw_state::constants::GW_MAX_KV_PAIRS */
+#ifndef GW_MAX_KV_PAIRS
 #define GW_MAX_KV_PAIRS 1024
+#endif
 #define GW_MAX_SCRIPT_SIZE 256
 /* MAX scripts in a tx */
 #define GW_MAX_SCRIPT_ENTRIES_SIZE 100
@@ -5,48 +5,76 @@
    The exit code of CKB-VM is -128 ~ 127
    The 1 ~ 127 is used by Godwoken runtime(validator_utils.h &
    generator_utils.h). (To avoid conflict with backend we actually using 50 ~
-   127) The Backend VM layer such as Polyjuice uses -1 ~ -128
+   111) The Backend VM layer such as Polyjuice uses -1 ~ -128
 
    In Godwoken runtime, we seperate errors into Fatal & Errors,
    Fatals represents errors that shouldn't be recovered by user programs,
     typically caused by lack of validation context.
    Errors represents the syscall errors caused by the user input.
+
+   Generated from `errors.toml` by `tools/gen-errors.py`, don't edit.
  */
 
 /* Data Fatals 5x */
+/* the buffer is too small for the data */
 #define GW_FATAL_BUFFER_OVERFLOW 50
+/* the validation context is invalid */
 #define GW_FATAL_INVALID_CONTEXT 51
+/* the data is not a valid molecule structure */
 #define GW_FATAL_INVALID_DATA 52
+/* the return data doesn't match the block */
 #define GW_FATAL_MISMATCH_RETURN_DATA 53
+/* the args of the layer2 transaction are unknown */
 #define GW_FATAL_UNKNOWN_ARGS 54
+/* the sUDT script is invalid */
 #define GW_FATAL_INVALID_SUDT_SCRIPT 55
+/* the state checkpoint doesn't match the state */
 #define GW_FATAL_INVALID_CHECK_POINT 56
 
 /* Notfound Fatals 6x */
+/* the data cell is missing */
 #define GW_FATAL_DATA_CELL_NOT_FOUND 60
+/* the state key is missing from the kv state */
 #define GW_FATAL_STATE_KEY_NOT_FOUND 61
+/* the signature cell is missing */
 #define GW_FATAL_SIGNATURE_CELL_NOT_FOUND 62
+/* the account script is missing */
 #define GW_FATAL_SCRIPT_NOT_FOUND 63
 
 /* Merkle Fatals 7x */
+/* failed to verify the kv state proof */
 #define GW_FATAL_SMT_VERIFY 70
+/* failed to fetch a key from the kv state */
 #define GW_FATAL_SMT_FETCH 71
+/* failed to store a key into the kv state */
 #define GW_FATAL_SMT_STORE 72
+/* failed to calculate the root of the kv state */
 #define GW_FATAL_SMT_CALCULATE_ROOT 73
 
 /* Syscall Errors */
+/* the account script hash already exists */
 #define GW_ERROR_DUPLICATED_SCRIPT_HASH 80
+/* the code hash of the account script is unknown */
 #define GW_ERROR_UNKNOWN_SCRIPT_CODE_HASH 81
+/* the account script is invalid */
 #define GW_ERROR_INVALID_ACCOUNT_SCRIPT 82
+/* the requested data is missing */
 #define GW_ERROR_NOT_FOUND 83
+/* failed to recover the account script hash from the signature */
 #define GW_ERROR_RECOVER 84
+/* the account doesn't exist */
 #define GW_ERROR_ACCOUNT_NOT_EXISTS 85
 
 /* sUDT errors */
+/* the sender's balance is insufficient */
 #define GW_SUDT_ERROR_INSUFFICIENT_BALANCE 92
+/* the receiver's balance overflows */
 #define GW_SUDT_ERROR_AMOUNT_OVERFLOW 93
+/* the receiver address is invalid */
 #define GW_SUDT_ERROR_TO_ADDR 94
+/* the sUDT account doesn't exist */
 #define GW_SUDT_ERROR_ACCOUNT_NOT_EXISTS 95
+/* the length of the short address is invalid */
 #define GW_SUDT_ERROR_SHORT_ADDR_LEN 96
 
 #endif
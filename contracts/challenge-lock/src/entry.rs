@@ -1,20 +1,18 @@
 // Import from `core` instead of from `std` since we are in no-std mode
 use core::{convert::TryInto, result::Result};
 
-use gw_utils::cells::rollup::MAX_ROLLUP_WITNESS_SIZE;
 use gw_utils::gw_types;
-use gw_utils::gw_types::packed::RollupActionUnionReader;
 use gw_utils::{
-    cells::rollup::{
-        load_rollup_config, parse_rollup_action, search_rollup_cell, search_rollup_state,
-    },
+    cells::rollup::{load_rollup_config, search_rollup_cell, search_rollup_state},
     ckb_std::{
         ckb_constants::Source,
         ckb_types::{bytes::Bytes, prelude::Unpack as CKBUnpack},
         debug,
-        high_level::load_script,
     },
     error::Error,
+    syscalls::Syscalls,
+    trace,
+    witness::{RollupActionType, RollupWitness},
 };
 
 use gw_types::{
@@ -24,8 +22,8 @@ use gw_types::{
 };
 
 /// args: rollup_type_hash | start challenge
-fn parse_lock_args() -> Result<([u8; 32], ChallengeLockArgs), Error> {
-    let script = load_script()?;
+fn parse_lock_args<S: Syscalls>(syscalls: &S) -> Result<([u8; 32], ChallengeLockArgs), Error> {
+    let script = syscalls.load_script()?;
     let args: Bytes = script.args().unpack();
 
     let mut rollup_type_hash: [u8; 32] = [0u8; 32];
@@ -52,32 +50,31 @@ fn parse_lock_args() -> Result<([u8; 32], ChallengeLockArgs), Error> {
 ///   * during the rollup halting, anyone can submit context to run verification on-chain and cancel this challenge
 ///   * the cancel-challenge tx must contains a verifier cell in the inputs which cell's lock script equals to the account.script
 ///   * the lock script of verifier cell reads the context from tx.witnesses and run verification
-pub fn main() -> Result<(), Error> {
-    let (rollup_script_hash, lock_args) = parse_lock_args()?;
+pub fn main<S: Syscalls>(syscalls: &S) -> Result<(), Error> {
+    let (rollup_script_hash, lock_args) = parse_lock_args(syscalls)?;
 
     // check rollup cell
-    let mut rollup_action_witness = [0u8; MAX_ROLLUP_WITNESS_SIZE];
-    let index =
-        search_rollup_cell(&rollup_script_hash, Source::Output).ok_or(Error::RollupCellNotFound)?;
-    let action = parse_rollup_action(&mut rollup_action_witness, index, Source::Output)?;
-    match action.to_enum() {
-        RollupActionUnionReader::RollupEnterChallenge(_)
-        | RollupActionUnionReader::RollupRevert(_) => {
+    let index = search_rollup_cell(syscalls, &rollup_script_hash, Source::Output)
+        .ok_or(Error::RollupCellNotFound)?;
+    let action_type = RollupWitness::load(syscalls, index, Source::Output)?.action_type();
+    match action_type {
+        RollupActionType::EnterChallenge | RollupActionType::Revert => {
             // state-validator will do the verification
+            trace!("challenge_lock.unlock_by_rollup");
             return Ok(());
         }
-        RollupActionUnionReader::RollupCancelChallenge(_) => {}
+        RollupActionType::CancelChallenge => {}
         _ => {
-            debug!("unsupport action {:?}", action.to_enum());
+            debug!("unsupport action {:?}", action_type);
             return Err(Error::InvalidArgs);
         }
     }
 
     // load rollup config
     let rollup_config = {
-        let prev_global_state = search_rollup_state(&rollup_script_hash, Source::Input)?
+        let prev_global_state = search_rollup_state(syscalls, &rollup_script_hash, Source::Input)?
             .ok_or(Error::RollupCellNotFound)?;
-        load_rollup_config(&prev_global_state.rollup_config_hash().unpack())?
+        load_rollup_config(syscalls, &prev_global_state.rollup_config_hash().unpack())?
     };
 
     // unlock via cancel challenge
@@ -90,11 +87,18 @@ pub fn main() -> Result<(), Error> {
     match target_type {
         ChallengeTargetType::TxExecution => {
             debug!("[challenge-lock] target: tx execution");
-            crate::verifications::tx_execution::verify_tx_execution(&rollup_config, &lock_args)?;
+            trace!("challenge_lock.cancel_tx_execution");
+            crate::verifications::tx_execution::verify_tx_execution(
+                syscalls,
+                &rollup_config,
+                &lock_args,
+            )?;
         }
         ChallengeTargetType::TxSignature => {
             debug!("[challenge-lock] target: tx signature");
+            trace!("challenge_lock.cancel_tx_signature");
             crate::verifications::tx_signature::verify_tx_signature(
+                syscalls,
                 &rollup_script_hash,
                 &rollup_config,
                 &lock_args,
@@ -102,7 +106,12 @@ pub fn main() -> Result<(), Error> {
         }
         ChallengeTargetType::Withdrawal => {
             debug!("[challenge-lock] target: withdrawal");
-            crate::verifications::withdrawal::verify_withdrawal(&rollup_script_hash, &lock_args)?;
+            trace!("challenge_lock.cancel_withdrawal");
+            crate::verifications::withdrawal::verify_withdrawal(
+                syscalls,
+                &rollup_script_hash,
+                &lock_args,
+            )?;
         }
     }
 
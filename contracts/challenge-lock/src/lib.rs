@@ -0,0 +1,13 @@
+//! Challenge-lock
+//!
+//! `main.rs` runs the lock on ckb-vm, the entry loads the transaction
+//! through `gw_utils::syscalls::Syscalls`.
+
+#![no_std]
+
+extern crate alloc;
+
+pub mod entry;
+pub mod verifications;
+
+pub use gw_utils::ckb_std;
@@ -7,12 +7,10 @@
 #![feature(alloc_error_handler)]
 #![feature(panic_info_message)]
 
-// define modules
-mod entry;
-mod verifications;
+use challenge_lock::entry;
+use gw_utils::{ckb_std, syscalls::CKBSyscalls};
 
 use ckb_std::default_alloc;
-pub use gw_utils::ckb_std;
 
 ckb_std::entry!(program_entry);
 default_alloc!();
@@ -20,7 +18,7 @@ default_alloc!();
 /// program entry
 fn program_entry() -> i8 {
     // Call main function and return error code
-    match entry::main() {
+    match entry::main(&CKBSyscalls) {
         Ok(_) => 0,
         Err(err) => err as i8,
     }
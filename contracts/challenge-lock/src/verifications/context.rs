@@ -2,6 +2,7 @@ use core::result::Result;
 use gw_common::{h256_ext::H256Ext, merkle_utils::calculate_state_checkpoint, state::State, H256};
 use gw_state::{
     ckb_smt::smt::{Pair, Tree},
+    error::map_smt_error,
     kv_state::KVState,
 };
 use gw_types::{
@@ -128,13 +129,13 @@ pub fn verify_tx_context(input: TxContextInput) -> Result<TxContext, Error> {
         let mut tree = Tree::new(&mut buf);
         tree.update(&H256::from_u32(tx_index).into(), &tx_witness_hash.into())
             .map_err(|err| {
-                debug!("[verify tx exist] update kv error: {}", err);
-                Error::MerkleProof
+                debug!("[verify tx exist] update kv error: {:?}", err);
+                map_smt_error(err)
             })?;
         tree.verify(&tx_witness_root, &tx_proof.raw_data())
             .map_err(|err| {
-                debug!("[verify tx exist] merkle verify error: {}", err);
-                Error::MerkleProof
+                debug!("[verify tx exist] merkle verify error: {:?}", err);
+                map_smt_error(err)
             })?;
     }
 
@@ -1,6 +1,6 @@
 use crate::verifications::context::{verify_tx_context, TxContext, TxContextInput};
 use core::result::Result;
-use gw_state::{ckb_smt::smt::Pair, constants::GW_MAX_KV_PAIRS, kv_state::KVState};
+use gw_state::{constants::GW_MAX_KV_PAIRS, kv_state::KVState};
 use gw_types::{
     packed::{
         ChallengeLockArgs, RollupConfig, VerifyTransactionWitness, VerifyTransactionWitnessReader,
@@ -14,17 +14,19 @@ use gw_utils::{
         ckb_constants::Source,
         ckb_types::{bytes::Bytes, prelude::Unpack as CKBUnpack},
         debug,
-        high_level::load_witness_args,
     },
     error::Error,
+    syscalls::Syscalls,
 };
 
 /// Verify tx execution
-pub fn verify_tx_execution(
+pub fn verify_tx_execution<S: Syscalls>(
+    syscalls: &S,
     rollup_config: &RollupConfig,
     lock_args: &ChallengeLockArgs,
 ) -> Result<(), Error> {
-    let witness_args: Bytes = load_witness_args(0, Source::GroupInput)?
+    let witness_args: Bytes = syscalls
+        .load_witness_args(0, Source::GroupInput)?
         .lock()
         .to_opt()
         .ok_or(Error::InvalidArgs)?
@@ -35,10 +37,10 @@ pub fn verify_tx_execution(
     };
     let ctx = unlock_args.context();
     let tx = unlock_args.l2tx();
-    let mut tree_buffer = [Pair::default(); GW_MAX_KV_PAIRS];
     let kv_state_proof: Bytes = unlock_args.kv_state_proof().unpack();
-    let kv_state = KVState::build(
-        &mut tree_buffer,
+    // the C backends load at most GW_MAX_KV_PAIRS pairs of a tx
+    let kv_state = KVState::build_with_capacity(
+        GW_MAX_KV_PAIRS,
         ctx.kv_state().as_reader(),
         &kv_state_proof,
         ctx.account_count().unpack(),
@@ -66,7 +68,7 @@ pub fn verify_tx_execution(
 
     // verify backend script is in the input
     // the backend will do the post state verification
-    if search_lock_hash(&receiver_script_hash.into(), Source::Input).is_none() {
+    if search_lock_hash(syscalls, &receiver_script_hash.into(), Source::Input).is_none() {
         debug!(
             "verify tx execution, can't find receiver_script_hash from the input: {:?}",
             &receiver_script_hash
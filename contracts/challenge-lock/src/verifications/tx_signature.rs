@@ -1,6 +1,6 @@
 use crate::verifications::context::{verify_tx_context, TxContext, TxContextInput};
 use core::result::Result;
-use gw_state::{ckb_smt::smt::Pair, constants::GW_MAX_KV_PAIRS, kv_state::KVState};
+use gw_state::{constants::GW_MAX_KV_PAIRS, kv_state::KVState};
 use gw_types::{
     packed::{
         ChallengeLockArgs, RollupConfig, VerifyTransactionSignatureWitness,
@@ -12,10 +12,10 @@ use gw_utils::{
     ckb_std::{
         ckb_constants::Source,
         ckb_types::{bytes::Bytes, prelude::Unpack as CKBUnpack},
-        high_level::load_witness_args,
     },
     error::Error,
     signature::check_l2_account_signature_cell,
+    syscalls::Syscalls,
 };
 use gw_utils::{
     gw_common::{blake2b::new_blake2b, H256},
@@ -45,12 +45,14 @@ fn calc_tx_message(
 }
 
 /// Verify tx signature
-pub fn verify_tx_signature(
+pub fn verify_tx_signature<S: Syscalls>(
+    syscalls: &S,
     rollup_script_hash: &[u8; 32],
     rollup_config: &RollupConfig,
     lock_args: &ChallengeLockArgs,
 ) -> Result<(), Error> {
-    let witness_args: Bytes = load_witness_args(0, Source::GroupInput)?
+    let witness_args: Bytes = syscalls
+        .load_witness_args(0, Source::GroupInput)?
         .lock()
         .to_opt()
         .ok_or(Error::InvalidArgs)?
@@ -62,10 +64,10 @@ pub fn verify_tx_signature(
     let ctx = unlock_args.context();
     let tx = unlock_args.l2tx();
     let account_count: u32 = ctx.account_count().unpack();
-    let mut tree_buffer = [Pair::default(); GW_MAX_KV_PAIRS];
     let kv_state_proof: Bytes = unlock_args.kv_state_proof().unpack();
-    let kv_state = KVState::build(
-        &mut tree_buffer,
+    // the C backends load at most GW_MAX_KV_PAIRS pairs of a tx
+    let kv_state = KVState::build_with_capacity(
+        GW_MAX_KV_PAIRS,
         ctx.kv_state().as_reader(),
         &kv_state_proof,
         account_count,
@@ -100,6 +102,6 @@ pub fn verify_tx_signature(
     );
 
     // verify sender's script is in the input
-    check_l2_account_signature_cell(&sender_script_hash, message)?;
+    check_l2_account_signature_cell(syscalls, &sender_script_hash, message)?;
     Ok(())
 }
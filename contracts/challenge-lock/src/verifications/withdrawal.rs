@@ -1,6 +1,9 @@
 use core::result::Result;
 use gw_common::{blake2b::new_blake2b, h256_ext::H256Ext, H256};
-use gw_state::ckb_smt::smt::{Pair, Tree};
+use gw_state::{
+    ckb_smt::smt::{Pair, Tree},
+    error::map_smt_error,
+};
 use gw_types::{
     packed::{
         ChallengeLockArgs, RawWithdrawalRequest, VerifyWithdrawalWitness,
@@ -15,10 +18,10 @@ use gw_utils::{
         ckb_constants::Source,
         ckb_types::{bytes::Bytes, prelude::Unpack as CKBUnpack},
         debug,
-        high_level::load_witness_args,
     },
     error::Error,
     signature::check_l2_account_signature_cell,
+    syscalls::Syscalls,
 };
 
 struct WithdrawalContext {
@@ -26,8 +29,12 @@ struct WithdrawalContext {
     sender_script_hash: H256,
 }
 
-fn verify_withdrawal_proof(lock_args: &ChallengeLockArgs) -> Result<WithdrawalContext, Error> {
-    let witness_args: Bytes = load_witness_args(0, Source::GroupInput)?
+fn verify_withdrawal_proof<S: Syscalls>(
+    syscalls: &S,
+    lock_args: &ChallengeLockArgs,
+) -> Result<WithdrawalContext, Error> {
+    let witness_args: Bytes = syscalls
+        .load_witness_args(0, Source::GroupInput)?
         .lock()
         .to_opt()
         .ok_or(Error::InvalidArgs)?
@@ -67,16 +74,16 @@ fn verify_withdrawal_proof(lock_args: &ChallengeLockArgs) -> Result<WithdrawalCo
             &withdrawal_witness_hash,
         )
         .map_err(|err| {
-            debug!("[verify withdrawal exist] update kv error: {}", err);
-            Error::MerkleProof
+            debug!("[verify withdrawal exist] update kv error: {:?}", err);
+            map_smt_error(err)
         })?;
         tree.verify(
             &withdrawal_witness_root,
             &unlock_args.withdrawal_proof().raw_data(),
         )
         .map_err(|err| {
-            debug!("[verify withdrawal exist] merkle verify error: {}", err);
-            Error::MerkleProof
+            debug!("[verify withdrawal exist] merkle verify error: {:?}", err);
+            map_smt_error(err)
         })?;
     }
 
@@ -101,18 +108,19 @@ fn calc_withdrawal_message(
 }
 
 /// Verify withdrawal signature
-pub fn verify_withdrawal(
+pub fn verify_withdrawal<S: Syscalls>(
+    syscalls: &S,
     rollup_script_hash: &[u8; 32],
     lock_args: &ChallengeLockArgs,
 ) -> Result<(), Error> {
     let WithdrawalContext {
         raw_withdrawal,
         sender_script_hash,
-    } = verify_withdrawal_proof(lock_args)?;
+    } = verify_withdrawal_proof(syscalls, lock_args)?;
 
     // verify withdrawal signature
     let message = calc_withdrawal_message(rollup_script_hash, &raw_withdrawal);
     // verify sender's script is in the input
-    check_l2_account_signature_cell(&sender_script_hash, message.into())?;
+    check_l2_account_signature_cell(syscalls, &sender_script_hash, message.into())?;
     Ok(())
 }
@@ -2,6 +2,12 @@ use std::env;
 use std::path::Path;
 
 fn main() {
+    // the C implementation isn't needed by the pure-Rust tree
+    if env::var("CARGO_FEATURE_PURE_RUST").is_ok()
+        && env::var("CARGO_FEATURE_DIFFERENTIAL").is_err()
+    {
+        return;
+    }
     let dir = env::var("CARGO_MANIFEST_DIR").unwrap();
     let root_dir = Path::new(&dir).parent().unwrap().parent().unwrap();
     env::set_current_dir(root_dir).unwrap();
@@ -0,0 +1,23 @@
+use alloc::vec::Vec;
+
+/// Pairs buffer of a tree, either caller-sized or allocated on the heap
+pub(crate) enum Buffer<'a, T> {
+    Borrowed(&'a mut [T]),
+    Owned(Vec<T>),
+}
+
+impl<'a, T> Buffer<'a, T> {
+    pub(crate) fn pairs(&self) -> &[T] {
+        match self {
+            Buffer::Borrowed(buf) => buf,
+            Buffer::Owned(buf) => buf,
+        }
+    }
+
+    pub(crate) fn pairs_mut(&mut self) -> &mut [T] {
+        match self {
+            Buffer::Borrowed(buf) => buf,
+            Buffer::Owned(buf) => buf,
+        }
+    }
+}
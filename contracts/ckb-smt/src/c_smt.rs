@@ -0,0 +1,139 @@
+//! Sparse merkle tree backed by the C implementation `ckb_smt.h`
+
+use crate::bindings::{
+    smt_calculate_root, smt_pair_t, smt_state_fetch, smt_state_init, smt_state_insert,
+    smt_state_normalize, smt_state_t, SMTErrorCode, SMTErrorCode_ERROR_INSUFFICIENT_CAPACITY,
+    SMTErrorCode_ERROR_NOT_FOUND,
+};
+use crate::buffer::Buffer;
+use alloc::vec;
+
+pub use crate::error::SMTError;
+pub type Pair = smt_pair_t;
+
+fn to_error(code: i32) -> SMTError {
+    match code as SMTErrorCode {
+        SMTErrorCode_ERROR_INSUFFICIENT_CAPACITY => SMTError::BufferFull,
+        SMTErrorCode_ERROR_NOT_FOUND => SMTError::KeyMissing,
+        _ => SMTError::ProofMalformed,
+    }
+}
+
+pub struct Tree<'a> {
+    /// the C state keeps a pointer to the buffer
+    buf: Buffer<'a, Pair>,
+    state: smt_state_t,
+}
+
+impl<'a> Tree<'a> {
+    /// Create a tree on a caller-sized buffer
+    pub fn new(buf: &'a mut [Pair]) -> Tree<'a> {
+        let state = Self::init_state(buf);
+        Self {
+            buf: Buffer::Borrowed(buf),
+            state,
+        }
+    }
+
+    /// Create a tree on a heap buffer which can hold `capacity` pairs
+    pub fn with_capacity(capacity: usize) -> Tree<'a> {
+        // moving a Vec doesn't move its heap buffer, so the pointer in the state stays valid
+        let mut buf = vec![Pair::default(); capacity];
+        let state = Self::init_state(&mut buf);
+        Self {
+            buf: Buffer::Owned(buf),
+            state,
+        }
+    }
+
+    fn init_state(buf: &mut [Pair]) -> smt_state_t {
+        unsafe {
+            let mut state = core::mem::MaybeUninit::uninit();
+            smt_state_init(state.as_mut_ptr(), buf.as_mut_ptr(), buf.len() as u32);
+            state.assume_init()
+        }
+    }
+
+    fn pairs(&self) -> &[Pair] {
+        &self.buf.pairs()[..self.len()]
+    }
+
+    pub fn update(&mut self, key: &[u8; 32], value: &[u8; 32]) -> Result<(), SMTError> {
+        match unsafe { smt_state_insert(&mut self.state, key.as_ptr(), value.as_ptr()) } {
+            0 => Ok(()),
+            err => Err(to_error(err)),
+        }
+    }
+
+    pub fn get(&self, key: &[u8; 32]) -> Result<[u8; 32], SMTError> {
+        let mut value = [0u8; 32];
+        match unsafe { smt_state_fetch(&self.state, key.as_ptr(), value.as_mut_ptr()) } {
+            0 => Ok(value),
+            err => Err(to_error(err)),
+        }
+    }
+
+    /// Remove every pair of the key, return the latest value
+    pub fn remove(&mut self, key: &[u8; 32]) -> Result<[u8; 32], SMTError> {
+        let value = self.get(key)?;
+        let len = self.len();
+        let pairs = self.buf.pairs_mut();
+        let mut kept = 0;
+        for i in 0..len {
+            if &pairs[i].key != key {
+                pairs[kept] = pairs[i];
+                kept += 1;
+            }
+        }
+        self.state.len = kept as u32;
+        Ok(value)
+    }
+
+    /// Iterate the (key, value) pairs in the order of updating,
+    /// a key may appear multiple times before `normalize`.
+    pub fn iter(&self) -> impl Iterator<Item = (&[u8; 32], &[u8; 32])> {
+        self.pairs().iter().map(|pair| (&pair.key, &pair.value))
+    }
+
+    pub fn normalize(&mut self) {
+        unsafe {
+            smt_state_normalize(&mut self.state);
+        }
+    }
+
+    pub fn calculate_root(&self, proof: &[u8]) -> Result<[u8; 32], SMTError> {
+        let mut root = [0u8; 32];
+        match unsafe {
+            smt_calculate_root(
+                root.as_mut_ptr(),
+                &self.state,
+                proof.as_ptr(),
+                proof.len() as u32,
+            )
+        } {
+            0 => Ok(root),
+            err => Err(to_error(err)),
+        }
+    }
+
+    pub fn verify(&mut self, root: &[u8; 32], proof: &[u8]) -> Result<(), SMTError> {
+        if &self.calculate_root(proof)? != root {
+            return Err(SMTError::RootMismatch);
+        }
+        Ok(())
+    }
+
+    /// Number of pairs in the buffer
+    pub fn len(&self) -> usize {
+        self.state.len as usize
+    }
+
+    /// Number of pairs the buffer can hold
+    pub fn capacity(&self) -> usize {
+        self.state.capacity as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.state.len == 0
+    }
+}
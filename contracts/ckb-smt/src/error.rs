@@ -0,0 +1,14 @@
+//! Errors of the sparse merkle tree
+
+/// Error of `Tree`, both implementations return the same errors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SMTError {
+    /// the pairs buffer is full
+    BufferFull,
+    /// the merkle proof can't be applied to the pairs
+    ProofMalformed,
+    /// the calculated root isn't the expected root
+    RootMismatch,
+    /// the key isn't in the tree
+    KeyMissing,
+}
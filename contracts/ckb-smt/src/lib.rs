@@ -1,6 +1,15 @@
 #![cfg_attr(not(tests), no_std)]
 
+extern crate alloc;
+
+#[cfg(any(not(feature = "pure-rust"), feature = "differential"))]
 #[allow(dead_code)]
 #[allow(non_upper_case_globals)]
 mod bindings;
+mod buffer;
+#[cfg(any(not(feature = "pure-rust"), feature = "differential"))]
+pub mod c_smt;
+pub mod error;
+#[cfg(feature = "pure-rust")]
+pub mod rust_smt;
 pub mod smt;
@@ -0,0 +1,315 @@
+//! Pure-Rust sparse merkle tree
+//!
+//! A port of `ckb_smt.h`, it follows the C implementation step by step
+//! so both implementations return the same roots and errors.
+
+use crate::buffer::Buffer;
+use alloc::vec;
+use blake2b_ref::Blake2bBuilder;
+
+pub use crate::error::SMTError;
+
+const STACK_SIZE: usize = 257;
+const TREE_HEIGHT: usize = 256;
+const BLAKE2B_PERSONALIZATION: &[u8] = b"ckb-default-hash";
+
+/// Same layout as `smt_pair_t`
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Pair {
+    pub key: [u8; 32],
+    pub value: [u8; 32],
+    pub order: u32,
+}
+
+fn is_zero(value: &[u8; 32]) -> bool {
+    value.iter().all(|b| *b == 0)
+}
+
+fn hash(lhs: &[u8; 32], rhs: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Blake2bBuilder::new(32)
+        .personal(BLAKE2B_PERSONALIZATION)
+        .build();
+    hasher.update(lhs);
+    hasher.update(rhs);
+    let mut out = [0u8; 32];
+    hasher.finalize(&mut out);
+    out
+}
+
+fn hash_leaf(key: &[u8; 32], value: &[u8; 32]) -> [u8; 32] {
+    if is_zero(value) {
+        return [0u8; 32];
+    }
+    hash(key, value)
+}
+
+fn merge(lhs: &[u8; 32], rhs: &[u8; 32]) -> [u8; 32] {
+    if is_zero(lhs) {
+        return *rhs;
+    }
+    if is_zero(rhs) {
+        return *lhs;
+    }
+    hash(lhs, rhs)
+}
+
+fn get_bit(key: &[u8; 32], height: usize) -> bool {
+    (key[height >> 3] >> (height & 7)) & 1 != 0
+}
+
+/// Clear the bits of key from 0 to `height`
+fn parent_path(key: &[u8; 32], height: usize) -> [u8; 32] {
+    let mut parent = *key;
+    for i in 0..=height {
+        parent[i >> 3] &= !(1 << (i & 7));
+    }
+    parent
+}
+
+/// Compare keys from the highest byte, then the insertion order
+fn cmp_pair(a: &Pair, b: &Pair) -> core::cmp::Ordering {
+    a.key
+        .iter()
+        .rev()
+        .cmp(b.key.iter().rev())
+        .then(a.order.cmp(&b.order))
+}
+
+pub struct Tree<'a> {
+    buf: Buffer<'a, Pair>,
+    len: usize,
+}
+
+impl<'a> Tree<'a> {
+    /// Create a tree on a caller-sized buffer
+    pub fn new(buf: &'a mut [Pair]) -> Tree<'a> {
+        Self {
+            buf: Buffer::Borrowed(buf),
+            len: 0,
+        }
+    }
+
+    /// Create a tree on a heap buffer which can hold `capacity` pairs
+    pub fn with_capacity(capacity: usize) -> Tree<'a> {
+        Self {
+            buf: Buffer::Owned(vec![Pair::default(); capacity]),
+            len: 0,
+        }
+    }
+
+    fn pairs(&self) -> &[Pair] {
+        &self.buf.pairs()[..self.len]
+    }
+
+    pub fn update(&mut self, key: &[u8; 32], value: &[u8; 32]) -> Result<(), SMTError> {
+        let len = self.len;
+        let pairs = self.buf.pairs_mut();
+        // append at the end if we have free space
+        if len < pairs.len() {
+            pairs[len] = Pair {
+                key: *key,
+                value: *value,
+                order: len as u32,
+            };
+            self.len += 1;
+            return Ok(());
+        }
+        // otherwise overwrite the latest pair of the key
+        match pairs[..len].iter_mut().rev().find(|pair| &pair.key == key) {
+            Some(pair) => {
+                pair.value = *value;
+                Ok(())
+            }
+            None => Err(SMTError::BufferFull),
+        }
+    }
+
+    pub fn get(&self, key: &[u8; 32]) -> Result<[u8; 32], SMTError> {
+        self.pairs()
+            .iter()
+            .rev()
+            .find(|pair| &pair.key == key)
+            .map(|pair| pair.value)
+            .ok_or(SMTError::KeyMissing)
+    }
+
+    /// Remove every pair of the key, return the latest value
+    pub fn remove(&mut self, key: &[u8; 32]) -> Result<[u8; 32], SMTError> {
+        let value = self.get(key)?;
+        let len = self.len;
+        let pairs = self.buf.pairs_mut();
+        let mut kept = 0;
+        for i in 0..len {
+            if &pairs[i].key != key {
+                pairs[kept] = pairs[i];
+                kept += 1;
+            }
+        }
+        self.len = kept;
+        Ok(value)
+    }
+
+    /// Iterate the (key, value) pairs in the order of updating,
+    /// a key may appear multiple times before `normalize`.
+    pub fn iter(&self) -> impl Iterator<Item = (&[u8; 32], &[u8; 32])> {
+        self.pairs().iter().map(|pair| (&pair.key, &pair.value))
+    }
+
+    pub fn normalize(&mut self) {
+        let len = self.len;
+        let pairs = &mut self.buf.pairs_mut()[..len];
+        // the latest pair of a key has the lowest order
+        for (i, pair) in pairs.iter_mut().enumerate() {
+            pair.order = (len - i) as u32;
+        }
+        pairs.sort_unstable_by(cmp_pair);
+        // remove duplicate keys, keep the first one
+        let mut sorted = 0;
+        let mut next = 0;
+        while next < len {
+            let item_index = next;
+            next += 1;
+            while next < len && pairs[item_index].key == pairs[next].key {
+                next += 1;
+            }
+            if item_index != sorted {
+                pairs[sorted].key = pairs[item_index].key;
+                pairs[sorted].value = pairs[item_index].value;
+            }
+            sorted += 1;
+        }
+        self.len = sorted;
+    }
+
+    pub fn calculate_root(&self, proof: &[u8]) -> Result<[u8; 32], SMTError> {
+        let pairs = self.pairs();
+        let mut stack_keys = [[0u8; 32]; STACK_SIZE];
+        let mut stack_values = [[0u8; 32]; STACK_SIZE];
+        let mut stack_heights = [0usize; STACK_SIZE];
+        let mut proof_index = 0;
+        let mut leave_index = 0;
+        let mut stack_top = 0;
+
+        while proof_index < proof.len() {
+            let op = proof[proof_index];
+            proof_index += 1;
+            match op {
+                // push the next leaf
+                0x4C => {
+                    if stack_top >= STACK_SIZE {
+                        return Err(SMTError::ProofMalformed);
+                    }
+                    let pair = pairs.get(leave_index).ok_or(SMTError::ProofMalformed)?;
+                    stack_keys[stack_top] = pair.key;
+                    stack_values[stack_top] = hash_leaf(&pair.key, &pair.value);
+                    stack_heights[stack_top] = 0;
+                    stack_top += 1;
+                    leave_index += 1;
+                }
+                // merge the top with a sibling from the proof
+                0x50 => {
+                    if stack_top == 0 {
+                        return Err(SMTError::ProofMalformed);
+                    }
+                    if proof_index + 32 > proof.len() {
+                        return Err(SMTError::ProofMalformed);
+                    }
+                    let mut sibling = [0u8; 32];
+                    sibling.copy_from_slice(&proof[proof_index..proof_index + 32]);
+                    proof_index += 32;
+                    let top = stack_top - 1;
+                    let height = stack_heights[top];
+                    if height >= TREE_HEIGHT {
+                        return Err(SMTError::ProofMalformed);
+                    }
+                    let key = stack_keys[top];
+                    stack_values[top] = if get_bit(&key, height) {
+                        merge(&sibling, &stack_values[top])
+                    } else {
+                        merge(&stack_values[top], &sibling)
+                    };
+                    stack_keys[top] = parent_path(&key, height);
+                    stack_heights[top] = height + 1;
+                }
+                // merge the two nodes on the top
+                0x48 => {
+                    if stack_top < 2 {
+                        return Err(SMTError::ProofMalformed);
+                    }
+                    let a = stack_top - 2;
+                    let b = stack_top - 1;
+                    let height = stack_heights[a];
+                    if height != stack_heights[b] || height >= TREE_HEIGHT {
+                        return Err(SMTError::ProofMalformed);
+                    }
+                    let parent_key_a = parent_path(&stack_keys[a], height);
+                    let parent_key_b = parent_path(&stack_keys[b], height);
+                    // the two nodes must be siblings
+                    if parent_key_a != parent_key_b {
+                        return Err(SMTError::ProofMalformed);
+                    }
+                    stack_values[a] = if get_bit(&stack_keys[a], height) {
+                        merge(&stack_values[b], &stack_values[a])
+                    } else {
+                        merge(&stack_values[a], &stack_values[b])
+                    };
+                    stack_keys[a] = parent_key_a;
+                    stack_heights[a] = height + 1;
+                    stack_top -= 1;
+                }
+                // merge the top with zero siblings
+                0x4F => {
+                    if stack_top == 0 {
+                        return Err(SMTError::ProofMalformed);
+                    }
+                    let n = *proof.get(proof_index).ok_or(SMTError::ProofMalformed)?;
+                    proof_index += 1;
+                    let zero_count = if n == 0 { TREE_HEIGHT } else { n as usize };
+                    let top = stack_top - 1;
+                    let base_height = stack_heights[top];
+                    if base_height + zero_count > TREE_HEIGHT {
+                        return Err(SMTError::ProofMalformed);
+                    }
+                    // merging with zero keeps the value, only the path moves up
+                    let height = base_height + zero_count - 1;
+                    stack_keys[top] = parent_path(&stack_keys[top], height);
+                    stack_heights[top] = base_height + zero_count;
+                }
+                _ => return Err(SMTError::ProofMalformed),
+            }
+        }
+        // all leaves must be used
+        if leave_index != pairs.len() {
+            return Err(SMTError::ProofMalformed);
+        }
+        if stack_top != 1 {
+            return Err(SMTError::ProofMalformed);
+        }
+        if stack_heights[0] != TREE_HEIGHT {
+            return Err(SMTError::ProofMalformed);
+        }
+        Ok(stack_values[0])
+    }
+
+    pub fn verify(&mut self, root: &[u8; 32], proof: &[u8]) -> Result<(), SMTError> {
+        if &self.calculate_root(proof)? != root {
+            return Err(SMTError::RootMismatch);
+        }
+        Ok(())
+    }
+
+    /// Number of pairs in the buffer
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Number of pairs the buffer can hold
+    pub fn capacity(&self) -> usize {
+        self.buf.pairs().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
@@ -3,27 +3,24 @@ use core::result::Result;
 
 use gw_utils::{
     cells::{
-        rollup::{
-            load_rollup_config, parse_rollup_action, search_rollup_cell, search_rollup_state,
-            MAX_ROLLUP_WITNESS_SIZE,
-        },
+        lock_cells::{parse_custodian_lock_args, parse_deposit_lock_args},
+        rollup::{load_rollup_config, search_rollup_cell, search_rollup_state},
         utils::search_lock_hash,
     },
-    ckb_std::high_level::load_cell_lock,
-    gw_types::packed::{DepositLockArgs, DepositLockArgsReader, RollupActionUnionReader},
+    syscalls::Syscalls,
+    trace,
+    witness::{RollupActionType, RollupWitness},
 };
 
 // Import CKB syscalls and structures
 // https://nervosnetwork.github.io/ckb-std/riscv64imac-unknown-none-elf/doc/ckb_std/index.html
 use crate::ckb_std::{
     ckb_constants::Source, ckb_types::bytes::Bytes, ckb_types::prelude::Unpack as CKBUnpack,
-    high_level::load_script, high_level::load_witness_args,
 };
 use gw_types::{
     core::ScriptHashType,
     packed::{
-        CustodianLockArgs, CustodianLockArgsReader, UnlockCustodianViaRevertWitness,
-        UnlockCustodianViaRevertWitnessReader,
+        CustodianLockArgs, UnlockCustodianViaRevertWitness, UnlockCustodianViaRevertWitnessReader,
     },
     prelude::*,
 };
@@ -31,9 +28,11 @@ use gw_utils::gw_types;
 
 use crate::error::Error;
 
-/// args: rollup_type_hash | custodian lock args
-fn parse_lock_args() -> Result<([u8; 32], CustodianLockArgs), Error> {
-    let script = load_script()?;
+/// args: rollup_type_hash | custodian lock args | deposit extension
+fn parse_lock_args<S: Syscalls>(
+    syscalls: &S,
+) -> Result<([u8; 32], CustodianLockArgs, Bytes), Error> {
+    let script = syscalls.load_script()?;
     let args: Bytes = script.args().unpack();
 
     let mut rollup_type_hash: [u8; 32] = [0u8; 32];
@@ -41,20 +40,17 @@ fn parse_lock_args() -> Result<([u8; 32], CustodianLockArgs), Error> {
         return Err(Error::InvalidArgs);
     }
     rollup_type_hash.copy_from_slice(&args[..32]);
-    match CustodianLockArgsReader::verify(&args.slice(32..), false) {
-        Ok(()) => Ok((
-            rollup_type_hash,
-            CustodianLockArgs::new_unchecked(args.slice(32..)),
-        )),
+    match parse_custodian_lock_args(&args[32..]) {
+        Ok((lock_args, deposit_extension)) => Ok((rollup_type_hash, lock_args, deposit_extension)),
         Err(_) => Err(Error::InvalidArgs),
     }
 }
 
-pub fn main() -> Result<(), Error> {
-    let (rollup_type_hash, lock_args) = parse_lock_args()?;
+pub fn main<S: Syscalls>(syscalls: &S) -> Result<(), Error> {
+    let (rollup_type_hash, lock_args, deposit_extension) = parse_lock_args(syscalls)?;
 
     // read global state from rollup cell
-    let global_state = match search_rollup_state(&rollup_type_hash, Source::Input)? {
+    let global_state = match search_rollup_state(syscalls, &rollup_type_hash, Source::Input)? {
         Some(state) => state,
         None => return Err(Error::RollupCellNotFound),
     };
@@ -64,14 +60,15 @@ pub fn main() -> Result<(), Error> {
 
     if deposit_block_number <= last_finalized_block_number {
         // this custodian lock is already finalized, rollup will handle the logic
+        trace!("custodian_lock.finalized");
         return Ok(());
     }
 
     // otherwise, the submitter try to prove the deposit is reverted.
-    let config = load_rollup_config(&global_state.rollup_config_hash().unpack())?;
+    let config = load_rollup_config(syscalls, &global_state.rollup_config_hash().unpack())?;
 
     // read the args
-    let witness_args = load_witness_args(0, Source::GroupInput)?;
+    let witness_args = syscalls.load_witness_args(0, Source::GroupInput)?;
     let data: Bytes = witness_args
         .lock()
         .to_opt()
@@ -84,11 +81,16 @@ pub fn main() -> Result<(), Error> {
     };
 
     // the reverted deposit cell must exists
-    let deposit_cell_index =
-        search_lock_hash(&unlock_args.deposit_lock_hash().unpack(), Source::Output)
-            .ok_or(Error::InvalidOutput)?;
-    let deposit_lock = load_cell_lock(deposit_cell_index, Source::Output)?;
-    let deposit_lock_args = {
+    trace!("custodian_lock.reverted_deposit_cell");
+    let deposit_cell_index = search_lock_hash(
+        syscalls,
+        &unlock_args.deposit_lock_hash().unpack(),
+        Source::Output,
+    )
+    .ok_or(Error::InvalidOutput)?;
+    trace!("custodian_lock.deposit_lock", index = deposit_cell_index);
+    let deposit_lock = syscalls.load_cell_lock(deposit_cell_index, Source::Output)?;
+    let (deposit_lock_args, reverted_deposit_extension) = {
         let args: Bytes = deposit_lock.args().unpack();
         if args.len() < rollup_type_hash.len() {
             return Err(Error::InvalidArgs);
@@ -97,34 +99,32 @@ pub fn main() -> Result<(), Error> {
             return Err(Error::InvalidArgs);
         }
 
-        match DepositLockArgsReader::verify(&args.slice(32..), false) {
-            Ok(_) => DepositLockArgs::new_unchecked(args.slice(32..)),
+        match parse_deposit_lock_args(&args[32..]) {
+            Ok(args) => args,
             Err(_) => return Err(Error::InvalidOutput),
         }
     };
+    // the deposit call or the batch entries are reverted with the deposit
     if deposit_lock.code_hash().as_slice() != config.deposit_script_type_hash().as_slice()
         || deposit_lock.hash_type() != ScriptHashType::Type.into()
         || deposit_lock_args.as_slice() != lock_args.deposit_lock_args().as_slice()
+        || reverted_deposit_extension != deposit_extension
     {
         return Err(Error::InvalidOutput);
     }
 
     // check deposit block is reverted
+    trace!("custodian_lock.reverted_block");
     let deposit_block_hash = lock_args.deposit_block_hash();
-    let mut rollup_action_witness = [0u8; MAX_ROLLUP_WITNESS_SIZE];
-    let rollup_action = {
-        let index = search_rollup_cell(&rollup_type_hash, Source::Output)
+    let rollup_witness = {
+        let index = search_rollup_cell(syscalls, &rollup_type_hash, Source::Output)
             .ok_or(Error::RollupCellNotFound)?;
-        parse_rollup_action(&mut rollup_action_witness, index, Source::Output)?
+        RollupWitness::load(syscalls, index, Source::Output)?
     };
 
-    match rollup_action.to_enum() {
-        RollupActionUnionReader::RollupSubmitBlock(args) => {
-            if args
-                .reverted_block_hashes()
-                .iter()
-                .any(|hash| hash.as_slice() == deposit_block_hash.as_slice())
-            {
+    match rollup_witness.action_type() {
+        RollupActionType::SubmitBlock => {
+            if rollup_witness.contains_reverted_block_hash(deposit_block_hash.as_slice())? {
                 return Ok(());
             }
             Err(Error::InvalidRevertedBlocks)
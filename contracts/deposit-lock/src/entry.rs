@@ -3,7 +3,7 @@
 //! The cell can be unlocked by the rollup cell which match the rollup_type_hash,
 //! or can be unlocked by user.
 //!
-//! Args: DepositLockArgs
+//! Args: DepositLockArgs | deposit extension (flags | items, or the entries of a batch deposit)
 
 // Import from `core` instead of from `std` since we are in no-std mode
 use core::result::Result;
@@ -17,34 +17,35 @@ use core::result::Result;
 use crate::ckb_std::{
     ckb_constants::Source,
     ckb_types::{bytes::Bytes, prelude::Unpack as CKBTypeUnpack},
-    high_level::{load_input_since, load_script},
-    since::Since,
 };
 
-use gw_utils::cells::{rollup::search_rollup_cell, utils::search_lock_hash};
-
-use gw_types::{
-    packed::{DepositLockArgs, DepositLockArgsReader},
-    prelude::*,
+use gw_utils::{
+    cells::{
+        lock_cells::parse_deposit_lock_args, rollup::search_rollup_cell, utils::search_lock_hash,
+    },
+    since::Since,
+    syscalls::Syscalls,
+    trace,
 };
+
+use gw_types::{packed::DepositLockArgs, prelude::*};
 use gw_utils::gw_types;
 
 use crate::error::Error;
 
 /// args: rollup_type_hash | deposit lock args
-fn parse_lock_args() -> Result<([u8; 32], DepositLockArgs), Error> {
+///
+/// the deposit extension is verified by the rollup state validator
+fn parse_lock_args<S: Syscalls>(syscalls: &S) -> Result<([u8; 32], DepositLockArgs), Error> {
     let mut rollup_type_hash = [0u8; 32];
-    let script = load_script()?;
+    let script = syscalls.load_script()?;
     let args: Bytes = script.args().unpack();
     if args.len() < rollup_type_hash.len() {
         return Err(Error::InvalidArgs);
     }
     rollup_type_hash.copy_from_slice(&args[..32]);
-    match DepositLockArgsReader::verify(&args.slice(32..), false) {
-        Ok(()) => Ok((
-            rollup_type_hash,
-            DepositLockArgs::new_unchecked(args.slice(32..)),
-        )),
+    match parse_deposit_lock_args(&args[32..]) {
+        Ok((lock_args, _extension)) => Ok((rollup_type_hash, lock_args)),
         Err(_) => Err(Error::InvalidArgs),
     }
 }
@@ -54,25 +55,31 @@ fn parse_lock_args() -> Result<([u8; 32], DepositLockArgs), Error> {
 // 2. unlock by user after timeout
 //
 // We always try the 1 first, then try 2, otherwise the unlock return a failure.
-pub fn main() -> Result<(), Error> {
-    let (rollup_type_hash, lock_args) = parse_lock_args()?;
+pub fn main<S: Syscalls>(syscalls: &S) -> Result<(), Error> {
+    let (rollup_type_hash, lock_args) = parse_lock_args(syscalls)?;
     // try unlock by Rollup
     // return success if rollup cell in the inputs, the following verification will be handled by rollup state validator.
-    if search_rollup_cell(&rollup_type_hash, Source::Input).is_some() {
+    if search_rollup_cell(syscalls, &rollup_type_hash, Source::Input).is_some() {
+        trace!("deposit_lock.unlock_by_rollup");
         return Ok(());
     }
 
     // unlock by user
     // 1. check since is satisfied the cancel timeout
-    let input_since = Since::new(load_input_since(0, Source::GroupInput)?);
+    trace!("deposit_lock.cancel_timeout");
+    let input_since = Since::new(syscalls.load_input_since(0, Source::GroupInput)?);
     let cancel_timeout = Since::new(lock_args.cancel_timeout().unpack());
-    if input_since.flags() != cancel_timeout.flags()
-        || input_since.as_u64() < cancel_timeout.as_u64()
-    {
+    // the since must have the lock type and the metric of the cancel timeout
+    if !input_since.satisfies(&cancel_timeout) {
         return Err(Error::InvalidSince);
     }
     // 2. search owner cell
-    match search_lock_hash(&lock_args.owner_lock_hash().unpack(), Source::Input) {
+    trace!("deposit_lock.owner_cell");
+    match search_lock_hash(
+        syscalls,
+        &lock_args.owner_lock_hash().unpack(),
+        Source::Input,
+    ) {
         Some(_) => Ok(()),
         None => Err(Error::OwnerCellNotFound),
     }
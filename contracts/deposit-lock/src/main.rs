@@ -1,6 +1,6 @@
 //! Generated by capsule
 //!
-//! `main.rs` is used to define rust lang items and modules.
+//! `main.rs` is used to define rust lang items.
 //! See `entry.rs` for the `main` function.
 //! See `error.rs` for the `Error` type.
 
@@ -10,11 +10,10 @@
 #![feature(alloc_error_handler)]
 #![feature(panic_info_message)]
 
-// define modules
-mod entry;
+use deposit_lock::entry;
+use gw_utils::{ckb_std, syscalls::CKBSyscalls};
 
 use ckb_std::default_alloc;
-pub use gw_utils::{ckb_std, error};
 
 ckb_std::entry!(program_entry);
 default_alloc!();
@@ -22,7 +21,7 @@ default_alloc!();
 /// program entry
 fn program_entry() -> i8 {
     // Call main function and return error code
-    match entry::main() {
+    match entry::main(&CKBSyscalls) {
         Ok(_) => 0,
         Err(err) => err as i8,
     }
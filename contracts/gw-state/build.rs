@@ -0,0 +1,26 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+
+/// Keep in sync with `GW_MAX_KV_PAIRS` in c/gw_def.h
+const DEFAULT_MAX_KV_PAIRS: usize = 1024;
+
+fn main() {
+    // the same variable is passed to the C Makefile
+    println!("cargo:rerun-if-env-changed=GW_MAX_KV_PAIRS");
+    let max_kv_pairs: usize = match env::var("GW_MAX_KV_PAIRS") {
+        Ok(value) => value
+            .parse()
+            .expect("GW_MAX_KV_PAIRS must be a positive integer"),
+        Err(_) => DEFAULT_MAX_KV_PAIRS,
+    };
+    let out_dir = env::var("OUT_DIR").unwrap();
+    fs::write(
+        Path::new(&out_dir).join("max_kv_pairs.rs"),
+        format!(
+            "/// MAX KV pairs in a tx\npub const GW_MAX_KV_PAIRS: usize = {};\n",
+            max_kv_pairs
+        ),
+    )
+    .unwrap();
+}
@@ -1,2 +1,3 @@
-/// MAX KV pairs in a txs
-pub const GW_MAX_KV_PAIRS: usize = 1024;
+// MAX KV pairs in a tx, configurable by the `GW_MAX_KV_PAIRS` env at build time,
+// the C scripts use the same value.
+include!(concat!(env!("OUT_DIR"), "/max_kv_pairs.rs"));
@@ -0,0 +1,12 @@
+use crate::ckb_smt::smt::SMTError;
+use gw_utils::error::Error;
+
+/// Convert an error of the sparse merkle tree into the exit code
+pub fn map_smt_error(err: SMTError) -> Error {
+    match err {
+        SMTError::BufferFull => Error::InsufficientKVStateCapacity,
+        SMTError::ProofMalformed => Error::SMTProofMalformed,
+        SMTError::RootMismatch => Error::SMTRootMismatch,
+        SMTError::KeyMissing => Error::SMTKeyMissing,
+    }
+}
@@ -1,10 +1,20 @@
-use crate::ckb_smt::smt::{Pair, Tree};
+use crate::ckb_smt::smt::{Pair, SMTError as TreeError, Tree};
+use crate::error::map_smt_error;
 use core::cell::RefCell;
 use gw_utils::ckb_std::debug;
 use gw_utils::error::Error;
 use gw_utils::gw_common::{error::Error as SMTError, state::State, H256};
 use gw_utils::gw_types::{packed::KVPairVecReader, prelude::*};
 
+/// Convert errors of updating the tree,
+/// an exhausted buffer is reported as `SMTError::Store`
+fn map_update_error(err: TreeError) -> SMTError {
+    match err {
+        TreeError::BufferFull => SMTError::Store,
+        _ => SMTError::MissingKey,
+    }
+}
+
 pub struct KVState<'a> {
     tree: RefCell<Tree<'a>>,
     proof: &'a [u8],
@@ -14,6 +24,7 @@ pub struct KVState<'a> {
 
 impl<'a> KVState<'a> {
     /// params:
+    /// - buf, the caller-sized buffer of the tree
     /// - kv_pairs, the kv pairs
     /// - proof, the merkle proof of kv_pairs
     /// - account count, account count in the current state
@@ -25,12 +36,39 @@ impl<'a> KVState<'a> {
         account_count: u32,
         current_root: Option<H256>,
     ) -> Result<KVState<'a>, Error> {
-        let mut tree = Tree::new(buf);
+        Self::build_on_tree(Tree::new(buf), kv_pairs, proof, account_count, current_root)
+    }
+
+    /// Build a KVState on a heap buffer which can hold `capacity` pairs,
+    /// use `kv_pairs.len()` as capacity if every updated key is in the kv_pairs.
+    pub fn build_with_capacity(
+        capacity: usize,
+        kv_pairs: KVPairVecReader,
+        proof: &'a [u8],
+        account_count: u32,
+        current_root: Option<H256>,
+    ) -> Result<KVState<'a>, Error> {
+        Self::build_on_tree(
+            Tree::with_capacity(capacity),
+            kv_pairs,
+            proof,
+            account_count,
+            current_root,
+        )
+    }
+
+    fn build_on_tree(
+        mut tree: Tree<'a>,
+        kv_pairs: KVPairVecReader,
+        proof: &'a [u8],
+        account_count: u32,
+        current_root: Option<H256>,
+    ) -> Result<KVState<'a>, Error> {
         for pair in kv_pairs.iter() {
             tree.update(&pair.k().unpack(), &pair.v().unpack())
                 .map_err(|err| {
-                    debug!("[kv state] build: update key error: {}", err);
-                    Error::SMTKeyMissing
+                    debug!("[kv state] build: update key error: {:?}", err);
+                    map_update_error(err)
                 })?;
         }
         Ok(KVState {
@@ -44,6 +82,30 @@ impl<'a> KVState<'a> {
     pub fn is_empty(&self) -> bool {
         self.tree.borrow().is_empty() && self.proof.is_empty()
     }
+
+    /// Verify the kv state against the expected root,
+    /// a malformed proof and a mismatched root are reported as distinct errors.
+    pub fn verify_root(&self, root: &H256) -> Result<(), Error> {
+        let calculated_root = self.calculate_tree_root().map_err(map_smt_error)?;
+        if &calculated_root != root {
+            debug!("[kv state] calculated root mismatch");
+            return Err(Error::SMTRootMismatch);
+        }
+        Ok(())
+    }
+
+    fn calculate_tree_root(&self) -> Result<H256, TreeError> {
+        if self.is_empty() {
+            return self.previous_root.ok_or_else(|| {
+                debug!("[kv state] calculate merkle root for an empty kv_state");
+                TreeError::ProofMalformed
+            });
+        }
+        let mut tree = self.tree.borrow_mut();
+        tree.normalize();
+        let root = tree.calculate_root(self.proof)?;
+        Ok(root.into())
+    }
 }
 
 impl<'a> State for KVState<'a> {
@@ -60,7 +122,7 @@ impl<'a> State for KVState<'a> {
         self.tree
             .borrow_mut()
             .update(&key.into(), &value.into())
-            .map_err(|_| SMTError::MissingKey)
+            .map_err(map_update_error)
     }
     fn get_account_count(&self) -> Result<u32, SMTError> {
         Ok(self.account_count)
@@ -70,18 +132,9 @@ impl<'a> State for KVState<'a> {
         Ok(())
     }
     fn calculate_root(&self) -> Result<H256, SMTError> {
-        if self.is_empty() {
-            return self.previous_root.ok_or_else(|| {
-                debug!("[kv state] calculate merkle root for an empty kv_state");
-                SMTError::MerkleProof
-            });
-        }
-        let mut tree = self.tree.borrow_mut();
-        tree.normalize();
-        let root = tree.calculate_root(self.proof).map_err(|err| {
-            debug!("[kv state] calculate root error: {}", err);
+        self.calculate_tree_root().map_err(|err| {
+            debug!("[kv state] calculate root error: {:?}", err);
             SMTError::MerkleProof
-        })?;
-        Ok(root.into())
+        })
     }
 }
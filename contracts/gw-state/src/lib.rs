@@ -7,4 +7,5 @@ extern crate alloc;
 pub use ckb_smt;
 
 pub mod constants;
+pub mod error;
 pub mod kv_state;
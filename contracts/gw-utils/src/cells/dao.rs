@@ -0,0 +1,74 @@
+//! Nervos DAO cells
+//!
+//! Finalized custodian cells can be deposited into the Nervos DAO,
+//! the DAO type script keeps the original capacity of the cell,
+//! the interest is only released by the phase-2 withdrawal.
+
+use super::{rollup::DAOConfig, types::CellValue};
+use crate::{error::Error, syscalls::Syscalls};
+use ckb_std::{
+    ckb_constants::Source,
+    ckb_types::prelude::{Entity as CKBEntity, Unpack as CKBUnpack},
+};
+use gw_common::H256;
+use gw_types::{bytes::Bytes, core::ScriptHashType};
+
+/// Size of the DAO cell data
+const DAO_DATA_SIZE: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DAOState {
+    /// deposited into the DAO, the cell data is zero
+    Deposited,
+    /// phase-1 withdrawal, the cell data is the block number of the deposit
+    Withdrawing { deposit_block_number: u64 },
+}
+
+/// Fetch the DAO state of a cell, return None if the cell's type script isn't the DAO script
+pub fn fetch_dao_state<S: Syscalls>(
+    syscalls: &S,
+    dao_config: &DAOConfig,
+    index: usize,
+    source: Source,
+) -> Result<Option<DAOState>, Error> {
+    let type_ = match syscalls.load_cell_type(index, source)? {
+        Some(type_) => type_,
+        None => return Ok(None),
+    };
+    if type_.code_hash().as_slice() != dao_config.script_type_hash
+        || type_.hash_type() != ScriptHashType::Type.into()
+    {
+        return Ok(None);
+    }
+    let args: Bytes = type_.args().unpack();
+    if !args.is_empty() {
+        return Err(Error::InvalidCustodianCell);
+    }
+    let data = syscalls.load_cell_data(index, source)?;
+    if data.len() != DAO_DATA_SIZE {
+        return Err(Error::InvalidCustodianCell);
+    }
+    let mut buf = [0u8; DAO_DATA_SIZE];
+    buf.copy_from_slice(&data);
+    let state = match u64::from_le_bytes(buf) {
+        0 => DAOState::Deposited,
+        deposit_block_number => DAOState::Withdrawing {
+            deposit_block_number,
+        },
+    };
+    Ok(Some(state))
+}
+
+/// The value of a DAO cell, which is counted at its original capacity
+pub fn fetch_dao_cell_value<S: Syscalls>(
+    syscalls: &S,
+    index: usize,
+    source: Source,
+) -> Result<CellValue, Error> {
+    let capacity = syscalls.load_cell_capacity(index, source)?;
+    Ok(CellValue {
+        sudt_script_hash: H256::zero(),
+        amount: 0,
+        capacity,
+    })
+}
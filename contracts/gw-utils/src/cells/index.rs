@@ -0,0 +1,215 @@
+//! Rollup cells index
+//!
+//! Classify inputs or outputs of the transaction into typed rollup cells in a single scan,
+//! instead of loading the lock of every cell again for each kind of rollup cell.
+
+use super::{
+    dao::{fetch_dao_cell_value, fetch_dao_state},
+    lock_cells::{
+        fetch_capacity_and_sudt_value, parse_custodian_lock_args, parse_deposit_extension,
+        parse_deposit_lock_args, parse_stake_lock_args, stake_cell_amount,
+    },
+    rollup::{dao_config, stake_token_config},
+    types::{ChallengeCell, CustodianCell, DepositRequestCell, StakeCell, WithdrawalCell},
+};
+use crate::{error::Error, syscalls::Syscalls};
+use alloc::vec::Vec;
+use ckb_std::{
+    ckb_constants::Source,
+    ckb_types::prelude::{Entity as CKBEntity, Unpack as CKBUnpack},
+    debug,
+    high_level::QueryIter,
+};
+use gw_common::{CKB_SUDT_SCRIPT_ARGS, H256};
+use gw_types::{
+    bytes::Bytes,
+    core::ScriptHashType,
+    packed::{Byte32Reader, ChallengeLockArgs, RollupConfig, WithdrawalLockArgs},
+    prelude::*,
+};
+
+fn parse_lock_args<ArgsType: Entity>(raw_args: &[u8]) -> Result<ArgsType, Error> {
+    ArgsType::from_slice(raw_args).map_err(|_err| Error::Encoding)
+}
+
+/// Rollup cells of the inputs or the outputs
+#[derive(Default)]
+pub struct RollupCells {
+    pub deposits: Vec<DepositRequestCell>,
+    pub custodians: Vec<CustodianCell>,
+    pub withdrawals: Vec<WithdrawalCell>,
+    pub stakes: Vec<StakeCell>,
+    pub challenges: Vec<ChallengeCell>,
+    /// Indices of the burn cells, the value is only loaded by the actions which check burns
+    pub burns: Vec<usize>,
+}
+
+impl RollupCells {
+    /// Scan cells of the source once and classify them
+    pub fn load<S: Syscalls>(
+        syscalls: &S,
+        rollup_type_hash: &H256,
+        config: &RollupConfig,
+        source: Source,
+    ) -> Result<Self, Error> {
+        let deposit_script_type_hash = config.deposit_script_type_hash();
+        let custodian_script_type_hash = config.custodian_script_type_hash();
+        let withdrawal_script_type_hash = config.withdrawal_script_type_hash();
+        let stake_script_type_hash = config.stake_script_type_hash();
+        let challenge_script_type_hash = config.challenge_script_type_hash();
+        let burn_lock_hash = config.burn_lock_hash();
+        let dao_config = dao_config(config)?;
+        let stake_token = stake_token_config(config)?;
+
+        let mut cells = RollupCells::default();
+        let locks = QueryIter::new(
+            |index, source| syscalls.load_cell_lock(index, source),
+            source,
+        );
+        for (index, lock) in locks.enumerate() {
+            // burn cells are identified by the lock hash
+            if syscalls.load_cell_lock_hash(index, source)? == burn_lock_hash.as_slice() {
+                cells.burns.push(index);
+            }
+
+            // rollup lock args: rollup_type_hash | lock args
+            let lock_args: Bytes = lock.args().unpack();
+            let is_rollup_lock = lock_args.len() > 32
+                && &lock_args[..32] == rollup_type_hash.as_slice()
+                && lock.hash_type() == ScriptHashType::Type.into();
+            if !is_rollup_lock {
+                continue;
+            }
+            let code_hash = lock.code_hash();
+            let raw_args = &lock_args[32..];
+
+            if code_hash.as_slice() == deposit_script_type_hash.as_slice() {
+                let (args, extension) = parse_deposit_lock_args(raw_args)?;
+                let value = fetch_capacity_and_sudt_value(syscalls, config, index, source)?;
+                let (entries, call, fee) = parse_deposit_extension(&args, &extension, &value)?;
+                cells.deposits.push(DepositRequestCell {
+                    index,
+                    args,
+                    extension,
+                    value,
+                    entries,
+                    call,
+                    fee,
+                });
+            } else if code_hash.as_slice() == custodian_script_type_hash.as_slice() {
+                let (args, deposit_extension) = parse_custodian_lock_args(raw_args)?;
+                let dao = match dao_config {
+                    Some(ref dao_config) => fetch_dao_state(syscalls, dao_config, index, source)?,
+                    None => None,
+                };
+                // DAO cells are counted at the original capacity
+                let value = match dao {
+                    Some(_) => fetch_dao_cell_value(syscalls, index, source)?,
+                    None => fetch_capacity_and_sudt_value(syscalls, config, index, source)?,
+                };
+                cells.custodians.push(CustodianCell {
+                    index,
+                    args,
+                    deposit_extension,
+                    value,
+                    dao,
+                });
+            } else if code_hash.as_slice() == withdrawal_script_type_hash.as_slice() {
+                let args: WithdrawalLockArgs = parse_lock_args(raw_args)?;
+                let value = fetch_capacity_and_sudt_value(syscalls, config, index, source)?;
+                cells
+                    .withdrawals
+                    .push(WithdrawalCell { index, args, value });
+            } else if code_hash.as_slice() == stake_script_type_hash.as_slice() {
+                let (args, delegation) = parse_stake_lock_args(raw_args)?;
+                let value = fetch_capacity_and_sudt_value(syscalls, config, index, source)?;
+                // CKB and the configured sUDT are accepted as staking assets
+                let amount = stake_cell_amount(stake_token.as_ref(), &value)?;
+                cells.stakes.push(StakeCell {
+                    index,
+                    args,
+                    capacity: value.capacity,
+                    amount,
+                    producer_lock_hash: delegation.map(|d| d.producer_lock_hash),
+                    unbonding: delegation.map_or(false, |d| d.unbonding),
+                });
+            } else if code_hash.as_slice() == challenge_script_type_hash.as_slice() {
+                let args: ChallengeLockArgs = parse_lock_args(raw_args)?;
+                let value = fetch_capacity_and_sudt_value(syscalls, config, index, source)?;
+                if value.sudt_script_hash != CKB_SUDT_SCRIPT_ARGS.into() || value.amount != 0 {
+                    debug!("found a challenge cell with simple UDT");
+                    continue;
+                }
+                cells.challenges.push(ChallengeCell { index, args, value });
+            }
+        }
+        Ok(cells)
+    }
+
+    /// Find the challenge cell,
+    /// return an error if there are multiple challenge cells.
+    pub fn find_challenge_cell(&self) -> Result<Option<&ChallengeCell>, Error> {
+        match self.challenges.as_slice() {
+            [] => Ok(None),
+            [cell] => Ok(Some(cell)),
+            _ => Err(Error::InvalidChallengeCell),
+        }
+    }
+
+    /// Find block producer's stake cell
+    /// this function return Option<&StakeCell> if we have 1 or zero stake cell
+    /// owned by the producer, otherwise return an error.
+    /// Stake cells delegated to the producer are allowed, other stake cells return an error.
+    pub fn find_block_producer_stake_cell(
+        &self,
+        owner_lock_hash: &Byte32Reader,
+    ) -> Result<Option<&StakeCell>, Error> {
+        if self
+            .stakes
+            .iter()
+            .any(|cell| match cell.producer_lock_hash {
+                Some(ref producer_lock_hash) => producer_lock_hash != owner_lock_hash.as_slice(),
+                None => cell.args.owner_lock_hash().as_slice() != owner_lock_hash.as_slice(),
+            })
+        {
+            debug!("found stake cell with unexpected owner_lock_hash");
+            return Err(Error::InvalidStakeCell);
+        }
+        let mut own_stake_cells = self
+            .stakes
+            .iter()
+            .filter(|cell| cell.producer_lock_hash.is_none());
+        let stake_cell = own_stake_cells.next();
+        // return an error if more than one stake cell returned
+        if own_stake_cells.next().is_some() {
+            debug!("expected no more than 1 stake cell of the block producer");
+            return Err(Error::InvalidStakeCell);
+        }
+        Ok(stake_cell)
+    }
+
+    /// Stake cells delegated to block producers
+    pub fn delegated_stake_cells(&self) -> impl Iterator<Item = &StakeCell> {
+        self.stakes
+            .iter()
+            .filter(|cell| cell.producer_lock_hash.is_some())
+    }
+}
+
+/// Rollup cells of the transaction
+pub struct RollupCellIndex {
+    pub inputs: RollupCells,
+    pub outputs: RollupCells,
+}
+
+impl RollupCellIndex {
+    pub fn build<S: Syscalls>(
+        syscalls: &S,
+        rollup_type_hash: &H256,
+        config: &RollupConfig,
+    ) -> Result<Self, Error> {
+        let inputs = RollupCells::load(syscalls, rollup_type_hash, config, Source::Input)?;
+        let outputs = RollupCells::load(syscalls, rollup_type_hash, config, Source::Output)?;
+        Ok(RollupCellIndex { inputs, outputs })
+    }
+}
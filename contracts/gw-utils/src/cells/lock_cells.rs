@@ -1,42 +1,36 @@
 //! Lock cells
 
-use super::types::{
-    BurnCell, CellValue, ChallengeCell, CustodianCell, DepositRequestCell, StakeCell,
-    WithdrawalCell,
-};
-use crate::error::Error;
-use alloc::vec::Vec;
-use ckb_std::{
-    ckb_constants::Source,
-    ckb_types::prelude::{Entity as CKBEntity, Unpack as CKBUnpack},
-    debug,
-    high_level::{
-        load_cell_capacity, load_cell_data, load_cell_lock, load_cell_lock_hash, load_cell_type,
-        load_cell_type_hash, QueryIter,
-    },
+use super::{
+    rollup::{is_allowed_token_type_hash, StakeTokenConfig},
+    token::parse_udt_amount,
+    types::{CellValue, Delegation, DepositCall, DepositEntry, DepositFee},
 };
+use crate::{error::Error, syscalls::Syscalls};
+use alloc::{vec, vec::Vec};
+use ckb_std::{ckb_constants::Source, ckb_types::prelude::Entity as CKBEntity, debug};
 use gw_common::{CKB_SUDT_SCRIPT_ARGS, H256};
 use gw_types::{
     bytes::Bytes,
     core::ScriptHashType,
     packed::{
-        Byte32, Byte32Reader, DepositLockArgs, RollupConfig, StakeLockArgs, WithdrawalLockArgs,
-        WithdrawalLockArgsReader,
+        CustodianLockArgs, DepositLockArgs, DepositRequestVec, RollupConfig, Script, StakeLockArgs,
+        StakeLockArgsReader,
     },
     prelude::*,
 };
 
-fn fetch_sudt_script_hash(
+fn fetch_sudt_script_hash<S: Syscalls>(
+    syscalls: &S,
     config: &RollupConfig,
     index: usize,
     source: Source,
 ) -> Result<Option<[u8; 32]>, Error> {
-    match load_cell_type(index, source)? {
+    match syscalls.load_cell_type(index, source)? {
         Some(type_) => {
             if type_.hash_type() == ScriptHashType::Type.into()
-                && type_.code_hash().as_slice() == config.l1_sudt_script_type_hash().as_slice()
+                && is_allowed_token_type_hash(config, type_.code_hash().as_slice())
             {
-                return Ok(load_cell_type_hash(index, source)?);
+                return Ok(syscalls.load_cell_type_hash(index, source)?);
             }
             Err(Error::InvalidSUDTCell)
         }
@@ -44,41 +38,18 @@ fn fetch_sudt_script_hash(
     }
 }
 
-/// used in filter_map
-fn extract_args_from_lock<ArgsType: Entity>(
-    lock: &crate::ckb_std::ckb_types::packed::Script,
-    rollup_type_hash: &H256,
-    lock_script_type_hash: &Byte32,
-) -> Option<Result<ArgsType, Error>> {
-    let lock_args: Bytes = lock.args().unpack();
-    let is_lock = lock_args.len() > 32
-        && &lock_args[..32] == rollup_type_hash.as_slice()
-        && lock.code_hash().as_slice() == lock_script_type_hash.as_slice()
-        && lock.hash_type() == ScriptHashType::Type.into();
-
-    // return none to skip this cell
-    if !is_lock {
-        return None;
-    }
-
-    // parse the remaining lock_args
-    let raw_args = lock_args[32..].to_vec();
-    Some(ArgsType::from_slice(&raw_args).map_err(|_err| Error::Encoding))
-}
-
-/// fetch capacity and SUDT value of a cell
-pub fn fetch_capacity_and_sudt_value(
+/// fetch capacity and SUDT value of a cell,
+/// the type script must be one of the L1 token scripts allowed by the rollup config
+pub fn fetch_capacity_and_sudt_value<S: Syscalls>(
+    syscalls: &S,
     config: &RollupConfig,
     index: usize,
     source: Source,
 ) -> Result<CellValue, Error> {
-    let capacity = load_cell_capacity(index, source)?;
-    let value = match fetch_sudt_script_hash(config, index, source)? {
+    let capacity = syscalls.load_cell_capacity(index, source)?;
+    let value = match fetch_sudt_script_hash(syscalls, config, index, source)? {
         Some(sudt_script_hash) => {
-            let data = load_cell_data(index, source)?;
-            let mut buf = [0u8; 16];
-            buf.copy_from_slice(&data[..16]);
-            let amount = u128::from_le_bytes(buf);
+            let amount = parse_udt_amount(&syscalls.load_cell_data(index, source)?)?;
             CellValue {
                 sudt_script_hash: sudt_script_hash.into(),
                 amount,
@@ -94,220 +65,207 @@ pub fn fetch_capacity_and_sudt_value(
     Ok(value)
 }
 
-pub fn collect_stake_cells(
-    rollup_type_hash: &H256,
-    config: &RollupConfig,
-    source: Source,
-) -> Result<Vec<StakeCell>, Error> {
-    let iter = QueryIter::new(load_cell_lock, source)
-        .enumerate()
-        .filter_map(|(index, lock)| -> Option<Result<StakeCell, _>> {
-            let args = match extract_args_from_lock::<StakeLockArgs>(
-                &lock,
-                rollup_type_hash,
-                &config.stake_script_type_hash(),
-            ) {
-                Some(Ok(args)) => args,
-                Some(Err(err)) => return Some(Err(err)),
-                None => return None,
-            };
-            let value = match fetch_capacity_and_sudt_value(config, index, source) {
-                Ok(value) => value,
-                Err(err) => return Some(Err(err)),
-            };
-            // we only accept CKB as staking assets for now
-            if value.sudt_script_hash != CKB_SUDT_SCRIPT_ARGS.into() || value.amount != 0 {
-                debug!("found a stake cell with simple UDT");
-                return Some(Err(Error::InvalidStakeCell));
+/// The trailing byte of a delegated stake cell's args which requests unbonding
+pub const STAKE_UNBONDING_FLAG: u8 = 1;
+
+/// Parse the stake lock args (without the rollup_type_hash prefix)
+///
+/// A delegated stake cell appends the block producer's `stake_cell_owner_lock_hash` to
+/// `StakeLockArgs`, its `owner_lock_hash` is the delegator which can unbond it.
+/// The delegator requests unbonding by appending `STAKE_UNBONDING_FLAG`, then the block
+/// producer can no longer spend the cell to back new blocks.
+/// Return the args and the delegation of a delegated stake cell.
+pub fn parse_stake_lock_args(
+    raw_args: &[u8],
+) -> Result<(StakeLockArgs, Option<Delegation>), Error> {
+    const DELEGATED_SIZE: usize = StakeLockArgs::TOTAL_SIZE + 32;
+    let unbonding = match raw_args.len() {
+        StakeLockArgs::TOTAL_SIZE | DELEGATED_SIZE => false,
+        len if len == DELEGATED_SIZE + 1 && raw_args[DELEGATED_SIZE] == STAKE_UNBONDING_FLAG => {
+            true
+        }
+        _ => return Err(Error::Encoding),
+    };
+    let args = &raw_args[..StakeLockArgs::TOTAL_SIZE];
+    let delegation = raw_args
+        .get(StakeLockArgs::TOTAL_SIZE..DELEGATED_SIZE)
+        .map(|lock_hash| {
+            let mut producer_lock_hash = [0u8; 32];
+            producer_lock_hash.copy_from_slice(lock_hash);
+            Delegation {
+                producer_lock_hash,
+                unbonding,
             }
-            let cell = StakeCell {
-                index,
-                args,
-                capacity: value.capacity,
-            };
-            Some(Ok(cell))
         });
-    // reject if found multiple stake cells
-    let cells = iter.collect::<Result<Vec<_>, Error>>()?;
-    Ok(cells)
+    match StakeLockArgsReader::verify(args, false) {
+        Ok(()) => Ok((
+            StakeLockArgs::new_unchecked(args.to_vec().into()),
+            delegation,
+        )),
+        Err(_) => Err(Error::Encoding),
+    }
 }
 
-/// Find block producer's stake cell
-/// this function return Option<StakeCell> if we have 1 or zero stake cell,
-/// otherwise return an error.
-pub fn find_block_producer_stake_cell(
-    rollup_type_hash: &H256,
-    config: &RollupConfig,
-    source: Source,
-    owner_lock_hash: &Byte32Reader,
-) -> Result<Option<StakeCell>, Error> {
-    let mut cells = collect_stake_cells(rollup_type_hash, config, source)?;
-    // return an error if more than one stake cell returned
-    if cells.len() > 1 {
-        debug!(
-            "expected no more than 1 stake cell from {:?}, found {}",
-            source,
-            cells.len()
-        );
-        return Err(Error::InvalidStakeCell);
+/// Split the molecule table args and the trailing bytes
+fn split_args_extension<T: Entity>(raw_args: &[u8]) -> Result<(T, Bytes), Error> {
+    if raw_args.len() < 4 {
+        return Err(Error::Encoding);
     }
-    if cells
-        .iter()
-        .any(|cell| cell.args.owner_lock_hash().as_slice() != owner_lock_hash.as_slice())
-    {
-        debug!("found stake cell with unexpected owner_lock_hash");
-        return Err(Error::InvalidStakeCell);
+    let mut total_size = [0u8; 4];
+    total_size.copy_from_slice(&raw_args[..4]);
+    let total_size = u32::from_le_bytes(total_size) as usize;
+    if raw_args.len() < total_size {
+        return Err(Error::Encoding);
     }
-    Ok(cells.pop())
+    let args = T::from_slice(&raw_args[..total_size]).map_err(|_err| Error::Encoding)?;
+    Ok((args, raw_args[total_size..].to_vec().into()))
 }
 
-pub fn find_challenge_cell(
-    rollup_type_hash: &H256,
-    config: &RollupConfig,
-    source: Source,
-) -> Result<Option<ChallengeCell>, Error> {
-    let iter = QueryIter::new(load_cell_lock, source)
-        .enumerate()
-        .filter_map(|(index, lock)| {
-            let args = match extract_args_from_lock(
-                &lock,
-                rollup_type_hash,
-                &config.challenge_script_type_hash(),
-            ) {
-                Some(Ok(args)) => args,
-                Some(Err(err)) => return Some(Err(err)),
-                None => return None,
-            };
-            let value = match fetch_capacity_and_sudt_value(config, index, source) {
-                Ok(value) => value,
-                Err(err) => {
-                    return Some(Err(err));
-                }
-            };
-            if value.sudt_script_hash != CKB_SUDT_SCRIPT_ARGS.into() || value.amount != 0 {
-                debug!("found a challenge cell with simple UDT");
-                return None;
-            }
-            let cell = ChallengeCell { index, args, value };
-            Some(Ok(cell))
-        })
-        .take(2);
-    // reject if found multiple stake cells
-    let mut cells = iter.collect::<Result<Vec<_>, Error>>()?;
-    if cells.len() > 1 {
-        return Err(Error::InvalidChallengeCell);
-    }
-    Ok(cells.pop())
+/// Parse the deposit lock args (without the rollup_type_hash prefix),
+/// return the args and the deposit extension which is appended to `DepositLockArgs`
+pub fn parse_deposit_lock_args(raw_args: &[u8]) -> Result<(DepositLockArgs, Bytes), Error> {
+    split_args_extension(raw_args)
 }
 
-pub fn collect_withdrawal_locks(
-    rollup_type_hash: &H256,
-    config: &RollupConfig,
-    source: Source,
-) -> Result<Vec<WithdrawalCell>, Error> {
-    QueryIter::new(load_cell_lock, source)
-        .enumerate()
-        .filter_map(|(index, lock)| {
-            let lock_args: Bytes = lock.args().unpack();
-            let is_withdrawal_lock = lock_args.len() > 32
-                && &lock_args[..32] == rollup_type_hash.as_slice()
-                && lock.code_hash().as_slice() == config.withdrawal_script_type_hash().as_slice()
-                && lock.hash_type() == ScriptHashType::Type.into();
-            if !is_withdrawal_lock {
-                return None;
-            }
-            let raw_args = lock_args[32..].to_vec();
-            let args = match WithdrawalLockArgsReader::verify(&raw_args, false) {
-                Ok(_) => WithdrawalLockArgs::new_unchecked(raw_args.into()),
-                Err(_) => {
-                    return Some(Err(Error::Encoding));
-                }
-            };
-            let value = match fetch_capacity_and_sudt_value(config, index, source) {
-                Ok(value) => value,
-                Err(err) => return Some(Err(err)),
-            };
-            Some(Ok(WithdrawalCell { index, args, value }))
-        })
-        .collect::<Result<_, Error>>()
+/// Parse the custodian lock args (without the rollup_type_hash prefix),
+/// the extension of the deposit is appended to `CustodianLockArgs`
+pub fn parse_custodian_lock_args(raw_args: &[u8]) -> Result<(CustodianLockArgs, Bytes), Error> {
+    split_args_extension(raw_args)
 }
 
-pub fn collect_custodian_locks(
-    rollup_type_hash: &H256,
-    config: &RollupConfig,
-    source: Source,
-) -> Result<Vec<CustodianCell>, Error> {
-    QueryIter::new(load_cell_lock, source)
-        .enumerate()
-        .filter_map(|(index, lock)| {
-            let args = match extract_args_from_lock(
-                &lock,
-                rollup_type_hash,
-                &config.custodian_script_type_hash(),
-            ) {
-                Some(Ok(args)) => args,
-                Some(Err(err)) => return Some(Err(err)),
-                None => return None,
-            };
-            let value = match fetch_capacity_and_sudt_value(config, index, source) {
-                Ok(value) => value,
-                Err(err) => return Some(Err(err)),
-            };
-            let cell = CustodianCell { index, args, value };
-            Some(Ok(cell))
+/// Flags of the deposit extension
+const DEPOSIT_EXTENSION_CALL: u8 = 0b01;
+const DEPOSIT_EXTENSION_FEE: u8 = 0b10;
+
+/// Parse the items of a single deposit extension
+///
+/// The extension is either empty or `flags (u8) | items`, an item is present if its flag is set.
+/// The items of a fixed size come first: the fee `sudt_id (u32 LE) | amount (u128 LE)`
+/// is present if the flag `0b10` is set. The call `to_id (u32 LE) | call args` takes
+/// the remaining bytes and is present if the flag `0b01` is set. Unknown flags are rejected.
+fn parse_deposit_items(
+    extension: &[u8],
+) -> Result<(Option<DepositCall>, Option<DepositFee>), Error> {
+    let (flags, mut rest) = match extension.split_first() {
+        None => return Ok((None, None)),
+        Some((flags, rest)) => (*flags, rest),
+    };
+    if flags & !(DEPOSIT_EXTENSION_CALL | DEPOSIT_EXTENSION_FEE) != 0 {
+        debug!("unknown deposit extension flags {}", flags);
+        return Err(Error::Encoding);
+    }
+    let fee = if flags & DEPOSIT_EXTENSION_FEE != 0 {
+        if rest.len() < 20 {
+            return Err(Error::Encoding);
+        }
+        let mut sudt_id = [0u8; 4];
+        sudt_id.copy_from_slice(&rest[..4]);
+        let mut amount = [0u8; 16];
+        amount.copy_from_slice(&rest[4..20]);
+        rest = &rest[20..];
+        Some(DepositFee {
+            sudt_id: u32::from_le_bytes(sudt_id),
+            amount: u128::from_le_bytes(amount),
         })
-        .collect::<Result<_, Error>>()
+    } else {
+        None
+    };
+    if flags & DEPOSIT_EXTENSION_CALL == 0 {
+        if !rest.is_empty() {
+            debug!("unexpected trailing bytes of the deposit extension");
+            return Err(Error::Encoding);
+        }
+        return Ok((None, fee));
+    }
+    if rest.len() < 4 {
+        return Err(Error::Encoding);
+    }
+    let mut to_id = [0u8; 4];
+    to_id.copy_from_slice(&rest[..4]);
+    let call = DepositCall {
+        to_id: u32::from_le_bytes(to_id),
+        args: rest[4..].to_vec().into(),
+    };
+    Ok((Some(call), fee))
 }
 
-pub fn collect_deposit_locks(
-    rollup_type_hash: &H256,
-    config: &RollupConfig,
-    source: Source,
-) -> Result<Vec<DepositRequestCell>, Error> {
-    QueryIter::new(load_cell_lock, source)
-        .enumerate()
-        .filter_map(|(index, lock)| {
-            let args: DepositLockArgs = match extract_args_from_lock(
-                &lock,
-                rollup_type_hash,
-                &config.deposit_script_type_hash(),
-            ) {
-                Some(Ok(args)) => args,
-                Some(Err(err)) => return Some(Err(err)),
-                None => return None,
-            };
-            let value = match fetch_capacity_and_sudt_value(config, index, source) {
-                Ok(value) => value,
-                Err(err) => return Some(Err(err)),
-            };
-            let account_script = args.layer2_lock();
-            let account_script_hash = account_script.hash().into();
-            let cell = DepositRequestCell {
-                index,
-                args,
-                value,
-                account_script,
-                account_script_hash,
-            };
-            Some(Ok(cell))
-        })
-        .collect::<Result<_, Error>>()
+/// Parse the layer2 accounts credited by a deposit cell, the optional deposit call and fee
+///
+/// A single deposit credits `layer2_lock` with the cell value, the extension carries
+/// the items of the deposit, see `parse_deposit_items`.
+/// A batch deposit has the default `layer2_lock`, the extension is a `DepositRequestVec`
+/// in which every entry has the sUDT of the cell and the entries sum up to the cell value.
+/// A batch deposit can't carry a call or a fee.
+pub fn parse_deposit_extension(
+    args: &DepositLockArgs,
+    extension: &[u8],
+    value: &CellValue,
+) -> Result<(Vec<DepositEntry>, Option<DepositCall>, Option<DepositFee>), Error> {
+    let layer2_lock = args.layer2_lock();
+    if layer2_lock.as_slice() != Script::default().as_slice() {
+        let entry = DepositEntry {
+            account_script_hash: layer2_lock.hash().into(),
+            account_script: layer2_lock,
+            capacity: value.capacity,
+            amount: value.amount,
+        };
+        let (call, fee) = parse_deposit_items(extension)?;
+        return Ok((vec![entry], call, fee));
+    }
+
+    // batch deposit
+    let requests = DepositRequestVec::from_slice(extension).map_err(|_err| Error::Encoding)?;
+    if requests.is_empty() {
+        debug!("empty batch deposit");
+        return Err(Error::InvalidDepositCell);
+    }
+    let mut total_capacity = 0u64;
+    let mut total_amount = 0u128;
+    let mut entries = Vec::with_capacity(requests.len());
+    for request in requests.into_iter() {
+        if request.sudt_script_hash().as_slice() != value.sudt_script_hash.as_slice() {
+            debug!("batch deposit entry mismatch the sUDT of the cell");
+            return Err(Error::InvalidDepositCell);
+        }
+        let capacity: u64 = request.capacity().unpack();
+        let amount: u128 = request.amount().unpack();
+        total_capacity = total_capacity
+            .checked_add(capacity)
+            .ok_or(Error::AmountOverflow)?;
+        total_amount = total_amount
+            .checked_add(amount)
+            .ok_or(Error::AmountOverflow)?;
+        let account_script = request.script();
+        entries.push(DepositEntry {
+            account_script_hash: account_script.hash().into(),
+            account_script,
+            capacity,
+            amount,
+        });
+    }
+    if total_capacity != value.capacity || total_amount != value.amount {
+        debug!("the total of batch deposit entries mismatch the cell value");
+        return Err(Error::InvalidDepositCell);
+    }
+    Ok((entries, None, None))
 }
 
-pub fn collect_burn_cells(config: &RollupConfig, source: Source) -> Result<Vec<BurnCell>, Error> {
-    QueryIter::new(load_cell_lock_hash, source)
-        .enumerate()
-        .filter_map(|(index, lock_hash)| {
-            let is_lock = lock_hash == config.burn_lock_hash().as_slice();
-            if !is_lock {
-                return None;
-            }
-            let value = match fetch_capacity_and_sudt_value(config, index, source) {
-                Ok(value) => value,
-                Err(err) => return Some(Err(err)),
-            };
-            let cell = BurnCell { index, value };
-            Some(Ok(cell))
-        })
-        .collect::<Result<_, Error>>()
+/// Return the sUDT amount of a stake cell,
+/// a stake cell holds CKB only or the sUDT configured as stake collateral
+pub fn stake_cell_amount(
+    stake_token: Option<&StakeTokenConfig>,
+    value: &CellValue,
+) -> Result<u128, Error> {
+    if value.sudt_script_hash == CKB_SUDT_SCRIPT_ARGS.into() && value.amount == 0 {
+        return Ok(0);
+    }
+    match stake_token {
+        Some(token) if value.sudt_script_hash.as_slice() == &token.sudt_script_hash[..] => {
+            Ok(value.amount)
+        }
+        _ => {
+            debug!("found a stake cell with unexpected simple UDT");
+            Err(Error::InvalidStakeCell)
+        }
+    }
 }
@@ -1,5 +1,8 @@
+pub mod dao;
+pub mod index;
 pub mod lock_cells;
 pub mod rollup;
+pub mod solvency;
 pub mod token;
 pub mod types;
 pub mod utils;
@@ -1,77 +1,433 @@
-use ckb_std::{
-    ckb_constants::Source,
-    debug,
-    high_level::{load_cell_data, load_cell_data_hash, load_cell_type_hash, QueryIter},
-    syscalls::{load_witness, SysError},
-};
+use alloc::{collections::BTreeMap, vec::Vec};
+use ckb_std::{ckb_constants::Source, high_level::QueryIter, syscalls::SysError};
 use gw_types::{
-    packed::{
-        GlobalState, GlobalStateReader, RollupActionReader, RollupConfig, RollupConfigReader,
-        WitnessArgsReader,
-    },
+    packed::{Byte32VecReader, GlobalState, RollupConfig, RollupConfigReader},
     prelude::*,
 };
 
-use crate::error::Error;
+use super::solvency::parse_global_state;
+use crate::{error::Error, syscalls::Syscalls};
+
+const NUMBER_SIZE: usize = 4;
+const BYTE32_SIZE: usize = 32;
+/// Number of the RollupConfig fields in the godwoken schema
+const ROLLUP_CONFIG_FIELDS: usize = 14;
+/// Field indexes of the extension fields
+const ALLOWED_TOKEN_TYPE_HASHES: usize = ROLLUP_CONFIG_FIELDS;
+const DAO_SCRIPT_TYPE_HASH: usize = ROLLUP_CONFIG_FIELDS + 1;
+const DAO_BENEFICIARY_LOCK_HASH: usize = ROLLUP_CONFIG_FIELDS + 2;
+const STAKE_TVL_RATE: usize = ROLLUP_CONFIG_FIELDS + 3;
+const STAKE_WITHDRAWAL_RATE: usize = ROLLUP_CONFIG_FIELDS + 4;
+const STAKE_UNBONDING_BLOCKS: usize = ROLLUP_CONFIG_FIELDS + 5;
+const SLASH_CAPACITY: usize = ROLLUP_CONFIG_FIELDS + 6;
+const SLASH_RATE: usize = ROLLUP_CONFIG_FIELDS + 7;
+const SLASH_PER_REVERTED_BLOCK: usize = ROLLUP_CONFIG_FIELDS + 8;
+const STAKE_SUDT_SCRIPT_HASH: usize = ROLLUP_CONFIG_FIELDS + 9;
+const REQUIRED_STAKING_SUDT_AMOUNT: usize = ROLLUP_CONFIG_FIELDS + 10;
+const DEPOSIT_SUDT_SCRIPT_HASHES: usize = ROLLUP_CONFIG_FIELDS + 11;
+const MIN_DEPOSIT_CAPACITY: usize = ROLLUP_CONFIG_FIELDS + 12;
+const MIN_DEPOSIT_AMOUNT: usize = ROLLUP_CONFIG_FIELDS + 13;
+const DEPOSIT_CAPS: usize = ROLLUP_CONFIG_FIELDS + 14;
+/// Size of a deposit cap: sudt_script_hash | amount (u128)
+const DEPOSIT_CAP_SIZE: usize = 48;
+
+/// Denominator of the stake rates, the rates are in basis points
+pub const STAKE_RATE_DENOMINATOR: u128 = 10000;
+
+fn read_number(data: &[u8], offset: usize) -> Option<usize> {
+    let mut buf = [0u8; NUMBER_SIZE];
+    buf.copy_from_slice(data.get(offset..offset + NUMBER_SIZE)?);
+    Some(u32::from_le_bytes(buf) as usize)
+}
+
+/// Read an extra field appended to the RollupConfig table
+///
+/// The godwoken schema doesn't define the extension fields, a config cell adds them as
+/// extra fields of the table (molecule compatible mode), so configs without them are still valid.
+fn extension_field(config: &RollupConfig, field_index: usize) -> Result<Option<&[u8]>, Error> {
+    let data = config.as_slice();
+    // RollupConfigReader verified the header, the offsets are in order
+    let header_size = read_number(data, NUMBER_SIZE).ok_or(Error::Encoding)?;
+    let field_count = header_size / NUMBER_SIZE - 1;
+    if field_count <= field_index {
+        return Ok(None);
+    }
+    let start = read_number(data, NUMBER_SIZE * (field_index + 1)).ok_or(Error::Encoding)?;
+    let end = if field_count > field_index + 1 {
+        read_number(data, NUMBER_SIZE * (field_index + 2)).ok_or(Error::Encoding)?
+    } else {
+        data.len()
+    };
+    data.get(start..end).map(Some).ok_or(Error::Encoding)
+}
+
+/// The optional `allowed_token_type_hashes: Byte32Vec` field appended to RollupConfig
+pub fn allowed_token_type_hashes(config: &RollupConfig) -> Result<Option<Byte32VecReader>, Error> {
+    match extension_field(config, ALLOWED_TOKEN_TYPE_HASHES)? {
+        Some(field) => match Byte32VecReader::verify(field, false) {
+            Ok(()) => Ok(Some(Byte32VecReader::new_unchecked(field))),
+            Err(_) => Err(Error::Encoding),
+        },
+        None => Ok(None),
+    }
+}
+
+/// Nervos DAO config of the custodian cells
+pub struct DAOConfig {
+    /// type hash of the Nervos DAO script
+    pub script_type_hash: [u8; 32],
+    /// lock hash of the cells which receive the DAO interest
+    pub beneficiary_lock_hash: [u8; 32],
+}
+
+/// Read a fixed size extension field into the buffer, return false if the field is absent
+///
+/// An empty field is treated as absent, so a config can skip it to set later fields
+fn read_fixed_field(
+    config: &RollupConfig,
+    field_index: usize,
+    buf: &mut [u8],
+) -> Result<bool, Error> {
+    match extension_field(config, field_index)? {
+        Some([]) | None => Ok(false),
+        Some(field) if field.len() == buf.len() => {
+            buf.copy_from_slice(field);
+            Ok(true)
+        }
+        Some(_) => Err(Error::Encoding),
+    }
+}
+
+fn read_byte32_field(config: &RollupConfig, field_index: usize) -> Result<Option<[u8; 32]>, Error> {
+    let mut hash = [0u8; BYTE32_SIZE];
+    if read_fixed_field(config, field_index, &mut hash)? {
+        Ok(Some(hash))
+    } else {
+        Ok(None)
+    }
+}
+
+fn read_u8_field(config: &RollupConfig, field_index: usize) -> Result<Option<u8>, Error> {
+    let mut buf = [0u8; 1];
+    if read_fixed_field(config, field_index, &mut buf)? {
+        Ok(Some(buf[0]))
+    } else {
+        Ok(None)
+    }
+}
 
-/// 524_288 we choose this value because it is smaller than the MAX_BLOCK_BYTES which is 597K
-pub const MAX_ROLLUP_WITNESS_SIZE: usize = 1 << 19;
+fn read_u32_field(config: &RollupConfig, field_index: usize) -> Result<Option<u32>, Error> {
+    let mut buf = [0u8; NUMBER_SIZE];
+    if read_fixed_field(config, field_index, &mut buf)? {
+        Ok(Some(u32::from_le_bytes(buf)))
+    } else {
+        Ok(None)
+    }
+}
 
-pub fn search_rollup_cell(rollup_type_hash: &[u8; 32], source: Source) -> Option<usize> {
-    QueryIter::new(load_cell_type_hash, source)
-        .position(|type_hash| type_hash.as_ref() == Some(rollup_type_hash))
+fn read_u64_field(config: &RollupConfig, field_index: usize) -> Result<Option<u64>, Error> {
+    let mut buf = [0u8; 8];
+    if read_fixed_field(config, field_index, &mut buf)? {
+        Ok(Some(u64::from_le_bytes(buf)))
+    } else {
+        Ok(None)
+    }
 }
 
-fn search_rollup_config_cell(rollup_config_hash: &[u8; 32]) -> Option<usize> {
-    QueryIter::new(load_cell_data_hash, Source::CellDep)
-        .position(|data_hash| data_hash.as_ref() == rollup_config_hash)
+fn read_u128_field(config: &RollupConfig, field_index: usize) -> Result<Option<u128>, Error> {
+    let mut buf = [0u8; 16];
+    if read_fixed_field(config, field_index, &mut buf)? {
+        Ok(Some(u128::from_le_bytes(buf)))
+    } else {
+        Ok(None)
+    }
 }
 
-pub fn load_rollup_config(rollup_config_hash: &[u8; 32]) -> Result<RollupConfig, Error> {
-    let index = search_rollup_config_cell(rollup_config_hash).ok_or(Error::RollupConfigNotFound)?;
-    let data = load_cell_data(index, Source::CellDep)?;
-    match RollupConfigReader::verify(&data, false) {
-        Ok(_) => Ok(RollupConfig::new_unchecked(data.into())),
-        Err(_) => Err(Error::Encoding),
+/// The optional `dao_script_type_hash: Byte32` and `dao_beneficiary_lock_hash: Byte32` fields
+/// appended to RollupConfig after `allowed_token_type_hashes`
+///
+/// Finalized custodian cells can be deposited into the Nervos DAO only if both fields exist.
+pub fn dao_config(config: &RollupConfig) -> Result<Option<DAOConfig>, Error> {
+    let script_type_hash = read_byte32_field(config, DAO_SCRIPT_TYPE_HASH)?;
+    let beneficiary_lock_hash = read_byte32_field(config, DAO_BENEFICIARY_LOCK_HASH)?;
+    match (script_type_hash, beneficiary_lock_hash) {
+        (Some(script_type_hash), Some(beneficiary_lock_hash)) => Ok(Some(DAOConfig {
+            script_type_hash,
+            beneficiary_lock_hash,
+        })),
+        (None, None) => Ok(None),
+        _ => Err(Error::Encoding),
     }
 }
 
-pub fn search_rollup_state(
+/// Dynamic stake rule of the block producer
+///
+/// The stake cell of a submitted block must hold at least:
+/// `required_staking_capacity + tvl * tvl_rate / 10000 + withdrawn * withdrawal_rate / 10000`,
+/// `tvl` is the CKB recorded by the custodian assets accumulator of the rollup cell and
+/// `withdrawn` is the CKB withdrawn by the block.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct StakeRates {
+    /// basis points of the custodian CKB
+    pub tvl_rate: u32,
+    /// basis points of the CKB withdrawn by the block
+    pub withdrawal_rate: u32,
+}
+
+impl StakeRates {
+    /// Calculate the required stake capacity, return None if it overflows
+    pub fn required_capacity(&self, base: u64, tvl: u128, withdrawn: u128) -> Option<u128> {
+        let tvl_stake = tvl.checked_mul(self.tvl_rate.into())? / STAKE_RATE_DENOMINATOR;
+        let withdrawal_stake =
+            withdrawn.checked_mul(self.withdrawal_rate.into())? / STAKE_RATE_DENOMINATOR;
+        u128::from(base)
+            .checked_add(tvl_stake)?
+            .checked_add(withdrawal_stake)
+    }
+}
+
+/// The optional `stake_tvl_rate: Uint32` and `stake_withdrawal_rate: Uint32` fields
+/// appended to RollupConfig after the DAO fields, an absent rate is zero
+pub fn stake_rates(config: &RollupConfig) -> Result<StakeRates, Error> {
+    Ok(StakeRates {
+        tvl_rate: read_u32_field(config, STAKE_TVL_RATE)?.unwrap_or(0),
+        withdrawal_rate: read_u32_field(config, STAKE_WITHDRAWAL_RATE)?.unwrap_or(0),
+    })
+}
+
+/// The optional `stake_unbonding_blocks: Uint64` field appended to RollupConfig after the stake rates
+///
+/// A stake cell can be fully unlocked by its owner only after this many L1 blocks since it was
+/// created, i.e. since the owner's last submitted block. An absent field is zero.
+pub fn stake_unbonding_blocks(config: &RollupConfig) -> Result<u64, Error> {
+    Ok(read_u64_field(config, STAKE_UNBONDING_BLOCKS)?.unwrap_or(0))
+}
+
+/// Slashing schedule of the stake which backs the reverted blocks
+///
+/// A producer's own stake cell loses `min(stake, capacity + stake * rate / 10000)`,
+/// multiplied by the number of the producer's reverted blocks if `per_reverted_block` is set.
+/// The rest is returned to the producer.
+///
+/// Only the producer's own stake is slashed. The delegated stake cells count towards the
+/// required stake of a block, but they aren't bound to the blocks they back, so a revert
+/// can't require them and doesn't accept them.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SlashSchedule {
+    /// fixed slashed capacity
+    pub capacity: u64,
+    /// basis points of the stake
+    pub rate: u32,
+    /// scale the slashed capacity by the number of reverted blocks
+    pub per_reverted_block: bool,
+}
+
+impl SlashSchedule {
+    /// Calculate the slashed capacity of a producer's own stake
+    pub fn slashed_capacity(&self, stake: u128, reverted_blocks: u64) -> u128 {
+        let scale = if self.per_reverted_block {
+            reverted_blocks.into()
+        } else {
+            1
+        };
+        let slashed = u128::from(self.capacity)
+            .saturating_add(stake.saturating_mul(self.rate.into()) / STAKE_RATE_DENOMINATOR)
+            .saturating_mul(scale);
+        core::cmp::min(stake, slashed)
+    }
+}
+
+/// The optional `slash_capacity: Uint64`, `slash_rate: Uint32` and `slash_per_reverted_block: Byte`
+/// fields appended to RollupConfig after `stake_unbonding_blocks`
+///
+/// Return None if neither the capacity nor the rate is set, the whole stake is forfeited.
+pub fn slash_schedule(config: &RollupConfig) -> Result<Option<SlashSchedule>, Error> {
+    let capacity = read_u64_field(config, SLASH_CAPACITY)?;
+    let rate = read_u32_field(config, SLASH_RATE)?;
+    let per_reverted_block = match read_u8_field(config, SLASH_PER_REVERTED_BLOCK)? {
+        Some(0) | None => false,
+        Some(1) => true,
+        Some(_) => return Err(Error::Encoding),
+    };
+    if capacity.is_none() && rate.is_none() {
+        return Ok(None);
+    }
+    Ok(Some(SlashSchedule {
+        capacity: capacity.unwrap_or(0),
+        rate: rate.unwrap_or(0),
+        per_reverted_block,
+    }))
+}
+
+/// sUDT accepted as stake collateral besides CKB
+pub struct StakeTokenConfig {
+    /// script hash of the sUDT type script
+    pub sudt_script_hash: [u8; 32],
+    /// required sUDT amount of the block producer's stake
+    pub required_amount: u128,
+}
+
+/// The optional `stake_sudt_script_hash: Byte32` and `required_staking_sudt_amount: Uint128`
+/// fields appended to RollupConfig after the slashing schedule
+///
+/// The sUDT must also be an L1 token allowed by the rollup config.
+pub fn stake_token_config(config: &RollupConfig) -> Result<Option<StakeTokenConfig>, Error> {
+    let sudt_script_hash = read_byte32_field(config, STAKE_SUDT_SCRIPT_HASH)?;
+    let required_amount = read_u128_field(config, REQUIRED_STAKING_SUDT_AMOUNT)?;
+    match (sudt_script_hash, required_amount) {
+        (Some(sudt_script_hash), Some(required_amount)) => Ok(Some(StakeTokenConfig {
+            sudt_script_hash,
+            required_amount,
+        })),
+        (None, None) => Ok(None),
+        _ => Err(Error::Encoding),
+    }
+}
+
+/// Deposit limits of the rollup
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DepositLimits {
+    /// sUDT script hashes accepted by deposits, None if every sUDT is accepted
+    pub sudt_script_hashes: Option<Vec<[u8; 32]>>,
+    /// minimal capacity of a deposit which creates a new account
+    pub min_capacity: u64,
+    /// minimal sUDT amount of a sUDT deposit which creates a new account
+    pub min_amount: u128,
+    /// max total of a token deposited in a block, CKB is keyed by `CKB_SUDT_SCRIPT_ARGS`
+    pub caps: BTreeMap<[u8; 32], u128>,
+}
+
+impl DepositLimits {
+    pub fn is_allowed_sudt(&self, sudt_script_hash: &[u8]) -> bool {
+        match self.sudt_script_hashes {
+            Some(ref hashes) => hashes.iter().any(|hash| &hash[..] == sudt_script_hash),
+            None => true,
+        }
+    }
+}
+
+/// The optional deposit limits appended to RollupConfig after the stake sUDT:
+///
+/// * `deposit_sudt_script_hashes: Byte32Vec`, every sUDT is accepted if the field is absent
+/// * `min_deposit_capacity: Uint64`
+/// * `min_deposit_amount: Uint128`
+/// * `deposit_caps`: item count (u32) | [sudt_script_hash (32 bytes) | amount (u128)]
+pub fn deposit_limits(config: &RollupConfig) -> Result<DepositLimits, Error> {
+    let sudt_script_hashes = match extension_field(config, DEPOSIT_SUDT_SCRIPT_HASHES)? {
+        Some([]) | None => None,
+        Some(field) => match Byte32VecReader::verify(field, false) {
+            Ok(()) => Some(
+                Byte32VecReader::new_unchecked(field)
+                    .to_entity()
+                    .into_iter()
+                    .map(|hash| hash.unpack())
+                    .collect(),
+            ),
+            Err(_) => return Err(Error::Encoding),
+        },
+    };
+    let min_capacity = read_u64_field(config, MIN_DEPOSIT_CAPACITY)?.unwrap_or(0);
+    let min_amount = read_u128_field(config, MIN_DEPOSIT_AMOUNT)?.unwrap_or(0);
+    let mut caps = BTreeMap::new();
+    match extension_field(config, DEPOSIT_CAPS)? {
+        Some([]) | None => {}
+        Some(field) => {
+            let count = read_number(field, 0).ok_or(Error::Encoding)?;
+            let items = &field[NUMBER_SIZE..];
+            if count.checked_mul(DEPOSIT_CAP_SIZE) != Some(items.len()) {
+                return Err(Error::Encoding);
+            }
+            for item in items.chunks_exact(DEPOSIT_CAP_SIZE) {
+                let mut sudt_script_hash = [0u8; 32];
+                sudt_script_hash.copy_from_slice(&item[..32]);
+                let mut amount = [0u8; 16];
+                amount.copy_from_slice(&item[32..]);
+                if caps
+                    .insert(sudt_script_hash, u128::from_le_bytes(amount))
+                    .is_some()
+                {
+                    return Err(Error::Encoding);
+                }
+            }
+        }
+    }
+    Ok(DepositLimits {
+        sudt_script_hashes,
+        min_capacity,
+        min_amount,
+        caps,
+    })
+}
+
+/// Check the code hash of an L1 token type script (hash_type Type) is allowed by the rollup
+pub fn is_allowed_token_type_hash(config: &RollupConfig, code_hash: &[u8]) -> bool {
+    if config.l1_sudt_script_type_hash().as_slice() == code_hash {
+        return true;
+    }
+    match allowed_token_type_hashes(config) {
+        Ok(Some(hashes)) => hashes.iter().any(|hash| hash.as_slice() == code_hash),
+        _ => false,
+    }
+}
+
+pub fn search_rollup_cell<S: Syscalls>(
+    syscalls: &S,
+    rollup_type_hash: &[u8; 32],
+    source: Source,
+) -> Option<usize> {
+    QueryIter::new(
+        |index, source| syscalls.load_cell_type_hash(index, source),
+        source,
+    )
+    .position(|type_hash| type_hash.as_ref() == Some(rollup_type_hash))
+}
+
+fn search_rollup_config_cell<S: Syscalls>(
+    syscalls: &S,
+    rollup_config_hash: &[u8; 32],
+) -> Option<usize> {
+    QueryIter::new(
+        |index, source| syscalls.load_cell_data_hash(index, source),
+        Source::CellDep,
+    )
+    .position(|data_hash| data_hash.as_ref() == rollup_config_hash)
+}
+
+pub fn load_rollup_config<S: Syscalls>(
+    syscalls: &S,
+    rollup_config_hash: &[u8; 32],
+) -> Result<RollupConfig, Error> {
+    let index = search_rollup_config_cell(syscalls, rollup_config_hash)
+        .ok_or(Error::RollupConfigNotFound)?;
+    let data = syscalls.load_cell_data(index, Source::CellDep)?;
+    // accept the extension fields
+    if RollupConfigReader::verify(&data, true).is_err() {
+        return Err(Error::Encoding);
+    }
+    let config = RollupConfig::new_unchecked(data.into());
+    allowed_token_type_hashes(&config)?;
+    dao_config(&config)?;
+    stake_rates(&config)?;
+    stake_unbonding_blocks(&config)?;
+    slash_schedule(&config)?;
+    stake_token_config(&config)?;
+    deposit_limits(&config)?;
+    Ok(config)
+}
+
+pub fn search_rollup_state<S: Syscalls>(
+    syscalls: &S,
     rollup_type_hash: &[u8; 32],
     source: Source,
 ) -> Result<Option<GlobalState>, SysError> {
-    let index = match QueryIter::new(load_cell_type_hash, source)
-        .position(|type_hash| type_hash.as_ref() == Some(rollup_type_hash))
-    {
+    let index = match search_rollup_cell(syscalls, rollup_type_hash, source) {
         Some(i) => i,
         None => return Ok(None),
     };
-    let data = load_cell_data(index, source)?;
-    match GlobalStateReader::verify(&data, false) {
-        Ok(()) => Ok(Some(GlobalState::new_unchecked(data.into()))),
+    let data = syscalls.load_cell_data(index, source)?;
+    // the custodian assets after the GlobalState are ignored
+    match parse_global_state(&data) {
+        Ok(global_state) => Ok(Some(global_state)),
         Err(_) => Err(SysError::Encoding),
     }
 }
-
-pub fn parse_rollup_action(
-    buf: &mut [u8; MAX_ROLLUP_WITNESS_SIZE],
-    index: usize,
-    source: Source,
-) -> Result<RollupActionReader, Error> {
-    let loaded_len = load_witness(buf, 0, index, source)?;
-    debug!("load rollup witness, loaded len: {}", loaded_len);
-
-    let witness_args = WitnessArgsReader::from_slice(&buf[..loaded_len]).map_err(|_err| {
-        debug!("witness is not a valid WitnessArgsReader");
-        Error::Encoding
-    })?;
-    let output = witness_args.output_type().to_opt().ok_or_else(|| {
-        debug!("WitnessArgs#output_type is none");
-        Error::Encoding
-    })?;
-    let action = RollupActionReader::from_slice(output.raw_data()).map_err(|_err| {
-        debug!("output is not a valid RollupActionReader");
-        Error::Encoding
-    })?;
-    Ok(action)
-}
@@ -0,0 +1,119 @@
+//! Custodian assets accumulator
+//!
+//! The rollup cell data may carry the total assets of the custodian cells after the GlobalState,
+//! so indexers and auditors can check the custodian solvency from the rollup cell:
+//!
+//! ```text
+//! rollup cell data: GlobalState | CustodianAssets (optional)
+//! CustodianAssets: item count (u32) | [sudt_script_hash (32 bytes) | amount (u128)]
+//! ```
+//!
+//! The godwoken schema doesn't define CustodianAssets, the layout is a molecule fixvec.
+//! Items are sorted by the bytes of `sudt_script_hash` without duplicates and zero amounts,
+//! CKB is recorded as `CKB_SUDT_SCRIPT_ARGS` in shannons.
+//! Custodian cells in the Nervos DAO are counted at their original capacity.
+
+use crate::error::Error;
+use alloc::{collections::BTreeMap, vec::Vec};
+use gw_common::H256;
+use gw_types::packed::{GlobalState, GlobalStateReader};
+use gw_types::prelude::*;
+
+const NUMBER_SIZE: usize = 4;
+const ITEM_SIZE: usize = 48;
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CustodianAssets(BTreeMap<[u8; 32], u128>);
+
+impl CustodianAssets {
+    pub fn from_slice(data: &[u8]) -> Result<Self, Error> {
+        if data.len() < NUMBER_SIZE {
+            return Err(Error::Encoding);
+        }
+        let mut buf = [0u8; NUMBER_SIZE];
+        buf.copy_from_slice(&data[..NUMBER_SIZE]);
+        let count = u32::from_le_bytes(buf) as usize;
+        let items = &data[NUMBER_SIZE..];
+        if count.checked_mul(ITEM_SIZE) != Some(items.len()) {
+            return Err(Error::Encoding);
+        }
+        let mut assets = BTreeMap::new();
+        let mut last_hash: Option<[u8; 32]> = None;
+        for item in items.chunks_exact(ITEM_SIZE) {
+            let mut hash = [0u8; 32];
+            hash.copy_from_slice(&item[..32]);
+            let mut amount = [0u8; 16];
+            amount.copy_from_slice(&item[32..]);
+            let amount = u128::from_le_bytes(amount);
+            // sorted, no duplicates and no zero amounts
+            if last_hash.map(|last| last >= hash).unwrap_or(false) || amount == 0 {
+                return Err(Error::InvalidCustodianAssets);
+            }
+            last_hash = Some(hash);
+            assets.insert(hash, amount);
+        }
+        Ok(CustodianAssets(assets))
+    }
+
+    pub fn to_vec(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(NUMBER_SIZE + ITEM_SIZE * self.0.len());
+        data.extend_from_slice(&(self.0.len() as u32).to_le_bytes());
+        for (hash, amount) in &self.0 {
+            data.extend_from_slice(hash);
+            data.extend_from_slice(&amount.to_le_bytes());
+        }
+        data
+    }
+
+    pub fn get(&self, sudt_script_hash: &[u8; 32]) -> u128 {
+        self.0.get(sudt_script_hash).copied().unwrap_or(0)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&[u8; 32], &u128)> {
+        self.0.iter()
+    }
+
+    /// Apply the assets of the consumed and the created custodian cells
+    pub fn apply(
+        &mut self,
+        input_assets: &BTreeMap<H256, u128>,
+        output_assets: &BTreeMap<H256, u128>,
+    ) -> Result<(), Error> {
+        for (k, v) in output_assets {
+            let balance = self.0.entry((*k).into()).or_insert(0);
+            *balance = balance
+                .checked_add(*v)
+                .ok_or(Error::InvalidCustodianAssets)?;
+        }
+        for (k, v) in input_assets {
+            let balance = self.0.entry((*k).into()).or_insert(0);
+            *balance = balance
+                .checked_sub(*v)
+                .ok_or(Error::InvalidCustodianAssets)?;
+        }
+        let balances = core::mem::take(&mut self.0);
+        self.0 = balances.into_iter().filter(|(_k, v)| *v != 0).collect();
+        Ok(())
+    }
+}
+
+/// Parse the rollup cell data into the GlobalState and the optional custodian assets
+pub fn parse_rollup_cell_data(
+    data: &[u8],
+) -> Result<(GlobalState, Option<CustodianAssets>), Error> {
+    let global_state = parse_global_state(data)?;
+    let assets = match &data[GlobalState::TOTAL_SIZE..] {
+        [] => None,
+        extension => Some(CustodianAssets::from_slice(extension)?),
+    };
+    Ok((global_state, assets))
+}
+
+/// Parse the GlobalState from the rollup cell data, the custodian assets are ignored
+pub fn parse_global_state(data: &[u8]) -> Result<GlobalState, Error> {
+    let global_state = data.get(..GlobalState::TOTAL_SIZE).ok_or(Error::Encoding)?;
+    match GlobalStateReader::verify(global_state, false) {
+        Ok(()) => Ok(GlobalState::new_unchecked(global_state.to_vec().into())),
+        Err(_) => Err(Error::Encoding),
+    }
+}
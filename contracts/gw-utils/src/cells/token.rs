@@ -1,14 +1,24 @@
-use crate::error::Error;
-use ckb_std::{
-    ckb_constants::Source,
-    high_level::{
-        load_cell_capacity, load_cell_data, load_cell_lock_hash, load_cell_type_hash, QueryIter,
-    },
-};
+use crate::{error::Error, syscalls::Syscalls};
+use ckb_std::{ckb_constants::Source, high_level::QueryIter};
+
+/// Size of the amount at the beginning of the sUDT / xUDT cell data
+pub const UDT_AMOUNT_SIZE: usize = 16;
+
+/// Parse the amount of a sUDT or xUDT cell,
+/// xUDT cells put the extension data after the amount.
+pub fn parse_udt_amount(data: &[u8]) -> Result<u128, Error> {
+    if data.len() < UDT_AMOUNT_SIZE {
+        return Err(Error::InvalidSUDTCell);
+    }
+    let mut buf = [0u8; UDT_AMOUNT_SIZE];
+    buf.copy_from_slice(&data[..UDT_AMOUNT_SIZE]);
+    Ok(u128::from_le_bytes(buf))
+}
 
 #[derive(PartialEq, Eq, Debug, Clone)]
 pub enum TokenType {
     CKB,
+    /// L1 token (sUDT or xUDT) identified by the type script hash
     SUDT([u8; 32]),
 }
 
@@ -28,31 +38,31 @@ pub struct CellTokenAmount {
     pub total_capacity: u128,
 }
 
-pub fn fetch_token_amount_by_lock_hash(
+pub fn fetch_token_amount_by_lock_hash<S: Syscalls>(
+    syscalls: &S,
     owner_lock_hash: &[u8; 32],
     token_type: &TokenType,
     source: Source,
 ) -> Result<CellTokenAmount, Error> {
     let mut total_token_amount = 0u128;
     let mut total_capacity = 0u128;
-    for (i, lock_hash) in QueryIter::new(load_cell_lock_hash, source)
-        .into_iter()
-        .enumerate()
+    for (i, lock_hash) in QueryIter::new(
+        |index, source| syscalls.load_cell_lock_hash(index, source),
+        source,
+    )
+    .enumerate()
     {
         if &lock_hash != owner_lock_hash {
             continue;
         }
 
-        let capacity = load_cell_capacity(i, source)?;
+        let capacity = syscalls.load_cell_capacity(i, source)?;
         total_capacity = total_capacity
             .checked_add(capacity as u128)
             .ok_or(Error::AmountOverflow)?;
-        let amount = match load_cell_type_hash(i, source)? {
+        let amount = match syscalls.load_cell_type_hash(i, source)? {
             Some(type_hash) if &TokenType::SUDT(type_hash) == token_type => {
-                let data = load_cell_data(i, source)?;
-                let mut buf = [0u8; 16];
-                buf.copy_from_slice(&data[..16]);
-                u128::from_le_bytes(buf)
+                parse_udt_amount(&syscalls.load_cell_data(i, source)?)?
             }
             _ => 0,
         };
@@ -1,10 +1,14 @@
 //! Cell types
 
+use super::{dao::DAOState, lock_cells::STAKE_UNBONDING_FLAG};
 use crate::gw_common::sparse_merkle_tree::H256;
+use crate::gw_types::bytes::Bytes;
 use crate::gw_types::packed::{
     ChallengeLockArgs, CustodianLockArgs, DepositLockArgs, Script, StakeLockArgs,
     WithdrawalLockArgs,
 };
+use crate::gw_types::prelude::*;
+use alloc::vec::Vec;
 
 #[derive(PartialEq, Eq, Debug, Clone)]
 pub struct CellValue {
@@ -20,35 +24,89 @@ pub struct WithdrawalCell {
     pub value: CellValue,
 }
 
+/// A layer2 contract call which is executed right after the deposit is minted
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct DepositCall {
+    pub to_id: u32,
+    pub args: Bytes,
+}
+
+/// A layer2 fee which is paid to the block producer from the minted deposit
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct DepositFee {
+    pub sudt_id: u32,
+    pub amount: u128,
+}
+
+/// A layer2 account credited by a deposit cell
+#[derive(Clone)]
+pub struct DepositEntry {
+    pub account_script: Script,
+    pub account_script_hash: H256,
+    pub capacity: u64,
+    pub amount: u128,
+}
+
 #[derive(Clone)]
 pub struct DepositRequestCell {
     pub index: usize,
     pub args: DepositLockArgs,
+    /// the raw bytes appended to `DepositLockArgs`
+    pub extension: Bytes,
     pub value: CellValue,
-    pub account_script: Script,
-    pub account_script_hash: H256,
+    /// a single entry of `layer2_lock` or the entries of a batch deposit
+    pub entries: Vec<DepositEntry>,
+    pub call: Option<DepositCall>,
+    pub fee: Option<DepositFee>,
 }
 
 #[derive(Debug)]
 pub struct CustodianCell {
     pub index: usize,
     pub args: CustodianLockArgs,
+    /// the extension of the deposit, which is kept to revert the custodian cell to the deposit cell
+    pub deposit_extension: Bytes,
     pub value: CellValue,
+    /// DAO state of the custodian cell, None if the cell isn't deposited into the DAO
+    pub dao: Option<DAOState>,
+}
+
+/// Delegation of a stake cell to a block producer
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct Delegation {
+    pub producer_lock_hash: [u8; 32],
+    /// the delegator requested unbonding, the stake no longer backs new blocks
+    pub unbonding: bool,
 }
 
 pub struct StakeCell {
     pub index: usize,
     pub args: StakeLockArgs,
     pub capacity: u64,
+    /// amount of the sUDT stake collateral, 0 for a CKB only stake cell
+    pub amount: u128,
+    /// the block producer which the stake is delegated to, None for the producer's own stake
+    pub producer_lock_hash: Option<[u8; 32]>,
+    /// the delegator requested unbonding of the delegated stake
+    pub unbonding: bool,
 }
 
-pub struct ChallengeCell {
-    pub index: usize,
-    pub args: ChallengeLockArgs,
-    pub value: CellValue,
+impl StakeCell {
+    /// The stake lock args (without the rollup_type_hash prefix)
+    pub fn lock_args(&self) -> Vec<u8> {
+        let mut args = self.args.as_slice().to_vec();
+        if let Some(producer_lock_hash) = self.producer_lock_hash {
+            args.extend_from_slice(&producer_lock_hash);
+            if self.unbonding {
+                args.push(STAKE_UNBONDING_FLAG);
+            }
+        }
+        args
+    }
 }
 
-pub struct BurnCell {
+pub struct ChallengeCell {
     pub index: usize,
+    pub args: ChallengeLockArgs,
     pub value: CellValue,
 }
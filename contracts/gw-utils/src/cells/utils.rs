@@ -1,8 +1,6 @@
+use crate::syscalls::Syscalls;
 use alloc::vec::Vec;
-use ckb_std::{
-    ckb_constants::Source,
-    high_level::{load_cell_lock_hash, QueryIter},
-};
+use ckb_std::{ckb_constants::Source, high_level::QueryIter};
 use gw_common::H256;
 use gw_types::{
     bytes::Bytes,
@@ -11,21 +9,36 @@ use gw_types::{
     prelude::*,
 };
 
-pub fn search_lock_hashes(owner_lock_hash: &[u8; 32], source: Source) -> Vec<usize> {
-    QueryIter::new(load_cell_lock_hash, source)
-        .enumerate()
-        .filter_map(|(i, lock_hash)| {
-            if &lock_hash == owner_lock_hash {
-                Some(i)
-            } else {
-                None
-            }
-        })
-        .collect()
+pub fn search_lock_hashes<S: Syscalls>(
+    syscalls: &S,
+    owner_lock_hash: &[u8; 32],
+    source: Source,
+) -> Vec<usize> {
+    QueryIter::new(
+        |index, source| syscalls.load_cell_lock_hash(index, source),
+        source,
+    )
+    .enumerate()
+    .filter_map(|(i, lock_hash)| {
+        if &lock_hash == owner_lock_hash {
+            Some(i)
+        } else {
+            None
+        }
+    })
+    .collect()
 }
 
-pub fn search_lock_hash(owner_lock_hash: &[u8; 32], source: Source) -> Option<usize> {
-    QueryIter::new(load_cell_lock_hash, source).position(|lock_hash| &lock_hash == owner_lock_hash)
+pub fn search_lock_hash<S: Syscalls>(
+    syscalls: &S,
+    owner_lock_hash: &[u8; 32],
+    source: Source,
+) -> Option<usize> {
+    QueryIter::new(
+        |index, source| syscalls.load_cell_lock_hash(index, source),
+        source,
+    )
+    .position(|lock_hash| &lock_hash == owner_lock_hash)
 }
 
 pub fn build_l2_sudt_script(
@@ -0,0 +1,147 @@
+//! Compressed encoding of the rollup witness
+//!
+//! The compressed data is decoded to the canonical molecule bytes, so the block hash,
+//! the tx witness hashes and the proofs are computed over the same data as the
+//! uncompressed encoding.
+//!
+//! Layout: `decoded size: u32 | word count: u32 | words: [u8; 32] * count | tokens`,
+//! a token is one of
+//!
+//! * `0x00 | varint(n) | n bytes`, literal bytes
+//! * `0x01 | varint(index)`, a 32 bytes word of the dictionary, e.g. a script hash or a KV key
+//! * `0x02 | varint(n)`, `n` zero bytes, e.g. zero KV values and empty fields
+//! * `0x03 | varint(value)`, a u32 number in little endian, e.g. an account id or a nonce
+//!
+//! varint is LEB128 of an u32. A token must produce at least one byte and the tokens must
+//! produce exactly the decoded size, so the cycles are bounded by the sizes of the compressed
+//! and the decoded data.
+//!
+//! The decoded buffer is allocated once from the decoded size. The compressed and the decoded
+//! data take at most `MAX_COMPRESSED_SIZE + MAX_DECOMPRESSED_SIZE` bytes, half of the 1 MiB
+//! heap of the scripts, a larger block must be submitted uncompressed.
+
+use crate::error::Error;
+use alloc::vec::Vec;
+use ckb_std::debug;
+
+const WORD_SIZE: usize = 32;
+const TAG_LITERAL: u8 = 0;
+const TAG_WORD: u8 = 1;
+const TAG_ZEROS: u8 = 2;
+const TAG_U32: u8 = 3;
+
+/// Max size of the compressed data
+pub const MAX_COMPRESSED_SIZE: usize = 256 * 1024;
+/// Max size of the decoded data
+pub const MAX_DECOMPRESSED_SIZE: usize = 256 * 1024;
+/// Max number of the dictionary words
+pub const MAX_DICTIONARY_WORDS: usize = 8192;
+
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        let end = self
+            .offset
+            .checked_add(len)
+            .filter(|end| *end <= self.data.len())
+            .ok_or(Error::Encoding)?;
+        let bytes = &self.data[self.offset..end];
+        self.offset = end;
+        Ok(bytes)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, Error> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    /// LEB128 of an u32, at most 5 bytes
+    fn read_varint(&mut self) -> Result<u32, Error> {
+        let mut value = 0u32;
+        for i in 0..5 {
+            let byte = self.read_u8()?;
+            let bits = u32::from(byte & 0x7f);
+            if i == 4 && bits > 0x0f {
+                return Err(Error::Encoding);
+            }
+            value |= bits << (7 * i);
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(Error::Encoding)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.offset == self.data.len()
+    }
+}
+
+fn read_u32(reader: &mut Reader) -> Result<usize, Error> {
+    let mut buf = [0u8; 4];
+    buf.copy_from_slice(reader.read_bytes(4)?);
+    Ok(u32::from_le_bytes(buf) as usize)
+}
+
+/// Decode the compressed data and append it to `prefix`,
+/// so a caller can put a header before the decoded data without copying it
+pub fn decompress(data: &[u8], prefix: &[u8]) -> Result<Vec<u8>, Error> {
+    if data.len() > MAX_COMPRESSED_SIZE {
+        debug!("the compressed data is too large {}", data.len());
+        return Err(Error::Encoding);
+    }
+    let mut reader = Reader { data, offset: 0 };
+    let size = read_u32(&mut reader)?;
+    if size > MAX_DECOMPRESSED_SIZE {
+        debug!("the decoded data is too large {}", size);
+        return Err(Error::Encoding);
+    }
+    let count = read_u32(&mut reader)?;
+    if count > MAX_DICTIONARY_WORDS {
+        debug!("too many dictionary words {}", count);
+        return Err(Error::Encoding);
+    }
+    let words = reader.read_bytes(count * WORD_SIZE)?;
+
+    // the decoded data must not exceed the allocated buffer
+    let end = prefix.len() + size;
+    let mut decoded = Vec::with_capacity(end);
+    decoded.extend_from_slice(prefix);
+    while !reader.is_empty() {
+        let tag = reader.read_u8()?;
+        let len = match tag {
+            TAG_LITERAL | TAG_ZEROS => reader.read_varint()? as usize,
+            TAG_WORD => WORD_SIZE,
+            TAG_U32 => 4,
+            _ => {
+                debug!("unknown compressed token {}", tag);
+                return Err(Error::Encoding);
+            }
+        };
+        if len == 0 || decoded.len() + len > end {
+            debug!("invalid compressed token length {}", len);
+            return Err(Error::Encoding);
+        }
+        match tag {
+            TAG_LITERAL => decoded.extend_from_slice(reader.read_bytes(len)?),
+            TAG_WORD => {
+                let index = reader.read_varint()? as usize;
+                if index >= count {
+                    debug!("dictionary word {} is out of bound", index);
+                    return Err(Error::Encoding);
+                }
+                decoded.extend_from_slice(&words[index * WORD_SIZE..(index + 1) * WORD_SIZE]);
+            }
+            TAG_ZEROS => decoded.resize(decoded.len() + len, 0),
+            _ => decoded.extend_from_slice(&reader.read_varint()?.to_le_bytes()),
+        }
+    }
+    if decoded.len() != end {
+        debug!("the decoded data doesn't match the decoded size {}", size);
+        return Err(Error::Encoding);
+    }
+    Ok(decoded)
+}
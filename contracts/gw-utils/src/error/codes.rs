@@ -0,0 +1,104 @@
+//! Error codes of the Rust scripts
+//!
+//! Generated from `errors.toml` by `tools/gen-errors.py`, don't edit.
+
+/// Error
+#[repr(i8)]
+pub enum Error {
+    /// syscall: the index of the cell, input or witness is out of bound
+    IndexOutOfBound = 1,
+    /// syscall: the loaded field is missing
+    ItemMissing = 2,
+    /// syscall: the buffer is shorter than the loaded data
+    LengthNotEnough = 3,
+    /// data is not a valid molecule structure
+    Encoding = 4,
+    /// the script args are invalid
+    InvalidArgs = 5,
+    /// the since of the input doesn't satisfy the lock
+    InvalidSince = 6,
+    /// the output cells don't match the unlock condition
+    InvalidOutput = 7,
+    /// no input cell is locked by the owner lock
+    OwnerCellNotFound = 8,
+    /// the rollup cell is missing
+    RollupCellNotFound = 9,
+    /// the rollup config cell is missing from the cell deps
+    RollupConfigNotFound = 10,
+    /// a required merkle proof is missing
+    ProofNotFound = 11,
+    /// the layer2 account doesn't exist
+    AccountNotFound = 12,
+    /// failed to verify a merkle proof
+    MerkleProof = 13,
+    /// the token amount overflows
+    AmountOverflow = 14,
+    /// the short address doesn't match a script hash
+    InvalidShortAddress = 15,
+    /// the token amount is insufficient
+    InsufficientAmount = 16,
+    /// the finalized custodian assets of the inputs are insufficient
+    InsufficientInputFinalizedAssets = 17,
+    /// the finalized custodian assets of the outputs are insufficient
+    InsufficientOutputFinalizedAssets = 18,
+    /// the withdrawal cell isn't for sell
+    NotForSell = 19,
+    /// the key is missing from the sparse merkle tree
+    SMTKeyMissing = 20,
+    /// the state checkpoint doesn't match the state
+    InvalidStateCheckpoint = 21,
+    /// the layer2 block is invalid
+    InvalidBlock = 22,
+    /// the rollup status doesn't allow the action
+    InvalidStatus = 23,
+    /// the stake cell can't be unlocked yet
+    InvalidStakeCellUnlock = 24,
+    /// the post global state doesn't match the expected one
+    InvalidPostGlobalState = 25,
+    /// the challenge cell is invalid
+    InvalidChallengeCell = 26,
+    /// the stake cell is invalid
+    InvalidStakeCell = 27,
+    /// the deposit cell is invalid
+    InvalidDepositCell = 28,
+    /// the withdrawal cell is invalid
+    InvalidWithdrawalCell = 29,
+    /// the custodian cell is invalid
+    InvalidCustodianCell = 30,
+    /// the reverted blocks are invalid
+    InvalidRevertedBlocks = 31,
+    /// the challenge reward is invalid
+    InvalidChallengeReward = 32,
+    /// the sUDT cell is invalid
+    InvalidSUDTCell = 33,
+    /// the challenge target doesn't exist in the block
+    InvalidChallengeTarget = 34,
+    /// the withdrawal request is invalid
+    InvalidWithdrawalRequest = 35,
+    /// the EOA lock script isn't allowed by the rollup config
+    UnknownEOAScript = 36,
+    /// the contract script isn't allowed by the rollup config
+    UnknownContractScript = 37,
+    /// the layer2 script is missing
+    ScriptNotFound = 38,
+    /// the cell of the account lock is missing from the cell deps
+    AccountLockCellNotFound = 39,
+    /// the cell of the account script is missing from the cell deps
+    AccountScriptCellNotFound = 40,
+    /// the type id of the cell is invalid
+    InvalidTypeID = 41,
+    /// the nonce of the layer2 transaction doesn't match the account
+    UnexpectedTxNonce = 42,
+    /// raise from signature verification script
+    WrongSignature = 43,
+    /// the kv state buffer is full
+    InsufficientKVStateCapacity = 44,
+    /// the proof of a sparse merkle tree is malformed
+    SMTProofMalformed = 45,
+    /// the root calculated from a sparse merkle tree isn't the expected root
+    SMTRootMismatch = 46,
+    /// the DAO withdrawal of the custodian cells is invalid
+    InvalidDAOWithdrawal = 47,
+    /// the custodian assets accumulator of the rollup cell is invalid
+    InvalidCustodianAssets = 48,
+}
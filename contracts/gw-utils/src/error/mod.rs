@@ -0,0 +1,43 @@
+//! godwoken validator errors
+//!
+//! The codes are registered in `errors.toml`, see `tools/gen-errors.py`.
+
+use ckb_std::error::SysError;
+use gw_common::{error::Error as CommonError, smt::Error as SMTError};
+
+mod codes;
+
+pub use codes::Error;
+
+impl From<SysError> for Error {
+    fn from(err: SysError) -> Self {
+        use SysError::*;
+        match err {
+            IndexOutOfBound => Self::IndexOutOfBound,
+            ItemMissing => Self::ItemMissing,
+            LengthNotEnough(_) => Self::LengthNotEnough,
+            Encoding => Self::Encoding,
+            Unknown(err_code) => panic!("unexpected sys error {}", err_code),
+        }
+    }
+}
+
+impl From<CommonError> for Error {
+    fn from(err: CommonError) -> Self {
+        use CommonError::*;
+        match err {
+            // KVState reports an exhausted buffer as a store error
+            Store => Self::InsufficientKVStateCapacity,
+            SMT(_) | MissingKey => Self::SMTKeyMissing,
+            MerkleProof => Self::MerkleProof,
+            AmountOverflow => Self::AmountOverflow,
+            InvalidShortAddress => Self::InvalidShortAddress,
+        }
+    }
+}
+
+impl From<SMTError> for Error {
+    fn from(_err: SMTError) -> Self {
+        Self::SMTKeyMissing
+    }
+}
@@ -1,4 +1,4 @@
-#![no_std]
+#![cfg_attr(not(test), no_std)]
 #![allow(clippy::upper_case_acronyms)]
 #![allow(clippy::match_like_matches_macro)]
 
@@ -10,6 +10,11 @@ pub use gw_common;
 pub use gw_types;
 
 pub mod cells;
+pub mod compression;
 pub mod error;
 pub mod signature;
+pub mod since;
+pub mod syscalls;
+pub mod trace;
 pub mod type_id;
+pub mod witness;
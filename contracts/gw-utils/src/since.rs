@@ -0,0 +1,271 @@
+//! Since comparison
+//!
+//! The since of an input is compared by the metric of its lock value:
+//! block numbers and timestamps are compared as integers, epochs are compared as
+//! `number + index / length`. A raw u64 comparison is wrong for the epoch fraction,
+//! and a since is never comparable with a since of another lock type or metric.
+//!
+//! See https://github.com/nervosnetwork/rfcs/blob/master/rfcs/0017-tx-valid-since/0017-tx-valid-since.md
+
+use core::cmp::Ordering;
+
+const LOCK_TYPE_FLAG: u64 = 1 << 63;
+const METRIC_TYPE_FLAG_MASK: u64 = 0x6000_0000_0000_0000;
+const REMAIN_FLAGS_MASK: u64 = 0x1f00_0000_0000_0000;
+const VALUE_MASK: u64 = 0x00ff_ffff_ffff_ffff;
+
+const METRIC_BLOCK_NUMBER: u64 = 0;
+const METRIC_EPOCH: u64 = 0x2000_0000_0000_0000;
+const METRIC_TIMESTAMP: u64 = 0x4000_0000_0000_0000;
+
+/// Epoch number with fraction: number (24 bits) | index (16 bits) | length (16 bits)
+#[derive(Clone, Copy, Debug)]
+pub struct EpochNumberWithFraction {
+    pub number: u64,
+    pub index: u64,
+    pub length: u64,
+}
+
+impl EpochNumberWithFraction {
+    fn from_value(value: u64) -> Option<Self> {
+        let epoch = EpochNumberWithFraction {
+            number: value & 0xff_ffff,
+            index: (value >> 24) & 0xffff,
+            length: (value >> 40) & 0xffff,
+        };
+        // a zero length is only allowed for the zero fraction
+        let is_well_formed = if epoch.length == 0 {
+            epoch.index == 0
+        } else {
+            epoch.index < epoch.length
+        };
+        if is_well_formed {
+            Some(epoch)
+        } else {
+            None
+        }
+    }
+
+    fn to_value(self) -> u64 {
+        self.number | (self.index << 24) | (self.length << 40)
+    }
+
+    fn compare(&self, other: &Self) -> Ordering {
+        // compare the fractions by cross multiplication, the fields are at most 16 bits
+        self.number.cmp(&other.number).then_with(|| {
+            let lhs = self.index * other.length.max(1);
+            let rhs = other.index * self.length.max(1);
+            lhs.cmp(&rhs)
+        })
+    }
+}
+
+/// Lock value of a since
+#[derive(Clone, Copy, Debug)]
+pub enum LockValue {
+    BlockNumber(u64),
+    EpochNumberWithFraction(EpochNumberWithFraction),
+    Timestamp(u64),
+}
+
+impl LockValue {
+    fn metric(&self) -> u64 {
+        match self {
+            LockValue::BlockNumber(_) => METRIC_BLOCK_NUMBER,
+            LockValue::EpochNumberWithFraction(_) => METRIC_EPOCH,
+            LockValue::Timestamp(_) => METRIC_TIMESTAMP,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Since(u64);
+
+impl Since {
+    pub fn new(v: u64) -> Self {
+        Since(v)
+    }
+
+    /// Build a since from the lock value, return None if the value overflows 56 bits
+    /// or the epoch fraction is malformed
+    pub fn from_lock_value(value: LockValue, relative: bool) -> Option<Self> {
+        let raw_value = match value {
+            LockValue::BlockNumber(n) | LockValue::Timestamp(n) => n,
+            LockValue::EpochNumberWithFraction(epoch) => {
+                if epoch.number > 0xff_ffff || epoch.length > 0xffff {
+                    return None;
+                }
+                let raw_value = epoch.to_value();
+                EpochNumberWithFraction::from_value(raw_value)?;
+                raw_value
+            }
+        };
+        if raw_value & !VALUE_MASK != 0 {
+            return None;
+        }
+        let lock_type = if relative { LOCK_TYPE_FLAG } else { 0 };
+        Some(Since(lock_type | value.metric() | raw_value))
+    }
+
+    pub fn as_u64(&self) -> u64 {
+        self.0
+    }
+
+    pub fn is_relative(&self) -> bool {
+        self.0 & LOCK_TYPE_FLAG != 0
+    }
+
+    pub fn is_absolute(&self) -> bool {
+        !self.is_relative()
+    }
+
+    /// Return the lock value, None if the since is invalid
+    pub fn lock_value(&self) -> Option<LockValue> {
+        if self.0 & REMAIN_FLAGS_MASK != 0 {
+            return None;
+        }
+        let value = self.0 & VALUE_MASK;
+        match self.0 & METRIC_TYPE_FLAG_MASK {
+            METRIC_BLOCK_NUMBER => Some(LockValue::BlockNumber(value)),
+            METRIC_EPOCH => {
+                EpochNumberWithFraction::from_value(value).map(LockValue::EpochNumberWithFraction)
+            }
+            METRIC_TIMESTAMP => Some(LockValue::Timestamp(value)),
+            _ => None,
+        }
+    }
+
+    /// Compare two since by the metric,
+    /// return None if either since is invalid or they have different lock types or metrics
+    pub fn compare(&self, other: &Since) -> Option<Ordering> {
+        if self.is_relative() != other.is_relative() {
+            return None;
+        }
+        match (self.lock_value()?, other.lock_value()?) {
+            (LockValue::BlockNumber(a), LockValue::BlockNumber(b)) => Some(a.cmp(&b)),
+            (LockValue::Timestamp(a), LockValue::Timestamp(b)) => Some(a.cmp(&b)),
+            (LockValue::EpochNumberWithFraction(a), LockValue::EpochNumberWithFraction(b)) => {
+                Some(a.compare(&b))
+            }
+            _ => None,
+        }
+    }
+
+    /// Return true if the since reaches the required since
+    pub fn satisfies(&self, required: &Since) -> bool {
+        match self.compare(required) {
+            Some(Ordering::Greater) | Some(Ordering::Equal) => true,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(n: u64, relative: bool) -> Since {
+        Since::from_lock_value(LockValue::BlockNumber(n), relative).unwrap()
+    }
+
+    fn timestamp(n: u64, relative: bool) -> Since {
+        Since::from_lock_value(LockValue::Timestamp(n), relative).unwrap()
+    }
+
+    fn epoch(number: u64, index: u64, length: u64) -> Option<Since> {
+        let epoch = EpochNumberWithFraction {
+            number,
+            index,
+            length,
+        };
+        Since::from_lock_value(LockValue::EpochNumberWithFraction(epoch), false)
+    }
+
+    #[test]
+    fn test_block_number() {
+        let since = block(10, false);
+        assert_eq!(since.as_u64(), 10);
+        assert!(since.is_absolute());
+        assert_eq!(since.compare(&block(20, false)), Some(Ordering::Less));
+        assert_eq!(since.compare(&block(10, false)), Some(Ordering::Equal));
+        assert!(since.satisfies(&block(10, false)));
+        assert!(!since.satisfies(&block(11, false)));
+    }
+
+    #[test]
+    fn test_timestamp() {
+        let since = timestamp(1000, false);
+        assert_eq!(since.as_u64(), METRIC_TIMESTAMP | 1000);
+        assert!(since.satisfies(&timestamp(999, false)));
+        assert!(!since.satisfies(&timestamp(1001, false)));
+    }
+
+    #[test]
+    fn test_epoch_with_fraction() {
+        let half = epoch(1, 1, 2).unwrap();
+        // a raw comparison orders 1 + 2/8 after 1 + 1/2 by the length bits
+        let quarter = epoch(1, 2, 8).unwrap();
+        assert!(quarter.as_u64() > half.as_u64());
+        assert_eq!(quarter.compare(&half), Some(Ordering::Less));
+        assert!(half.satisfies(&quarter));
+        assert!(!quarter.satisfies(&half));
+        // equal fractions of different lengths
+        assert_eq!(
+            half.compare(&epoch(1, 2, 4).unwrap()),
+            Some(Ordering::Equal)
+        );
+        // the epoch number is compared first
+        assert!(epoch(2, 0, 0)
+            .unwrap()
+            .satisfies(&epoch(1, 99, 100).unwrap()));
+        // a zero length is the start of the epoch
+        assert_eq!(
+            epoch(1, 0, 0).unwrap().compare(&epoch(1, 0, 10).unwrap()),
+            Some(Ordering::Equal)
+        );
+    }
+
+    #[test]
+    fn test_malformed_epoch() {
+        assert!(epoch(1, 2, 2).is_none());
+        assert!(epoch(1, 1, 0).is_none());
+        assert!(epoch(0x100_0000, 0, 0).is_none());
+        assert!(epoch(1, 0, 0x1_0000).is_none());
+        // index >= length in a raw since
+        let since = Since::new(METRIC_EPOCH | 1 | (3 << 24) | (2 << 40));
+        assert!(since.lock_value().is_none());
+        assert_eq!(since.compare(&epoch(1, 0, 0).unwrap()), None);
+    }
+
+    #[test]
+    fn test_relative_and_absolute() {
+        let relative = block(10, true);
+        assert_eq!(relative.as_u64(), LOCK_TYPE_FLAG | 10);
+        assert!(relative.is_relative());
+        assert!(relative.satisfies(&block(10, true)));
+        assert_eq!(relative.compare(&block(10, false)), None);
+        assert!(!relative.satisfies(&block(0, false)));
+        assert!(!block(10, false).satisfies(&block(0, true)));
+    }
+
+    #[test]
+    fn test_mismatched_metric() {
+        assert_eq!(block(10, false).compare(&timestamp(10, false)), None);
+        assert!(!timestamp(10, false).satisfies(&block(0, false)));
+        assert!(!block(10, false).satisfies(&epoch(0, 0, 0).unwrap()));
+    }
+
+    #[test]
+    fn test_invalid_since() {
+        // the value overflows 56 bits
+        assert!(Since::from_lock_value(LockValue::BlockNumber(1 << 56), false).is_none());
+        // the remaining flags are set
+        let since = Since::new(REMAIN_FLAGS_MASK | 10);
+        assert!(since.lock_value().is_none());
+        assert_eq!(since.compare(&since), None);
+        // the reserved metric
+        let since = Since::new(METRIC_TYPE_FLAG_MASK | 10);
+        assert!(since.lock_value().is_none());
+        assert!(!since.satisfies(&block(0, false)));
+    }
+}
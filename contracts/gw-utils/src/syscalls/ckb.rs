@@ -0,0 +1,75 @@
+use super::Syscalls;
+use alloc::vec::Vec;
+use ckb_std::{
+    ckb_constants::Source,
+    ckb_types::packed::{CellInput, Script, WitnessArgs},
+    error::SysError,
+    high_level, syscalls,
+};
+
+/// Syscalls of ckb-vm
+pub struct CKBSyscalls;
+
+impl Syscalls for CKBSyscalls {
+    fn load_script(&self) -> Result<Script, SysError> {
+        high_level::load_script()
+    }
+
+    fn load_script_hash(&self) -> Result<[u8; 32], SysError> {
+        high_level::load_script_hash()
+    }
+
+    fn load_cell_capacity(&self, index: usize, source: Source) -> Result<u64, SysError> {
+        high_level::load_cell_capacity(index, source)
+    }
+
+    fn load_cell_lock(&self, index: usize, source: Source) -> Result<Script, SysError> {
+        high_level::load_cell_lock(index, source)
+    }
+
+    fn load_cell_lock_hash(&self, index: usize, source: Source) -> Result<[u8; 32], SysError> {
+        high_level::load_cell_lock_hash(index, source)
+    }
+
+    fn load_cell_type(&self, index: usize, source: Source) -> Result<Option<Script>, SysError> {
+        high_level::load_cell_type(index, source)
+    }
+
+    fn load_cell_type_hash(
+        &self,
+        index: usize,
+        source: Source,
+    ) -> Result<Option<[u8; 32]>, SysError> {
+        high_level::load_cell_type_hash(index, source)
+    }
+
+    fn load_cell_data(&self, index: usize, source: Source) -> Result<Vec<u8>, SysError> {
+        high_level::load_cell_data(index, source)
+    }
+
+    fn load_cell_data_hash(&self, index: usize, source: Source) -> Result<[u8; 32], SysError> {
+        high_level::load_cell_data_hash(index, source)
+    }
+
+    fn load_input(&self, index: usize, source: Source) -> Result<CellInput, SysError> {
+        high_level::load_input(index, source)
+    }
+
+    fn load_input_since(&self, index: usize, source: Source) -> Result<u64, SysError> {
+        high_level::load_input_since(index, source)
+    }
+
+    fn load_witness_args(&self, index: usize, source: Source) -> Result<WitnessArgs, SysError> {
+        high_level::load_witness_args(index, source)
+    }
+
+    fn load_witness(
+        &self,
+        buf: &mut [u8],
+        offset: usize,
+        index: usize,
+        source: Source,
+    ) -> Result<usize, SysError> {
+        syscalls::load_witness(buf, offset, index, source)
+    }
+}
@@ -0,0 +1,203 @@
+//! In-memory syscalls
+//!
+//! Serve a transaction from memory with the semantics of ckb-vm:
+//! the script groups are the inputs (and for a type script, the outputs)
+//! whose lock or type script hash equals to the hash of `script`.
+
+use super::Syscalls;
+use alloc::vec::Vec;
+use ckb_std::{
+    ckb_constants::Source,
+    ckb_types::{
+        bytes::Bytes,
+        packed::{CellInput, CellOutput, Script, WitnessArgs},
+        prelude::*,
+    },
+    error::SysError,
+};
+use gw_common::blake2b::new_blake2b;
+
+fn hash(data: &[u8]) -> [u8; 32] {
+    let mut hasher = new_blake2b();
+    hasher.update(data);
+    let mut hash = [0u8; 32];
+    hasher.finalize(&mut hash);
+    hash
+}
+
+fn to_u64(data: &[u8]) -> u64 {
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(data);
+    u64::from_le_bytes(buf)
+}
+
+/// Copy data from `offset` into the buffer like ckb-vm
+fn load_data(buf: &mut [u8], offset: usize, data: &[u8]) -> Result<usize, SysError> {
+    let data = data.get(offset..).unwrap_or_default();
+    let size = core::cmp::min(buf.len(), data.len());
+    buf[..size].copy_from_slice(&data[..size]);
+    if size < data.len() {
+        return Err(SysError::LengthNotEnough(data.len()));
+    }
+    Ok(data.len())
+}
+
+/// A cell of the transaction
+#[derive(Debug, Clone)]
+pub struct MemoryCell {
+    pub output: CellOutput,
+    pub data: Bytes,
+}
+
+impl MemoryCell {
+    pub fn new(output: CellOutput, data: Bytes) -> Self {
+        MemoryCell { output, data }
+    }
+
+    fn lock_hash(&self) -> [u8; 32] {
+        hash(self.output.lock().as_slice())
+    }
+
+    fn type_hash(&self) -> Option<[u8; 32]> {
+        self.output
+            .type_()
+            .to_opt()
+            .map(|type_| hash(type_.as_slice()))
+    }
+}
+
+/// A transaction and the running script
+#[derive(Debug, Clone, Default)]
+pub struct MemorySyscalls {
+    pub script: Script,
+    pub inputs: Vec<(CellInput, MemoryCell)>,
+    pub outputs: Vec<MemoryCell>,
+    pub cell_deps: Vec<MemoryCell>,
+    pub witnesses: Vec<Bytes>,
+}
+
+impl MemorySyscalls {
+    /// Index of the n-th cell of the script group in the inputs or the outputs
+    fn group_index(&self, index: usize, source: Source) -> Option<usize> {
+        let script_hash = hash(self.script.as_slice());
+        match source {
+            Source::GroupInput => self
+                .inputs
+                .iter()
+                .enumerate()
+                .filter(|(_i, (_input, cell))| {
+                    cell.lock_hash() == script_hash || cell.type_hash() == Some(script_hash)
+                })
+                .nth(index)
+                .map(|(i, _)| i),
+            Source::GroupOutput => self
+                .outputs
+                .iter()
+                .enumerate()
+                .filter(|(_i, cell)| cell.type_hash() == Some(script_hash))
+                .nth(index)
+                .map(|(i, _)| i),
+            _ => Some(index),
+        }
+    }
+
+    fn cell(&self, index: usize, source: Source) -> Result<&MemoryCell, SysError> {
+        let index = self
+            .group_index(index, source)
+            .ok_or(SysError::IndexOutOfBound)?;
+        let cell = match source {
+            Source::Input | Source::GroupInput => self.inputs.get(index).map(|(_input, cell)| cell),
+            Source::Output | Source::GroupOutput => self.outputs.get(index),
+            Source::CellDep => self.cell_deps.get(index),
+            Source::HeaderDep => None,
+        };
+        cell.ok_or(SysError::IndexOutOfBound)
+    }
+
+    fn input(&self, index: usize, source: Source) -> Result<&CellInput, SysError> {
+        let index = self
+            .group_index(index, source)
+            .ok_or(SysError::IndexOutOfBound)?;
+        match source {
+            Source::Input | Source::GroupInput => self
+                .inputs
+                .get(index)
+                .map(|(input, _cell)| input)
+                .ok_or(SysError::IndexOutOfBound),
+            _ => Err(SysError::IndexOutOfBound),
+        }
+    }
+
+    fn witness(&self, index: usize, source: Source) -> Result<&Bytes, SysError> {
+        let index = self
+            .group_index(index, source)
+            .ok_or(SysError::IndexOutOfBound)?;
+        self.witnesses.get(index).ok_or(SysError::IndexOutOfBound)
+    }
+}
+
+impl Syscalls for MemorySyscalls {
+    fn load_script(&self) -> Result<Script, SysError> {
+        Ok(self.script.clone())
+    }
+
+    fn load_script_hash(&self) -> Result<[u8; 32], SysError> {
+        Ok(hash(self.script.as_slice()))
+    }
+
+    fn load_cell_capacity(&self, index: usize, source: Source) -> Result<u64, SysError> {
+        let cell = self.cell(index, source)?;
+        Ok(to_u64(cell.output.capacity().as_slice()))
+    }
+
+    fn load_cell_lock(&self, index: usize, source: Source) -> Result<Script, SysError> {
+        Ok(self.cell(index, source)?.output.lock())
+    }
+
+    fn load_cell_lock_hash(&self, index: usize, source: Source) -> Result<[u8; 32], SysError> {
+        Ok(self.cell(index, source)?.lock_hash())
+    }
+
+    fn load_cell_type(&self, index: usize, source: Source) -> Result<Option<Script>, SysError> {
+        Ok(self.cell(index, source)?.output.type_().to_opt())
+    }
+
+    fn load_cell_type_hash(
+        &self,
+        index: usize,
+        source: Source,
+    ) -> Result<Option<[u8; 32]>, SysError> {
+        Ok(self.cell(index, source)?.type_hash())
+    }
+
+    fn load_cell_data(&self, index: usize, source: Source) -> Result<Vec<u8>, SysError> {
+        Ok(self.cell(index, source)?.data.to_vec())
+    }
+
+    fn load_cell_data_hash(&self, index: usize, source: Source) -> Result<[u8; 32], SysError> {
+        Ok(hash(&self.cell(index, source)?.data))
+    }
+
+    fn load_input(&self, index: usize, source: Source) -> Result<CellInput, SysError> {
+        self.input(index, source).map(Clone::clone)
+    }
+
+    fn load_input_since(&self, index: usize, source: Source) -> Result<u64, SysError> {
+        Ok(to_u64(self.input(index, source)?.since().as_slice()))
+    }
+
+    fn load_witness_args(&self, index: usize, source: Source) -> Result<WitnessArgs, SysError> {
+        let witness = self.witness(index, source)?;
+        WitnessArgs::from_slice(witness).map_err(|_err| SysError::Encoding)
+    }
+
+    fn load_witness(
+        &self,
+        buf: &mut [u8],
+        offset: usize,
+        index: usize,
+        source: Source,
+    ) -> Result<usize, SysError> {
+        load_data(buf, offset, self.witness(index, source)?)
+    }
+}
@@ -0,0 +1,48 @@
+//! Syscalls abstraction
+//!
+//! Scripts load cells, inputs and witnesses through the `Syscalls` trait.
+//! `CKBSyscalls` calls the syscalls of ckb-vm, `MemorySyscalls` serves a
+//! transaction from memory, the native tests of the state-validator use it.
+
+use alloc::vec::Vec;
+use ckb_std::{
+    ckb_constants::Source,
+    ckb_types::packed::{CellInput, Script, WitnessArgs},
+    error::SysError,
+};
+
+mod ckb;
+#[cfg(feature = "memory-syscalls")]
+mod memory;
+
+pub use ckb::CKBSyscalls;
+#[cfg(feature = "memory-syscalls")]
+pub use memory::{MemoryCell, MemorySyscalls};
+
+/// Loading functions used by the scripts, same as `ckb_std::high_level`
+pub trait Syscalls {
+    fn load_script(&self) -> Result<Script, SysError>;
+    fn load_script_hash(&self) -> Result<[u8; 32], SysError>;
+    fn load_cell_capacity(&self, index: usize, source: Source) -> Result<u64, SysError>;
+    fn load_cell_lock(&self, index: usize, source: Source) -> Result<Script, SysError>;
+    fn load_cell_lock_hash(&self, index: usize, source: Source) -> Result<[u8; 32], SysError>;
+    fn load_cell_type(&self, index: usize, source: Source) -> Result<Option<Script>, SysError>;
+    fn load_cell_type_hash(
+        &self,
+        index: usize,
+        source: Source,
+    ) -> Result<Option<[u8; 32]>, SysError>;
+    fn load_cell_data(&self, index: usize, source: Source) -> Result<Vec<u8>, SysError>;
+    fn load_cell_data_hash(&self, index: usize, source: Source) -> Result<[u8; 32], SysError>;
+    fn load_input(&self, index: usize, source: Source) -> Result<CellInput, SysError>;
+    fn load_input_since(&self, index: usize, source: Source) -> Result<u64, SysError>;
+    fn load_witness_args(&self, index: usize, source: Source) -> Result<WitnessArgs, SysError>;
+    /// Partially load the witness from `offset`, same as `ckb_std::syscalls::load_witness`
+    fn load_witness(
+        &self,
+        buf: &mut [u8],
+        offset: usize,
+        index: usize,
+        source: Source,
+    ) -> Result<usize, SysError>;
+}
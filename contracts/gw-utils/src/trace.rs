@@ -0,0 +1,58 @@
+//! Verification trace
+//!
+//! `trace!` records a checkpoint of the verification as one line of debug output:
+//!
+//! `gw-trace <step> [<key>=<value> ...]`
+//!
+//! Checkpoints are recorded in debug builds, or in release builds with the `trace` feature,
+//! the tests crate parses them into a failure report.
+
+use core::fmt::Display;
+
+pub const TRACE_PREFIX: &str = "gw-trace";
+
+/// Record a checkpoint, use the `trace!` macro instead
+#[cfg(any(feature = "trace", debug_assertions))]
+pub fn record(step: &str, fields: &[(&str, &dyn Display)]) {
+    use alloc::string::String;
+    use core::fmt::Write;
+
+    let mut line = String::from(TRACE_PREFIX);
+    let _ = write!(line, " {}", step);
+    for (key, value) in fields {
+        let _ = write!(line, " {}={}", key, value);
+    }
+    ckb_std::syscalls::debug(line);
+}
+
+/// Record a checkpoint, use the `trace!` macro instead
+#[cfg(not(any(feature = "trace", debug_assertions)))]
+#[inline(always)]
+pub fn record(_step: &str, _fields: &[(&str, &dyn Display)]) {}
+
+/// Display bytes as hex
+pub struct Hex<'a>(pub &'a [u8]);
+
+impl<'a> Display for Hex<'a> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        for byte in self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+/// Record a checkpoint of the verification
+///
+/// ```ignore
+/// trace!("submit_block.withdrawal_cell", index = cell.index);
+/// ```
+#[macro_export]
+macro_rules! trace {
+    ($step:expr $(, $key:ident = $value:expr)* $(,)?) => {
+        $crate::trace::record(
+            $step,
+            &[$((stringify!($key), &$value as &dyn core::fmt::Display)),*],
+        )
+    };
+}
@@ -0,0 +1,376 @@
+//! Lazy rollup witness reader
+//!
+//! Read the rollup action from the witness by offsets with `Syscalls::load_witness`,
+//! so a script can inspect the action type or `reverted_block_hashes` without
+//! loading the whole witness into a fixed size buffer.
+//!
+//! Only the molecule headers of the fields we read are checked,
+//! the state-validator verifies the whole `RollupAction` in the same transaction.
+//!
+//! A `RollupSubmitBlock` can also be encoded compressed with an extra item id,
+//! see `compression`. It's decoded to the canonical `RollupAction` when loaded.
+//! The compressed item is `reverted_block_hashes: Byte32Vec | compressed RollupSubmitBlock`,
+//! the uncompressed copy of the hashes lets the locks search them without decoding the block,
+//! `load_action` checks it equals the decoded field.
+
+use crate::{
+    compression::{decompress, MAX_COMPRESSED_SIZE},
+    error::Error,
+    syscalls::Syscalls,
+};
+use alloc::{vec, vec::Vec};
+use ckb_std::{ckb_constants::Source, debug, syscalls::SysError};
+use gw_types::{
+    packed::{RollupAction, RollupActionReader, RollupActionUnionReader},
+    prelude::*,
+};
+
+const NUMBER_SIZE: usize = 4;
+const BYTE32_SIZE: usize = 32;
+/// Number of hashes loaded by one syscall when searching reverted block hashes
+const HASHES_BATCH_SIZE: usize = 32;
+
+/// Field numbers of molecule tables
+const WITNESS_ARGS_FIELDS: usize = 3;
+const WITNESS_ARGS_OUTPUT_TYPE: usize = 2;
+const ROLLUP_SUBMIT_BLOCK_FIELDS: usize = 3;
+const ROLLUP_SUBMIT_BLOCK_REVERTED_BLOCK_HASHES: usize = 1;
+/// RollupAction item ids of the extension actions, appended after the godwoken schema items
+pub const DAO_WITHDRAW_ITEM_ID: usize = 4;
+pub const COMPACT_CUSTODIANS_ITEM_ID: usize = 5;
+/// RollupAction item id of the compressed RollupSubmitBlock
+pub const COMPRESSED_SUBMIT_BLOCK_ITEM_ID: usize = 6;
+/// RollupAction item id of RollupSubmitBlock
+const SUBMIT_BLOCK_ITEM_ID: u32 = 0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RollupActionType {
+    SubmitBlock,
+    EnterChallenge,
+    CancelChallenge,
+    Revert,
+    /// Withdraw custodian cells from the Nervos DAO
+    DAOWithdraw,
+    /// Merge, split or re-denominate finalized custodian cells
+    CompactCustodians,
+}
+
+impl RollupActionType {
+    /// item id of the RollupAction union
+    fn from_item_id(item_id: usize) -> Option<Self> {
+        match item_id {
+            0 => Some(Self::SubmitBlock),
+            1 => Some(Self::EnterChallenge),
+            2 => Some(Self::CancelChallenge),
+            3 => Some(Self::Revert),
+            DAO_WITHDRAW_ITEM_ID => Some(Self::DAOWithdraw),
+            COMPACT_CUSTODIANS_ITEM_ID => Some(Self::CompactCustodians),
+            _ => None,
+        }
+    }
+
+    /// The actions which are not defined by the godwoken schema,
+    /// they're encoded as extra item ids of the RollupAction union with an empty item.
+    pub fn is_extension(self) -> bool {
+        match self {
+            Self::DAOWithdraw | Self::CompactCustodians => true,
+            _ => false,
+        }
+    }
+}
+
+/// Load exactly `buf.len()` bytes of the witness from `offset`
+fn load_witness_exact<S: Syscalls>(
+    syscalls: &S,
+    buf: &mut [u8],
+    offset: usize,
+    index: usize,
+    source: Source,
+) -> Result<(), Error> {
+    match syscalls.load_witness(buf, offset, index, source) {
+        Ok(len) | Err(SysError::LengthNotEnough(len)) if len >= buf.len() => Ok(()),
+        Ok(_) | Err(SysError::LengthNotEnough(_)) => {
+            debug!("witness is shorter than the expected offset {}", offset);
+            Err(Error::Encoding)
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+fn load_number<S: Syscalls>(
+    syscalls: &S,
+    offset: usize,
+    index: usize,
+    source: Source,
+) -> Result<usize, Error> {
+    let mut buf = [0u8; NUMBER_SIZE];
+    load_witness_exact(syscalls, &mut buf, offset, index, source)?;
+    Ok(u32::from_le_bytes(buf) as usize)
+}
+
+/// Load the range of a field of the molecule table located at `offset`
+///
+/// return the absolute (start, end) of the field
+fn load_table_field<S: Syscalls>(
+    syscalls: &S,
+    offset: usize,
+    size: usize,
+    field_count: usize,
+    field_index: usize,
+    index: usize,
+    source: Source,
+) -> Result<(usize, usize), Error> {
+    let header_size = NUMBER_SIZE * (field_count + 1);
+    if size < header_size || load_number(syscalls, offset, index, source)? != size {
+        return Err(Error::Encoding);
+    }
+    // the first offset must be equal to the header size
+    if load_number(syscalls, offset + NUMBER_SIZE, index, source)? != header_size {
+        return Err(Error::Encoding);
+    }
+    let start = load_number(
+        syscalls,
+        offset + NUMBER_SIZE * (field_index + 1),
+        index,
+        source,
+    )?;
+    let end = if field_index + 1 == field_count {
+        size
+    } else {
+        load_number(
+            syscalls,
+            offset + NUMBER_SIZE * (field_index + 2),
+            index,
+            source,
+        )?
+    };
+    if start < header_size || start > end || end > size {
+        return Err(Error::Encoding);
+    }
+    Ok((offset + start, offset + end))
+}
+
+/// Rollup witness
+pub struct RollupWitness<'a, S> {
+    syscalls: &'a S,
+    index: usize,
+    source: Source,
+    /// offset of the RollupAction in the witness
+    action_offset: usize,
+    /// size of the RollupAction
+    action_size: usize,
+    action_type: RollupActionType,
+    /// the RollupSubmitBlock is compressed
+    compressed: bool,
+}
+
+impl<'a, S: Syscalls> RollupWitness<'a, S> {
+    /// Locate the RollupAction in WitnessArgs#output_type
+    pub fn load(syscalls: &'a S, index: usize, source: Source) -> Result<Self, Error> {
+        // load total size of the witness
+        let witness_size = match syscalls.load_witness(&mut [], 0, index, source) {
+            Ok(len) | Err(SysError::LengthNotEnough(len)) => len,
+            Err(err) => return Err(err.into()),
+        };
+        debug!("load rollup witness, witness size: {}", witness_size);
+
+        let (start, end) = load_table_field(
+            syscalls,
+            0,
+            witness_size,
+            WITNESS_ARGS_FIELDS,
+            WITNESS_ARGS_OUTPUT_TYPE,
+            index,
+            source,
+        )
+        .map_err(|err| {
+            debug!("witness is not a valid WitnessArgs");
+            err
+        })?;
+        if start == end {
+            debug!("WitnessArgs#output_type is none");
+            return Err(Error::Encoding);
+        }
+        // output_type is Bytes: item count | data
+        let action_offset = start + NUMBER_SIZE;
+        let action_size = load_number(syscalls, start, index, source)?;
+        if action_offset + action_size != end || action_size < NUMBER_SIZE {
+            debug!("output is not a valid RollupAction");
+            return Err(Error::Encoding);
+        }
+        let item_id = load_number(syscalls, action_offset, index, source)?;
+        let compressed = item_id == COMPRESSED_SUBMIT_BLOCK_ITEM_ID;
+        let action_type = if compressed {
+            RollupActionType::SubmitBlock
+        } else {
+            RollupActionType::from_item_id(item_id).ok_or_else(|| {
+                debug!("unknown RollupAction item id {}", item_id);
+                Error::Encoding
+            })?
+        };
+        if action_type.is_extension() && action_size != NUMBER_SIZE {
+            debug!("the extension action {:?} must be empty", action_type);
+            return Err(Error::Encoding);
+        }
+
+        Ok(RollupWitness {
+            syscalls,
+            index,
+            source,
+            action_offset,
+            action_size,
+            action_type,
+            compressed,
+        })
+    }
+
+    pub fn action_type(&self) -> RollupActionType {
+        self.action_type
+    }
+
+    /// Load and verify the whole RollupAction, a compressed RollupSubmitBlock is decoded
+    ///
+    /// return an error for the extension actions, which aren't godwoken RollupActions
+    pub fn load_action(&self) -> Result<RollupAction, Error> {
+        if self.action_type.is_extension() {
+            return Err(Error::Encoding);
+        }
+        if self.compressed {
+            return self.load_compressed_action();
+        }
+        let mut buf = vec![0u8; self.action_size];
+        load_witness_exact(
+            self.syscalls,
+            &mut buf,
+            self.action_offset,
+            self.index,
+            self.source,
+        )?;
+        verify_action(buf)
+    }
+
+    fn load_compressed_action(&self) -> Result<RollupAction, Error> {
+        let (hashes_start, hashes_end) = self.reverted_block_hashes_field()?;
+        let data_size = self.action_offset + self.action_size - hashes_end;
+        if data_size > MAX_COMPRESSED_SIZE {
+            debug!(
+                "the compressed RollupSubmitBlock is too large {}",
+                data_size
+            );
+            return Err(Error::Encoding);
+        }
+        let mut data = vec![0u8; data_size];
+        load_witness_exact(
+            self.syscalls,
+            &mut data,
+            hashes_end,
+            self.index,
+            self.source,
+        )?;
+        let buf = decompress(&data, &SUBMIT_BLOCK_ITEM_ID.to_le_bytes()).map_err(|err| {
+            debug!("output is not a valid compressed RollupSubmitBlock");
+            err
+        })?;
+        drop(data);
+        let action = verify_action(buf)?;
+        // the uncompressed reverted_block_hashes must equal the decoded field
+        let mut hashes = vec![0u8; hashes_end - hashes_start];
+        load_witness_exact(
+            self.syscalls,
+            &mut hashes,
+            hashes_start,
+            self.index,
+            self.source,
+        )?;
+        match action.as_reader().to_enum() {
+            RollupActionUnionReader::RollupSubmitBlock(args)
+                if args.reverted_block_hashes().as_slice() == hashes.as_slice() =>
+            {
+                Ok(action)
+            }
+            _ => {
+                debug!("reverted_block_hashes doesn't match the compressed RollupSubmitBlock");
+                Err(Error::Encoding)
+            }
+        }
+    }
+
+    /// Locate RollupSubmitBlock#reverted_block_hashes
+    ///
+    /// return the absolute (start, end) of the Byte32Vec
+    fn reverted_block_hashes_field(&self) -> Result<(usize, usize), Error> {
+        if !self.compressed {
+            return load_table_field(
+                self.syscalls,
+                self.action_offset + NUMBER_SIZE,
+                self.action_size - NUMBER_SIZE,
+                ROLLUP_SUBMIT_BLOCK_FIELDS,
+                ROLLUP_SUBMIT_BLOCK_REVERTED_BLOCK_HASHES,
+                self.index,
+                self.source,
+            );
+        }
+        // the uncompressed copy before the compressed data
+        let start = self.action_offset + NUMBER_SIZE;
+        let count = load_number(self.syscalls, start, self.index, self.source)?;
+        count
+            .checked_mul(BYTE32_SIZE)
+            .and_then(|size| size.checked_add(start + NUMBER_SIZE))
+            .filter(|end| *end <= self.action_offset + self.action_size)
+            .map(|end| (start, end))
+            .ok_or_else(|| {
+                debug!("reverted_block_hashes is out of the compressed RollupSubmitBlock");
+                Error::Encoding
+            })
+    }
+
+    /// Search `block_hash` in RollupSubmitBlock#reverted_block_hashes
+    pub fn contains_reverted_block_hash(&self, block_hash: &[u8]) -> Result<bool, Error> {
+        if self.action_type != RollupActionType::SubmitBlock {
+            return Err(Error::InvalidRevertedBlocks);
+        }
+        let (start, end) = self.reverted_block_hashes_field()?;
+        // reverted_block_hashes is Byte32Vec: item count | items
+        let count = load_number(self.syscalls, start, self.index, self.source)?;
+        let hashes_offset = start + NUMBER_SIZE;
+        if count
+            .checked_mul(BYTE32_SIZE)
+            .and_then(|size| size.checked_add(hashes_offset))
+            != Some(end)
+        {
+            debug!("reverted_block_hashes is not a valid Byte32Vec");
+            return Err(Error::Encoding);
+        }
+        let mut buf = [0u8; BYTE32_SIZE * HASHES_BATCH_SIZE];
+        let mut loaded = 0;
+        while loaded < count {
+            let batch = core::cmp::min(HASHES_BATCH_SIZE, count - loaded);
+            let batch_buf = &mut buf[..batch * BYTE32_SIZE];
+            load_witness_exact(
+                self.syscalls,
+                batch_buf,
+                hashes_offset + loaded * BYTE32_SIZE,
+                self.index,
+                self.source,
+            )?;
+            if batch_buf
+                .chunks_exact(BYTE32_SIZE)
+                .any(|hash| hash == block_hash)
+            {
+                return Ok(true);
+            }
+            loaded += batch;
+        }
+        Ok(false)
+    }
+}
+
+fn verify_action(buf: Vec<u8>) -> Result<RollupAction, Error> {
+    match RollupActionReader::verify(&buf, false) {
+        Ok(()) => Ok(RollupAction::new_unchecked(buf.into())),
+        Err(_) => {
+            debug!("output is not a valid RollupActionReader");
+            Err(Error::Encoding)
+        }
+    }
+}
@@ -11,63 +11,274 @@ use core::result::Result;
 // https://nervosnetwork.github.io/ckb-std/riscv64imac-unknown-none-elf/doc/ckb_std/index.html
 use crate::ckb_std::{
     ckb_constants::Source,
-    ckb_types::{bytes::Bytes, prelude::Unpack as CKBTypeUnpack},
-    high_level::load_script,
+    ckb_types::{
+        bytes::Bytes,
+        prelude::{
+            Builder as CKBBuilder, Entity as CKBEntity, Pack as CKBPack, Unpack as CKBTypeUnpack,
+        },
+    },
+    debug,
+    high_level::QueryIter,
 };
+use alloc::vec::Vec;
 
-use gw_utils::cells::{
-    rollup::{search_rollup_cell, search_rollup_state},
-    utils::search_lock_hash,
-};
 use gw_utils::gw_types;
-
-use gw_types::{
-    packed::{StakeLockArgs, StakeLockArgsReader},
-    prelude::*,
+use gw_utils::{
+    cells::{
+        lock_cells::{
+            fetch_capacity_and_sudt_value, parse_stake_lock_args, stake_cell_amount,
+            STAKE_UNBONDING_FLAG,
+        },
+        rollup::{
+            load_rollup_config, search_rollup_cell, stake_rates, stake_token_config,
+            stake_unbonding_blocks,
+        },
+        solvency::parse_rollup_cell_data,
+        types::Delegation,
+        utils::{search_lock_hash, search_lock_hashes},
+    },
+    gw_common::{blake2b::new_blake2b, CKB_SUDT_SCRIPT_ARGS},
+    since::{LockValue, Since},
+    syscalls::Syscalls,
+    trace,
 };
 
+use gw_types::{packed::StakeLockArgs, prelude::*};
+
 use crate::error::Error;
 
-/// args: rollup_type_hash | stake lock args
-fn parse_lock_args() -> Result<([u8; 32], StakeLockArgs), Error> {
+/// args: rollup_type_hash | stake lock args | producer lock hash | unbonding flag
+/// (the producer lock hash and the unbonding flag are of the delegated stake only)
+fn parse_lock_args<S: Syscalls>(
+    syscalls: &S,
+) -> Result<([u8; 32], StakeLockArgs, Option<Delegation>), Error> {
     let mut rollup_type_hash = [0u8; 32];
-    let script = load_script()?;
+    let script = syscalls.load_script()?;
     let args: Bytes = script.args().unpack();
     if args.len() < rollup_type_hash.len() {
         return Err(Error::InvalidArgs);
     }
     rollup_type_hash.copy_from_slice(&args[..32]);
-    match StakeLockArgsReader::verify(&args.slice(32..), false) {
-        Ok(()) => Ok((
-            rollup_type_hash,
-            StakeLockArgs::new_unchecked(args.slice(32..)),
-        )),
+    match parse_stake_lock_args(&args[32..]) {
+        Ok((lock_args, delegation)) => Ok((rollup_type_hash, lock_args, delegation)),
         Err(_) => Err(Error::InvalidArgs),
     }
 }
 
-pub fn main() -> Result<(), Error> {
-    let (rollup_type_hash, lock_args) = parse_lock_args()?;
+/// Check the relative since of every stake cell reaches the unbonding period
+fn check_unbonding_period<S: Syscalls>(syscalls: &S, unbonding_blocks: u64) -> Result<(), Error> {
+    if unbonding_blocks == 0 {
+        return Ok(());
+    }
+    // an unbonding period which overflows the since can't be reached
+    let required_since = Since::from_lock_value(LockValue::BlockNumber(unbonding_blocks), true)
+        .ok_or(Error::InvalidSince)?;
+    for since in QueryIter::new(
+        |index, source| syscalls.load_input_since(index, source),
+        Source::GroupInput,
+    ) {
+        if !Since::new(since).satisfies(&required_since) {
+            debug!("the stake cell is in the unbonding period");
+            return Err(Error::InvalidSince);
+        }
+    }
+    Ok(())
+}
+
+/// The lock hash of the delegated stake cell which requested unbonding
+fn unbonding_lock_hash<S: Syscalls>(syscalls: &S) -> Result<[u8; 32], Error> {
+    let script = syscalls.load_script()?;
+    let args: Bytes = script.args().unpack();
+    let mut args: Vec<u8> = args.to_vec();
+    args.push(STAKE_UNBONDING_FLAG);
+    let script = script
+        .as_builder()
+        .args(CKBPack::pack(&Bytes::from(args)))
+        .build();
+    let mut hasher = new_blake2b();
+    hasher.update(script.as_slice());
+    let mut lock_hash = [0u8; 32];
+    hasher.finalize(&mut lock_hash);
+    Ok(lock_hash)
+}
 
-    // Unlock by User
-    // read global state from rollup cell in deps
-    if let Some(global_state) = search_rollup_state(&rollup_type_hash, Source::CellDep)? {
-        let stake_block_number: u64 = lock_args.stake_block_number().unpack();
-        let last_finalized_block_number: u64 = global_state.last_finalized_block_number().unpack();
-
-        // 1. check if stake_block_number is finalized
-        // 2. check if owner_lock_hash exists in input cells
-        if stake_block_number <= last_finalized_block_number
-            && search_lock_hash(&lock_args.owner_lock_hash().unpack(), Source::Input).is_some()
-        {
+/// Unlock by the owner, the rollup cell and the rollup config cell must be in the cell deps
+///
+/// * top up or withdraw the excess: one output cell keeps the stake lock, its capacity and sUDT
+///   amount must not be less than the input stake cells or the current staking requirement,
+///   the excess can only be withdrawn after the stake block is finalized,
+///   a delegated stake cell can only be topped up
+/// * request unbonding: a delegated stake cell is moved to one output cell with the unbonding
+///   flag appended to the lock args, the block producer can't re-stamp it afterwards
+/// * unbond: no output keeps the stake lock, the stake block must be finalized and the input since
+///   must be a relative L1 block number of at least `stake_unbonding_blocks`,
+///   a delegated stake cell must request unbonding first
+fn unlock_by_owner<S: Syscalls>(
+    syscalls: &S,
+    rollup_type_hash: &[u8; 32],
+    lock_args: &StakeLockArgs,
+    delegation: Option<Delegation>,
+) -> Result<(), Error> {
+    let is_delegated = delegation.is_some();
+    let index = search_rollup_cell(syscalls, rollup_type_hash, Source::CellDep)
+        .ok_or(Error::RollupCellNotFound)?;
+    let data = syscalls.load_cell_data(index, Source::CellDep)?;
+    let (global_state, custodian_assets) = parse_rollup_cell_data(&data)?;
+    let rollup_config = load_rollup_config(syscalls, &global_state.rollup_config_hash().unpack())?;
+
+    // the last block backed by the stake can still be challenged before it is finalized
+    let stake_block_number: u64 = lock_args.stake_block_number().unpack();
+    let last_finalized_block_number: u64 = global_state.last_finalized_block_number().unpack();
+    let is_finalized = stake_block_number <= last_finalized_block_number;
+
+    let stake_lock_hash = syscalls.load_script_hash()?;
+    let stake_token = stake_token_config(&rollup_config)?;
+    // (capacity, sUDT amount) of a stake cell
+    let load_stake_value = |index: usize, source: Source| -> Result<(u128, u128), Error> {
+        let value = fetch_capacity_and_sudt_value(syscalls, &rollup_config, index, source)?;
+        let amount = stake_cell_amount(stake_token.as_ref(), &value)?;
+        Ok((value.capacity.into(), amount))
+    };
+    // (capacity, sUDT amount) of the input stake cells
+    let load_input_stake_value = || -> Result<(u128, u128), Error> {
+        let mut input_capacity = 0u128;
+        let mut input_amount = 0u128;
+        let group_inputs = QueryIter::new(
+            |index, source| syscalls.load_cell_capacity(index, source),
+            Source::GroupInput,
+        )
+        .count();
+        for index in 0..group_inputs {
+            let (capacity, amount) = load_stake_value(index, Source::GroupInput)?;
+            input_capacity += capacity;
+            input_amount = input_amount
+                .checked_add(amount)
+                .ok_or(Error::AmountOverflow)?;
+        }
+        Ok((input_capacity, input_amount))
+    };
+
+    // request unbonding of a delegated stake cell
+    if let Some(Delegation {
+        unbonding: false, ..
+    }) = delegation
+    {
+        let unbonding_lock_hash = unbonding_lock_hash(syscalls)?;
+        let unbonding_cells = search_lock_hashes(syscalls, &unbonding_lock_hash, Source::Output);
+        if let [index] = unbonding_cells.as_slice() {
+            if search_lock_hash(syscalls, &stake_lock_hash, Source::Output).is_some() {
+                debug!("the delegated stake cell is both kept and unbonding");
+                return Err(Error::InvalidStakeCellUnlock);
+            }
+            let (input_capacity, input_amount) = load_input_stake_value()?;
+            let (output_capacity, output_amount) = load_stake_value(*index, Source::Output)?;
+            if output_capacity < input_capacity || output_amount < input_amount {
+                debug!("the unbonding stake cell decreases");
+                return Err(Error::InvalidStakeCellUnlock);
+            }
+            trace!("stake_lock.request_unbonding");
             return Ok(());
         }
+        if !unbonding_cells.is_empty() {
+            debug!("more than one unbonding stake cell");
+            return Err(Error::InvalidStakeCellUnlock);
+        }
+    }
+
+    let output_stake_cells = search_lock_hashes(syscalls, &stake_lock_hash, Source::Output);
+    match output_stake_cells.as_slice() {
+        // top up or withdraw the excess
+        [index] => {
+            let (input_capacity, input_amount) = load_input_stake_value()?;
+            let (output_capacity, output_amount) = load_stake_value(*index, Source::Output)?;
+            if output_capacity >= input_capacity && output_amount >= input_amount {
+                trace!("stake_lock.top_up");
+                return Ok(());
+            }
+            // the requirement is checked against the producer's own stake
+            if is_delegated {
+                debug!("the delegated stake can't be withdrawn partially");
+                return Err(Error::InvalidStakeCellUnlock);
+            }
+            if !is_finalized {
+                debug!("the stake block isn't finalized, the excess can't be withdrawn");
+                return Err(Error::InvalidStakeCellUnlock);
+            }
+            // the withdrawals of a future block are unknown, only the TVL part is required
+            let rates = stake_rates(&rollup_config)?;
+            let tvl = match custodian_assets {
+                Some(assets) => assets.get(&CKB_SUDT_SCRIPT_ARGS),
+                None if rates.tvl_rate == 0 => 0,
+                None => return Err(Error::InvalidCustodianAssets),
+            };
+            let required_capacity = rates
+                .required_capacity(rollup_config.required_staking_capacity().unpack(), tvl, 0)
+                .ok_or(Error::AmountOverflow)?;
+            if output_capacity < required_capacity {
+                debug!(
+                    "the stake cell's capacity is insufficient, required: {}",
+                    required_capacity
+                );
+                return Err(Error::InvalidStakeCellUnlock);
+            }
+            if let Some(stake_token) = stake_token {
+                if output_amount < stake_token.required_amount {
+                    debug!(
+                        "the stake cell's sUDT amount is insufficient, required: {}",
+                        stake_token.required_amount
+                    );
+                    return Err(Error::InvalidStakeCellUnlock);
+                }
+            }
+            trace!("stake_lock.withdraw_excess");
+            Ok(())
+        }
+        // unbond
+        [] => {
+            if let Some(Delegation {
+                unbonding: false, ..
+            }) = delegation
+            {
+                debug!("the delegated stake cell must request unbonding first");
+                return Err(Error::InvalidStakeCellUnlock);
+            }
+            if !is_finalized {
+                debug!("the stake block isn't finalized");
+                return Err(Error::InvalidStakeCellUnlock);
+            }
+            check_unbonding_period(syscalls, stake_unbonding_blocks(&rollup_config)?)?;
+            trace!("stake_lock.unbond");
+            Ok(())
+        }
+        _ => {
+            debug!("more than one output stake cell");
+            Err(Error::InvalidStakeCellUnlock)
+        }
     }
+}
+
+pub fn main<S: Syscalls>(syscalls: &S) -> Result<(), Error> {
+    let (rollup_type_hash, lock_args, delegation) = parse_lock_args(syscalls)?;
 
     // Unlock by Rollup cell
     // check if rollup cell exists in the inputs, the following verification will be handled
     // by rollup state validator.
-    if search_rollup_cell(&rollup_type_hash, Source::Input).is_some() {
+    if search_rollup_cell(syscalls, &rollup_type_hash, Source::Input).is_some() {
+        trace!("stake_lock.unlock_by_rollup");
+        return Ok(());
+    }
+
+    // Unlock by User
+    // check if owner_lock_hash exists in input cells
+    if search_lock_hash(
+        syscalls,
+        &lock_args.owner_lock_hash().unpack(),
+        Source::Input,
+    )
+    .is_some()
+    {
+        unlock_by_owner(syscalls, &rollup_type_hash, &lock_args, delegation)?;
+        trace!("stake_lock.unlock_by_user");
         return Ok(());
     }
 
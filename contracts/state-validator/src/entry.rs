@@ -4,121 +4,187 @@ use core::result::Result;
 // Import heap related library from `alloc`
 // https://doc.rust-lang.org/alloc/index.html
 use gw_utils::{
-    cells::rollup::{load_rollup_config, parse_rollup_action, MAX_ROLLUP_WITNESS_SIZE},
-    ckb_std::{
-        ckb_types::prelude::Unpack as CKBUnpack,
-        high_level::{load_cell_capacity, load_cell_data, load_script},
+    cells::{
+        index::RollupCellIndex,
+        rollup::load_rollup_config,
+        solvency::{parse_rollup_cell_data, CustodianAssets},
     },
-    gw_types::packed::RollupActionUnionReader,
+    ckb_std::ckb_types::prelude::Unpack as CKBUnpack,
+    gw_common::H256,
+    gw_types::packed::{RollupAction, RollupActionUnionReader, RollupConfig},
+    syscalls::Syscalls,
+    trace,
     type_id::{check_type_id, TYPE_ID_SIZE},
+    witness::{RollupActionType, RollupWitness},
 };
 
 // Import CKB syscalls and structures
 // https://nervosnetwork.github.io/ckb-std/riscv64imac-unknown-none-elf/doc/ckb_std/index.html
-use crate::{
-    ckb_std::{ckb_constants::Source, high_level::load_script_hash},
-    verifications,
-};
+use crate::{ckb_std::ckb_constants::Source, verifications};
 
-use gw_types::{
-    bytes::Bytes,
-    packed::{GlobalState, GlobalStateReader},
-    prelude::*,
-};
+use gw_types::{bytes::Bytes, packed::GlobalState, prelude::*};
 use gw_utils::gw_types;
 
 use gw_utils::error::Error;
 
-pub fn parse_global_state(source: Source) -> Result<GlobalState, Error> {
-    let data = load_cell_data(0, source)?;
-    match GlobalStateReader::verify(&data, false) {
-        Ok(_) => Ok(GlobalState::new_unchecked(data.into())),
-        Err(_) => Err(Error::Encoding),
-    }
+/// Parse the GlobalState and the optional custodian assets of the rollup cell
+pub fn parse_rollup_state<S: Syscalls>(
+    syscalls: &S,
+    source: Source,
+) -> Result<(GlobalState, Option<CustodianAssets>), Error> {
+    let data = syscalls.load_cell_data(0, source)?;
+    parse_rollup_cell_data(&data)
 }
 
 /// return true if we are in the initialization, otherwise return false
-fn check_initialization() -> Result<bool, Error> {
-    if load_cell_capacity(0, Source::GroupInput).is_ok() {
+fn check_initialization<S: Syscalls>(syscalls: &S) -> Result<bool, Error> {
+    if syscalls.load_cell_capacity(0, Source::GroupInput).is_ok() {
         return Ok(false);
     }
     // no input Rollup cell, which represents we are in the initialization
-    let post_global_state = parse_global_state(Source::GroupOutput)?;
+    let (post_global_state, post_custodian_assets) =
+        parse_rollup_state(syscalls, Source::GroupOutput)?;
+    // there is no custodian cell before the initialization
+    if let Some(assets) = post_custodian_assets {
+        if assets != CustodianAssets::default() {
+            return Err(Error::InvalidCustodianAssets);
+        }
+    }
     // check config cell exists
-    let _rollup_config = load_rollup_config(&post_global_state.rollup_config_hash().unpack())?;
+    let _rollup_config =
+        load_rollup_config(syscalls, &post_global_state.rollup_config_hash().unpack())?;
     Ok(true)
 }
 
-pub fn main() -> Result<(), Error> {
+pub fn main<S: Syscalls>(syscalls: &S) -> Result<(), Error> {
     // check type_id
     {
-        let script = load_script()?;
+        let script = syscalls.load_script()?;
         let args: Bytes = CKBUnpack::unpack(&script.args());
         if args.len() < TYPE_ID_SIZE {
             return Err(Error::InvalidTypeID);
         }
         let mut type_id = [0u8; TYPE_ID_SIZE];
         type_id.copy_from_slice(&args[..TYPE_ID_SIZE]);
-        check_type_id(type_id)?;
+        check_type_id(syscalls, type_id)?;
     }
     // return success if we are in the initialization
-    if check_initialization()? {
+    if check_initialization(syscalls)? {
+        trace!("state_validator.initialization");
         return Ok(());
     }
     // basic verification
-    let prev_global_state = parse_global_state(Source::GroupInput)?;
-    let post_global_state = parse_global_state(Source::GroupOutput)?;
-    let rollup_config = load_rollup_config(&prev_global_state.rollup_config_hash().unpack())?;
-    let rollup_type_hash = load_script_hash()?.into();
+    let (prev_global_state, prev_custodian_assets) =
+        parse_rollup_state(syscalls, Source::GroupInput)?;
+    let (post_global_state, post_custodian_assets) =
+        parse_rollup_state(syscalls, Source::GroupOutput)?;
+    let rollup_config =
+        load_rollup_config(syscalls, &prev_global_state.rollup_config_hash().unpack())?;
+    let rollup_type_hash: H256 = syscalls.load_script_hash()?.into();
+    // classify rollup cells of the transaction
+    let cells = RollupCellIndex::build(syscalls, &rollup_type_hash, &rollup_config)?;
 
     // load rollup action
-    let mut rollup_witness_buf = [0u8; MAX_ROLLUP_WITNESS_SIZE];
-    let action = parse_rollup_action(&mut rollup_witness_buf, 0, Source::GroupOutput)?;
-    match action.to_enum() {
+    let rollup_witness = RollupWitness::load(syscalls, 0, Source::GroupOutput)?;
+    match rollup_witness.action_type() {
+        RollupActionType::DAOWithdraw => {
+            trace!("state_validator.dao_withdraw");
+            // verify DAO withdrawal of custodian cells
+            verifications::dao::verify(
+                syscalls,
+                &rollup_config,
+                &cells,
+                &prev_global_state,
+                &post_global_state,
+            )?;
+        }
+        RollupActionType::CompactCustodians => {
+            trace!("state_validator.compact_custodians");
+            // verify compaction of finalized custodian cells
+            verifications::compaction::verify(&cells, &prev_global_state, &post_global_state)?;
+        }
+        _ => {
+            let action = rollup_witness.load_action()?;
+            verify_rollup_action(
+                syscalls,
+                rollup_type_hash,
+                &rollup_config,
+                &cells,
+                &action,
+                &prev_global_state,
+                &post_global_state,
+                prev_custodian_assets.as_ref(),
+            )?;
+        }
+    }
+
+    // check the custodian assets accumulator
+    trace!("state_validator.custodian_assets");
+    verifications::solvency::verify(&cells, prev_custodian_assets, post_custodian_assets)
+}
+
+fn verify_rollup_action<S: Syscalls>(
+    syscalls: &S,
+    rollup_type_hash: H256,
+    rollup_config: &RollupConfig,
+    cells: &RollupCellIndex,
+    action: &RollupAction,
+    prev_global_state: &GlobalState,
+    post_global_state: &GlobalState,
+    prev_custodian_assets: Option<&CustodianAssets>,
+) -> Result<(), Error> {
+    match action.as_reader().to_enum() {
         RollupActionUnionReader::RollupSubmitBlock(args) => {
+            trace!("state_validator.submit_block");
             // verify submit block
             verifications::submit_block::verify(
                 rollup_type_hash,
-                &rollup_config,
+                rollup_config,
+                cells,
                 &args.block(),
-                &prev_global_state,
-                &post_global_state,
+                prev_global_state,
+                post_global_state,
+                prev_custodian_assets,
             )?;
             // merkle verify reverted_block_hashes,
             // other rollup locks will check reverted blocks by compare block hash with this field
             verifications::submit_block::verify_reverted_block_hashes(
                 args.reverted_block_hashes().unpack(),
                 args.reverted_block_proof().unpack(),
-                &prev_global_state,
+                prev_global_state,
             )?;
         }
         RollupActionUnionReader::RollupEnterChallenge(args) => {
+            trace!("state_validator.enter_challenge");
             // verify enter challenge
             verifications::challenge::verify_enter_challenge(
-                rollup_type_hash,
-                &rollup_config,
+                cells,
                 args,
-                &prev_global_state,
-                &post_global_state,
+                prev_global_state,
+                post_global_state,
             )?;
         }
         RollupActionUnionReader::RollupCancelChallenge(_args) => {
+            trace!("state_validator.cancel_challenge");
             // verify cancel challenge
             verifications::challenge::verify_cancel_challenge(
-                rollup_type_hash,
-                &rollup_config,
-                &prev_global_state,
-                &post_global_state,
+                syscalls,
+                rollup_config,
+                cells,
+                prev_global_state,
+                post_global_state,
             )?;
         }
         RollupActionUnionReader::RollupRevert(args) => {
+            trace!("state_validator.revert");
             // verify revert
             verifications::revert::verify(
-                rollup_type_hash,
-                &rollup_config,
+                syscalls,
+                rollup_config,
+                cells,
                 args,
-                &prev_global_state,
-                &post_global_state,
+                prev_global_state,
+                post_global_state,
             )?;
         }
     }
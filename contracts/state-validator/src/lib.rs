@@ -0,0 +1,17 @@
+//! State validator
+//!
+//! The verification of the Rollup on-chain logic, `main.rs` runs it on ckb-vm,
+//! the entry loads the transaction through `gw_utils::syscalls::Syscalls`.
+
+#![cfg_attr(not(test), no_std)]
+
+extern crate alloc;
+
+pub mod entry;
+pub mod types;
+pub mod verifications;
+
+#[cfg(test)]
+mod tests;
+
+pub use gw_utils::ckb_std;
@@ -9,21 +9,19 @@
 #![feature(alloc_error_handler)]
 #![feature(panic_info_message)]
 
-// define modules
-mod entry;
-mod types;
-mod verifications;
+use gw_utils::{ckb_std, syscalls::CKBSyscalls};
+use state_validator::entry;
 
 use ckb_std::default_alloc;
-pub use gw_utils::ckb_std;
 
 ckb_std::entry!(program_entry);
-default_alloc!();
+// the rollup action is loaded into the heap
+default_alloc!(4 * 1024, 1024 * 1024, 64);
 
 /// program entry
 fn program_entry() -> i8 {
     // Call main function and return error code
-    match entry::main() {
+    match entry::main(&CKBSyscalls) {
         Ok(_) => 0,
         Err(err) => err as i8,
     }
@@ -0,0 +1,265 @@
+//! Native tests of the verification, the transaction is served by `MemorySyscalls`
+//!
+//! The full scenarios run on ckb-vm in the `tests` crate.
+
+use crate::{entry, verifications};
+use gw_utils::{
+    cells::index::RollupCellIndex,
+    ckb_std::ckb_types::{
+        bytes::Bytes as CKBBytes,
+        packed::{BytesOpt, CellInput, CellOutput, OutPoint, WitnessArgs},
+        prelude::{Builder as CKBBuilder, Entity as CKBEntity, Pack as CKBPack},
+    },
+    error::Error,
+    gw_common::{blake2b::new_blake2b, H256},
+    gw_types::{
+        bytes::Bytes,
+        core::ScriptHashType,
+        packed::{CustodianLockArgs, GlobalState, RollupConfig, Script},
+        prelude::*,
+    },
+    syscalls::{MemoryCell, MemorySyscalls},
+    witness::COMPACT_CUSTODIANS_ITEM_ID,
+};
+
+const CKB: u64 = 100000000;
+
+fn hash(data: &[u8]) -> [u8; 32] {
+    let mut hasher = new_blake2b();
+    hasher.update(data);
+    let mut hash = [0u8; 32];
+    hasher.finalize(&mut hash);
+    hash
+}
+
+fn to_ckb_script(script: &Script) -> gw_utils::ckb_std::ckb_types::packed::Script {
+    CKBEntity::from_slice(script.as_slice()).expect("script")
+}
+
+fn input(index: u32) -> CellInput {
+    let out_point = OutPoint::new_builder().index(CKBPack::pack(&index)).build();
+    CellInput::new_builder().previous_output(out_point).build()
+}
+
+fn cell(capacity: u64, lock: &Script, type_: Option<&Script>, data: &[u8]) -> MemoryCell {
+    let output = CellOutput::new_builder()
+        .capacity(CKBPack::pack(&capacity))
+        .lock(to_ckb_script(lock))
+        .build();
+    let output = match type_ {
+        Some(type_) => {
+            let type_ = gw_utils::ckb_std::ckb_types::packed::ScriptOpt::new_builder()
+                .set(Some(to_ckb_script(type_)))
+                .build();
+            output.as_builder().type_(type_).build()
+        }
+        None => output,
+    };
+    MemoryCell::new(output, data.to_vec().into())
+}
+
+fn rollup_witness(item_id: usize) -> CKBBytes {
+    // RollupAction union with an extension item id and an empty item
+    let action: CKBBytes = (item_id as u32).to_le_bytes().to_vec().into();
+    let output_type = BytesOpt::new_builder()
+        .set(Some(CKBPack::pack(&action)))
+        .build();
+    let witness = WitnessArgs::new_builder().output_type(output_type).build();
+    witness.as_slice().to_vec().into()
+}
+
+struct Rollup {
+    config: RollupConfig,
+    global_state: GlobalState,
+    type_script: Script,
+    custodian_script_type_hash: [u8; 32],
+}
+
+impl Rollup {
+    fn new() -> Self {
+        let custodian_script_type_hash = hash(b"custodian_lock_type_id");
+        let config = RollupConfig::new_builder()
+            .custodian_script_type_hash(Pack::pack(&custodian_script_type_hash))
+            .build();
+        let global_state = GlobalState::new_builder()
+            .rollup_config_hash(Pack::pack(&hash(config.as_slice())))
+            .build();
+        // the type id is calculated from the first input of the initialization
+        let mut type_id = new_blake2b();
+        type_id.update(input(0).as_slice());
+        type_id.update(&0u64.to_le_bytes());
+        let mut args = [0u8; 32];
+        type_id.finalize(&mut args);
+        let type_script = Script::new_builder()
+            .code_hash(Pack::pack(&hash(b"state_validator")))
+            .hash_type(ScriptHashType::Type.into())
+            .args(Pack::pack(&Bytes::from(args.to_vec())))
+            .build();
+        Rollup {
+            config,
+            global_state,
+            type_script,
+            custodian_script_type_hash,
+        }
+    }
+
+    fn rollup_cell(&self, global_state: &GlobalState) -> MemoryCell {
+        cell(
+            1000 * CKB,
+            &Script::default(),
+            Some(&self.type_script),
+            global_state.as_slice(),
+        )
+    }
+
+    fn config_cell(&self) -> MemoryCell {
+        cell(1000 * CKB, &Script::default(), None, self.config.as_slice())
+    }
+
+    fn custodian_cell(&self, deposit_block_number: u64, capacity: u64) -> MemoryCell {
+        let lock_args = CustodianLockArgs::new_builder()
+            .deposit_block_number(Pack::pack(&deposit_block_number))
+            .build();
+        let mut args = hash(self.type_script.as_slice()).to_vec();
+        args.extend_from_slice(lock_args.as_slice());
+        let lock = Script::new_builder()
+            .code_hash(Pack::pack(&self.custodian_script_type_hash))
+            .hash_type(ScriptHashType::Type.into())
+            .args(Pack::pack(&Bytes::from(args)))
+            .build();
+        cell(capacity, &lock, None, &[])
+    }
+
+    /// The initialization creates the rollup cell from a normal input
+    fn initialize(&self) -> MemorySyscalls {
+        MemorySyscalls {
+            script: to_ckb_script(&self.type_script),
+            inputs: vec![(input(0), cell(1000 * CKB, &Script::default(), None, &[]))],
+            outputs: vec![self.rollup_cell(&self.global_state)],
+            cell_deps: vec![self.config_cell()],
+            witnesses: vec![],
+        }
+    }
+
+    /// The compaction of the custodian cells, the global state is unchanged by default
+    fn compact(
+        &self,
+        inputs: Vec<MemoryCell>,
+        outputs: Vec<MemoryCell>,
+        post_global_state: &GlobalState,
+    ) -> MemorySyscalls {
+        let mut tx = MemorySyscalls {
+            script: to_ckb_script(&self.type_script),
+            inputs: vec![(input(0), self.rollup_cell(&self.global_state))],
+            outputs: vec![self.rollup_cell(post_global_state)],
+            cell_deps: vec![self.config_cell()],
+            witnesses: vec![rollup_witness(COMPACT_CUSTODIANS_ITEM_ID)],
+        };
+        for (i, cell) in inputs.into_iter().enumerate() {
+            tx.inputs.push((input(i as u32 + 1), cell));
+        }
+        tx.outputs.extend(outputs);
+        tx
+    }
+}
+
+fn error_code(result: Result<(), Error>) -> Result<(), i8> {
+    result.map_err(|err| err as i8)
+}
+
+#[test]
+fn test_initialize_rollup() {
+    let rollup = Rollup::new();
+    let tx = rollup.initialize();
+    assert_eq!(error_code(entry::main(&tx)), Ok(()));
+}
+
+#[test]
+fn test_initialize_rollup_with_wrong_type_id() {
+    let rollup = Rollup::new();
+    let mut tx = rollup.initialize();
+    tx.inputs[0].0 = input(1);
+    assert_eq!(
+        error_code(entry::main(&tx)),
+        Err(Error::InvalidTypeID as i8)
+    );
+}
+
+#[test]
+fn test_initialize_rollup_without_config() {
+    let rollup = Rollup::new();
+    let mut tx = rollup.initialize();
+    tx.cell_deps.clear();
+    assert_eq!(
+        error_code(entry::main(&tx)),
+        Err(Error::RollupConfigNotFound as i8)
+    );
+}
+
+#[test]
+fn test_compact_custodian_cells() {
+    let rollup = Rollup::new();
+    let inputs = (0..10)
+        .map(|_| rollup.custodian_cell(0, 300 * CKB))
+        .collect();
+    let outputs = vec![
+        rollup.custodian_cell(0, 1000 * CKB),
+        rollup.custodian_cell(0, 2000 * CKB),
+    ];
+    let tx = rollup.compact(inputs, outputs, &rollup.global_state);
+    assert_eq!(error_code(entry::main(&tx)), Ok(()));
+}
+
+#[test]
+fn test_compaction_loses_assets() {
+    let rollup = Rollup::new();
+    let inputs = vec![
+        rollup.custodian_cell(0, 300 * CKB),
+        rollup.custodian_cell(0, 300 * CKB),
+    ];
+    let outputs = vec![rollup.custodian_cell(0, 599 * CKB)];
+    let tx = rollup.compact(inputs, outputs, &rollup.global_state);
+    assert_eq!(
+        error_code(entry::main(&tx)),
+        Err(Error::InsufficientOutputFinalizedAssets as i8)
+    );
+}
+
+#[test]
+fn test_compaction_changes_global_state() {
+    let rollup = Rollup::new();
+    let inputs = vec![rollup.custodian_cell(0, 300 * CKB)];
+    let outputs = vec![rollup.custodian_cell(0, 300 * CKB)];
+    let post_global_state = rollup
+        .global_state
+        .clone()
+        .as_builder()
+        .last_finalized_block_number(Pack::pack(&1u64))
+        .build();
+    let tx = rollup.compact(inputs, outputs, &post_global_state);
+    assert_eq!(
+        error_code(entry::main(&tx)),
+        Err(Error::InvalidPostGlobalState as i8)
+    );
+}
+
+#[test]
+fn test_compact_unfinalized_custodian_cell() {
+    let rollup = Rollup::new();
+    let inputs = vec![
+        rollup.custodian_cell(0, 300 * CKB),
+        rollup.custodian_cell(1, 300 * CKB),
+    ];
+    let outputs = vec![rollup.custodian_cell(0, 600 * CKB)];
+    let tx = rollup.compact(inputs, outputs, &rollup.global_state);
+    // run the verification directly with the cells of the transaction
+    let rollup_type_hash: H256 = hash(rollup.type_script.as_slice()).into();
+    let cells = RollupCellIndex::build(&tx, &rollup_type_hash, &rollup.config)
+        .map_err(|err| err as i8)
+        .expect("rollup cells");
+    assert_eq!(cells.inputs.custodians.len(), 2);
+    assert_eq!(cells.outputs.custodians.len(), 1);
+    let result =
+        verifications::compaction::verify(&cells, &rollup.global_state, &rollup.global_state);
+    assert_eq!(error_code(result), Err(Error::InvalidCustodianCell as i8));
+}
@@ -1,41 +1,49 @@
 use alloc::vec;
 use core::convert::TryInto;
-use gw_common::{smt::Blake2bHasher, sparse_merkle_tree::CompiledMerkleProof, H256};
+use gw_common::{smt::Blake2bHasher, sparse_merkle_tree::CompiledMerkleProof};
 use gw_types::{
     core::{ChallengeTargetType, Status},
     packed::{GlobalState, RollupConfig},
     prelude::*,
 };
 use gw_utils::{
-    cells::lock_cells::{collect_burn_cells, find_challenge_cell},
+    cells::{index::RollupCellIndex, types::ChallengeCell},
     ckb_std::{ckb_constants::Source, debug},
     error::Error,
+    syscalls::Syscalls,
+    trace,
+    trace::Hex,
 };
-use gw_utils::{cells::types::ChallengeCell, gw_types};
 use gw_utils::{
-    gw_common,
+    gw_common, gw_types,
     gw_types::packed::{RawL2Block, RollupEnterChallengeReader},
 };
 
 use super::{check_rollup_lock_cells, check_status};
 
 pub fn verify_enter_challenge(
-    rollup_type_hash: H256,
-    config: &RollupConfig,
+    cells: &RollupCellIndex,
     args: RollupEnterChallengeReader,
     prev_global_state: &GlobalState,
     post_global_state: &GlobalState,
 ) -> Result<(), Error> {
+    trace!("enter_challenge.status");
     check_status(prev_global_state, Status::Running)?;
     // check challenge cells
-    let has_input_challenge =
-        find_challenge_cell(&rollup_type_hash, config, Source::Input)?.is_some();
+    trace!("enter_challenge.challenge_cell");
+    let has_input_challenge = cells.inputs.find_challenge_cell()?.is_some();
     if has_input_challenge {
         return Err(Error::InvalidChallengeCell);
     }
-    let challenge_cell = find_challenge_cell(&rollup_type_hash, config, Source::Output)?
+    let challenge_cell = cells
+        .outputs
+        .find_challenge_cell()?
         .ok_or(Error::InvalidChallengeCell)?;
     // check that challenge target is exists
+    trace!(
+        "enter_challenge.challenged_block",
+        index = challenge_cell.index
+    );
     let witness = args.witness();
     let challenged_block = witness.raw_l2block();
     // check challenged block isn't finazlied
@@ -68,6 +76,11 @@ pub fn verify_enter_challenge(
         .try_into()
         .map_err(|_| Error::InvalidChallengeTarget)?;
     let target_index: u32 = challenge_target.target_index().unpack();
+    trace!(
+        "enter_challenge.target",
+        target_type = Hex(challenge_target.target_type().as_slice()),
+        target_index = target_index
+    );
     match target_type {
         ChallengeTargetType::TxExecution | ChallengeTargetType::TxSignature => {
             let tx_count: u32 = challenged_block.submit_transactions().tx_count().unpack();
@@ -86,8 +99,10 @@ pub fn verify_enter_challenge(
         }
     }
     // check rollup lock cells
-    check_rollup_lock_cells(&rollup_type_hash, config)?;
+    trace!("enter_challenge.rollup_lock_cells");
+    check_rollup_lock_cells(cells)?;
     // check post global state
+    trace!("enter_challenge.post_global_state");
     let actual_post_global_state = {
         let status: u8 = Status::Halting.into();
         prev_global_state
@@ -102,31 +117,36 @@ pub fn verify_enter_challenge(
     Ok(())
 }
 
-pub fn verify_cancel_challenge(
-    rollup_type_hash: H256,
+pub fn verify_cancel_challenge<S: Syscalls>(
+    syscalls: &S,
     config: &RollupConfig,
+    cells: &RollupCellIndex,
     prev_global_state: &GlobalState,
     post_global_state: &GlobalState,
 ) -> Result<(), Error> {
+    trace!("cancel_challenge.status");
     check_status(prev_global_state, Status::Halting)?;
     // check challenge cells
-    let has_input_challenge =
-        find_challenge_cell(&rollup_type_hash, config, Source::Input)?.is_some();
-    let has_output_challenge =
-        find_challenge_cell(&rollup_type_hash, config, Source::Output)?.is_some();
-    if !has_input_challenge || has_output_challenge {
-        debug!("cancel challenge, invalid challenge cell");
-        return Err(Error::InvalidChallengeCell);
-    }
+    trace!("cancel_challenge.challenge_cell");
+    let input_challenge_cell = cells.inputs.find_challenge_cell()?;
+    let has_output_challenge = cells.outputs.find_challenge_cell()?.is_some();
+    let challenge_cell = match input_challenge_cell {
+        Some(cell) if !has_output_challenge => cell,
+        _ => {
+            debug!("cancel challenge, invalid challenge cell");
+            return Err(Error::InvalidChallengeCell);
+        }
+    };
 
     // Check cancel burn
-    let challenge_cell = find_challenge_cell(&rollup_type_hash, config, Source::Input)?
-        .ok_or(Error::InvalidChallengeCell)?;
-    check_cancel_burn(config, &challenge_cell)?;
+    trace!("cancel_challenge.burn", index = challenge_cell.index);
+    check_cancel_burn(syscalls, config, cells, challenge_cell)?;
 
     // check rollup lock cells
-    check_rollup_lock_cells(&rollup_type_hash, config)?;
+    trace!("cancel_challenge.rollup_lock_cells");
+    check_rollup_lock_cells(cells)?;
     // check post global state
+    trace!("cancel_challenge.post_global_state");
     let actual_post_global_state = {
         let status: u8 = Status::Running.into();
         prev_global_state
@@ -142,20 +162,35 @@ pub fn verify_cancel_challenge(
     Ok(())
 }
 
-fn check_cancel_burn(config: &RollupConfig, challenge_cell: &ChallengeCell) -> Result<(), Error> {
+/// Sum the capacity of the burn cells
+fn get_burn_cells_capacity<S: Syscalls>(
+    syscalls: &S,
+    burns: &[usize],
+    source: Source,
+) -> Result<u128, Error> {
+    let mut total: u128 = 0;
+    for &index in burns {
+        let capacity = syscalls.load_cell_capacity(index, source)?;
+        total = total.saturating_add(capacity.into());
+    }
+    Ok(total)
+}
+
+fn check_cancel_burn<S: Syscalls>(
+    syscalls: &S,
+    config: &RollupConfig,
+    cells: &RollupCellIndex,
+    challenge_cell: &ChallengeCell,
+) -> Result<(), Error> {
     let reward_burn_rate: u8 = config.reward_burn_rate().into();
     let challenge_capacity = challenge_cell.value.capacity as u128;
     let expected_burn_capacity = challenge_capacity.saturating_mul(reward_burn_rate.into()) / 100;
 
     let burned_capacity: u128 = {
-        let input_burned_capacity: u128 = collect_burn_cells(config, Source::Input)?
-            .into_iter()
-            .map(|c| c.value.capacity as u128)
-            .sum();
-        let output_burned_capacity: u128 = collect_burn_cells(config, Source::Output)?
-            .into_iter()
-            .map(|c| c.value.capacity as u128)
-            .sum();
+        let input_burned_capacity =
+            get_burn_cells_capacity(syscalls, &cells.inputs.burns, Source::Input)?;
+        let output_burned_capacity =
+            get_burn_cells_capacity(syscalls, &cells.outputs.burns, Source::Output)?;
         output_burned_capacity.saturating_sub(input_burned_capacity)
     };
     if burned_capacity < expected_burn_capacity {
@@ -0,0 +1,75 @@
+use gw_types::{core::Status, packed::GlobalState};
+use gw_utils::{
+    cells::{index::RollupCellIndex, types::CustodianCell},
+    ckb_std::debug,
+    error::Error,
+    gw_types, trace,
+};
+
+use super::{
+    build_assets_map_from_cells, check_assets_conserved, check_rollup_lock_cells_except_custodian,
+    check_status, is_finalized_custodian,
+};
+
+/// Custodian cells of the compaction must be finalized and not deposited into the DAO
+fn check_custodian_cells(
+    custodian_cells: &[CustodianCell],
+    global_state: &GlobalState,
+) -> Result<(), Error> {
+    for cell in custodian_cells {
+        if !is_finalized_custodian(cell, global_state) {
+            debug!("custodian cell {} isn't finalized", cell.index);
+            return Err(Error::InvalidCustodianCell);
+        }
+        if cell.dao.is_some() {
+            debug!("custodian cell {} is a DAO cell", cell.index);
+            return Err(Error::InvalidCustodianCell);
+        }
+    }
+    Ok(())
+}
+
+/// Verify the compaction of the finalized custodian cells
+///
+/// The finalized custodian cells can be merged, split or re-denominated
+/// without producing a block, as long as the assets of each token are conserved.
+pub fn verify(
+    cells: &RollupCellIndex,
+    prev_global_state: &GlobalState,
+    post_global_state: &GlobalState,
+) -> Result<(), Error> {
+    trace!("compact_custodians.status");
+    check_status(prev_global_state, Status::Running)?;
+
+    // check rollup lock cells
+    trace!("compact_custodians.rollup_lock_cells");
+    check_rollup_lock_cells_except_custodian(&cells.inputs)?;
+    check_rollup_lock_cells_except_custodian(&cells.outputs)?;
+
+    // check custodian cells
+    trace!(
+        "compact_custodians.custodian_cells",
+        inputs = cells.inputs.custodians.len(),
+        outputs = cells.outputs.custodians.len()
+    );
+    if cells.inputs.custodians.is_empty() {
+        return Err(Error::InvalidCustodianCell);
+    }
+    check_custodian_cells(&cells.inputs.custodians, prev_global_state)?;
+    check_custodian_cells(&cells.outputs.custodians, prev_global_state)?;
+
+    // check the assets of each token are conserved
+    trace!("compact_custodians.assets");
+    let input_assets =
+        build_assets_map_from_cells(cells.inputs.custodians.iter().map(|c| &c.value))?;
+    let output_assets =
+        build_assets_map_from_cells(cells.outputs.custodians.iter().map(|c| &c.value))?;
+    check_assets_conserved(input_assets, output_assets)?;
+
+    // check post global state
+    trace!("compact_custodians.post_global_state");
+    if post_global_state != prev_global_state {
+        return Err(Error::InvalidPostGlobalState);
+    }
+    Ok(())
+}
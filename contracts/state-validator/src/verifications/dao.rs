@@ -0,0 +1,140 @@
+use alloc::collections::BTreeSet;
+use gw_types::{core::Status, packed::GlobalState, packed::RollupConfig};
+use gw_utils::{
+    cells::{dao::DAOState, index::RollupCellIndex, rollup::dao_config, types::CustodianCell},
+    ckb_std::{ckb_constants::Source, debug, high_level::QueryIter},
+    error::Error,
+    gw_common::CKB_SUDT_SCRIPT_ARGS,
+    gw_types,
+    syscalls::Syscalls,
+    trace,
+};
+
+use super::{check_rollup_lock_cells_except_custodian, check_status, is_finalized_custodian};
+
+fn sum_capacity(cells: &[CustodianCell]) -> Result<u64, Error> {
+    cells.iter().try_fold(0u64, |sum, cell| {
+        sum.checked_add(cell.value.capacity)
+            .ok_or(Error::AmountOverflow)
+    })
+}
+
+/// Sum capacity of the cells which are not custodian cells,
+/// the cells locked by `excluded_lock_hash` are skipped
+fn sum_other_capacity<S: Syscalls>(
+    syscalls: &S,
+    custodians: &[CustodianCell],
+    excluded_lock_hash: Option<&[u8; 32]>,
+    source: Source,
+) -> Result<u128, Error> {
+    let custodian_indexes: BTreeSet<usize> = custodians.iter().map(|cell| cell.index).collect();
+    let mut sum = 0u128;
+    let capacities = QueryIter::new(
+        |index, source| syscalls.load_cell_capacity(index, source),
+        source,
+    );
+    for (index, capacity) in capacities.enumerate() {
+        if custodian_indexes.contains(&index) {
+            continue;
+        }
+        if let Some(lock_hash) = excluded_lock_hash {
+            if &syscalls.load_cell_lock_hash(index, source)? == lock_hash {
+                continue;
+            }
+        }
+        sum += u128::from(capacity);
+    }
+    Ok(sum)
+}
+
+/// Verify the DAO withdrawal of the finalized custodian cells
+///
+/// * phase-1: deposited custodian cells are converted to withdrawing custodian cells
+/// * phase-2: withdrawing custodian cells are withdrawn to CKB custodian cells
+///
+/// The custodian cells keep the original capacity, the DAO interest goes to the beneficiary lock.
+/// The DAO type script verifies the phase-1 / phase-2 rules.
+pub fn verify<S: Syscalls>(
+    syscalls: &S,
+    config: &RollupConfig,
+    cells: &RollupCellIndex,
+    prev_global_state: &GlobalState,
+    post_global_state: &GlobalState,
+) -> Result<(), Error> {
+    trace!("dao_withdraw.status");
+    check_status(prev_global_state, Status::Running)?;
+    let dao_config = dao_config(config)?.ok_or_else(|| {
+        debug!("the rollup doesn't enable the DAO");
+        Error::InvalidDAOWithdrawal
+    })?;
+
+    // check rollup lock cells
+    trace!("dao_withdraw.rollup_lock_cells");
+    check_rollup_lock_cells_except_custodian(&cells.inputs)?;
+    check_rollup_lock_cells_except_custodian(&cells.outputs)?;
+
+    // input custodian cells must be finalized DAO cells
+    trace!("dao_withdraw.input_custodian_cells");
+    if cells.inputs.custodians.is_empty() {
+        return Err(Error::InvalidDAOWithdrawal);
+    }
+    for cell in &cells.inputs.custodians {
+        if cell.dao.is_none() || !is_finalized_custodian(cell, prev_global_state) {
+            debug!(
+                "input custodian cell {} isn't a finalized DAO cell",
+                cell.index
+            );
+            return Err(Error::InvalidCustodianCell);
+        }
+    }
+    // output custodian cells must be finalized withdrawing DAO cells or CKB cells
+    trace!("dao_withdraw.output_custodian_cells");
+    for cell in &cells.outputs.custodians {
+        let is_withdrawal = match cell.dao {
+            Some(DAOState::Withdrawing { .. }) => true,
+            Some(DAOState::Deposited) => false,
+            None => {
+                cell.value.sudt_script_hash == CKB_SUDT_SCRIPT_ARGS.into() && cell.value.amount == 0
+            }
+        };
+        if !is_withdrawal || !is_finalized_custodian(cell, prev_global_state) {
+            debug!("invalid output custodian cell {}", cell.index);
+            return Err(Error::InvalidCustodianCell);
+        }
+    }
+
+    // custodian cells keep the original capacity
+    trace!("dao_withdraw.custodian_capacity");
+    let input_capacity = sum_capacity(&cells.inputs.custodians)?;
+    let output_capacity = sum_capacity(&cells.outputs.custodians)?;
+    if input_capacity != output_capacity {
+        debug!(
+            "custodian capacity mismatch, inputs: {}, outputs: {}",
+            input_capacity, output_capacity
+        );
+        return Err(Error::InvalidDAOWithdrawal);
+    }
+
+    // the interest can only go to the beneficiary,
+    // other cells can't take more capacity than they provide
+    trace!("dao_withdraw.interest");
+    let other_input_capacity =
+        sum_other_capacity(syscalls, &cells.inputs.custodians, None, Source::Input)?;
+    let other_output_capacity = sum_other_capacity(
+        syscalls,
+        &cells.outputs.custodians,
+        Some(&dao_config.beneficiary_lock_hash),
+        Source::Output,
+    )?;
+    if other_output_capacity > other_input_capacity {
+        debug!("the DAO interest doesn't go to the beneficiary");
+        return Err(Error::InvalidDAOWithdrawal);
+    }
+
+    // check post global state
+    trace!("dao_withdraw.post_global_state");
+    if post_global_state != prev_global_state {
+        return Err(Error::InvalidPostGlobalState);
+    }
+    Ok(())
+}
@@ -1,66 +1,116 @@
-use gw_common::H256;
-use gw_types::{
-    core::Status,
-    packed::{GlobalState, RollupConfig},
-};
-use gw_utils::gw_common;
-use gw_utils::gw_types;
+use alloc::collections::BTreeMap;
+use gw_common::{CKB_SUDT_SCRIPT_ARGS, H256};
+use gw_types::{core::Status, packed::GlobalState, prelude::*};
 use gw_utils::{
-    cells::lock_cells::{
-        collect_custodian_locks, collect_deposit_locks, collect_stake_cells,
-        collect_withdrawal_locks,
+    cells::{
+        index::{RollupCellIndex, RollupCells},
+        types::{CellValue, CustodianCell, StakeCell},
     },
-    ckb_std::{ckb_constants::Source, debug},
+    ckb_std::debug,
     error::Error,
 };
+use gw_utils::{gw_common, gw_types};
 
 pub mod challenge;
+pub mod compaction;
+pub mod dao;
 pub mod revert;
+pub mod solvency;
 pub mod submit_block;
 
+/// Total capacity and sUDT amount of stake cells
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct StakeValue {
+    pub capacity: u128,
+    pub amount: u128,
+}
+
+impl StakeValue {
+    pub fn add(&mut self, cell: &StakeCell) {
+        self.capacity = self.capacity.saturating_add(cell.capacity.into());
+        self.amount = self.amount.saturating_add(cell.amount);
+    }
+
+    pub fn saturating_add(&self, other: &StakeValue) -> StakeValue {
+        StakeValue {
+            capacity: self.capacity.saturating_add(other.capacity),
+            amount: self.amount.saturating_add(other.amount),
+        }
+    }
+
+    pub fn saturating_sub(&self, other: &StakeValue) -> StakeValue {
+        StakeValue {
+            capacity: self.capacity.saturating_sub(other.capacity),
+            amount: self.amount.saturating_sub(other.amount),
+        }
+    }
+
+    /// Return true if neither the capacity nor the amount is less than the other's
+    pub fn covers(&self, other: &StakeValue) -> bool {
+        self.capacity >= other.capacity && self.amount >= other.amount
+    }
+}
+
 /// this function ensure transaction doesn't contains any deposit / withdrawal / custodian
-pub fn check_rollup_lock_cells_except_stake(
-    rollup_type_hash: &H256,
-    config: &RollupConfig,
-) -> Result<(), Error> {
-    if !collect_deposit_locks(rollup_type_hash, config, Source::Input)?.is_empty() {
+pub fn check_rollup_lock_cells_except_stake(cells: &RollupCellIndex) -> Result<(), Error> {
+    if !cells.inputs.deposits.is_empty() {
         return Err(Error::InvalidDepositCell);
     }
-    if !collect_deposit_locks(rollup_type_hash, config, Source::Output)?.is_empty() {
+    if !cells.outputs.deposits.is_empty() {
         return Err(Error::InvalidDepositCell);
     }
-    if !collect_withdrawal_locks(rollup_type_hash, config, Source::Input)?.is_empty() {
+    if !cells.inputs.withdrawals.is_empty() {
         return Err(Error::InvalidWithdrawalCell);
     }
-    if !collect_withdrawal_locks(rollup_type_hash, config, Source::Output)?.is_empty() {
+    if !cells.outputs.withdrawals.is_empty() {
         return Err(Error::InvalidWithdrawalCell);
     }
-    if !collect_custodian_locks(rollup_type_hash, config, Source::Input)?.is_empty() {
+    if !cells.inputs.custodians.is_empty() {
         return Err(Error::InvalidCustodianCell);
     }
-    if !collect_custodian_locks(rollup_type_hash, config, Source::Output)?.is_empty() {
+    if !cells.outputs.custodians.is_empty() {
         return Err(Error::InvalidCustodianCell);
     }
     Ok(())
 }
 
+/// this function ensure transaction doesn't contains any deposit / withdrawal / stake / challenge cells
+pub fn check_rollup_lock_cells_except_custodian(cells: &RollupCells) -> Result<(), Error> {
+    if !cells.deposits.is_empty() {
+        return Err(Error::InvalidDepositCell);
+    }
+    if !cells.withdrawals.is_empty() {
+        return Err(Error::InvalidWithdrawalCell);
+    }
+    if !cells.stakes.is_empty() {
+        return Err(Error::InvalidStakeCell);
+    }
+    if !cells.challenges.is_empty() {
+        return Err(Error::InvalidChallengeCell);
+    }
+    Ok(())
+}
+
 /// this function ensure transaction doesn't contains any deposit / withdrawal / custodian / stake cells
-pub fn check_rollup_lock_cells(
-    rollup_type_hash: &H256,
-    config: &RollupConfig,
-) -> Result<(), Error> {
-    check_rollup_lock_cells_except_stake(rollup_type_hash, config)?;
-    if !collect_stake_cells(rollup_type_hash, config, Source::Input)?.is_empty() {
+pub fn check_rollup_lock_cells(cells: &RollupCellIndex) -> Result<(), Error> {
+    check_rollup_lock_cells_except_stake(cells)?;
+    if !cells.inputs.stakes.is_empty() {
         debug!("unexpected input stake cell");
         return Err(Error::InvalidStakeCell);
     }
-    if !collect_stake_cells(rollup_type_hash, config, Source::Output)?.is_empty() {
+    if !cells.outputs.stakes.is_empty() {
         debug!("unexpected output stake cell");
         return Err(Error::InvalidStakeCell);
     }
     Ok(())
 }
 
+pub fn is_finalized_custodian(cell: &CustodianCell, global_state: &GlobalState) -> bool {
+    let deposit_block_number: u64 = cell.args.deposit_block_number().unpack();
+    let last_finalized_block_number: u64 = global_state.last_finalized_block_number().unpack();
+    deposit_block_number <= last_finalized_block_number
+}
+
 pub fn check_status(global_state: &GlobalState, status: Status) -> Result<(), Error> {
     let expected_status: u8 = status.into();
     let status: u8 = global_state.status().into();
@@ -69,3 +119,40 @@ pub fn check_status(global_state: &GlobalState, status: Status) -> Result<(), Er
     }
     Ok(())
 }
+
+pub fn build_assets_map_from_cells<'a, I: Iterator<Item = &'a CellValue>>(
+    cells: I,
+) -> Result<BTreeMap<H256, u128>, Error> {
+    let mut assets = BTreeMap::new();
+    for cell in cells {
+        let sudt_balance = assets.entry(cell.sudt_script_hash).or_insert(0u128);
+        *sudt_balance = sudt_balance
+            .checked_add(cell.amount)
+            .ok_or(Error::AmountOverflow)?;
+        let ckb_balance = assets.entry(CKB_SUDT_SCRIPT_ARGS.into()).or_insert(0u128);
+        *ckb_balance = ckb_balance
+            .checked_add(cell.capacity.into())
+            .ok_or(Error::AmountOverflow)?;
+    }
+    Ok(assets)
+}
+
+/// Check the input finalized assets == the output finalized assets
+pub fn check_assets_conserved(
+    input_assets: BTreeMap<H256, u128>,
+    mut output_assets: BTreeMap<H256, u128>,
+) -> Result<(), Error> {
+    // 1. output finalized assets - input finalized assets
+    for (k, v) in input_assets {
+        let balance = output_assets.entry(k).or_insert(0);
+        *balance = balance
+            .checked_sub(v)
+            .ok_or(Error::InsufficientOutputFinalizedAssets)?;
+    }
+    // 2. check output finalized assets is empty
+    let output_assets_is_empty = output_assets.iter().all(|(_k, v)| v == &0);
+    if !output_assets_is_empty {
+        return Err(Error::InsufficientInputFinalizedAssets);
+    }
+    Ok(())
+}
@@ -11,55 +11,57 @@ use gw_types::{
 use gw_utils::gw_types;
 use gw_utils::{
     cells::{
-        lock_cells::{
-            collect_burn_cells, collect_stake_cells, fetch_capacity_and_sudt_value,
-            find_challenge_cell,
-        },
-        types::ChallengeCell,
+        index::RollupCellIndex,
+        lock_cells::fetch_capacity_and_sudt_value,
+        rollup::{slash_schedule, stake_token_config, StakeTokenConfig},
+        types::{CellValue, ChallengeCell, StakeCell},
         utils::search_lock_hashes,
     },
-    ckb_std::{
-        ckb_constants::Source,
-        debug,
-        high_level::load_input_since,
-        since::{LockValue, Since},
-    },
+    ckb_std::{ckb_constants::Source, debug},
+    since::{LockValue, Since},
+    syscalls::Syscalls,
+    trace,
 };
 use gw_utils::{
     gw_common,
     gw_types::packed::{RawL2BlockReader, RollupRevertReader},
 };
 
-use super::{check_rollup_lock_cells_except_stake, check_status};
-use alloc::{collections::BTreeSet, vec::Vec};
+use super::{check_rollup_lock_cells_except_stake, check_status, StakeValue};
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    vec::Vec,
+};
 use gw_utils::error::Error;
 
 /// Check challenge cell is maturity(on the layer1)
-fn check_challenge_maturity(
+fn check_challenge_maturity<S: Syscalls>(
+    syscalls: &S,
     config: &RollupConfig,
     challenge_cell: &ChallengeCell,
 ) -> Result<(), Error> {
     let challenge_maturity_blocks: u64 = config.challenge_maturity_blocks().unpack();
-    let since = Since::new(load_input_since(challenge_cell.index, Source::Input)?);
-    if let Some(LockValue::BlockNumber(n)) = since.extract_lock_value() {
-        if since.is_relative() && n >= challenge_maturity_blocks {
-            return Ok(());
-        }
+    let required_since =
+        Since::from_lock_value(LockValue::BlockNumber(challenge_maturity_blocks), true)
+            .ok_or(Error::InvalidChallengeCell)?;
+    let since = Since::new(syscalls.load_input_since(challenge_cell.index, Source::Input)?);
+    if since.satisfies(&required_since) {
+        return Ok(());
     }
     Err(Error::InvalidChallengeCell)
 }
 
-fn check_challenge_cell(
-    rollup_type_hash: &H256,
+fn check_challenge_cell<S: Syscalls>(
+    syscalls: &S,
     config: &RollupConfig,
+    cells: &RollupCellIndex,
     challenge_cell: &ChallengeCell,
     revert_target_block_hash: &H256,
 ) -> Result<(), Error> {
     // check challenge maturity
-    check_challenge_maturity(config, challenge_cell)?;
+    check_challenge_maturity(syscalls, config, challenge_cell)?;
     // check other challenge cells
-    let has_output_challenge =
-        find_challenge_cell(rollup_type_hash, config, Source::Output)?.is_some();
+    let has_output_challenge = cells.outputs.find_challenge_cell()?.is_some();
     if has_output_challenge {
         return Err(Error::InvalidChallengeCell);
     }
@@ -72,26 +74,141 @@ fn check_challenge_cell(
     Ok(())
 }
 
-pub fn get_receiver_cells_capacity(
+/// Add the capacity and the amount of the sUDT stake collateral of a cell
+fn add_cell_value(
+    total: &mut StakeValue,
+    value: &CellValue,
+    stake_token: Option<&StakeTokenConfig>,
+) {
+    total.capacity = total.capacity.saturating_add(value.capacity.into());
+    if let Some(stake_token) = stake_token {
+        if value.sudt_script_hash.as_slice() == &stake_token.sudt_script_hash[..] {
+            total.amount = total.amount.saturating_add(value.amount);
+        }
+    }
+}
+
+/// Sum the capacity and the amount of the sUDT stake collateral of the receiver cells
+pub fn get_receiver_cells_value<S: Syscalls>(
+    syscalls: &S,
     config: &RollupConfig,
     lock_hash: &[u8; 32],
+    stake_token: Option<&StakeTokenConfig>,
+    source: Source,
+) -> Result<StakeValue, Error> {
+    let mut total = StakeValue::default();
+    for index in search_lock_hashes(syscalls, lock_hash, source) {
+        let value = fetch_capacity_and_sudt_value(syscalls, config, index, source)?;
+        add_cell_value(&mut total, &value, stake_token);
+    }
+    Ok(total)
+}
+
+/// Sum the capacity and the amount of the sUDT stake collateral of the burn cells
+fn get_burn_cells_value<S: Syscalls>(
+    syscalls: &S,
+    config: &RollupConfig,
+    burns: &[usize],
+    stake_token: Option<&StakeTokenConfig>,
     source: Source,
-) -> Result<u128, Error> {
-    let capacity = search_lock_hashes(lock_hash, source)
-        .into_iter()
-        .map(|index| {
-            fetch_capacity_and_sudt_value(config, index, source).map(|value| value.capacity.into())
+) -> Result<StakeValue, Error> {
+    let mut total = StakeValue::default();
+    for &index in burns {
+        let value = fetch_capacity_and_sudt_value(syscalls, config, index, source)?;
+        add_cell_value(&mut total, &value, stake_token);
+    }
+    Ok(total)
+}
+
+/// Calculate the slashed capacity and sUDT amount of every input stake cell by the slashing
+/// schedule, a producer's stake cell is slashed by its own stake and the number of the
+/// producer's reverted blocks
+fn calculate_slashed_values(
+    config: &RollupConfig,
+    stake_cells: &[StakeCell],
+    reverted_blocks: &[RawL2BlockReader],
+) -> Result<Vec<StakeValue>, Error> {
+    let schedule = match slash_schedule(config)? {
+        Some(schedule) => schedule,
+        // forfeit the whole stake
+        None => {
+            return Ok(stake_cells
+                .iter()
+                .map(|cell| StakeValue {
+                    capacity: cell.capacity.into(),
+                    amount: cell.amount,
+                })
+                .collect())
+        }
+    };
+    // producer => number of reverted blocks
+    let mut producers: BTreeMap<[u8; 32], u64> = BTreeMap::new();
+    for block in reverted_blocks {
+        *producers
+            .entry(block.stake_cell_owner_lock_hash().to_entity().unpack())
+            .or_default() += 1;
+    }
+    stake_cells
+        .iter()
+        .map(|cell| {
+            let owner_lock_hash: [u8; 32] = cell.args.owner_lock_hash().unpack();
+            let blocks = producers.get(&owner_lock_hash).copied().unwrap_or(0);
+            let capacity: u128 = cell.capacity.into();
+            if capacity == 0 {
+                return Ok(StakeValue::default());
+            }
+            let slashed = schedule.slashed_capacity(capacity, blocks);
+            // the sUDT is slashed by the same share as the capacity
+            let amount = cell
+                .amount
+                .checked_mul(slashed)
+                .map(|amount| amount / capacity)
+                .ok_or(Error::AmountOverflow)?;
+            Ok(StakeValue {
+                capacity: slashed,
+                amount,
+            })
         })
-        .collect::<Result<Vec<u128>, Error>>()?
-        .into_iter()
-        .sum();
-    Ok(capacity)
+        .collect()
+}
+
+/// The remaining stake must be returned to the output stake cells with the same lock
+fn check_returned_stake_cells(
+    cells: &RollupCellIndex,
+    slashed_values: &[StakeValue],
+) -> Result<(), Error> {
+    // stake lock args => (remaining stake, returned stake)
+    let mut stakes: BTreeMap<Vec<u8>, (StakeValue, StakeValue)> = BTreeMap::new();
+    for (cell, slashed) in cells.inputs.stakes.iter().zip(slashed_values) {
+        let mut remaining = StakeValue::default();
+        remaining.add(cell);
+        let stake = stakes.entry(cell.lock_args()).or_default();
+        stake.0 = stake.0.saturating_add(&remaining.saturating_sub(slashed));
+    }
+    for cell in &cells.outputs.stakes {
+        match stakes.get_mut(&cell.lock_args()) {
+            Some(stake) => stake.1.add(cell),
+            None => {
+                debug!("unexpected output stake cell");
+                return Err(Error::InvalidStakeCell);
+            }
+        }
+    }
+    if stakes
+        .values()
+        .any(|(remaining_stake, returned_stake)| !returned_stake.covers(remaining_stake))
+    {
+        debug!("the remaining stake isn't returned");
+        return Err(Error::InvalidStakeCell);
+    }
+    Ok(())
 }
 
 /// Check rewards
-fn check_rewards(
-    rollup_type_hash: &H256,
+fn check_rewards<S: Syscalls>(
+    syscalls: &S,
     config: &RollupConfig,
+    cells: &RollupCellIndex,
     reverted_blocks: &[RawL2BlockReader],
     challenge_cell: &ChallengeCell,
 ) -> Result<(), Error> {
@@ -100,7 +217,16 @@ fn check_rewards(
         .map(|b| b.stake_cell_owner_lock_hash().to_entity())
         .collect();
 
-    let stake_cells = collect_stake_cells(rollup_type_hash, config, Source::Input)?;
+    let stake_cells = &cells.inputs.stakes;
+    // only the producer's own stake is slashed, a delegated stake cell isn't bound to the blocks
+    // it backed, so a revert can't require it
+    if stake_cells
+        .iter()
+        .any(|cell| cell.producer_lock_hash.is_some())
+    {
+        debug!("delegated stake cells aren't slashed");
+        return Err(Error::InvalidStakeCell);
+    }
     let reverted_stake_cells_set: BTreeSet<_> = stake_cells
         .iter()
         .map(|cell| cell.args.owner_lock_hash())
@@ -111,40 +237,71 @@ fn check_rewards(
         return Err(Error::InvalidStakeCell);
     }
 
-    // calcuate rewards assets & burn assets
-    let total_stake_capacity: u128 = stake_cells.iter().map(|cell| cell.capacity as u128).sum();
+    // slash the stake and return the remaining
+    let slashed_values = calculate_slashed_values(config, stake_cells, reverted_blocks)?;
+    check_returned_stake_cells(cells, &slashed_values)?;
+
+    // calcuate rewards assets & burn assets, for both the capacity and the sUDT
+    let stake_token = stake_token_config(config)?;
+    let total_slashed = slashed_values
+        .iter()
+        .fold(StakeValue::default(), |total, slashed| {
+            total.saturating_add(slashed)
+        });
     let reward_burn_rate: u8 = config.reward_burn_rate().into();
-    let expected_reward_capacity =
-        total_stake_capacity.saturating_mul(reward_burn_rate.into()) / 100;
-    let expected_burn_capacity = total_stake_capacity.saturating_sub(expected_reward_capacity);
-    // collect rewards receiver cells capacity
-    let received_capacity: u128 = {
+    let reward = |value: u128| value.saturating_mul(reward_burn_rate.into()) / 100;
+    let slashed_reward = StakeValue {
+        capacity: reward(total_slashed.capacity),
+        amount: reward(total_slashed.amount),
+    };
+    let expected_burn = total_slashed.saturating_sub(&slashed_reward);
+    // the challenge cell's capacity is returned to the challenger
+    let expected_reward = slashed_reward.saturating_add(&StakeValue {
+        capacity: challenge_cell.value.capacity.into(),
+        amount: 0,
+    });
+    // collect rewards receiver cells value
+    let received = {
         let rewards_receiver_lock_hash = challenge_cell.args.rewards_receiver_lock().hash();
-        let input_capacity =
-            get_receiver_cells_capacity(config, &rewards_receiver_lock_hash, Source::Input)?;
-        let output_capacity =
-            get_receiver_cells_capacity(config, &rewards_receiver_lock_hash, Source::Output)?;
-        output_capacity.saturating_sub(input_capacity)
+        let input_value = get_receiver_cells_value(
+            syscalls,
+            config,
+            &rewards_receiver_lock_hash,
+            stake_token.as_ref(),
+            Source::Input,
+        )?;
+        let output_value = get_receiver_cells_value(
+            syscalls,
+            config,
+            &rewards_receiver_lock_hash,
+            stake_token.as_ref(),
+            Source::Output,
+        )?;
+        output_value.saturating_sub(&input_value)
     };
     // make sure rewards are sent to the challenger
-    if received_capacity
-        < expected_reward_capacity.saturating_add(challenge_cell.value.capacity.into())
-    {
+    if !received.covers(&expected_reward) {
         return Err(Error::InvalidChallengeReward);
     }
     // check burned assets
-    let burned_capacity: u128 = {
-        let input_burned_capacity: u128 = collect_burn_cells(config, Source::Input)?
-            .into_iter()
-            .map(|c| c.value.capacity as u128)
-            .sum();
-        let output_burned_capacity: u128 = collect_burn_cells(config, Source::Output)?
-            .into_iter()
-            .map(|c| c.value.capacity as u128)
-            .sum();
-        output_burned_capacity.saturating_sub(input_burned_capacity)
+    let burned = {
+        let input_burned = get_burn_cells_value(
+            syscalls,
+            config,
+            &cells.inputs.burns,
+            stake_token.as_ref(),
+            Source::Input,
+        )?;
+        let output_burned = get_burn_cells_value(
+            syscalls,
+            config,
+            &cells.outputs.burns,
+            stake_token.as_ref(),
+            Source::Output,
+        )?;
+        output_burned.saturating_sub(&input_burned)
     };
-    if burned_capacity < expected_burn_capacity {
+    if !burned.covers(&expected_burn) {
         return Err(Error::InvalidChallengeReward);
     }
     Ok(())
@@ -274,37 +431,43 @@ fn check_reverted_blocks(
 /// 1. check revert merkle roots
 /// 2. check reverted block root
 /// 3. check other lock cells
-pub fn verify(
-    rollup_type_hash: H256,
+pub fn verify<S: Syscalls>(
+    syscalls: &S,
     config: &RollupConfig,
+    cells: &RollupCellIndex,
     revert_args: RollupRevertReader,
     prev_global_state: &GlobalState,
     post_global_state: &GlobalState,
 ) -> Result<(), Error> {
+    trace!("revert.status");
     check_status(prev_global_state, Status::Halting)?;
     // check rollup lock cells,
     // we do not handle the reverting of lock cells in here,
     // instead we handle them in the submitting layer2 block action
-    check_rollup_lock_cells_except_stake(&rollup_type_hash, config)?;
-    // do not accept stake cells in the output
-    if !collect_stake_cells(&rollup_type_hash, config, Source::Output)?.is_empty() {
-        return Err(Error::InvalidStakeCell);
-    }
+    trace!("revert.rollup_lock_cells");
+    // the output stake cells which return the remaining stake are checked with the rewards
+    check_rollup_lock_cells_except_stake(cells)?;
     // load reverted blocks
     let reverted_blocks_vec = revert_args.reverted_blocks();
     let reverted_blocks: Vec<_> = reverted_blocks_vec.iter().collect();
     // check challenge cells
-    let challenge_cell = find_challenge_cell(&rollup_type_hash, config, Source::Input)?
+    let challenge_cell = cells
+        .inputs
+        .find_challenge_cell()?
         .ok_or(Error::InvalidChallengeCell)?;
     // the first reverted block is challenged target block
     let challenged_block = reverted_blocks.get(0).ok_or(Error::InvalidRevertedBlocks)?;
+    trace!("revert.challenge_cell", index = challenge_cell.index);
     check_challenge_cell(
-        &rollup_type_hash,
+        syscalls,
         config,
-        &challenge_cell,
+        cells,
+        challenge_cell,
         &challenged_block.hash().into(),
     )?;
-    check_rewards(&rollup_type_hash, config, &reverted_blocks, &challenge_cell)?;
+    trace!("revert.rewards");
+    check_rewards(syscalls, config, cells, &reverted_blocks, challenge_cell)?;
+    trace!("revert.reverted_blocks", count = reverted_blocks.len());
     let reverted_global_state = check_reverted_blocks(
         config,
         &reverted_blocks,
@@ -312,6 +475,7 @@ pub fn verify(
         prev_global_state,
         post_global_state,
     )?;
+    trace!("revert.post_global_state");
     if post_global_state != &reverted_global_state {
         return Err(Error::InvalidPostGlobalState);
     }
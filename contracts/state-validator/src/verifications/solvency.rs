@@ -0,0 +1,45 @@
+use gw_utils::{
+    cells::{index::RollupCellIndex, solvency::CustodianAssets},
+    ckb_std::debug,
+    error::Error,
+};
+
+use super::build_assets_map_from_cells;
+
+/// Verify the custodian assets accumulator of the rollup cell
+///
+/// The accumulator records the total assets of the custodian cells,
+/// which is deposited minus withdrawn assets:
+/// post assets = prev assets + output custodian assets - input custodian assets
+///
+/// Every action is checked by the same rule:
+/// * submit block adds the deposits and the reverted withdrawals,
+///   subtracts the withdrawals and the reverted deposits
+/// * revert and challenges don't touch custodian cells, the accumulator is carried over
+/// * DAO withdrawal and custodian compaction conserve the custodian assets
+///
+/// A rollup cell without the accumulator must keep it absent.
+pub fn verify(
+    cells: &RollupCellIndex,
+    prev_assets: Option<CustodianAssets>,
+    post_assets: Option<CustodianAssets>,
+) -> Result<(), Error> {
+    let (mut expected_assets, post_assets) = match (prev_assets, post_assets) {
+        (None, None) => return Ok(()),
+        (Some(prev_assets), Some(post_assets)) => (prev_assets, post_assets),
+        _ => {
+            debug!("the custodian assets accumulator must exist in both rollup cells");
+            return Err(Error::InvalidCustodianAssets);
+        }
+    };
+    let input_assets =
+        build_assets_map_from_cells(cells.inputs.custodians.iter().map(|c| &c.value))?;
+    let output_assets =
+        build_assets_map_from_cells(cells.outputs.custodians.iter().map(|c| &c.value))?;
+    expected_assets.apply(&input_assets, &output_assets)?;
+    if expected_assets != post_assets {
+        debug!("custodian assets mismatch, expected: {:?}", expected_assets);
+        return Err(Error::InvalidCustodianAssets);
+    }
+    Ok(())
+}
@@ -5,28 +5,32 @@ use core::result::Result;
 // https://doc.rust-lang.org/alloc/index.html
 use alloc::{collections::BTreeMap, vec::Vec};
 use gw_state::ckb_smt::smt::{Pair, Tree};
-use gw_state::constants::GW_MAX_KV_PAIRS;
+use gw_state::error::map_smt_error;
 use gw_utils::gw_types::packed::{L2BlockReader, WithdrawalRequestReader};
 
 // Import CKB syscalls and structures
 // https://nervosnetwork.github.io/ckb-std/riscv64imac-unknown-none-elf/doc/ckb_std/index.html
-use crate::ckb_std::{ckb_constants::Source, debug};
+use crate::ckb_std::debug;
 use gw_state::kv_state::KVState;
 use gw_utils::gw_common;
 use gw_utils::gw_types;
 
-use super::check_status;
+use super::{build_assets_map_from_cells, check_assets_conserved, check_status, StakeValue};
 use crate::types::BlockContext;
 use gw_utils::{
     cells::{
-        lock_cells::{
-            collect_custodian_locks, collect_deposit_locks, collect_withdrawal_locks,
-            find_block_producer_stake_cell, find_challenge_cell,
+        dao::DAOState,
+        index::RollupCellIndex,
+        rollup::{deposit_limits, stake_rates, stake_token_config, DepositLimits},
+        solvency::CustodianAssets,
+        types::{
+            CustodianCell, DepositCall, DepositEntry, DepositFee, DepositRequestCell,
+            WithdrawalCell,
         },
-        types::{CellValue, DepositRequestCell, WithdrawalCell},
         utils::build_l2_sudt_script,
     },
     error::Error,
+    trace,
 };
 
 use gw_common::{
@@ -44,30 +48,49 @@ use gw_types::{
     prelude::*,
 };
 
-fn build_assets_map_from_cells<'a, I: Iterator<Item = &'a CellValue>>(
-    cells: I,
-) -> Result<BTreeMap<H256, u128>, Error> {
-    let mut assets = BTreeMap::new();
-    for cell in cells {
-        let sudt_balance = assets.entry(cell.sudt_script_hash).or_insert(0u128);
-        *sudt_balance = sudt_balance
-            .checked_add(cell.amount)
-            .ok_or(Error::AmountOverflow)?;
-        let ckb_balance = assets.entry(CKB_SUDT_SCRIPT_ARGS.into()).or_insert(0u128);
-        *ckb_balance = ckb_balance
-            .checked_add(cell.capacity.into())
-            .ok_or(Error::AmountOverflow)?;
-    }
-    Ok(assets)
+/// The side which has an unmatched item
+enum Unmatched {
+    Left,
+    Right,
+}
+
+/// Match two lists of keys as multisets.
+///
+/// Both sides are sorted and compared in order, so it costs O(n log n) instead of
+/// searching and removing items one by one.
+/// The left side is checked first: return `Unmatched::Left` if a left key has no
+/// corresponded right key, then return `Unmatched::Right` if any right key remains.
+fn match_sorted_keys<K: Ord>(mut lhs: Vec<K>, mut rhs: Vec<K>) -> Result<(), Unmatched> {
+    lhs.sort_unstable();
+    rhs.sort_unstable();
+    let mut rhs_iter = rhs.iter().peekable();
+    let mut has_unmatched_rhs = false;
+    for key in &lhs {
+        // skip right keys which are smaller than the current left key
+        while rhs_iter.peek().map(|k| *k < key).unwrap_or(false) {
+            rhs_iter.next();
+            has_unmatched_rhs = true;
+        }
+        match rhs_iter.next() {
+            Some(k) if k == key => {}
+            _ => return Err(Unmatched::Left),
+        }
+    }
+    if has_unmatched_rhs || rhs_iter.next().is_some() {
+        return Err(Unmatched::Right);
+    }
+    Ok(())
 }
 
 fn check_withdrawal_cells<'a>(
     context: &BlockContext,
-    mut withdrawal_requests: Vec<WithdrawalRequestReader<'a>>,
+    withdrawal_requests: Vec<WithdrawalRequestReader<'a>>,
     withdrawal_cells: &[WithdrawalCell],
 ) -> Result<(), Error> {
     // iter outputs withdrawal cells, check each cell has a corresponded withdrawal request
+    let mut cell_keys = Vec::with_capacity(withdrawal_cells.len());
     for cell in withdrawal_cells {
+        trace!("submit_block.withdrawal_cell", index = cell.index);
         // check withdrawal cell block info
         let withdrawal_block_hash: H256 = cell.args.withdrawal_block_hash().unpack();
         if withdrawal_block_hash != context.block_hash
@@ -77,69 +100,118 @@ fn check_withdrawal_cells<'a>(
             return Err(Error::InvalidWithdrawalCell);
         }
 
-        let cell_account_script_hash: H256 = cell.args.account_script_hash().unpack();
-        // check that there is a corresponded withdrawal request
-        match withdrawal_requests.iter().position(|request| {
+        let account_script_hash: H256 = cell.args.account_script_hash().unpack();
+        cell_keys.push((
+            account_script_hash,
+            cell.value.sudt_script_hash,
+            cell.value.amount,
+            cell.value.capacity,
+        ));
+    }
+    let request_keys: Vec<(H256, H256, u128, u64)> = withdrawal_requests
+        .iter()
+        .map(|request| {
             let raw = request.raw();
-            let account_script_hash: H256 = raw.account_script_hash().unpack();
-            let sudt_script_hash: H256 = raw.sudt_script_hash().unpack();
-            let amount: u128 = raw.amount().unpack();
-            let capacity: u64 = raw.capacity().unpack();
-
-            account_script_hash == cell_account_script_hash
-                && sudt_script_hash == cell.value.sudt_script_hash
-                && amount == cell.value.amount
-                && capacity == cell.value.capacity
-        }) {
-            Some(index) => {
-                withdrawal_requests.remove(index);
-            }
-            None => {
-                debug!("withdrawal cell mismatch the amount of assets");
-                return Err(Error::InvalidWithdrawalCell);
-            }
+            (
+                raw.account_script_hash().unpack(),
+                raw.sudt_script_hash().unpack(),
+                raw.amount().unpack(),
+                raw.capacity().unpack(),
+            )
+        })
+        .collect();
+    // check that there is a corresponded withdrawal request for each cell
+    match match_sorted_keys(cell_keys, request_keys) {
+        Ok(()) => Ok(()),
+        Err(Unmatched::Left) => {
+            debug!("withdrawal cell mismatch the amount of assets");
+            Err(Error::InvalidWithdrawalCell)
+        }
+        Err(Unmatched::Right) => {
+            // Some withdrawal requests hasn't has a corresponded withdrawal cell
+            debug!("withdrawal requests has no corresponded withdrawal cells");
+            Err(Error::InvalidWithdrawalCell)
         }
     }
-    // Some withdrawal requests hasn't has a corresponded withdrawal cell
-    if !withdrawal_requests.is_empty() {
-        debug!(
-            "withdrawal requests has no corresponded withdrawal cells: {}",
-            withdrawal_requests.len()
-        );
-        return Err(Error::InvalidWithdrawalCell);
+}
+
+/// Check unfinalized custodian cells are one-to-one corresponded to the deposit cells
+fn check_custodian_and_deposit_cells(
+    custodian_cells: &[&CustodianCell],
+    deposit_cells: &[DepositRequestCell],
+) -> Result<(), Error> {
+    let custodian_keys: Vec<_> = custodian_cells
+        .iter()
+        .map(|cell| {
+            let value = &cell.value;
+            (
+                cell.args.as_reader().deposit_lock_args().as_slice(),
+                &cell.deposit_extension,
+                &value.sudt_script_hash,
+                value.amount,
+                value.capacity,
+            )
+        })
+        .collect();
+    let deposit_keys: Vec<_> = deposit_cells
+        .iter()
+        .map(|cell| {
+            let value = &cell.value;
+            (
+                cell.args.as_slice(),
+                &cell.extension,
+                &value.sudt_script_hash,
+                value.amount,
+                value.capacity,
+            )
+        })
+        .collect();
+    match_sorted_keys(custodian_keys, deposit_keys).map_err(|unmatched| match unmatched {
+        Unmatched::Left => Error::InvalidCustodianCell,
+        Unmatched::Right => Error::InvalidDepositCell,
+    })
+}
+
+/// Check the DAO custodian cells of a block submission
+///
+/// * unfinalized custodian cells correspond to the deposits, they can't be deposited into the DAO
+/// * finalized custodian cells can be deposited into the DAO, they are counted at the original capacity
+/// * the DAO withdrawals are verified by the DAO withdraw action instead of the block submission
+fn check_dao_custodian_cells(
+    finalized_custodian_cells: &[&CustodianCell],
+    unfinalized_custodian_cells: &[&CustodianCell],
+) -> Result<(), Error> {
+    if unfinalized_custodian_cells
+        .iter()
+        .any(|cell| cell.dao.is_some())
+    {
+        debug!("unfinalized custodian cell can't be deposited into the DAO");
+        return Err(Error::InvalidCustodianCell);
+    }
+    if finalized_custodian_cells
+        .iter()
+        .any(|cell| matches!(cell.dao, Some(DAOState::Withdrawing { .. })))
+    {
+        debug!("withdrawing DAO custodian cell in the block submission");
+        return Err(Error::InvalidCustodianCell);
     }
     Ok(())
 }
 
 fn check_input_custodian_cells(
-    config: &RollupConfig,
     context: &BlockContext,
-    output_withdrawal_cells: Vec<WithdrawalCell>,
+    cells: &RollupCellIndex,
+    output_withdrawal_cells: &[WithdrawalCell],
 ) -> Result<BTreeMap<H256, u128>, Error> {
     // collect input custodian cells
     let (finalized_custodian_cells, unfinalized_custodian_cells): (Vec<_>, Vec<_>) =
-        collect_custodian_locks(&context.rollup_type_hash, config, Source::Input)?
-            .into_iter()
-            .partition(|cell| {
-                let number: u64 = cell.args.deposit_block_number().unpack();
-                number <= context.finalized_number
-            });
+        cells.inputs.custodians.iter().partition(|cell| {
+            let number: u64 = cell.args.deposit_block_number().unpack();
+            number <= context.finalized_number
+        });
+    check_dao_custodian_cells(&finalized_custodian_cells, &unfinalized_custodian_cells)?;
     // check unfinalized custodian cells == reverted deposit requests
-    let mut reverted_deposit_cells =
-        collect_deposit_locks(&context.rollup_type_hash, config, Source::Output)?;
-    for custodian_cell in unfinalized_custodian_cells {
-        let index = reverted_deposit_cells
-            .iter()
-            .position(|cell| {
-                custodian_cell.args.deposit_lock_args() == cell.args
-                    && custodian_cell.value == cell.value
-            })
-            .ok_or(Error::InvalidCustodianCell)?;
-        reverted_deposit_cells.remove(index);
-    }
-    if !reverted_deposit_cells.is_empty() {
-        return Err(Error::InvalidDepositCell);
-    }
+    check_custodian_and_deposit_cells(&unfinalized_custodian_cells, &cells.outputs.deposits)?;
     // check input finalized custodian cells >= withdrawal cells
     let withdrawal_assets =
         build_assets_map_from_cells(output_withdrawal_cells.iter().map(|c| &c.value))?;
@@ -156,39 +228,23 @@ fn check_input_custodian_cells(
 }
 
 fn check_output_custodian_cells(
-    config: &RollupConfig,
     context: &BlockContext,
-    mut deposit_cells: Vec<DepositRequestCell>,
+    cells: &RollupCellIndex,
     input_finalized_assets: BTreeMap<H256, u128>,
 ) -> Result<(), Error> {
     // collect output custodian cells
     let (finalized_custodian_cells, unfinalized_custodian_cells): (Vec<_>, Vec<_>) =
-        collect_custodian_locks(&context.rollup_type_hash, config, Source::Output)?
-            .into_iter()
-            .partition(|cell| {
-                let number: u64 = cell.args.deposit_block_number().unpack();
-                number <= context.finalized_number
-            });
+        cells.outputs.custodians.iter().partition(|cell| {
+            let number: u64 = cell.args.deposit_block_number().unpack();
+            number <= context.finalized_number
+        });
+    check_dao_custodian_cells(&finalized_custodian_cells, &unfinalized_custodian_cells)?;
     // check deposits request cells == unfinalized custodian cells
-    for custodian_cell in unfinalized_custodian_cells {
-        let index = deposit_cells
-            .iter()
-            .position(|cell| {
-                custodian_cell.args.deposit_lock_args() == cell.args
-                    && custodian_cell.value == cell.value
-            })
-            .ok_or(Error::InvalidCustodianCell)?;
-        deposit_cells.remove(index);
-    }
-    if !deposit_cells.is_empty() {
-        return Err(Error::InvalidDepositCell);
-    }
+    check_custodian_and_deposit_cells(&unfinalized_custodian_cells, &cells.inputs.deposits)?;
     // check reverted withdrawals <= finalized custodian cells
     {
-        let reverted_withdrawals =
-            collect_withdrawal_locks(&context.rollup_type_hash, config, Source::Input)?;
         let reverted_withdrawal_assets =
-            build_assets_map_from_cells(reverted_withdrawals.iter().map(|c| &c.value))?;
+            build_assets_map_from_cells(cells.inputs.withdrawals.iter().map(|c| &c.value))?;
         let mut output_finalized_assets =
             build_assets_map_from_cells(finalized_custodian_cells.iter().map(|c| &c.value))?;
         // calculate output finalized assets - reverted withdrawal assets
@@ -199,77 +255,243 @@ fn check_output_custodian_cells(
                 .ok_or(Error::InsufficientOutputFinalizedAssets)?;
         }
         // check the remain inputs finalized assets == outputs finalized assets
-        // 1. output finalized assets - input finalized assets
-        for (k, v) in input_finalized_assets {
-            let balance = output_finalized_assets.entry(k).or_insert(0);
-            *balance = balance
-                .checked_sub(v)
-                .ok_or(Error::InsufficientOutputFinalizedAssets)?;
-        }
-        // 2. check output finalized assets is empty
-        let output_assets_is_empty = output_finalized_assets.iter().all(|(_k, v)| v == &0);
-        if !output_assets_is_empty {
-            return Err(Error::InsufficientInputFinalizedAssets);
-        }
+        check_assets_conserved(input_finalized_assets, output_finalized_assets)?;
     }
     Ok(())
 }
 
-fn check_layer2_deposit(
+/// Mint a deposit entry to the layer2 account, return the account id
+fn mint_deposit_entry(
     rollup_type_hash: &H256,
     config: &RollupConfig,
+    limits: &DepositLimits,
     kv_state: &mut KVState,
+    sudt_script_hash: &H256,
+    entry: &DepositEntry,
+) -> Result<u32, Error> {
+    // check that account's script is a valid EOA or contract script
+    if entry.account_script.hash_type() != ScriptHashType::Type.into() {
+        return Err(Error::UnknownEOAScript);
+    }
+    let code_hash = entry.account_script.code_hash();
+    let is_eoa = config
+        .allowed_eoa_type_hashes()
+        .into_iter()
+        .any(|allowed_code_hash| allowed_code_hash == code_hash);
+    let is_contract = config
+        .allowed_contract_type_hashes()
+        .into_iter()
+        .any(|allowed_code_hash| allowed_code_hash == code_hash);
+    if !is_eoa && !is_contract {
+        return Err(Error::UnknownEOAScript);
+    }
+    // find or create EOA, a contract account must exist
+    let account_id = match kv_state.get_account_id_by_script_hash(&entry.account_script_hash)? {
+        Some(id) => id,
+        None if is_eoa => {
+            // dust deposits can't create accounts
+            let is_sudt = sudt_script_hash.as_slice() != CKB_SUDT_SCRIPT_ARGS;
+            if entry.capacity < limits.min_capacity || (is_sudt && entry.amount < limits.min_amount)
+            {
+                debug!("the deposit is too small to create an account");
+                return Err(Error::InvalidDepositCell);
+            }
+            kv_state.create_account(entry.account_script_hash)?
+        }
+        None => {
+            debug!("deposit to a contract account which doesn't exist");
+            return Err(Error::AccountNotFound);
+        }
+    };
+    let short_address = to_short_address(&entry.account_script_hash);
+    // mint CKB
+    kv_state.mint_sudt(CKB_SUDT_ACCOUNT_ID, short_address, entry.capacity.into())?;
+    if sudt_script_hash.as_slice() == CKB_SUDT_SCRIPT_ARGS {
+        if entry.amount != 0 {
+            // SUDT amount must equals to zero if sudt script hash is equals to CKB_SUDT_SCRIPT_ARGS
+            return Err(Error::InvalidDepositCell);
+        }
+        return Ok(account_id);
+    }
+    // find or create Simple UDT account
+    let l2_sudt_script = build_l2_sudt_script(rollup_type_hash, config, sudt_script_hash);
+    let l2_sudt_script_hash: [u8; 32] = l2_sudt_script.hash();
+    let sudt_id = match kv_state.get_account_id_by_script_hash(&l2_sudt_script_hash.into())? {
+        Some(id) => id,
+        None => kv_state.create_account(l2_sudt_script_hash.into())?,
+    };
+    // prevent fake CKB SUDT, the caller should filter these invalid deposits
+    if sudt_id == CKB_SUDT_ACCOUNT_ID {
+        return Err(Error::InvalidDepositCell);
+    }
+    // mint SUDT
+    kv_state.mint_sudt(sudt_id, short_address, entry.amount)?;
+    Ok(account_id)
+}
+
+/// Check the deposits of the block don't exceed the deposit caps
+fn check_deposit_caps(
+    limits: &DepositLimits,
     deposit_cells: &[DepositRequestCell],
 ) -> Result<(), Error> {
-    for request in deposit_cells {
-        // check that account's script is a valid EOA script
-        if request.account_script.hash_type() != ScriptHashType::Type.into() {
-            return Err(Error::UnknownEOAScript);
+    let deposited_assets = build_assets_map_from_cells(deposit_cells.iter().map(|c| &c.value))?;
+    for (sudt_script_hash, cap) in &limits.caps {
+        let deposited = deposited_assets
+            .get(&H256::from(*sudt_script_hash))
+            .copied()
+            .unwrap_or(0);
+        if deposited > *cap {
+            debug!(
+                "deposits exceed the cap of {:?}, deposited: {} cap: {}",
+                sudt_script_hash, deposited, cap
+            );
+            return Err(Error::InvalidDepositCell);
         }
-        if !config
-            .allowed_eoa_type_hashes()
-            .into_iter()
-            .any(|code_hash| code_hash == request.account_script.code_hash())
-        {
-            return Err(Error::UnknownEOAScript);
+    }
+    Ok(())
+}
+
+/// Pay the deposit fee to the block producer,
+/// the fee is deducted from the CKB or the sUDT minted by the deposit entry
+fn pay_deposit_fee(
+    rollup_type_hash: &H256,
+    config: &RollupConfig,
+    kv_state: &mut KVState,
+    block_producer_id: u32,
+    sudt_script_hash: &H256,
+    entry: &DepositEntry,
+    fee: &DepositFee,
+) -> Result<(), Error> {
+    let minted = if fee.sudt_id == CKB_SUDT_ACCOUNT_ID {
+        entry.capacity.into()
+    } else if sudt_script_hash.as_slice() != CKB_SUDT_SCRIPT_ARGS {
+        let l2_sudt_script_hash: [u8; 32] =
+            build_l2_sudt_script(rollup_type_hash, config, sudt_script_hash).hash();
+        let deposit_sudt_id =
+            kv_state.get_account_id_by_script_hash(&l2_sudt_script_hash.into())?;
+        if deposit_sudt_id != Some(fee.sudt_id) {
+            debug!("the deposit fee isn't paid in the deposited assets");
+            return Err(Error::InvalidDepositCell);
         }
-        // find or create EOA
-        if kv_state
-            .get_account_id_by_script_hash(&request.account_script_hash)?
-            .is_none()
+        entry.amount
+    } else {
+        debug!("the deposit fee isn't paid in the deposited assets");
+        return Err(Error::InvalidDepositCell);
+    };
+    if fee.amount > minted {
+        debug!("the deposit fee exceeds the deposit");
+        return Err(Error::InvalidDepositCell);
+    }
+    let block_producer_script_hash = kv_state.get_script_hash(block_producer_id)?;
+    kv_state.burn_sudt(
+        fee.sudt_id,
+        to_short_address(&entry.account_script_hash),
+        fee.amount,
+    )?;
+    kv_state.mint_sudt(
+        fee.sudt_id,
+        to_short_address(&block_producer_script_hash),
+        fee.amount,
+    )?;
+    Ok(())
+}
+
+/// Mint the deposits to layer2 accounts, a batch deposit mints each of its entries,
+/// the deposit fees are paid to the block producer,
+/// return the deposit calls with the account id of the depositor in the order of deposits
+fn check_layer2_deposit<'a>(
+    rollup_type_hash: &H256,
+    config: &RollupConfig,
+    kv_state: &mut KVState,
+    block: &L2BlockReader,
+    deposit_cells: &'a [DepositRequestCell],
+) -> Result<Vec<(u32, &'a DepositCall)>, Error> {
+    let block_producer_id: u32 = block.raw().block_producer_id().unpack();
+    let limits = deposit_limits(config)?;
+    check_deposit_caps(&limits, deposit_cells)?;
+    let mut deposit_calls = Vec::new();
+    for request in deposit_cells {
+        trace!("submit_block.deposit_cell", index = request.index);
+        let sudt_script_hash = &request.value.sudt_script_hash;
+        if sudt_script_hash.as_slice() != CKB_SUDT_SCRIPT_ARGS
+            && !limits.is_allowed_sudt(sudt_script_hash.as_slice())
         {
-            let _new_id = kv_state.create_account(request.account_script_hash)?;
+            debug!("the sUDT isn't accepted by deposits");
+            return Err(Error::InvalidDepositCell);
         }
-        let short_address = to_short_address(&request.account_script_hash);
-        // mint CKB
-        kv_state.mint_sudt(
-            CKB_SUDT_ACCOUNT_ID,
-            short_address,
-            request.value.capacity.into(),
-        )?;
-        if request.value.sudt_script_hash.as_slice() == CKB_SUDT_SCRIPT_ARGS {
-            if request.value.amount != 0 {
-                // SUDT amount must equals to zero if sudt script hash is equals to CKB_SUDT_SCRIPT_ARGS
+        for entry in &request.entries {
+            // a deposit call is the first transaction of the account which the deposit creates
+            if request.call.is_some()
+                && kv_state
+                    .get_account_id_by_script_hash(&entry.account_script_hash)?
+                    .is_some()
+            {
+                debug!("the deposit call is sent by an existing account");
                 return Err(Error::InvalidDepositCell);
             }
-            continue;
-        }
-        // find or create Simple UDT account
-        let l2_sudt_script =
-            build_l2_sudt_script(rollup_type_hash, config, &request.value.sudt_script_hash);
-        let l2_sudt_script_hash: [u8; 32] = l2_sudt_script.hash();
-        let sudt_id = match kv_state.get_account_id_by_script_hash(&l2_sudt_script_hash.into())? {
-            Some(id) => id,
-            None => kv_state.create_account(l2_sudt_script_hash.into())?,
-        };
-        // prevent fake CKB SUDT, the caller should filter these invalid deposits
-        if sudt_id == CKB_SUDT_ACCOUNT_ID {
-            return Err(Error::InvalidDepositCell);
+            let account_id = mint_deposit_entry(
+                rollup_type_hash,
+                config,
+                &limits,
+                kv_state,
+                &request.value.sudt_script_hash,
+                entry,
+            )?;
+            // only a single deposit carries a fee or a call
+            if let Some(fee) = &request.fee {
+                pay_deposit_fee(
+                    rollup_type_hash,
+                    config,
+                    kv_state,
+                    block_producer_id,
+                    &request.value.sudt_script_hash,
+                    entry,
+                    fee,
+                )?;
+            }
+            if let Some(call) = &request.call {
+                deposit_calls.push((account_id, call));
+            }
         }
-        // mint SUDT
-        kv_state.mint_sudt(sudt_id, short_address, request.value.amount)?;
     }
 
+    Ok(deposit_calls)
+}
+
+/// Check the deposit calls are executed right after the deposits are minted
+///
+/// The block transactions start with the deposit calls in the order of deposits,
+/// a deposit call is sent by the account created by the deposit with the nonce 0.
+/// It's signed by the layer2 lock like other transactions, the depositor only names
+/// the layer2 lock, so the signature and the execution are challenged like other transactions.
+fn check_deposit_calls(
+    block: &L2BlockReader,
+    deposit_calls: &[(u32, &DepositCall)],
+) -> Result<(), Error> {
+    let transactions = block.transactions();
+    if transactions.len() < deposit_calls.len() {
+        debug!(
+            "missing deposit calls, transactions: {} deposit calls: {}",
+            transactions.len(),
+            deposit_calls.len()
+        );
+        return Err(Error::InvalidBlock);
+    }
+    for (index, (tx, (from_id, call))) in transactions.iter().zip(deposit_calls).enumerate() {
+        let raw_tx = tx.raw();
+        let tx_from_id: u32 = raw_tx.from_id().unpack();
+        let tx_to_id: u32 = raw_tx.to_id().unpack();
+        let tx_nonce: u32 = raw_tx.nonce().unpack();
+        // the account is created by the deposit, so the call is its first transaction
+        if tx_from_id != *from_id
+            || tx_nonce != 0
+            || tx_to_id != call.to_id
+            || raw_tx.args().raw_data() != call.args.as_ref()
+        {
+            debug!("deposit call mismatch the transaction, index: {}", index);
+            return Err(Error::InvalidBlock);
+        }
+    }
     Ok(())
 }
 
@@ -304,7 +526,8 @@ fn check_layer2_withdrawal(
     };
     let block_producer_short_address = to_short_address(&block_producer_script_hash);
 
-    for request in withdrawals.iter() {
+    for (index, request) in withdrawals.iter().enumerate() {
+        trace!("submit_block.withdrawal_request", index = index);
         let raw = request.raw();
         let l2_sudt_script_hash: [u8; 32] =
             build_l2_sudt_script(rollup_type_hash, config, &raw.sudt_script_hash().unpack()).hash();
@@ -354,7 +577,6 @@ fn check_layer2_withdrawal(
 fn load_block_context_and_state<'a>(
     rollup_type_hash: H256,
     config: &RollupConfig,
-    tree_buffer: &'a mut [Pair],
     kv_state_proof: &'a Bytes,
     l2block: &L2BlockReader,
     prev_global_state: &GlobalState,
@@ -384,14 +606,14 @@ fn load_block_context_and_state<'a>(
         block_tree
             .update(&block_smt_key, &H256::zero().into())
             .map_err(|err| {
-                debug!("[verify block exist] update kv error: {}", err);
-                Error::MerkleProof
+                debug!("[verify block exist] update kv error: {:?}", err);
+                map_smt_error(err)
             })?;
         block_tree
             .verify(&prev_block_root, &block_proof)
             .map_err(|err| {
-                debug!("[verify block exist] merkle verify error: {}", err);
-                Error::MerkleProof
+                debug!("[verify block exist] merkle verify error: {:?}", err);
+                map_smt_error(err)
             })?;
     }
 
@@ -409,14 +631,14 @@ fn load_block_context_and_state<'a>(
         block_tree
             .update(&block_smt_key, &block_hash.into())
             .map_err(|err| {
-                debug!("[verify block exist] update kv error: {}", err);
-                Error::MerkleProof
+                debug!("[verify block exist] update kv error: {:?}", err);
+                map_smt_error(err)
             })?;
         block_tree
             .verify(&post_block_root, &block_proof)
             .map_err(|err| {
-                debug!("[verify block exist] merkle verify error: {}", err);
-                Error::MerkleProof
+                debug!("[verify block exist] merkle verify error: {:?}", err);
+                map_smt_error(err)
             })?;
     }
 
@@ -437,16 +659,20 @@ fn load_block_context_and_state<'a>(
     let finalized_number = number.saturating_sub(config.finality_blocks().unpack());
 
     // Check pre account merkle proof
-    let kv_state = KVState::build(
-        tree_buffer,
-        l2block.kv_state(),
+    // the kv_state contains every key touched by the block, size the buffer by it
+    let kv_pairs = l2block.kv_state();
+    let kv_state = KVState::build_with_capacity(
+        kv_pairs.len(),
+        kv_pairs,
         kv_state_proof,
         account_count,
         Some(prev_account_root),
     )?;
-    if !kv_state.is_empty() && kv_state.calculate_root()? != prev_account_root {
-        debug!("Block context wrong, kv state doesn't match the prev_account_root");
-        return Err(Error::MerkleProof);
+    if !kv_state.is_empty() {
+        kv_state.verify_root(&prev_account_root).map_err(|err| {
+            debug!("Block context wrong, kv state doesn't match the prev_account_root");
+            err
+        })?;
     }
 
     let context = BlockContext {
@@ -460,33 +686,79 @@ fn load_block_context_and_state<'a>(
     Ok((context, kv_state))
 }
 
+/// Calculate the required capacity of the block producer's stake cell
+fn required_staking_capacity(
+    config: &RollupConfig,
+    block: &L2BlockReader,
+    prev_custodian_assets: Option<&CustodianAssets>,
+) -> Result<u128, Error> {
+    let base: u64 = config.required_staking_capacity().unpack();
+    let rates = stake_rates(config)?;
+    // the TVL is recorded by the custodian assets accumulator
+    let tvl = match prev_custodian_assets {
+        Some(assets) => assets.get(&CKB_SUDT_SCRIPT_ARGS),
+        None if rates.tvl_rate == 0 => 0,
+        None => {
+            debug!("the stake tvl rate requires the custodian assets accumulator");
+            return Err(Error::InvalidCustodianAssets);
+        }
+    };
+    let withdrawn = block
+        .withdrawals()
+        .iter()
+        .map(|withdrawal| {
+            let capacity: u64 = withdrawal.raw().capacity().unpack();
+            u128::from(capacity)
+        })
+        .sum();
+    rates
+        .required_capacity(base, tvl, withdrawn)
+        .ok_or(Error::AmountOverflow)
+}
+
 fn verify_block_producer(
     config: &RollupConfig,
-    context: &BlockContext,
+    cells: &RollupCellIndex,
     block: &L2BlockReader,
+    prev_custodian_assets: Option<&CustodianAssets>,
 ) -> Result<(), Error> {
     let raw_block = block.raw();
     let owner_lock_hash = raw_block.stake_cell_owner_lock_hash();
     // make sure we have one stake cell in the output
-    let output_stake_cell = find_block_producer_stake_cell(
-        &context.rollup_type_hash,
-        config,
-        Source::Output,
-        &owner_lock_hash,
-    )?
-    .ok_or(Error::InvalidStakeCell)?;
-    // check stake cell capacity
-    if output_stake_cell.capacity < config.required_staking_capacity().unpack() {
-        debug!("stake cell's capacity is insufficient");
+    let output_stake_cell = cells
+        .outputs
+        .find_block_producer_stake_cell(&owner_lock_hash)?
+        .ok_or(Error::InvalidStakeCell)?;
+    // check stake cell capacity, the stake cells delegated to the producer are counted,
+    // but a revert only slashes the producer's own stake, see `SlashSchedule`
+    let required_capacity = required_staking_capacity(config, block, prev_custodian_assets)?;
+    let mut stake = StakeValue::default();
+    stake.add(output_stake_cell);
+    for cell in cells.outputs.delegated_stake_cells() {
+        stake.add(cell);
+    }
+    if stake.capacity < required_capacity {
+        debug!(
+            "stake cell's capacity is insufficient, required: {}",
+            required_capacity
+        );
         return Err(Error::InvalidStakeCell);
     }
+    // check the sUDT stake collateral
+    if let Some(stake_token) = stake_token_config(config)? {
+        if stake.amount < stake_token.required_amount {
+            debug!(
+                "stake cell's sUDT amount is insufficient, required: {}",
+                stake_token.required_amount
+            );
+            return Err(Error::InvalidStakeCell);
+        }
+    }
     // make sure input stake cell is identical to the output stake cell if we have one
-    if let Some(input_stake_cell) = find_block_producer_stake_cell(
-        &context.rollup_type_hash,
-        config,
-        Source::Input,
-        &owner_lock_hash,
-    )? {
+    if let Some(input_stake_cell) = cells
+        .inputs
+        .find_block_producer_stake_cell(&owner_lock_hash)?
+    {
         let expected_stake_lock_args = input_stake_cell
             .args
             .as_builder()
@@ -494,15 +766,61 @@ fn verify_block_producer(
             .build();
         if expected_stake_lock_args != output_stake_cell.args
             || input_stake_cell.capacity > output_stake_cell.capacity
+            || input_stake_cell.amount > output_stake_cell.amount
         {
             debug!("the output stake cell isn't corresponded to the input one");
             return Err(Error::InvalidStakeCell);
         }
     }
+    check_delegated_stake_cells(cells, raw_block.number().unpack())?;
 
     Ok(())
 }
 
+/// Delegated stake cells are bumped to the submitted block like the producer's stake cell,
+/// they may be merged or added, but the stake of a delegator must not decrease.
+/// The cells which requested unbonding are left to the delegators, the unbonding period
+/// starts from the request and can't be restarted by the block producer.
+fn check_delegated_stake_cells(cells: &RollupCellIndex, block_number: u64) -> Result<(), Error> {
+    let delegated_stake_cells = cells
+        .inputs
+        .delegated_stake_cells()
+        .chain(cells.outputs.delegated_stake_cells());
+    for cell in delegated_stake_cells {
+        if cell.unbonding {
+            debug!("the delegated stake cell is unbonding");
+            return Err(Error::InvalidStakeCell);
+        }
+    }
+    // delegator => (input stake, output stake)
+    let mut delegations: BTreeMap<[u8; 32], (StakeValue, StakeValue)> = BTreeMap::new();
+    for cell in cells.inputs.delegated_stake_cells() {
+        let delegation = delegations
+            .entry(cell.args.owner_lock_hash().unpack())
+            .or_default();
+        delegation.0.add(cell);
+    }
+    for cell in cells.outputs.delegated_stake_cells() {
+        let stake_block_number: u64 = cell.args.stake_block_number().unpack();
+        if stake_block_number != block_number {
+            debug!("the delegated stake cell isn't bumped to the submitted block");
+            return Err(Error::InvalidStakeCell);
+        }
+        let delegation = delegations
+            .entry(cell.args.owner_lock_hash().unpack())
+            .or_default();
+        delegation.1.add(cell);
+    }
+    if delegations
+        .values()
+        .any(|(input_stake, output_stake)| !output_stake.covers(input_stake))
+    {
+        debug!("the delegated stake decreases");
+        return Err(Error::InvalidStakeCell);
+    }
+    Ok(())
+}
+
 fn check_state_checkpoints(block: &L2BlockReader) -> Result<(), Error> {
     let raw_block = block.raw();
     let checkpoint_list = raw_block.state_checkpoint_list();
@@ -642,63 +960,79 @@ fn check_block_withdrawals(block: &L2BlockReader) -> Result<(), Error> {
 pub fn verify(
     rollup_type_hash: H256,
     config: &RollupConfig,
+    cells: &RollupCellIndex,
     block: &L2BlockReader,
     prev_global_state: &GlobalState,
     post_global_state: &GlobalState,
+    prev_custodian_assets: Option<&CustodianAssets>,
 ) -> Result<(), Error> {
+    trace!("submit_block.status");
     check_status(prev_global_state, Status::Running)?;
 
     // check checkpoints
+    trace!("submit_block.state_checkpoints");
     check_state_checkpoints(block)?;
 
     // Check withdrawals root
+    trace!("submit_block.withdrawals_root");
     check_block_withdrawals(block)?;
 
-    let mut tree_buffer = [Pair::default(); GW_MAX_KV_PAIRS];
     let kv_state_proof: Bytes = block.kv_state_proof().unpack();
 
+    trace!("submit_block.block_context");
     let (context, mut kv_state) = load_block_context_and_state(
         rollup_type_hash,
         config,
-        &mut tree_buffer,
         &kv_state_proof,
         block,
         prev_global_state,
         post_global_state,
     )?;
     // Verify block producer
-    verify_block_producer(config, &context, block)?;
-    // collect withdrawal cells
-    let withdrawal_cells: Vec<_> =
-        collect_withdrawal_locks(&context.rollup_type_hash, config, Source::Output)?;
-    // collect deposit cells
-    let deposit_cells = collect_deposit_locks(&context.rollup_type_hash, config, Source::Input)?;
+    trace!("submit_block.block_producer");
+    verify_block_producer(config, cells, block, prev_custodian_assets)?;
+    // withdrawal cells
+    let withdrawal_cells = &cells.outputs.withdrawals;
+    // deposit cells
+    let deposit_cells = &cells.inputs.deposits;
     // Check new cells and reverted cells: deposit / withdrawal / custodian
     let withdrawal_requests_vec = block.withdrawals();
     let withdrawal_requests = withdrawal_requests_vec.iter().collect();
-    check_withdrawal_cells(&context, withdrawal_requests, &withdrawal_cells)?;
-    let input_finalized_assets = check_input_custodian_cells(config, &context, withdrawal_cells)?;
-    check_output_custodian_cells(
-        config,
-        &context,
-        deposit_cells.clone(),
-        input_finalized_assets,
-    )?;
+    trace!("submit_block.withdrawal_cells");
+    check_withdrawal_cells(&context, withdrawal_requests, withdrawal_cells)?;
+    trace!("submit_block.input_custodian_cells");
+    let input_finalized_assets = check_input_custodian_cells(&context, cells, withdrawal_cells)?;
+    trace!("submit_block.output_custodian_cells");
+    check_output_custodian_cells(&context, cells, input_finalized_assets)?;
     // Ensure no challenge cells in submitting block transaction
-    if find_challenge_cell(&rollup_type_hash, config, Source::Input)?.is_some()
-        || find_challenge_cell(&rollup_type_hash, config, Source::Output)?.is_some()
+    trace!("submit_block.challenge_cells");
+    if cells.inputs.find_challenge_cell()?.is_some()
+        || cells.outputs.find_challenge_cell()?.is_some()
     {
         return Err(Error::InvalidChallengeCell);
     }
 
     // Withdrawal token: Layer2 SUDT -> withdrawals
+    trace!("submit_block.layer2_withdrawal");
     check_layer2_withdrawal(&rollup_type_hash, config, &mut kv_state, block)?;
     // Mint token: deposit requests -> layer2 SUDT
-    check_layer2_deposit(&rollup_type_hash, config, &mut kv_state, &deposit_cells)?;
+    trace!("submit_block.layer2_deposit");
+    let deposit_calls = check_layer2_deposit(
+        &rollup_type_hash,
+        config,
+        &mut kv_state,
+        block,
+        deposit_cells,
+    )?;
     // Check transactions
+    trace!("submit_block.transactions");
     check_block_transactions(block, &kv_state)?;
+    // Deposit calls are the first transactions of the block
+    trace!("submit_block.deposit_calls");
+    check_deposit_calls(block, &deposit_calls)?;
 
     // Verify Post state
+    trace!("submit_block.post_global_state");
     let actual_post_global_state = {
         // because of the optimistic challenge mechanism,
         // we just believe the post account in the block,
@@ -733,6 +1067,10 @@ pub fn verify_reverted_block_hashes(
     prev_global_state: &GlobalState,
 ) -> Result<(), Error> {
     let reverted_block_root = prev_global_state.reverted_block_root().unpack();
+    trace!(
+        "submit_block.reverted_block_hashes",
+        count = reverted_block_hashes.len()
+    );
     if reverted_block_hashes.is_empty() && reverted_block_proof.is_empty() {
         return Ok(());
     }
@@ -742,15 +1080,15 @@ pub fn verify_reverted_block_hashes(
         block_tree
             .update(&key.into(), &H256::one().into())
             .map_err(|err| {
-                debug!("[verify reverted block] update kv error: {}", err);
-                Error::MerkleProof
+                debug!("[verify reverted block] update kv error: {:?}", err);
+                map_smt_error(err)
             })?;
     }
     block_tree
         .verify(&reverted_block_root, &reverted_block_proof)
         .map_err(|err| {
-            debug!("[verify reverted block] merkle verify error: {}", err);
-            Error::MerkleProof
+            debug!("[verify reverted block] merkle verify error: {:?}", err);
+            map_smt_error(err)
         })?;
     Ok(())
 }
@@ -15,7 +15,7 @@ use crate::{
 };
 use gw_utils::{
     cells::utils::search_lock_hash, ckb_std::high_level::load_witness_args, error::Error,
-    gw_common::H256,
+    gw_common::H256, syscalls::CKBSyscalls, trace,
 };
 
 /// Eth account lock
@@ -32,12 +32,14 @@ pub fn main() -> Result<(), Error> {
     let (owner_lock_hash, message) = parse_data()?;
 
     // check owner lock hash cell
+    trace!("tron_account_lock.owner_cell");
     // to prevent others unlock this cell
-    if search_lock_hash(&owner_lock_hash, Source::Input).is_none() {
+    if search_lock_hash(&CKBSyscalls, &owner_lock_hash, Source::Input).is_none() {
         return Err(Error::OwnerCellNotFound);
     }
 
     // verify signature
+    trace!("tron_account_lock.signature");
     debug!("Verify message signature {:?}", &message);
     verify_message_signature(tron_address, message)?;
 
@@ -1,27 +1,24 @@
 // Import from `core` instead of from `std` since we are in no-std mode
 use core::result::Result;
 
-use gw_utils::{
-    cells::rollup::MAX_ROLLUP_WITNESS_SIZE,
-    gw_types::{
-        self,
-        core::ScriptHashType,
-        packed::{
-            CustodianLockArgs, CustodianLockArgsReader, RollupActionUnionReader,
-            UnlockWithdrawalWitnessUnion, WithdrawalLockArgs, WithdrawalLockArgsReader,
-        },
+use gw_utils::gw_types::{
+    self,
+    core::ScriptHashType,
+    packed::{
+        CustodianLockArgs, CustodianLockArgsReader, UnlockWithdrawalWitnessUnion,
+        WithdrawalLockArgs, WithdrawalLockArgsReader,
     },
 };
 use gw_utils::{
     cells::{
-        rollup::{
-            load_rollup_config, parse_rollup_action, search_rollup_cell, search_rollup_state,
-        },
+        rollup::{load_rollup_config, search_rollup_cell, search_rollup_state},
         token::fetch_token_amount_by_lock_hash,
         token::TokenType,
         utils::search_lock_hash,
     },
-    ckb_std::high_level::load_cell_lock,
+    syscalls::Syscalls,
+    trace,
+    witness::{RollupActionType, RollupWitness},
 };
 
 // Import CKB syscalls and structures
@@ -29,9 +26,6 @@ use gw_utils::{
 use crate::ckb_std::{
     ckb_constants::Source,
     ckb_types::{self, bytes::Bytes, prelude::Unpack as CKBUnpack},
-    high_level::{
-        load_cell_capacity, load_cell_data, load_cell_type_hash, load_script, load_witness_args,
-    },
 };
 
 use crate::error::Error;
@@ -62,12 +56,12 @@ fn parse_lock_args(
     }
 }
 
-pub fn main() -> Result<(), Error> {
-    let script = load_script()?;
+pub fn main<S: Syscalls>(syscalls: &S) -> Result<(), Error> {
+    let script = syscalls.load_script()?;
     let (rollup_type_hash, lock_args) = parse_lock_args(&script)?;
 
     // load unlock arguments from witness
-    let witness_args = load_witness_args(0, Source::GroupInput)?;
+    let witness_args = syscalls.load_witness_args(0, Source::GroupInput)?;
     let unlock_args = {
         let unlock_args: Bytes = witness_args
             .lock()
@@ -83,38 +77,33 @@ pub fn main() -> Result<(), Error> {
     // execute verification
     match unlock_args.to_enum() {
         UnlockWithdrawalWitnessUnion::UnlockWithdrawalViaRevert(unlock_args) => {
-            let mut rollup_action_witness = [0u8; MAX_ROLLUP_WITNESS_SIZE];
+            trace!("withdrawal_lock.unlock_via_revert");
             let withdrawal_block_hash = lock_args.withdrawal_block_hash();
             // prove the block is reverted
-            let rollup_action = {
-                let index = search_rollup_cell(&rollup_type_hash, Source::Output)
+            let rollup_witness = {
+                let index = search_rollup_cell(syscalls, &rollup_type_hash, Source::Output)
                     .ok_or(Error::RollupCellNotFound)?;
-                parse_rollup_action(&mut rollup_action_witness, index, Source::Output)?
+                RollupWitness::load(syscalls, index, Source::Output)?
             };
-            match rollup_action.to_enum() {
-                RollupActionUnionReader::RollupSubmitBlock(args) => {
-                    if !args
-                        .reverted_block_hashes()
-                        .iter()
-                        .any(|hash| hash.as_slice() == withdrawal_block_hash.as_slice())
-                    {
-                        return Err(Error::InvalidRevertedBlocks);
-                    }
-                }
-                _ => {
-                    return Err(Error::InvalidRevertedBlocks);
-                }
+            if rollup_witness.action_type() != RollupActionType::SubmitBlock
+                || !rollup_witness.contains_reverted_block_hash(withdrawal_block_hash.as_slice())?
+            {
+                return Err(Error::InvalidRevertedBlocks);
             }
             let custodian_lock_hash: [u8; 32] = unlock_args.custodian_lock_hash().unpack();
             // check there are a reverted custodian lock in the output
-            let custodian_cell_index = match search_lock_hash(&custodian_lock_hash, Source::Output)
-            {
-                Some(index) => index,
-                None => return Err(Error::InvalidOutput),
-            };
+            let custodian_cell_index =
+                match search_lock_hash(syscalls, &custodian_lock_hash, Source::Output) {
+                    Some(index) => index,
+                    None => return Err(Error::InvalidOutput),
+                };
 
             // check reverted custodian deposit info.
-            let custodian_lock = load_cell_lock(custodian_cell_index, Source::Output)?;
+            trace!(
+                "withdrawal_lock.custodian_cell",
+                index = custodian_cell_index
+            );
+            let custodian_lock = syscalls.load_cell_lock(custodian_cell_index, Source::Output)?;
             let custodian_lock_args = {
                 let args: Bytes = custodian_lock.args().unpack();
                 if args.len() < rollup_type_hash.len() {
@@ -133,9 +122,9 @@ pub fn main() -> Result<(), Error> {
                 custodian_lock_args.deposit_block_hash().unpack();
             let custodian_deposit_block_number: u64 =
                 custodian_lock_args.deposit_block_number().unpack();
-            let global_state = search_rollup_state(&rollup_type_hash, Source::Input)?
+            let global_state = search_rollup_state(syscalls, &rollup_type_hash, Source::Input)?
                 .ok_or(Error::RollupCellNotFound)?;
-            let config = load_rollup_config(&global_state.rollup_config_hash().unpack())?;
+            let config = load_rollup_config(syscalls, &global_state.rollup_config_hash().unpack())?;
             if custodian_lock.code_hash().as_slice()
                 != config.custodian_script_type_hash().as_slice()
                 || custodian_lock.hash_type() != ScriptHashType::Type.into()
@@ -146,19 +135,21 @@ pub fn main() -> Result<(), Error> {
             }
 
             // check capacity, data_hash, type_hash
-            check_output_cell_has_same_content(custodian_cell_index)?;
+            check_output_cell_has_same_content(syscalls, custodian_cell_index)?;
             Ok(())
         }
         UnlockWithdrawalWitnessUnion::UnlockWithdrawalViaFinalize(_unlock_args) => {
+            trace!("withdrawal_lock.unlock_via_finalize");
             // try search rollup state from deps
-            let global_state = match search_rollup_state(&rollup_type_hash, Source::CellDep)? {
-                Some(state) => state,
-                None => {
-                    // then try search rollup state from inputs
-                    search_rollup_state(&rollup_type_hash, Source::Input)?
-                        .ok_or(Error::RollupCellNotFound)?
-                }
-            };
+            let global_state =
+                match search_rollup_state(syscalls, &rollup_type_hash, Source::CellDep)? {
+                    Some(state) => state,
+                    None => {
+                        // then try search rollup state from inputs
+                        search_rollup_state(syscalls, &rollup_type_hash, Source::Input)?
+                            .ok_or(Error::RollupCellNotFound)?
+                    }
+                };
             // check finality
             let withdrawal_block_number: u64 = lock_args.withdrawal_block_number().unpack();
             let last_finalized_block_number: u64 =
@@ -170,23 +161,38 @@ pub fn main() -> Result<(), Error> {
             }
 
             // withdrawal lock is finalized, unlock for owner
-            if search_lock_hash(&lock_args.owner_lock_hash().unpack(), Source::Input).is_none() {
+            if search_lock_hash(
+                syscalls,
+                &lock_args.owner_lock_hash().unpack(),
+                Source::Input,
+            )
+            .is_none()
+            {
                 return Err(Error::OwnerCellNotFound);
             }
             Ok(())
         }
 
         UnlockWithdrawalWitnessUnion::UnlockWithdrawalViaTrade(unlock_args) => {
+            trace!("withdrawal_lock.unlock_via_trade");
             // rollup cell does not in this tx, which means this is a buying tx
             // return success if tx has enough output send to owner
             // make sure output >= input + sell_amount
             let payment_lock_hash = lock_args.payment_lock_hash().unpack();
             let sudt_script_hash: [u8; 32] = lock_args.sudt_script_hash().unpack();
             let token_type: TokenType = sudt_script_hash.into();
-            let input_token =
-                fetch_token_amount_by_lock_hash(&payment_lock_hash, &token_type, Source::Input)?;
-            let output_token =
-                fetch_token_amount_by_lock_hash(&payment_lock_hash, &token_type, Source::Output)?;
+            let input_token = fetch_token_amount_by_lock_hash(
+                syscalls,
+                &payment_lock_hash,
+                &token_type,
+                Source::Input,
+            )?;
+            let output_token = fetch_token_amount_by_lock_hash(
+                syscalls,
+                &payment_lock_hash,
+                &token_type,
+                Source::Output,
+            )?;
             let sell_amount: u128 = lock_args.sell_amount().unpack();
             let sell_capacity: u64 = lock_args.sell_capacity().unpack();
             // Withdrawal cell is not for sell
@@ -210,20 +216,24 @@ pub fn main() -> Result<(), Error> {
             // make sure the output should only change owner_lock_hash field
             let new_lock_hash = unlock_args.owner_lock().hash();
 
-            let index = match search_lock_hash(&new_lock_hash, Source::Output) {
+            let index = match search_lock_hash(syscalls, &new_lock_hash, Source::Output) {
                 Some(i) => i,
                 None => return Err(Error::InvalidOutput),
             };
             // check new withdraw cell
-            check_output_cell_has_same_content(index)?;
+            trace!("withdrawal_lock.new_withdrawal_cell", index = index);
+            check_output_cell_has_same_content(syscalls, index)?;
             Ok(())
         }
     }
 }
 
-fn check_output_cell_has_same_content(output_index: usize) -> Result<(), Error> {
-    if load_cell_capacity(0, Source::GroupInput)?
-        != load_cell_capacity(output_index, Source::Output)?
+fn check_output_cell_has_same_content<S: Syscalls>(
+    syscalls: &S,
+    output_index: usize,
+) -> Result<(), Error> {
+    if syscalls.load_cell_capacity(0, Source::GroupInput)?
+        != syscalls.load_cell_capacity(output_index, Source::Output)?
     {
         return Err(Error::InvalidOutput);
     }
@@ -231,12 +241,14 @@ fn check_output_cell_has_same_content(output_index: usize) -> Result<(), Error>
     // TODO: use load_cell_data_hash
     // NOTE: load_cell_data_hash from inputs throw ItemMissing error. Comparing data directly
     // as temporary workaround. Right now data should be sudt amount only, 16 bytes long.
-    if load_cell_data(0, Source::GroupInput)? != load_cell_data(output_index, Source::Output)? {
+    if syscalls.load_cell_data(0, Source::GroupInput)?
+        != syscalls.load_cell_data(output_index, Source::Output)?
+    {
         return Err(Error::InvalidOutput);
     }
 
-    if load_cell_type_hash(0, Source::GroupInput)?
-        != load_cell_type_hash(output_index, Source::Output)?
+    if syscalls.load_cell_type_hash(0, Source::GroupInput)?
+        != syscalls.load_cell_type_hash(output_index, Source::Output)?
     {
         return Err(Error::InvalidOutput);
     }
@@ -1,4 +1,6 @@
 #[cfg(test)]
 mod script_tests;
 #[cfg(test)]
+mod smt_tests;
+#[cfg(test)]
 mod testing_tool;
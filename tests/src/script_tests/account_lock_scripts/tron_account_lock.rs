@@ -1,3 +1,4 @@
+use crate::script_tests::utils::error_codes::rust;
 use crate::script_tests::utils::layer1::*;
 use crate::testing_tool::programs::{
     ALWAYS_SUCCESS_CODE_HASH, ALWAYS_SUCCESS_PROGRAM, SECP256K1_DATA, TRON_ACCOUNT_LOCK_PROGRAM,
@@ -16,8 +17,6 @@ use gw_generator::account_lock_manage::{secp256k1::Secp256k1Eth, LockAlgorithm};
 use rand::{thread_rng, Rng};
 use sha3::{Digest, Keccak256};
 
-const ERROR_WRONG_SIGNATURE: i8 = 43;
-
 fn gen_tx(dummy: &mut DummyDataLoader, lock_args: Bytes, message: Bytes) -> TransactionView {
     let mut rng = thread_rng();
     // setup sighash_all dep
@@ -265,7 +264,7 @@ fn test_wrong_signature() {
     let script_cell_index = 0;
     assert_error_eq!(
         verify_result.unwrap_err(),
-        ScriptError::ValidationFailure(ERROR_WRONG_SIGNATURE).input_lock_script(script_cell_index)
+        ScriptError::ValidationFailure(rust::WRONG_SIGNATURE).input_lock_script(script_cell_index)
     );
     let mut lock_args = vec![0u8; 32];
     lock_args.extend(pubkey_hash.as_ref());
@@ -0,0 +1,229 @@
+use crate::script_tests::utils::error_codes::rust;
+use crate::script_tests::utils::layer1::{
+    always_success_script, build_resolved_tx, random_out_point, DummyDataLoader, MAX_CYCLES,
+};
+use crate::testing_tool::programs::{
+    ALWAYS_SUCCESS_PROGRAM, DEPOSIT_LOCK_CODE_HASH, DEPOSIT_LOCK_PROGRAM,
+};
+use ckb_error::assert_error_eq;
+use ckb_script::{ScriptError, TransactionScriptsVerifier};
+use ckb_types::{
+    bytes::Bytes,
+    core::{Capacity, Cycle, DepType, ScriptHashType, TransactionBuilder},
+    packed::{CellDep, CellInput, CellOutput, Script},
+    prelude::{Pack as CKBPack, Unpack},
+};
+use gw_types::packed::DepositLockArgs;
+use gw_types::prelude::*;
+
+const RELATIVE: bool = true;
+const ABSOLUTE: bool = false;
+
+fn block_number(n: u64, relative: bool) -> u64 {
+    let lock_type = if relative { 1 << 63 } else { 0 };
+    lock_type | n
+}
+
+fn epoch(number: u64, index: u64, length: u64, relative: bool) -> u64 {
+    let lock_type = if relative { 1 << 63 } else { 0 };
+    lock_type | (0b01 << 61) | (length << 40) | (index << 24) | number
+}
+
+fn timestamp(seconds: u64, relative: bool) -> u64 {
+    let lock_type = if relative { 1 << 63 } else { 0 };
+    lock_type | (0b10 << 61) | seconds
+}
+
+/// Cancel a deposit by the owner, the deposit cell is spent with the input since
+fn cancel_deposit(cancel_timeout: u64, since: u64) -> Result<Cycle, ckb_error::Error> {
+    let mut data_loader = DummyDataLoader::default();
+    // deploy scripts
+    let deposit_lock_out_point = random_out_point();
+    data_loader.cells.insert(
+        deposit_lock_out_point.clone(),
+        (
+            CellOutput::new_builder()
+                .capacity(
+                    Capacity::bytes(DEPOSIT_LOCK_PROGRAM.len())
+                        .expect("script capacity")
+                        .pack(),
+                )
+                .build(),
+            DEPOSIT_LOCK_PROGRAM.clone(),
+        ),
+    );
+    let always_success_out_point = random_out_point();
+    data_loader.cells.insert(
+        always_success_out_point.clone(),
+        (
+            CellOutput::new_builder()
+                .capacity(
+                    Capacity::bytes(ALWAYS_SUCCESS_PROGRAM.len())
+                        .expect("script capacity")
+                        .pack(),
+                )
+                .build(),
+            ALWAYS_SUCCESS_PROGRAM.clone(),
+        ),
+    );
+    // owner cell
+    let owner_lock = always_success_script();
+    let owner_lock_hash: [u8; 32] = owner_lock.calc_script_hash().unpack();
+    let owner_cell_out_point = random_out_point();
+    data_loader.cells.insert(
+        owner_cell_out_point.clone(),
+        (
+            CellOutput::new_builder().lock(owner_lock).build(),
+            Bytes::default(),
+        ),
+    );
+    // deposit cell
+    let deposit_args = DepositLockArgs::new_builder()
+        .owner_lock_hash(Pack::pack(&owner_lock_hash))
+        .cancel_timeout(Pack::pack(&cancel_timeout))
+        .build();
+    let lock_args = {
+        let rollup_type_hash = [42u8; 32];
+        let mut args = rollup_type_hash.to_vec();
+        args.extend_from_slice(deposit_args.as_slice());
+        Bytes::from(args)
+    };
+    let deposit_cell_out_point = random_out_point();
+    data_loader.cells.insert(
+        deposit_cell_out_point.clone(),
+        (
+            CellOutput::new_builder()
+                .lock(
+                    Script::new_builder()
+                        .code_hash(CKBPack::pack(&*DEPOSIT_LOCK_CODE_HASH))
+                        .hash_type(ScriptHashType::Data.into())
+                        .args(CKBPack::pack(&lock_args))
+                        .build(),
+                )
+                .build(),
+            Bytes::default(),
+        ),
+    );
+    let tx = TransactionBuilder::default()
+        .cell_dep(
+            CellDep::new_builder()
+                .out_point(deposit_lock_out_point)
+                .dep_type(DepType::Code.into())
+                .build(),
+        )
+        .cell_dep(
+            CellDep::new_builder()
+                .out_point(always_success_out_point)
+                .dep_type(DepType::Code.into())
+                .build(),
+        )
+        .input(CellInput::new(deposit_cell_out_point, since))
+        .input(CellInput::new(owner_cell_out_point, 0))
+        .output(CellOutput::new_builder().build())
+        .output_data(CKBPack::pack(&Bytes::default()))
+        .build();
+    let resolved_tx = build_resolved_tx(&data_loader, &tx);
+    let mut verifier = TransactionScriptsVerifier::new(&resolved_tx, &data_loader);
+    verifier.set_debug_printer(|_script, msg| println!("[script debug] {}", msg));
+    verifier.verify(MAX_CYCLES)
+}
+
+fn assert_cancel_deposit(cancel_timeout: u64, since: u64, expected_success: bool) {
+    let result = cancel_deposit(cancel_timeout, since);
+    if expected_success {
+        result.unwrap_or_else(|err| {
+            panic!(
+                "cancel timeout {:#x} since {:#x}: {}",
+                cancel_timeout, since, err
+            )
+        });
+    } else {
+        let expected_err = ScriptError::ValidationFailure(rust::INVALID_SINCE).input_lock_script(0);
+        assert_error_eq!(result.unwrap_err(), expected_err);
+    }
+}
+
+#[test]
+fn test_cancel_deposit_by_block_number() {
+    for &relative in &[ABSOLUTE, RELATIVE] {
+        let cancel_timeout = block_number(100, relative);
+        assert_cancel_deposit(cancel_timeout, block_number(99, relative), false);
+        assert_cancel_deposit(cancel_timeout, block_number(100, relative), true);
+        assert_cancel_deposit(cancel_timeout, block_number(101, relative), true);
+    }
+}
+
+#[test]
+fn test_cancel_deposit_by_timestamp() {
+    for &relative in &[ABSOLUTE, RELATIVE] {
+        let cancel_timeout = timestamp(3600, relative);
+        assert_cancel_deposit(cancel_timeout, timestamp(3599, relative), false);
+        assert_cancel_deposit(cancel_timeout, timestamp(3600, relative), true);
+        assert_cancel_deposit(cancel_timeout, timestamp(3601, relative), true);
+    }
+}
+
+#[test]
+fn test_cancel_deposit_by_epoch() {
+    for &relative in &[ABSOLUTE, RELATIVE] {
+        // epoch 10 1/4
+        let cancel_timeout = epoch(10, 1, 4, relative);
+        assert_cancel_deposit(cancel_timeout, epoch(9, 999, 1000, relative), false);
+        assert_cancel_deposit(cancel_timeout, epoch(10, 0, 4, relative), false);
+        assert_cancel_deposit(cancel_timeout, epoch(10, 1, 4, relative), true);
+        assert_cancel_deposit(cancel_timeout, epoch(10, 2, 8, relative), true);
+        assert_cancel_deposit(cancel_timeout, epoch(11, 0, 1, relative), true);
+        assert_cancel_deposit(cancel_timeout, epoch(11, 0, 0, relative), true);
+        // the raw value of 10 1/2 is less than 10 1/4
+        assert_cancel_deposit(cancel_timeout, epoch(10, 1, 2, relative), true);
+        // the raw value of 10 1/5 is greater than 10 1/4
+        assert_cancel_deposit(cancel_timeout, epoch(10, 1, 5, relative), false);
+    }
+}
+
+#[test]
+fn test_cancel_deposit_by_malformed_epoch() {
+    let cancel_timeout = epoch(10, 0, 1, ABSOLUTE);
+    // index must be less than length
+    assert_cancel_deposit(cancel_timeout, epoch(11, 4, 4, ABSOLUTE), false);
+    assert_cancel_deposit(cancel_timeout, epoch(11, 1, 0, ABSOLUTE), false);
+    // a malformed cancel timeout can't be reached
+    assert_cancel_deposit(epoch(10, 2, 1, ABSOLUTE), epoch(11, 0, 1, ABSOLUTE), false);
+}
+
+#[test]
+fn test_cancel_deposit_with_mismatch_since() {
+    // lock types
+    assert_cancel_deposit(
+        block_number(100, RELATIVE),
+        block_number(200, ABSOLUTE),
+        false,
+    );
+    assert_cancel_deposit(
+        block_number(100, ABSOLUTE),
+        block_number(200, RELATIVE),
+        false,
+    );
+    // metrics
+    assert_cancel_deposit(block_number(100, RELATIVE), timestamp(200, RELATIVE), false);
+    assert_cancel_deposit(
+        block_number(100, ABSOLUTE),
+        epoch(200, 0, 1, ABSOLUTE),
+        false,
+    );
+    assert_cancel_deposit(timestamp(100, ABSOLUTE), epoch(200, 0, 1, ABSOLUTE), false);
+    assert_cancel_deposit(timestamp(100, RELATIVE), block_number(200, ABSOLUTE), false);
+    assert_cancel_deposit(epoch(1, 0, 1, RELATIVE), timestamp(200, RELATIVE), false);
+}
+
+#[test]
+fn test_cancel_deposit_with_invalid_since() {
+    let cancel_timeout = block_number(100, ABSOLUTE);
+    // the metric flag 0b11 is invalid
+    assert_cancel_deposit(cancel_timeout, (0b11 << 61) | 200, false);
+    // the remaining flags must be zero
+    assert_cancel_deposit(cancel_timeout, (1 << 56) | 200, false);
+    // an invalid cancel timeout can't be reached
+    assert_cancel_deposit((0b11 << 61) | 100, (0b11 << 61) | 200, false);
+    assert_cancel_deposit((1 << 56) | 100, (1 << 56) | 200, false);
+}
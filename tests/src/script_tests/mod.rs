@@ -1,5 +1,7 @@
 mod account_lock_scripts;
+mod deposit_lock;
 mod l2_scripts;
 mod l2_scripts_validator;
+mod stake_lock;
 mod state_validator;
 pub mod utils;
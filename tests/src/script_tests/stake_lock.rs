@@ -0,0 +1,303 @@
+use crate::script_tests::utils::error_codes::rust;
+use crate::script_tests::utils::layer1::{
+    always_success_script, build_resolved_tx, random_out_point, DummyDataLoader, MAX_CYCLES,
+};
+use crate::script_tests::utils::rollup::{build_type_id_script, with_stake_unbonding_blocks};
+use crate::testing_tool::programs::{
+    ALWAYS_SUCCESS_PROGRAM, STAKE_LOCK_CODE_HASH, STAKE_LOCK_PROGRAM,
+};
+use ckb_error::assert_error_eq;
+use ckb_script::{ScriptError, TransactionScriptsVerifier};
+use ckb_types::{
+    bytes::Bytes,
+    core::{Capacity, Cycle, DepType, ScriptHashType, TransactionBuilder},
+    packed::{CellDep, CellInput, CellOutput, Script},
+    prelude::{Pack as CKBPack, Unpack},
+};
+use gw_types::packed::{GlobalState, RollupConfig, StakeLockArgs};
+use gw_types::prelude::*;
+
+const CKB: u64 = 100000000;
+const REQUIRED_STAKING_CAPACITY: u64 = 1000 * CKB;
+const STAKE_UNBONDING_BLOCKS: u64 = 100;
+const STAKE_BLOCK_NUMBER: u64 = 10;
+const FINALIZED: u64 = STAKE_BLOCK_NUMBER;
+const NOT_FINALIZED: u64 = STAKE_BLOCK_NUMBER - 1;
+/// The trailing lock args byte of a delegated stake cell which requested unbonding
+const STAKE_UNBONDING_FLAG: u8 = 1;
+
+/// Relative since of L1 block number
+fn relative_blocks(n: u64) -> u64 {
+    (1 << 63) | n
+}
+
+/// The lock args following the stake lock args
+#[derive(Clone, Copy)]
+enum Stake {
+    Own,
+    Delegated,
+    Unbonding,
+}
+
+impl Stake {
+    fn extra_args(self) -> Vec<u8> {
+        let producer_lock_hash = [42u8; 32];
+        match self {
+            Stake::Own => Vec::new(),
+            Stake::Delegated => producer_lock_hash.to_vec(),
+            Stake::Unbonding => {
+                let mut args = producer_lock_hash.to_vec();
+                args.push(STAKE_UNBONDING_FLAG);
+                args
+            }
+        }
+    }
+}
+
+/// Unlock a stake cell of `input_capacity` by the owner,
+/// the stake cell is kept in the output with `output_capacity` unless it is unbonded
+fn unlock_stake(
+    input_capacity: u64,
+    output_capacity: Option<u64>,
+    last_finalized_block_number: u64,
+    since: u64,
+) -> Result<Cycle, ckb_error::Error> {
+    unlock_stake_cell(
+        (Stake::Own, input_capacity),
+        output_capacity.map(|capacity| (Stake::Own, capacity)),
+        last_finalized_block_number,
+        since,
+    )
+}
+
+/// Unlock a stake cell by the owner, the output stake cell shares the input's stake lock args
+fn unlock_stake_cell(
+    (input_stake, input_capacity): (Stake, u64),
+    output: Option<(Stake, u64)>,
+    last_finalized_block_number: u64,
+    since: u64,
+) -> Result<Cycle, ckb_error::Error> {
+    let mut data_loader = DummyDataLoader::default();
+    let mut deploy = |data: Bytes, type_: Option<Script>| {
+        let out_point = random_out_point();
+        let cell = CellOutput::new_builder()
+            .capacity(Capacity::bytes(data.len()).expect("script capacity").pack())
+            .type_(CKBPack::pack(&type_))
+            .build();
+        data_loader.cells.insert(out_point.clone(), (cell, data));
+        CellDep::new_builder()
+            .out_point(out_point)
+            .dep_type(DepType::Code.into())
+            .build()
+    };
+    // deploy scripts
+    let stake_lock_dep = deploy(STAKE_LOCK_PROGRAM.clone(), None);
+    let always_success_dep = deploy(ALWAYS_SUCCESS_PROGRAM.clone(), None);
+    // rollup config & rollup cell
+    let rollup_config = with_stake_unbonding_blocks(
+        &RollupConfig::new_builder()
+            .required_staking_capacity(Pack::pack(&REQUIRED_STAKING_CAPACITY))
+            .build(),
+        STAKE_UNBONDING_BLOCKS,
+    );
+    let rollup_config_hash: [u8; 32] =
+        CellOutput::calc_data_hash(&rollup_config.as_bytes()).unpack();
+    let rollup_config_dep = deploy(rollup_config.as_bytes(), None);
+    let rollup_type_script = build_type_id_script(b"rollup_type_id");
+    let rollup_type_hash: [u8; 32] = rollup_type_script.calc_script_hash().unpack();
+    let global_state = GlobalState::new_builder()
+        .rollup_config_hash(Pack::pack(&rollup_config_hash))
+        .last_finalized_block_number(Pack::pack(&last_finalized_block_number))
+        .build();
+    let rollup_dep = deploy(global_state.as_bytes(), Some(rollup_type_script));
+    // owner cell
+    let owner_lock = always_success_script();
+    let owner_lock_hash: [u8; 32] = owner_lock.calc_script_hash().unpack();
+    let owner_cell_out_point = random_out_point();
+    data_loader.cells.insert(
+        owner_cell_out_point.clone(),
+        (
+            CellOutput::new_builder().lock(owner_lock).build(),
+            Bytes::default(),
+        ),
+    );
+    // stake cell
+    let stake_args = StakeLockArgs::new_builder()
+        .owner_lock_hash(Pack::pack(&owner_lock_hash))
+        .stake_block_number(Pack::pack(&STAKE_BLOCK_NUMBER))
+        .build();
+    let stake_lock = |stake: Stake| {
+        let mut args = rollup_type_hash.to_vec();
+        args.extend_from_slice(stake_args.as_slice());
+        args.extend(stake.extra_args());
+        Script::new_builder()
+            .code_hash(CKBPack::pack(&*STAKE_LOCK_CODE_HASH))
+            .hash_type(ScriptHashType::Data.into())
+            .args(CKBPack::pack(&Bytes::from(args)))
+            .build()
+    };
+    let stake_cell_out_point = random_out_point();
+    data_loader.cells.insert(
+        stake_cell_out_point.clone(),
+        (
+            CellOutput::new_builder()
+                .lock(stake_lock(input_stake))
+                .capacity(CKBPack::pack(&input_capacity))
+                .build(),
+            Bytes::default(),
+        ),
+    );
+    let output = match output {
+        Some((stake, capacity)) => CellOutput::new_builder()
+            .lock(stake_lock(stake))
+            .capacity(CKBPack::pack(&capacity))
+            .build(),
+        None => CellOutput::new_builder().build(),
+    };
+    let tx = TransactionBuilder::default()
+        .cell_dep(stake_lock_dep)
+        .cell_dep(always_success_dep)
+        .cell_dep(rollup_config_dep)
+        .cell_dep(rollup_dep)
+        .input(CellInput::new(stake_cell_out_point, since))
+        .input(CellInput::new(owner_cell_out_point, 0))
+        .output(output)
+        .output_data(CKBPack::pack(&Bytes::default()))
+        .build();
+    let resolved_tx = build_resolved_tx(&data_loader, &tx);
+    let mut verifier = TransactionScriptsVerifier::new(&resolved_tx, &data_loader);
+    verifier.set_debug_printer(|_script, msg| println!("[script debug] {}", msg));
+    verifier.verify(MAX_CYCLES)
+}
+
+#[test]
+fn test_top_up_stake() {
+    // the stake can be topped up before the stake block is finalized
+    let output_capacity = REQUIRED_STAKING_CAPACITY + 500 * CKB;
+    unlock_stake(
+        REQUIRED_STAKING_CAPACITY,
+        Some(output_capacity),
+        NOT_FINALIZED,
+        0,
+    )
+    .expect("return success");
+}
+
+#[test]
+fn test_withdraw_excess_stake() {
+    let input_capacity = REQUIRED_STAKING_CAPACITY + 500 * CKB;
+    unlock_stake(
+        input_capacity,
+        Some(REQUIRED_STAKING_CAPACITY),
+        FINALIZED,
+        0,
+    )
+    .expect("return success");
+    let expected_err =
+        ScriptError::ValidationFailure(rust::INVALID_STAKE_CELL_UNLOCK).input_lock_script(0);
+    // the output must keep the requirement
+    let err = unlock_stake(
+        input_capacity,
+        Some(REQUIRED_STAKING_CAPACITY - 1),
+        FINALIZED,
+        0,
+    )
+    .unwrap_err();
+    assert_error_eq!(err, expected_err.clone());
+    // the last block backed by the stake can still be challenged
+    let err = unlock_stake(
+        input_capacity,
+        Some(REQUIRED_STAKING_CAPACITY),
+        NOT_FINALIZED,
+        0,
+    )
+    .unwrap_err();
+    assert_error_eq!(err, expected_err);
+}
+
+#[test]
+fn test_unbond_stake() {
+    unlock_stake(
+        REQUIRED_STAKING_CAPACITY,
+        None,
+        FINALIZED,
+        relative_blocks(STAKE_UNBONDING_BLOCKS),
+    )
+    .expect("return success");
+    // the unbonding period isn't reached
+    let err = unlock_stake(
+        REQUIRED_STAKING_CAPACITY,
+        None,
+        FINALIZED,
+        relative_blocks(STAKE_UNBONDING_BLOCKS - 1),
+    )
+    .unwrap_err();
+    let expected_err = ScriptError::ValidationFailure(rust::INVALID_SINCE).input_lock_script(0);
+    assert_error_eq!(err, expected_err);
+    // the stake block isn't finalized
+    let err = unlock_stake(
+        REQUIRED_STAKING_CAPACITY,
+        None,
+        NOT_FINALIZED,
+        relative_blocks(STAKE_UNBONDING_BLOCKS),
+    )
+    .unwrap_err();
+    let expected_err =
+        ScriptError::ValidationFailure(rust::INVALID_STAKE_CELL_UNLOCK).input_lock_script(0);
+    assert_error_eq!(err, expected_err);
+}
+
+#[test]
+fn test_request_delegated_stake_unbonding() {
+    // the request doesn't wait for the stake block
+    unlock_stake_cell(
+        (Stake::Delegated, REQUIRED_STAKING_CAPACITY),
+        Some((Stake::Unbonding, REQUIRED_STAKING_CAPACITY)),
+        NOT_FINALIZED,
+        0,
+    )
+    .expect("return success");
+    // the delegated stake can't be withdrawn while requesting
+    let err = unlock_stake_cell(
+        (Stake::Delegated, REQUIRED_STAKING_CAPACITY),
+        Some((Stake::Unbonding, REQUIRED_STAKING_CAPACITY - 1)),
+        FINALIZED,
+        0,
+    )
+    .unwrap_err();
+    let expected_err =
+        ScriptError::ValidationFailure(rust::INVALID_STAKE_CELL_UNLOCK).input_lock_script(0);
+    assert_error_eq!(err, expected_err);
+}
+
+#[test]
+fn test_unbond_delegated_stake() {
+    unlock_stake_cell(
+        (Stake::Unbonding, REQUIRED_STAKING_CAPACITY),
+        None,
+        FINALIZED,
+        relative_blocks(STAKE_UNBONDING_BLOCKS),
+    )
+    .expect("return success");
+    // the unbonding period counts from the request
+    let err = unlock_stake_cell(
+        (Stake::Unbonding, REQUIRED_STAKING_CAPACITY),
+        None,
+        FINALIZED,
+        relative_blocks(STAKE_UNBONDING_BLOCKS - 1),
+    )
+    .unwrap_err();
+    let expected_err = ScriptError::ValidationFailure(rust::INVALID_SINCE).input_lock_script(0);
+    assert_error_eq!(err, expected_err);
+    // a bonded delegated stake cell must request unbonding first
+    let err = unlock_stake_cell(
+        (Stake::Delegated, REQUIRED_STAKING_CAPACITY),
+        None,
+        FINALIZED,
+        relative_blocks(STAKE_UNBONDING_BLOCKS),
+    )
+    .unwrap_err();
+    let expected_err =
+        ScriptError::ValidationFailure(rust::INVALID_STAKE_CELL_UNLOCK).input_lock_script(0);
+    assert_error_eq!(err, expected_err);
+}
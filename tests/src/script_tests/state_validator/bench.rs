@@ -0,0 +1,389 @@
+//! Cycle benchmarks of the state-validator
+//!
+//! These tests are ignored by default, run them with:
+//! `cargo test bench_ -- --ignored --nocapture`
+//!
+//! The absolute cycles depend on the toolchain, so the benchmarks assert how the cycles scale:
+//! the cycles per item must not grow with the number of items, which catches a quadratic
+//! classification or matching of the cells.
+
+use crate::script_tests::utils::layer1::build_simple_tx;
+use crate::script_tests::utils::rollup::{
+    build_always_success_cell, build_rollup_locked_cell, build_type_id_script, CellContext,
+    CellContextParam,
+};
+use crate::testing_tool::chain::{
+    apply_block_result, construct_block, setup_chain, setup_chain_with_account_lock_manage,
+};
+use crate::testing_tool::programs::STATE_VALIDATOR_CODE_HASH;
+use ckb_types::{
+    packed::CellInput,
+    prelude::{Pack as CKBPack, Unpack},
+};
+use gw_generator::account_lock_manage::{always_success::AlwaysSuccess, AccountLockManage};
+use gw_types::prelude::*;
+use gw_types::{
+    bytes::Bytes,
+    core::ScriptHashType,
+    packed::{
+        CustodianLockArgs, DepositRequest, RawWithdrawalRequest, RollupAction, RollupActionUnion,
+        RollupConfig, RollupSubmitBlock, Script, StakeLockArgs, WithdrawalLockArgs,
+        WithdrawalRequest,
+    },
+};
+
+/// Max ratio of the cycles per item of the largest count to the smallest one,
+/// a linear or `n log n` verification stays below it, a quadratic one is about 10x
+const MAX_MARGINAL_CYCLES_RATIO: u64 = 2;
+
+/// Print the cycles of each count, and assert the marginal cycles per item
+/// between the largest counts don't exceed `MAX_MARGINAL_CYCLES_RATIO` times
+/// the ones between the smallest counts
+fn check_cycles_scaling(name: &str, counts: &[usize], cycles: &[u64]) {
+    for (count, cycles) in counts.iter().zip(cycles) {
+        println!("{} with {} items: {} cycles", name, count, cycles);
+    }
+    let marginal = |i: usize| (cycles[i + 1] - cycles[i]) / (counts[i + 1] - counts[i]) as u64;
+    let first = marginal(0);
+    let last = marginal(counts.len() - 2);
+    println!("{}: {} -> {} cycles per item", name, first, last);
+    assert!(
+        last <= first * MAX_MARGINAL_CYCLES_RATIO,
+        "{}: the cycles per item grow from {} to {}",
+        name,
+        first,
+        last
+    );
+}
+
+/// Submit a block which merges and splits `count` finalized custodian cells,
+/// return the cycles of the transaction
+fn submit_block_with_finalized_custodians(count: usize) -> u64 {
+    let rollup_type_script = {
+        Script::new_builder()
+            .code_hash(Pack::pack(&*STATE_VALIDATOR_CODE_HASH))
+            .hash_type(ScriptHashType::Data.into())
+            .args(Pack::pack(&Bytes::from(vec![42u8; 32])))
+            .build()
+    };
+    // rollup lock & config
+    let stake_lock_type = build_type_id_script(b"stake_lock_type_id");
+    let stake_script_type_hash: [u8; 32] = stake_lock_type.calc_script_hash().unpack();
+    let custodian_lock_type = build_type_id_script(b"custodian_lock_type_id");
+    let custodian_script_type_hash: [u8; 32] = custodian_lock_type.calc_script_hash().unpack();
+    let rollup_config = RollupConfig::new_builder()
+        .stake_script_type_hash(Pack::pack(&stake_script_type_hash))
+        .custodian_script_type_hash(Pack::pack(&custodian_script_type_hash))
+        .build();
+    // setup chain
+    let chain = setup_chain(rollup_type_script.clone(), rollup_config.clone());
+    // deploy scripts
+    let param = CellContextParam {
+        stake_lock_type,
+        custodian_lock_type,
+        ..Default::default()
+    };
+    let mut ctx = CellContext::new(&rollup_config, param);
+    let stake_capacity = 10000_00000000u64;
+    let input_stake_cell = {
+        let cell = build_rollup_locked_cell(
+            &rollup_type_script.hash(),
+            &stake_script_type_hash,
+            stake_capacity,
+            StakeLockArgs::default().as_bytes(),
+        );
+        let out_point = ctx.insert_cell(cell, Bytes::default());
+        CellInput::new_builder().previous_output(out_point).build()
+    };
+    let output_stake_cell = {
+        let lock_args = StakeLockArgs::new_builder()
+            .stake_block_number(Pack::pack(&1))
+            .build();
+        build_rollup_locked_cell(
+            &rollup_type_script.hash(),
+            &stake_script_type_hash,
+            stake_capacity,
+            lock_args.as_bytes(),
+        )
+    };
+    // finalized custodian cells
+    let finalized_custodian_cell = {
+        let args = CustodianLockArgs::new_builder()
+            .deposit_block_hash(Pack::pack(&[0u8; 32]))
+            .deposit_block_number(Pack::pack(&0))
+            .build();
+        build_rollup_locked_cell(
+            &rollup_type_script.hash(),
+            &custodian_script_type_hash,
+            300_00000000u64,
+            args.as_bytes(),
+        )
+    };
+    let input_custodian_cells: Vec<_> = (0..count)
+        .map(|_| {
+            let out_point = ctx.insert_cell(finalized_custodian_cell.clone(), Bytes::new());
+            CellInput::new_builder().previous_output(out_point).build()
+        })
+        .collect();
+    let output_custodian_cells = vec![finalized_custodian_cell; count];
+    // create a rollup cell
+    let rollup_cell = build_always_success_cell(
+        1000_00000000u64,
+        Some(ckb_types::packed::Script::new_unchecked(
+            rollup_type_script.as_bytes(),
+        )),
+    );
+    let initial_rollup_cell_data = chain.local_state().last_global_state().as_bytes();
+    // submit a new block
+    let block_result = {
+        let mem_pool = chain.mem_pool().lock();
+        construct_block(&chain, &mem_pool, Vec::default()).unwrap()
+    };
+    let rollup_cell_data = block_result.global_state.as_bytes();
+    let witness = {
+        let rollup_action = RollupAction::new_builder()
+            .set(RollupActionUnion::RollupSubmitBlock(
+                RollupSubmitBlock::new_builder()
+                    .block(block_result.block.clone())
+                    .build(),
+            ))
+            .build();
+        ckb_types::packed::WitnessArgs::new_builder()
+            .output_type(CKBPack::pack(&Some(rollup_action.as_bytes())))
+            .build()
+    };
+    let tx = build_simple_tx(
+        &mut ctx.inner,
+        (rollup_cell.clone(), initial_rollup_cell_data),
+        (rollup_cell, rollup_cell_data),
+    )
+    .as_advanced_builder()
+    .input(input_stake_cell)
+    .output(output_stake_cell)
+    .output_data(CKBPack::pack(&Bytes::default()))
+    .inputs(input_custodian_cells)
+    .outputs(output_custodian_cells)
+    .outputs_data((0..count).map(|_| CKBPack::pack(&Bytes::new())))
+    .cell_dep(ctx.stake_lock_dep.clone())
+    .cell_dep(ctx.custodian_lock_dep.clone())
+    .cell_dep(ctx.always_success_dep.clone())
+    .cell_dep(ctx.state_validator_dep.clone())
+    .cell_dep(ctx.rollup_config_dep.clone())
+    .witness(CKBPack::pack(&witness.as_bytes()))
+    .build();
+    ctx.verify_tx(tx).expect("return success")
+}
+
+#[ignore]
+#[test]
+fn bench_submit_block_with_custodian_cells() {
+    let counts = [10, 100, 1000];
+    let cycles: Vec<_> = counts
+        .iter()
+        .map(|&count| submit_block_with_finalized_custodians(count))
+        .collect();
+    check_cycles_scaling("submit block with custodian cells", &counts, &cycles);
+}
+
+/// Submit a block which contains `count` withdrawal requests,
+/// return the cycles of the transaction
+fn submit_block_with_withdrawals(count: usize) -> u64 {
+    let rollup_type_script = {
+        Script::new_builder()
+            .code_hash(Pack::pack(&*STATE_VALIDATOR_CODE_HASH))
+            .hash_type(ScriptHashType::Data.into())
+            .args(Pack::pack(&Bytes::from(vec![42u8; 32])))
+            .build()
+    };
+    // rollup lock & config
+    let stake_lock_type = build_type_id_script(b"stake_lock_type_id");
+    let stake_script_type_hash: [u8; 32] = stake_lock_type.calc_script_hash().unpack();
+    let custodian_lock_type = build_type_id_script(b"custodian_lock_type_id");
+    let custodian_script_type_hash: [u8; 32] = custodian_lock_type.calc_script_hash().unpack();
+    let withdrawal_lock_type = build_type_id_script(b"withdrawal_lock_type_id");
+    let withdrawal_script_type_hash: [u8; 32] = withdrawal_lock_type.calc_script_hash().unpack();
+    let eoa_lock_type = build_type_id_script(b"eoa_lock_type_id");
+    let eoa_lock_type_hash: [u8; 32] = eoa_lock_type.calc_script_hash().unpack();
+    let rollup_config = RollupConfig::new_builder()
+        .stake_script_type_hash(Pack::pack(&stake_script_type_hash))
+        .custodian_script_type_hash(Pack::pack(&custodian_script_type_hash))
+        .withdrawal_script_type_hash(Pack::pack(&withdrawal_script_type_hash))
+        .build();
+    // setup chain
+    let mut account_lock_manage = AccountLockManage::default();
+    account_lock_manage.register_lock_algorithm(eoa_lock_type_hash.into(), Box::new(AlwaysSuccess));
+    let mut chain = setup_chain_with_account_lock_manage(
+        rollup_type_script.clone(),
+        rollup_config.clone(),
+        account_lock_manage,
+    );
+    // create a rollup cell
+    let rollup_cell = build_always_success_cell(
+        1000_00000000u64,
+        Some(ckb_types::packed::Script::new_unchecked(
+            rollup_type_script.as_bytes(),
+        )),
+    );
+    // deposit accounts
+    let withdrawal_capacity = 400_00000000u64;
+    let account_scripts: Vec<_> = (0..count)
+        .map(|i| {
+            Script::new_builder()
+                .code_hash(Pack::pack(&eoa_lock_type_hash))
+                .hash_type(ScriptHashType::Type.into())
+                .args(Pack::pack(&Bytes::from((i as u32).to_le_bytes().to_vec())))
+                .build()
+        })
+        .collect();
+    {
+        let deposit_requests: Vec<_> = account_scripts
+            .iter()
+            .map(|script| {
+                DepositRequest::new_builder()
+                    .capacity(Pack::pack(&450_00000000u64))
+                    .script(script.clone())
+                    .build()
+            })
+            .collect();
+        let produce_block_result = {
+            let mem_pool = chain.mem_pool().lock();
+            construct_block(&chain, &mem_pool, deposit_requests.clone()).unwrap()
+        };
+        let rollup_cell = gw_types::packed::CellOutput::new_unchecked(rollup_cell.as_bytes());
+        apply_block_result(
+            &mut chain,
+            rollup_cell,
+            produce_block_result,
+            deposit_requests,
+        );
+    }
+    let initial_rollup_cell_data = chain.local_state().last_global_state().as_bytes();
+    // submit a block with withdrawals
+    let block_result = {
+        let mut mem_pool = chain.mem_pool().lock();
+        for script in &account_scripts {
+            let withdrawal = WithdrawalRequest::new_builder()
+                .raw(
+                    RawWithdrawalRequest::new_builder()
+                        .nonce(Pack::pack(&0u32))
+                        .capacity(Pack::pack(&withdrawal_capacity))
+                        .account_script_hash(Pack::pack(&script.hash()))
+                        .sell_capacity(Pack::pack(&withdrawal_capacity))
+                        .build(),
+                )
+                .build();
+            mem_pool.push_withdrawal_request(withdrawal).unwrap();
+        }
+        construct_block(&chain, &mem_pool, Vec::default()).unwrap()
+    };
+    assert_eq!(block_result.block.withdrawals().len(), count);
+    // deploy scripts
+    let param = CellContextParam {
+        stake_lock_type,
+        custodian_lock_type,
+        withdrawal_lock_type,
+        eoa_lock_type,
+        ..Default::default()
+    };
+    let mut ctx = CellContext::new(&rollup_config, param);
+    let stake_capacity = 10000_00000000u64;
+    let input_stake_cell = {
+        let cell = build_rollup_locked_cell(
+            &rollup_type_script.hash(),
+            &stake_script_type_hash,
+            stake_capacity,
+            StakeLockArgs::default().as_bytes(),
+        );
+        let out_point = ctx.insert_cell(cell, Bytes::default());
+        CellInput::new_builder().previous_output(out_point).build()
+    };
+    let output_stake_cell = {
+        let lock_args = StakeLockArgs::new_builder()
+            .stake_block_number(block_result.block.raw().number())
+            .build();
+        build_rollup_locked_cell(
+            &rollup_type_script.hash(),
+            &stake_script_type_hash,
+            stake_capacity,
+            lock_args.as_bytes(),
+        )
+    };
+    // finalized custodian cell covers all withdrawals
+    let input_custodian_cell = {
+        let args = CustodianLockArgs::new_builder()
+            .deposit_block_hash(Pack::pack(&[0u8; 32]))
+            .deposit_block_number(Pack::pack(&0))
+            .build();
+        let cell = build_rollup_locked_cell(
+            &rollup_type_script.hash(),
+            &custodian_script_type_hash,
+            withdrawal_capacity * count as u64,
+            args.as_bytes(),
+        );
+        let out_point = ctx.insert_cell(cell, Bytes::new());
+        CellInput::new_builder().previous_output(out_point).build()
+    };
+    // withdrawal cells, in the reverse order of the withdrawal requests
+    let output_withdrawal_cells: Vec<_> = account_scripts
+        .iter()
+        .rev()
+        .map(|script| {
+            let args = WithdrawalLockArgs::new_builder()
+                .account_script_hash(Pack::pack(&script.hash()))
+                .withdrawal_block_hash(Pack::pack(&block_result.block.raw().hash()))
+                .withdrawal_block_number(block_result.block.raw().number())
+                .sell_capacity(Pack::pack(&withdrawal_capacity))
+                .build();
+            build_rollup_locked_cell(
+                &rollup_type_script.hash(),
+                &withdrawal_script_type_hash,
+                withdrawal_capacity,
+                args.as_bytes(),
+            )
+        })
+        .collect();
+    let rollup_cell_data = block_result.global_state.as_bytes();
+    let witness = {
+        let rollup_action = RollupAction::new_builder()
+            .set(RollupActionUnion::RollupSubmitBlock(
+                RollupSubmitBlock::new_builder()
+                    .block(block_result.block.clone())
+                    .build(),
+            ))
+            .build();
+        ckb_types::packed::WitnessArgs::new_builder()
+            .output_type(CKBPack::pack(&Some(rollup_action.as_bytes())))
+            .build()
+    };
+    let tx = build_simple_tx(
+        &mut ctx.inner,
+        (rollup_cell.clone(), initial_rollup_cell_data),
+        (rollup_cell, rollup_cell_data),
+    )
+    .as_advanced_builder()
+    .input(input_stake_cell)
+    .output(output_stake_cell)
+    .output_data(CKBPack::pack(&Bytes::default()))
+    .input(input_custodian_cell)
+    .outputs(output_withdrawal_cells)
+    .outputs_data((0..count).map(|_| CKBPack::pack(&Bytes::new())))
+    .cell_dep(ctx.stake_lock_dep.clone())
+    .cell_dep(ctx.custodian_lock_dep.clone())
+    .cell_dep(ctx.always_success_dep.clone())
+    .cell_dep(ctx.state_validator_dep.clone())
+    .cell_dep(ctx.rollup_config_dep.clone())
+    .witness(CKBPack::pack(&witness.as_bytes()))
+    .build();
+    ctx.verify_tx(tx).expect("return success")
+}
+
+#[ignore]
+#[test]
+fn bench_submit_block_with_withdrawals() {
+    let counts = [10, 100, 1000];
+    let cycles: Vec<_> = counts
+        .iter()
+        .map(|&count| submit_block_with_withdrawals(count))
+        .collect();
+    check_cycles_scaling("submit block with withdrawals", &counts, &cycles);
+}
@@ -1,3 +1,4 @@
+use crate::script_tests::utils::error_codes::rust;
 use crate::script_tests::utils::layer1::build_simple_tx_with_out_point;
 use crate::script_tests::utils::layer1::random_out_point;
 use crate::script_tests::utils::rollup::{
@@ -7,7 +8,11 @@ use crate::script_tests::utils::rollup::{
 use crate::testing_tool::chain::{
     apply_block_result, construct_block, setup_chain_with_account_lock_manage,
 };
-use crate::testing_tool::programs::STATE_VALIDATOR_CODE_HASH;
+use crate::testing_tool::programs::{
+    DEFAULT_MAX_KV_PAIRS, MAX_KV_PAIRS, STATE_VALIDATOR_CODE_HASH,
+};
+use ckb_error::assert_error_eq;
+use ckb_script::ScriptError;
 use ckb_types::{
     packed::{CellInput, CellOutput},
     prelude::{Pack as CKBPack, Unpack},
@@ -36,6 +41,34 @@ use gw_types::{
 
 #[test]
 fn test_cancel_tx_execute() {
+    cancel_tx_execute(0).expect("return success");
+}
+
+#[test]
+fn test_cancel_tx_execute_above_max_kv_pairs() {
+    let err = cancel_tx_execute(*MAX_KV_PAIRS).unwrap_err();
+    let expected_err =
+        ScriptError::ValidationFailure(rust::INSUFFICIENT_KV_STATE_CAPACITY).input_lock_script(1);
+    assert_error_eq!(err, expected_err);
+}
+
+// Run with the scripts built by a raised limit, e.g. `GW_MAX_KV_PAIRS=2048`,
+// and the same env for the tests
+#[test]
+#[ignore]
+fn test_cancel_tx_execute_with_raised_max_kv_pairs() {
+    assert!(
+        *MAX_KV_PAIRS > DEFAULT_MAX_KV_PAIRS,
+        "set GW_MAX_KV_PAIRS above {}",
+        DEFAULT_MAX_KV_PAIRS
+    );
+    // more than the default limit of kv pairs
+    cancel_tx_execute(DEFAULT_MAX_KV_PAIRS).expect("return success");
+}
+
+/// Cancel a tx execution challenge, the context carries `absent_kv_pairs` more kv pairs
+/// of the absent keys besides the keys touched by the tx
+fn cancel_tx_execute(absent_kv_pairs: usize) -> Result<ckb_types::core::Cycle, ckb_error::Error> {
     let input_out_point = random_out_point();
     let type_id = calculate_state_validator_type_id(input_out_point.clone());
     let rollup_type_script = {
@@ -264,7 +297,7 @@ fn test_cancel_tx_execute() {
             tree.get_nonce(receiver_id).unwrap();
             tree.get_script_hash(sudt_id).unwrap();
             let account_count = tree.get_account_count().unwrap();
-            let touched_keys: Vec<H256> = tree
+            let mut touched_keys: Vec<H256> = tree
                 .tracker_mut()
                 .touched_keys()
                 .unwrap()
@@ -272,13 +305,21 @@ fn test_cancel_tx_execute() {
                 .clone()
                 .into_iter()
                 .collect();
-            let kv_state = touched_keys
+            let mut kv_state = touched_keys
                 .iter()
                 .map(|k| {
                     let v = tree.get_raw(k).unwrap();
                     (*k, v)
                 })
                 .collect::<Vec<(H256, H256)>>();
+            // the absent keys are proved to be zero
+            for index in 0..absent_kv_pairs {
+                let mut key = [0xffu8; 32];
+                key[..4].copy_from_slice(&(index as u32).to_le_bytes());
+                assert!(tree.get_raw(&key.into()).unwrap().is_zero());
+                touched_keys.push(key.into());
+                kv_state.push((key.into(), H256::zero()));
+            }
 
             let kv_state_proof: Bytes = {
                 let smt = state_db.account_smt().unwrap();
@@ -372,5 +413,5 @@ fn test_cancel_tx_execute() {
     .cell_dep(ctx.eoa_lock_dep.clone())
     .cell_dep(ctx.l2_sudt_dep.clone())
     .build();
-    ctx.verify_tx(tx).expect("return success");
+    ctx.verify_tx(tx)
 }
@@ -1,3 +1,4 @@
+use crate::script_tests::utils::error_codes::rust;
 use crate::script_tests::utils::layer1::build_simple_tx_with_out_point;
 use crate::script_tests::utils::layer1::random_out_point;
 use crate::script_tests::utils::rollup::{
@@ -8,6 +9,8 @@ use crate::testing_tool::chain::{
     apply_block_result, construct_block, setup_chain_with_account_lock_manage,
 };
 use crate::testing_tool::programs::STATE_VALIDATOR_CODE_HASH;
+use ckb_error::assert_error_eq;
+use ckb_script::ScriptError;
 use ckb_types::{
     packed::{CellInput, CellOutput},
     prelude::{Pack as CKBPack, Unpack},
@@ -34,8 +37,12 @@ use gw_types::{
     },
 };
 
-#[test]
-fn test_cancel_tx_signature() {
+/// Cancel the challenge of the tx signature of an unsigned transaction,
+/// the sender of the transaction exists before the challenged block,
+/// the signature is checked by the sender's lock cell if `with_signature_cell` is true
+fn cancel_tx_signature(
+    with_signature_cell: bool,
+) -> Result<ckb_types::core::Cycle, ckb_error::Error> {
     let input_out_point = random_out_point();
     let type_id = calculate_state_validator_type_id(input_out_point.clone());
     let rollup_type_script = {
@@ -340,7 +347,7 @@ fn test_cancel_tx_signature() {
         .status(Status::Running.into())
         .build()
         .as_bytes();
-    let tx = build_simple_tx_with_out_point(
+    let mut tx = build_simple_tx_with_out_point(
         &mut ctx.inner,
         (rollup_cell.clone(), initial_rollup_cell_data),
         input_out_point,
@@ -349,16 +356,32 @@ fn test_cancel_tx_signature() {
     .as_advanced_builder()
     .witness(CKBPack::pack(&witness.as_bytes()))
     .input(input_challenge_cell)
-    .witness(CKBPack::pack(&challenge_witness.as_bytes()))
-    .input(input_unlock_cell)
-    .witness(Default::default())
-    .cell_dep(ctx.challenge_lock_dep.clone())
-    .cell_dep(ctx.stake_lock_dep.clone())
-    .cell_dep(ctx.always_success_dep.clone())
-    .cell_dep(ctx.state_validator_dep.clone())
-    .cell_dep(ctx.rollup_config_dep.clone())
-    .cell_dep(ctx.eoa_lock_dep.clone())
-    .cell_dep(ctx.l2_sudt_dep.clone())
-    .build();
-    ctx.verify_tx(tx).expect("return success");
+    .witness(CKBPack::pack(&challenge_witness.as_bytes()));
+    if with_signature_cell {
+        tx = tx.input(input_unlock_cell).witness(Default::default());
+    }
+    let tx = tx
+        .cell_dep(ctx.challenge_lock_dep.clone())
+        .cell_dep(ctx.stake_lock_dep.clone())
+        .cell_dep(ctx.always_success_dep.clone())
+        .cell_dep(ctx.state_validator_dep.clone())
+        .cell_dep(ctx.rollup_config_dep.clone())
+        .cell_dep(ctx.eoa_lock_dep.clone())
+        .cell_dep(ctx.l2_sudt_dep.clone())
+        .build();
+    ctx.verify_tx(tx)
+}
+
+#[test]
+fn test_cancel_tx_signature() {
+    cancel_tx_signature(true).expect("return success");
+}
+
+#[test]
+fn test_cancel_unsigned_tx_signature() {
+    // every transaction, including a deposit call, is signed by the sender's lock cell
+    let err = cancel_tx_signature(false).unwrap_err();
+    let expected_err =
+        ScriptError::ValidationFailure(rust::ACCOUNT_LOCK_CELL_NOT_FOUND).input_lock_script(1);
+    assert_error_eq!(err, expected_err);
 }
@@ -0,0 +1,245 @@
+use crate::script_tests::utils::error_codes::rust;
+use crate::script_tests::utils::layer1::{build_simple_tx_with_out_point, random_out_point};
+use crate::script_tests::utils::rollup::{
+    build_always_success_cell, build_rollup_locked_cell, build_type_id_script,
+    calculate_state_validator_type_id, CellContext, CellContextParam,
+};
+use crate::testing_tool::chain::setup_chain;
+use crate::testing_tool::programs::STATE_VALIDATOR_CODE_HASH;
+use ckb_error::assert_error_eq;
+use ckb_script::ScriptError;
+use ckb_types::{
+    packed::{CellInput, CellOutput},
+    prelude::{Pack as CKBPack, Unpack},
+};
+use gw_types::prelude::*;
+use gw_types::{
+    bytes::Bytes,
+    core::ScriptHashType,
+    packed::{CustodianLockArgs, GlobalState, RollupConfig, Script},
+};
+
+const CKB: u64 = 100000000;
+/// RollupAction item id of the custodian compaction
+const COMPACT_CUSTODIANS_ITEM_ID: u32 = 5;
+
+struct CompactionContext {
+    ctx: CellContext,
+    global_state: GlobalState,
+    rollup_type_script: Script,
+    rollup_out_point: ckb_types::packed::OutPoint,
+    custodian_script_type_hash: [u8; 32],
+    sudt_type: ckb_types::packed::Script,
+}
+
+impl CompactionContext {
+    fn new() -> Self {
+        let rollup_out_point = random_out_point();
+        let type_id = calculate_state_validator_type_id(rollup_out_point.clone());
+        let rollup_type_script = {
+            Script::new_builder()
+                .code_hash(Pack::pack(&*STATE_VALIDATOR_CODE_HASH))
+                .hash_type(ScriptHashType::Data.into())
+                .args(Pack::pack(&Bytes::from(type_id.to_vec())))
+                .build()
+        };
+        // rollup lock & config
+        let custodian_lock_type = build_type_id_script(b"custodian_lock_type_id");
+        let custodian_script_type_hash: [u8; 32] = custodian_lock_type.calc_script_hash().unpack();
+        let l1_sudt_type = build_type_id_script(b"l1_sudt_type_id");
+        let l1_sudt_script_type_hash: [u8; 32] = l1_sudt_type.calc_script_hash().unpack();
+        let rollup_config = RollupConfig::new_builder()
+            .custodian_script_type_hash(Pack::pack(&custodian_script_type_hash))
+            .l1_sudt_script_type_hash(Pack::pack(&l1_sudt_script_type_hash))
+            .build();
+        // setup chain
+        let chain = setup_chain(rollup_type_script.clone(), rollup_config.clone());
+        let global_state = chain.local_state().last_global_state().clone();
+        // deploy scripts
+        let param = CellContextParam {
+            custodian_lock_type,
+            l1_sudt_type,
+            ..Default::default()
+        };
+        let ctx = CellContext::new(&rollup_config, param);
+        let sudt_type = ckb_types::packed::Script::new_builder()
+            .code_hash(CKBPack::pack(&l1_sudt_script_type_hash))
+            .hash_type(ScriptHashType::Type.into())
+            .args(CKBPack::pack(&Bytes::from(b"sudt owner".to_vec())))
+            .build();
+        CompactionContext {
+            ctx,
+            global_state,
+            rollup_type_script,
+            rollup_out_point,
+            custodian_script_type_hash,
+            sudt_type,
+        }
+    }
+
+    fn custodian_cell(
+        &self,
+        deposit_block_number: u64,
+        capacity: u64,
+        sudt_amount: Option<u128>,
+    ) -> (CellOutput, Bytes) {
+        let args = CustodianLockArgs::new_builder()
+            .deposit_block_hash(Pack::pack(&[0u8; 32]))
+            .deposit_block_number(Pack::pack(&deposit_block_number))
+            .build();
+        let cell = build_rollup_locked_cell(
+            &self.rollup_type_script.hash(),
+            &self.custodian_script_type_hash,
+            capacity,
+            args.as_bytes(),
+        );
+        match sudt_amount {
+            Some(amount) => (
+                cell.as_builder()
+                    .type_(CKBPack::pack(&Some(self.sudt_type.clone())))
+                    .build(),
+                Bytes::from(amount.to_le_bytes().to_vec()),
+            ),
+            None => (cell, Bytes::default()),
+        }
+    }
+
+    /// finalized CKB custodian cell
+    fn ckb_custodian(&self, capacity: u64) -> (CellOutput, Bytes) {
+        self.custodian_cell(0, capacity, None)
+    }
+
+    /// finalized sUDT custodian cell
+    fn sudt_custodian(&self, capacity: u64, amount: u128) -> (CellOutput, Bytes) {
+        self.custodian_cell(0, capacity, Some(amount))
+    }
+
+    /// Submit the custodian compaction action, the global state is unchanged
+    fn compact(
+        mut self,
+        inputs: Vec<(CellOutput, Bytes)>,
+        outputs: Vec<(CellOutput, Bytes)>,
+    ) -> Result<ckb_types::core::Cycle, ckb_error::Error> {
+        let rollup_cell = build_always_success_cell(
+            1000 * CKB,
+            Some(ckb_types::packed::Script::new_unchecked(
+                self.rollup_type_script.as_bytes(),
+            )),
+        );
+        let inputs: Vec<_> = inputs
+            .into_iter()
+            .map(|(cell, data)| {
+                let out_point = self.ctx.insert_cell(cell, data);
+                CellInput::new_builder().previous_output(out_point).build()
+            })
+            .collect();
+        // RollupAction union with the extra item id and an empty item
+        let rollup_action = Bytes::from(COMPACT_CUSTODIANS_ITEM_ID.to_le_bytes().to_vec());
+        let witness = ckb_types::packed::WitnessArgs::new_builder()
+            .output_type(CKBPack::pack(&Some(rollup_action)))
+            .build();
+        let mut tx = build_simple_tx_with_out_point(
+            &mut self.ctx.inner,
+            (rollup_cell.clone(), self.global_state.as_bytes()),
+            self.rollup_out_point.clone(),
+            (rollup_cell, self.global_state.as_bytes()),
+        )
+        .as_advanced_builder()
+        .inputs(inputs);
+        for (cell, data) in outputs {
+            tx = tx.output(cell).output_data(CKBPack::pack(&data));
+        }
+        let tx = tx
+            .cell_dep(self.ctx.custodian_lock_dep.clone())
+            .cell_dep(self.ctx.l1_sudt_dep.clone())
+            .cell_dep(self.ctx.always_success_dep.clone())
+            .cell_dep(self.ctx.state_validator_dep.clone())
+            .cell_dep(self.ctx.rollup_config_dep.clone())
+            .witness(CKBPack::pack(&witness.as_bytes()))
+            .build();
+        self.ctx.verify_tx(tx)
+    }
+}
+
+#[test]
+fn test_merge_custodian_cells() {
+    let ctx = CompactionContext::new();
+    let inputs = (0..10).map(|_| ctx.ckb_custodian(300 * CKB)).collect();
+    let outputs = vec![ctx.ckb_custodian(3000 * CKB)];
+    ctx.compact(inputs, outputs).expect("return success");
+}
+
+#[test]
+fn test_split_and_redenominate_custodian_cells() {
+    let ctx = CompactionContext::new();
+    let inputs = vec![
+        ctx.sudt_custodian(400 * CKB, 100),
+        ctx.sudt_custodian(400 * CKB, 50),
+        ctx.ckb_custodian(1000 * CKB),
+    ];
+    let outputs = vec![
+        ctx.sudt_custodian(500 * CKB, 120),
+        ctx.sudt_custodian(500 * CKB, 30),
+        ctx.ckb_custodian(400 * CKB),
+        ctx.ckb_custodian(400 * CKB),
+    ];
+    ctx.compact(inputs, outputs).expect("return success");
+}
+
+#[test]
+fn test_compaction_loses_assets() {
+    let ctx = CompactionContext::new();
+    let inputs = vec![ctx.ckb_custodian(300 * CKB), ctx.ckb_custodian(300 * CKB)];
+    let outputs = vec![ctx.ckb_custodian(599 * CKB)];
+    let err = ctx.compact(inputs, outputs).unwrap_err();
+    let expected_err = ScriptError::ValidationFailure(rust::INSUFFICIENT_OUTPUT_FINALIZED_ASSETS)
+        .input_type_script(0);
+    assert_error_eq!(err, expected_err);
+
+    let ctx = CompactionContext::new();
+    let inputs = vec![
+        ctx.sudt_custodian(400 * CKB, 100),
+        ctx.sudt_custodian(400 * CKB, 50),
+    ];
+    let outputs = vec![ctx.sudt_custodian(800 * CKB, 149)];
+    let err = ctx.compact(inputs, outputs).unwrap_err();
+    assert_error_eq!(err, expected_err);
+}
+
+#[test]
+fn test_compaction_mints_assets() {
+    let ctx = CompactionContext::new();
+    let inputs = vec![ctx.sudt_custodian(400 * CKB, 100)];
+    let outputs = vec![
+        ctx.sudt_custodian(200 * CKB, 50),
+        ctx.sudt_custodian(200 * CKB, 51),
+    ];
+    let err = ctx.compact(inputs, outputs).unwrap_err();
+    let expected_err = ScriptError::ValidationFailure(rust::INSUFFICIENT_INPUT_FINALIZED_ASSETS)
+        .input_type_script(0);
+    assert_error_eq!(err, expected_err);
+}
+
+#[test]
+fn test_compact_unfinalized_custodian_cells() {
+    let expected_err =
+        ScriptError::ValidationFailure(rust::INVALID_CUSTODIAN_CELL).input_type_script(0);
+
+    let ctx = CompactionContext::new();
+    let inputs = vec![
+        ctx.ckb_custodian(300 * CKB),
+        ctx.custodian_cell(1, 300 * CKB, None),
+    ];
+    let outputs = vec![ctx.ckb_custodian(600 * CKB)];
+    let err = ctx.compact(inputs, outputs).unwrap_err();
+    assert_error_eq!(err, expected_err);
+
+    let ctx = CompactionContext::new();
+    let inputs = vec![ctx.ckb_custodian(600 * CKB)];
+    let outputs = vec![
+        ctx.ckb_custodian(300 * CKB),
+        ctx.custodian_cell(1, 300 * CKB, None),
+    ];
+    let err = ctx.compact(inputs, outputs).unwrap_err();
+    assert_error_eq!(err, expected_err);
+}
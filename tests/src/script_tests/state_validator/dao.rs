@@ -0,0 +1,324 @@
+use crate::script_tests::utils::error_codes::rust;
+use crate::script_tests::utils::layer1::{
+    always_success_script, build_simple_tx_with_out_point, random_out_point,
+};
+use crate::script_tests::utils::rollup::{
+    build_always_success_cell, build_rollup_locked_cell, build_type_id_script,
+    calculate_state_validator_type_id, with_dao_config, CellContext, CellContextParam,
+};
+use crate::testing_tool::chain::{construct_block, setup_chain};
+use crate::testing_tool::programs::STATE_VALIDATOR_CODE_HASH;
+use ckb_error::assert_error_eq;
+use ckb_script::ScriptError;
+use ckb_types::{
+    packed::{CellInput, CellOutput},
+    prelude::{Pack as CKBPack, Unpack},
+};
+use gw_chain::chain::Chain;
+use gw_types::prelude::*;
+use gw_types::{
+    bytes::Bytes,
+    core::ScriptHashType,
+    packed::{
+        CustodianLockArgs, RollupAction, RollupActionUnion, RollupConfig, RollupSubmitBlock,
+        Script, StakeLockArgs,
+    },
+};
+
+const CUSTODIAN_CAPACITY: u64 = 1000_00000000u64;
+const INTEREST: u64 = 10_00000000u64;
+/// RollupAction item id of the DAO withdrawal
+const DAO_WITHDRAW_ITEM_ID: u32 = 4;
+
+struct DAOContext {
+    ctx: CellContext,
+    chain: Chain,
+    rollup_type_script: Script,
+    rollup_out_point: ckb_types::packed::OutPoint,
+    custodian_script_type_hash: [u8; 32],
+    stake_script_type_hash: [u8; 32],
+    dao_type: ckb_types::packed::Script,
+    beneficiary_lock: ckb_types::packed::Script,
+}
+
+impl DAOContext {
+    fn new() -> Self {
+        let rollup_out_point = random_out_point();
+        let type_id = calculate_state_validator_type_id(rollup_out_point.clone());
+        let rollup_type_script = {
+            Script::new_builder()
+                .code_hash(Pack::pack(&*STATE_VALIDATOR_CODE_HASH))
+                .hash_type(ScriptHashType::Data.into())
+                .args(Pack::pack(&Bytes::from(type_id.to_vec())))
+                .build()
+        };
+        // rollup lock & config
+        let stake_lock_type = build_type_id_script(b"stake_lock_type_id");
+        let stake_script_type_hash: [u8; 32] = stake_lock_type.calc_script_hash().unpack();
+        let custodian_lock_type = build_type_id_script(b"custodian_lock_type_id");
+        let custodian_script_type_hash: [u8; 32] = custodian_lock_type.calc_script_hash().unpack();
+        let dao_type = build_type_id_script(b"dao_type_id");
+        let dao_script_type_hash: [u8; 32] = dao_type.calc_script_hash().unpack();
+        let beneficiary_lock = always_success_script()
+            .as_builder()
+            .args(CKBPack::pack(&Bytes::from(b"beneficiary".to_vec())))
+            .build();
+        let beneficiary_lock_hash: [u8; 32] = beneficiary_lock.calc_script_hash().unpack();
+        let rollup_config = {
+            let config = RollupConfig::new_builder()
+                .stake_script_type_hash(Pack::pack(&stake_script_type_hash))
+                .custodian_script_type_hash(Pack::pack(&custodian_script_type_hash))
+                .build();
+            with_dao_config(&config, &dao_script_type_hash, &beneficiary_lock_hash)
+        };
+        // setup chain
+        let chain = setup_chain(rollup_type_script.clone(), rollup_config.clone());
+        // deploy scripts
+        let param = CellContextParam {
+            stake_lock_type,
+            custodian_lock_type,
+            dao_type: dao_type.clone(),
+            ..Default::default()
+        };
+        let ctx = CellContext::new(&rollup_config, param);
+        DAOContext {
+            ctx,
+            chain,
+            rollup_type_script,
+            rollup_out_point,
+            custodian_script_type_hash,
+            stake_script_type_hash,
+            dao_type,
+            beneficiary_lock,
+        }
+    }
+
+    /// finalized custodian cell, with the DAO data if it's a DAO cell
+    fn custodian_cell(&self, capacity: u64, dao_data: Option<u64>) -> (CellOutput, Bytes) {
+        let args = CustodianLockArgs::new_builder()
+            .deposit_block_hash(Pack::pack(&[0u8; 32]))
+            .deposit_block_number(Pack::pack(&0))
+            .build();
+        let cell = build_rollup_locked_cell(
+            &self.rollup_type_script.hash(),
+            &self.custodian_script_type_hash,
+            capacity,
+            args.as_bytes(),
+        );
+        match dao_data {
+            Some(data) => (
+                cell.as_builder()
+                    .type_(CKBPack::pack(&Some(self.dao_type.clone())))
+                    .build(),
+                Bytes::from(data.to_le_bytes().to_vec()),
+            ),
+            None => (cell, Bytes::default()),
+        }
+    }
+
+    fn beneficiary_cell(&self, capacity: u64) -> (CellOutput, Bytes) {
+        let cell = CellOutput::new_builder()
+            .capacity(CKBPack::pack(&capacity))
+            .lock(self.beneficiary_lock.clone())
+            .build();
+        (cell, Bytes::default())
+    }
+
+    fn rollup_cell(&self) -> CellOutput {
+        build_always_success_cell(
+            1000_00000000u64,
+            Some(ckb_types::packed::Script::new_unchecked(
+                self.rollup_type_script.as_bytes(),
+            )),
+        )
+    }
+
+    fn verify_tx(
+        mut self,
+        rollup_action: Bytes,
+        rollup_cell_data: Bytes,
+        inputs: Vec<(CellOutput, Bytes)>,
+        outputs: Vec<(CellOutput, Bytes)>,
+    ) -> Result<ckb_types::core::Cycle, ckb_error::Error> {
+        let global_state = self.chain.local_state().last_global_state();
+        let rollup_cell = self.rollup_cell();
+        let inputs: Vec<_> = inputs
+            .into_iter()
+            .map(|(cell, data)| {
+                let out_point = self.ctx.insert_cell(cell, data);
+                CellInput::new_builder().previous_output(out_point).build()
+            })
+            .collect();
+        let witness = ckb_types::packed::WitnessArgs::new_builder()
+            .output_type(CKBPack::pack(&Some(rollup_action)))
+            .build();
+        let mut tx = build_simple_tx_with_out_point(
+            &mut self.ctx.inner,
+            (rollup_cell.clone(), global_state.as_bytes()),
+            self.rollup_out_point.clone(),
+            (rollup_cell, rollup_cell_data),
+        )
+        .as_advanced_builder()
+        .inputs(inputs);
+        for (cell, data) in outputs {
+            tx = tx.output(cell).output_data(CKBPack::pack(&data));
+        }
+        let tx = tx
+            .cell_dep(self.ctx.stake_lock_dep.clone())
+            .cell_dep(self.ctx.custodian_lock_dep.clone())
+            .cell_dep(self.ctx.dao_dep.clone())
+            .cell_dep(self.ctx.always_success_dep.clone())
+            .cell_dep(self.ctx.state_validator_dep.clone())
+            .cell_dep(self.ctx.rollup_config_dep.clone())
+            .witness(CKBPack::pack(&witness.as_bytes()))
+            .build();
+        self.ctx.verify_tx(tx)
+    }
+
+    /// Submit the DAO withdraw action, the global state is unchanged
+    fn dao_withdraw(
+        self,
+        inputs: Vec<(CellOutput, Bytes)>,
+        outputs: Vec<(CellOutput, Bytes)>,
+    ) -> Result<ckb_types::core::Cycle, ckb_error::Error> {
+        let global_state = self.chain.local_state().last_global_state();
+        // RollupAction union with the extra item id and an empty item
+        let rollup_action = Bytes::from(DAO_WITHDRAW_ITEM_ID.to_le_bytes().to_vec());
+        self.verify_tx(rollup_action, global_state.as_bytes(), inputs, outputs)
+    }
+
+    /// Submit an empty block with the custodian cells
+    fn submit_block(
+        self,
+        mut inputs: Vec<(CellOutput, Bytes)>,
+        mut outputs: Vec<(CellOutput, Bytes)>,
+    ) -> Result<ckb_types::core::Cycle, ckb_error::Error> {
+        let stake_capacity = 10000_00000000u64;
+        let input_stake_cell = build_rollup_locked_cell(
+            &self.rollup_type_script.hash(),
+            &self.stake_script_type_hash,
+            stake_capacity,
+            StakeLockArgs::default().as_bytes(),
+        );
+        let output_stake_cell = {
+            let lock_args = StakeLockArgs::new_builder()
+                .stake_block_number(Pack::pack(&1))
+                .build();
+            build_rollup_locked_cell(
+                &self.rollup_type_script.hash(),
+                &self.stake_script_type_hash,
+                stake_capacity,
+                lock_args.as_bytes(),
+            )
+        };
+        inputs.insert(0, (input_stake_cell, Bytes::default()));
+        outputs.insert(0, (output_stake_cell, Bytes::default()));
+        let block_result = {
+            let mem_pool = self.chain.mem_pool().lock();
+            construct_block(&self.chain, &mem_pool, Vec::default()).unwrap()
+        };
+        let rollup_action = RollupAction::new_builder()
+            .set(RollupActionUnion::RollupSubmitBlock(
+                RollupSubmitBlock::new_builder()
+                    .block(block_result.block.clone())
+                    .build(),
+            ))
+            .build();
+        let rollup_cell_data = block_result.global_state.as_bytes();
+        self.verify_tx(rollup_action.as_bytes(), rollup_cell_data, inputs, outputs)
+    }
+}
+
+#[test]
+fn test_deposit_custodian_cells_into_dao() {
+    let ctx = DAOContext::new();
+    let inputs = vec![ctx.custodian_cell(CUSTODIAN_CAPACITY, None)];
+    let outputs = vec![ctx.custodian_cell(CUSTODIAN_CAPACITY, Some(0))];
+    ctx.submit_block(inputs, outputs).expect("return success");
+}
+
+#[test]
+fn test_submit_block_with_dao_custodian_cells() {
+    // DAO custodian cells are counted at the original capacity
+    let ctx = DAOContext::new();
+    let inputs = vec![
+        ctx.custodian_cell(CUSTODIAN_CAPACITY, Some(0)),
+        ctx.custodian_cell(CUSTODIAN_CAPACITY, None),
+    ];
+    let outputs = vec![ctx.custodian_cell(CUSTODIAN_CAPACITY * 2, None)];
+    ctx.submit_block(inputs, outputs).expect("return success");
+
+    // withdrawing DAO custodian cells are handled by the DAO withdraw action
+    let ctx = DAOContext::new();
+    let inputs = vec![ctx.custodian_cell(CUSTODIAN_CAPACITY, Some(42))];
+    let outputs = vec![ctx.custodian_cell(CUSTODIAN_CAPACITY, None)];
+    let err = ctx.submit_block(inputs, outputs).unwrap_err();
+    let expected_err =
+        ScriptError::ValidationFailure(rust::INVALID_CUSTODIAN_CELL).input_type_script(0);
+    assert_error_eq!(err, expected_err);
+}
+
+#[test]
+fn test_dao_withdraw_phase1() {
+    let ctx = DAOContext::new();
+    let inputs = vec![ctx.custodian_cell(CUSTODIAN_CAPACITY, Some(0))];
+    let outputs = vec![ctx.custodian_cell(CUSTODIAN_CAPACITY, Some(42))];
+    ctx.dao_withdraw(inputs, outputs).expect("return success");
+}
+
+#[test]
+fn test_dao_withdraw_phase2() {
+    let ctx = DAOContext::new();
+    let inputs = vec![
+        ctx.custodian_cell(CUSTODIAN_CAPACITY, Some(42)),
+        ctx.custodian_cell(CUSTODIAN_CAPACITY, Some(43)),
+    ];
+    let outputs = vec![
+        ctx.custodian_cell(CUSTODIAN_CAPACITY * 2, None),
+        ctx.beneficiary_cell(INTEREST),
+    ];
+    ctx.dao_withdraw(inputs, outputs).expect("return success");
+}
+
+#[test]
+fn test_dao_withdraw_interest_to_other_lock() {
+    let ctx = DAOContext::new();
+    let inputs = vec![ctx.custodian_cell(CUSTODIAN_CAPACITY, Some(42))];
+    let outputs = vec![
+        ctx.custodian_cell(CUSTODIAN_CAPACITY, None),
+        build_always_success_cell_pair(INTEREST),
+    ];
+    let err = ctx.dao_withdraw(inputs, outputs).unwrap_err();
+    let expected_err =
+        ScriptError::ValidationFailure(rust::INVALID_DAO_WITHDRAWAL).input_type_script(0);
+    assert_error_eq!(err, expected_err);
+}
+
+#[test]
+fn test_dao_withdraw_mismatch_custodian_capacity() {
+    let ctx = DAOContext::new();
+    let inputs = vec![ctx.custodian_cell(CUSTODIAN_CAPACITY, Some(42))];
+    let outputs = vec![
+        ctx.custodian_cell(CUSTODIAN_CAPACITY - INTEREST, None),
+        ctx.beneficiary_cell(INTEREST * 2),
+    ];
+    let err = ctx.dao_withdraw(inputs, outputs).unwrap_err();
+    let expected_err =
+        ScriptError::ValidationFailure(rust::INVALID_DAO_WITHDRAWAL).input_type_script(0);
+    assert_error_eq!(err, expected_err);
+}
+
+#[test]
+fn test_dao_withdraw_non_dao_custodian_cells() {
+    let ctx = DAOContext::new();
+    let inputs = vec![ctx.custodian_cell(CUSTODIAN_CAPACITY, None)];
+    let outputs = vec![ctx.custodian_cell(CUSTODIAN_CAPACITY, Some(42))];
+    let err = ctx.dao_withdraw(inputs, outputs).unwrap_err();
+    let expected_err =
+        ScriptError::ValidationFailure(rust::INVALID_CUSTODIAN_CELL).input_type_script(0);
+    assert_error_eq!(err, expected_err);
+}
+
+fn build_always_success_cell_pair(capacity: u64) -> (CellOutput, Bytes) {
+    (build_always_success_cell(capacity, None), Bytes::default())
+}
@@ -0,0 +1,728 @@
+use crate::script_tests::utils::compression::{
+    compress_rollup_action, compressed_action, compressed_padding, MAX_COMPRESSED_SIZE,
+    MAX_DECOMPRESSED_SIZE,
+};
+use crate::script_tests::utils::error_codes::rust;
+use crate::script_tests::utils::layer1::{build_simple_tx_with_out_point, random_out_point};
+use crate::script_tests::utils::rollup::{
+    build_always_success_cell, build_rollup_locked_cell, build_type_id_script,
+    calculate_state_validator_type_id, with_deposit_limits, CellContext, CellContextParam,
+};
+use crate::testing_tool::chain::{construct_block_with_txs, setup_chain};
+use crate::testing_tool::programs::STATE_VALIDATOR_CODE_HASH;
+use ckb_error::assert_error_eq;
+use ckb_script::ScriptError;
+use ckb_types::{
+    packed::CellInput,
+    prelude::{Pack as CKBPack, Unpack},
+};
+use gw_common::{builtins::CKB_SUDT_ACCOUNT_ID, state::to_short_address, CKB_SUDT_SCRIPT_ARGS};
+use gw_types::prelude::*;
+use gw_types::{
+    bytes::Bytes,
+    core::ScriptHashType,
+    packed::{
+        Byte32, CustodianLockArgs, DepositLockArgs, DepositRequest, DepositRequestVec,
+        L2Transaction, RawL2Transaction, RollupAction, RollupActionUnion, RollupConfig,
+        RollupSubmitBlock, SUDTArgs, SUDTQuery, Script, StakeLockArgs,
+    },
+};
+
+const CKB: u64 = 100000000;
+const DEPOSIT_CAPACITY: u64 = 200 * CKB;
+const EOA_TYPE_HASH: [u8; 32] = [3u8; 32];
+const UNKNOWN_TYPE_HASH: [u8; 32] = [4u8; 32];
+/// The meta contract and the CKB simple UDT are created in genesis
+const FIRST_DEPOSIT_ACCOUNT_ID: u32 = 2;
+
+/// Build the layer2 script of the CKB simple UDT, which is a contract account created in genesis
+fn ckb_sudt_script(rollup_type_hash: &[u8; 32], l2_sudt_script_type_hash: &[u8; 32]) -> Script {
+    let mut args = rollup_type_hash.to_vec();
+    args.extend_from_slice(&CKB_SUDT_SCRIPT_ARGS);
+    Script::new_builder()
+        .code_hash(Pack::pack(l2_sudt_script_type_hash))
+        .hash_type(ScriptHashType::Type.into())
+        .args(Pack::pack(&Bytes::from(args)))
+        .build()
+}
+
+/// Build an EOA script of the depositor
+fn eoa_script(args: &[u8]) -> Script {
+    Script::new_builder()
+        .code_hash(Pack::pack(&EOA_TYPE_HASH))
+        .hash_type(ScriptHashType::Type.into())
+        .args(Pack::pack(&Bytes::from(args.to_vec())))
+        .build()
+}
+
+/// Build the deposit requests of a batch deposit to EOAs
+fn batch_deposit_requests(capacities: &[u64]) -> Vec<DepositRequest> {
+    capacities
+        .iter()
+        .enumerate()
+        .map(|(i, capacity)| {
+            DepositRequest::new_builder()
+                .capacity(Pack::pack(capacity))
+                .script(eoa_script(&[i as u8]))
+                .build()
+        })
+        .collect()
+}
+
+/// Encode the entries of a batch deposit, which are appended to the lock args
+fn batch_extension(requests: &[DepositRequest]) -> Bytes {
+    DepositRequestVec::new_builder()
+        .set(requests.to_vec())
+        .build()
+        .as_bytes()
+}
+
+/// Submit a block which mints a deposit to the layer2 account,
+/// `build_account_script` receives the rollup type hash and the l2 sUDT script type hash
+fn submit_block_with_deposit<F: Fn(&[u8; 32], &[u8; 32]) -> Script>(
+    build_account_script: F,
+) -> Result<ckb_types::core::Cycle, ckb_error::Error> {
+    submit_block_with_limited_deposit(build_account_script, |config| config.clone())
+}
+
+/// Submit a block which mints a deposit to the layer2 account,
+/// the rollup config is extended by `extend_config`
+fn submit_block_with_limited_deposit<F: Fn(&[u8; 32], &[u8; 32]) -> Script>(
+    build_account_script: F,
+    extend_config: fn(&RollupConfig) -> RollupConfig,
+) -> Result<ckb_types::core::Cycle, ckb_error::Error> {
+    let build_deposit = |rollup_type_hash: &[u8; 32], l2_sudt_script_type_hash: &[u8; 32]| {
+        let account_script = build_account_script(rollup_type_hash, l2_sudt_script_type_hash);
+        let deposit_args = DepositLockArgs::new_builder()
+            .layer2_lock(account_script.clone())
+            .build();
+        let deposit_requests = vec![DepositRequest::new_builder()
+            .capacity(Pack::pack(&DEPOSIT_CAPACITY))
+            .script(account_script)
+            .build()];
+        (deposit_args, Bytes::default(), deposit_requests)
+    };
+    submit_block_with_deposit_cells(build_deposit, None, extend_config)
+}
+
+/// Submit a block which mints a deposit to an EOA, the deposit pays a fee to the block producer
+fn submit_block_with_deposit_fee(
+    fee_sudt_id: u32,
+    fee_amount: u128,
+) -> Result<ckb_types::core::Cycle, ckb_error::Error> {
+    let build_deposit = |_rollup_type_hash: &[u8; 32], _l2_sudt_script_type_hash: &[u8; 32]| {
+        let account_script = eoa_script(b"depositor");
+        let deposit_args = DepositLockArgs::new_builder()
+            .layer2_lock(account_script.clone())
+            .build();
+        // extension: flags | fee sudt_id | fee amount
+        let mut extension = vec![0b10u8];
+        extension.extend_from_slice(&fee_sudt_id.to_le_bytes());
+        extension.extend_from_slice(&fee_amount.to_le_bytes());
+        let deposit_requests = vec![DepositRequest::new_builder()
+            .capacity(Pack::pack(&DEPOSIT_CAPACITY))
+            .script(account_script)
+            .build()];
+        (deposit_args, Bytes::from(extension), deposit_requests)
+    };
+    submit_block_with_deposit_cells(build_deposit, None, |config| config.clone())
+}
+
+/// Submit a block which mints a deposit to the layer2 account, the deposit calls `to_id` with `args`,
+/// the block executes `txs` after the deposit
+/// `build_account_script` receives the rollup type hash and the l2 sUDT script type hash
+fn submit_block_with_deposit_call<F: Fn(&[u8; 32], &[u8; 32]) -> Script>(
+    build_account_script: F,
+    to_id: u32,
+    args: &[u8],
+    txs: Vec<L2Transaction>,
+) -> Result<ckb_types::core::Cycle, ckb_error::Error> {
+    let build_deposit = |rollup_type_hash: &[u8; 32], l2_sudt_script_type_hash: &[u8; 32]| {
+        let account_script = build_account_script(rollup_type_hash, l2_sudt_script_type_hash);
+        let deposit_args = DepositLockArgs::new_builder()
+            .layer2_lock(account_script.clone())
+            .build();
+        // extension: flags | call to_id | call args
+        let mut extension = vec![0b01u8];
+        extension.extend_from_slice(&to_id.to_le_bytes());
+        extension.extend_from_slice(args);
+        let deposit_requests = vec![DepositRequest::new_builder()
+            .capacity(Pack::pack(&DEPOSIT_CAPACITY))
+            .script(account_script)
+            .build()];
+        (deposit_args, Bytes::from(extension), deposit_requests)
+    };
+    submit_block_with_deposit_txs(
+        build_deposit,
+        None,
+        |config| config.clone(),
+        txs,
+        |action| action.as_bytes(),
+    )
+}
+
+/// Build the transaction of a deposit call, the signature is only checked by a challenge
+fn deposit_call_tx(from_id: u32, to_id: u32, args: &[u8]) -> L2Transaction {
+    L2Transaction::new_builder()
+        .raw(
+            RawL2Transaction::new_builder()
+                .from_id(Pack::pack(&from_id))
+                .to_id(Pack::pack(&to_id))
+                .nonce(Pack::pack(&0u32))
+                .args(Pack::pack(&Bytes::from(args.to_vec())))
+                .build(),
+        )
+        .build()
+}
+
+/// Query the CKB balance of the account, the call doesn't change the balances
+fn query_ckb_args(account_script: &Script) -> Bytes {
+    SUDTArgs::new_builder()
+        .set(
+            SUDTQuery::new_builder()
+                .short_address(Pack::pack(&Bytes::copy_from_slice(to_short_address(
+                    &account_script.hash().into(),
+                ))))
+                .build(),
+        )
+        .build()
+        .as_bytes()
+}
+
+/// Submit a block which mints a deposit cell of `DEPOSIT_CAPACITY`,
+/// `build_deposit` returns the lock args, the deposit extension and the deposit requests of
+/// the cell, it receives the rollup type hash and the l2 sUDT script type hash.
+/// `reverted_batch` is the deposit extensions of the input custodian cell and the output
+/// deposit cell of a reverted batch deposit.
+fn submit_block_with_deposit_cells<F>(
+    build_deposit: F,
+    reverted_batch: Option<(Bytes, Bytes)>,
+    extend_config: fn(&RollupConfig) -> RollupConfig,
+) -> Result<ckb_types::core::Cycle, ckb_error::Error>
+where
+    F: Fn(&[u8; 32], &[u8; 32]) -> (DepositLockArgs, Bytes, Vec<DepositRequest>),
+{
+    submit_block_with_deposit_txs(
+        build_deposit,
+        reverted_batch,
+        extend_config,
+        Vec::new(),
+        |action| action.as_bytes(),
+    )
+}
+
+/// Submit a block which mints a deposit cell like `submit_block_with_deposit_cells`,
+/// the block executes `txs` after the deposits, `encode_action` encodes the rollup action
+/// in the witness
+fn submit_block_with_deposit_txs<F>(
+    build_deposit: F,
+    reverted_batch: Option<(Bytes, Bytes)>,
+    extend_config: fn(&RollupConfig) -> RollupConfig,
+    txs: Vec<L2Transaction>,
+    encode_action: fn(&RollupAction) -> Bytes,
+) -> Result<ckb_types::core::Cycle, ckb_error::Error>
+where
+    F: Fn(&[u8; 32], &[u8; 32]) -> (DepositLockArgs, Bytes, Vec<DepositRequest>),
+{
+    let capacity = 1000 * CKB;
+    let input_out_point = random_out_point();
+    let type_id = calculate_state_validator_type_id(input_out_point.clone());
+    let rollup_type_script = {
+        Script::new_builder()
+            .code_hash(Pack::pack(&*STATE_VALIDATOR_CODE_HASH))
+            .hash_type(ScriptHashType::Data.into())
+            .args(Pack::pack(&Bytes::from(type_id.to_vec())))
+            .build()
+    };
+    // rollup lock & config
+    let stake_lock_type = build_type_id_script(b"stake_lock_type_id");
+    let stake_script_type_hash: [u8; 32] = stake_lock_type.calc_script_hash().unpack();
+    let deposit_lock_type = build_type_id_script(b"deposit_lock_type_id");
+    let deposit_script_type_hash: [u8; 32] = deposit_lock_type.calc_script_hash().unpack();
+    let custodian_lock_type = build_type_id_script(b"custodian_lock_type_id");
+    let custodian_script_type_hash: [u8; 32] = custodian_lock_type.calc_script_hash().unpack();
+    let l2_sudt_type = build_type_id_script(b"l2_sudt_type_id");
+    let l2_sudt_script_type_hash: [u8; 32] = l2_sudt_type.calc_script_hash().unpack();
+    let allowed_eoa_type_hashes: Vec<Byte32> = vec![Pack::pack(&EOA_TYPE_HASH)];
+    let allowed_contract_type_hashes: Vec<Byte32> = vec![Pack::pack(&l2_sudt_script_type_hash)];
+    let rollup_config = {
+        let config = RollupConfig::new_builder()
+            .stake_script_type_hash(Pack::pack(&stake_script_type_hash))
+            .deposit_script_type_hash(Pack::pack(&deposit_script_type_hash))
+            .custodian_script_type_hash(Pack::pack(&custodian_script_type_hash))
+            .l2_sudt_validator_script_type_hash(Pack::pack(&l2_sudt_script_type_hash))
+            .finality_blocks(Pack::pack(&10))
+            .allowed_eoa_type_hashes(PackVec::pack(allowed_eoa_type_hashes))
+            .allowed_contract_type_hashes(PackVec::pack(allowed_contract_type_hashes))
+            .build();
+        extend_config(&config)
+    };
+    // setup chain
+    let chain = setup_chain(rollup_type_script.clone(), rollup_config.clone());
+    // deploy scripts
+    let param = CellContextParam {
+        stake_lock_type,
+        deposit_lock_type,
+        custodian_lock_type,
+        l2_sudt_type,
+        ..Default::default()
+    };
+    let mut ctx = CellContext::new(&rollup_config, param);
+    let input_stake_cell = {
+        let cell = build_rollup_locked_cell(
+            &rollup_type_script.hash(),
+            &stake_script_type_hash,
+            capacity,
+            StakeLockArgs::default().as_bytes(),
+        );
+        let out_point = ctx.insert_cell(cell, Bytes::default());
+        CellInput::new_builder().previous_output(out_point).build()
+    };
+    let output_stake_cell = {
+        let lock_args = StakeLockArgs::new_builder()
+            .stake_block_number(Pack::pack(&1))
+            .build();
+        build_rollup_locked_cell(
+            &rollup_type_script.hash(),
+            &stake_script_type_hash,
+            capacity,
+            lock_args.as_bytes(),
+        )
+    };
+    // create a rollup cell
+    let rollup_cell = build_always_success_cell(
+        capacity,
+        Some(ckb_types::packed::Script::new_unchecked(
+            rollup_type_script.as_bytes(),
+        )),
+    );
+    let global_state = chain.local_state().last_global_state();
+    // deposit to the layer2 accounts
+    let (deposit_args, deposit_extension, deposit_requests) =
+        build_deposit(&rollup_type_script.hash(), &l2_sudt_script_type_hash);
+    let with_extension = |args: Bytes, extension: &Bytes| {
+        let mut args = args.to_vec();
+        args.extend_from_slice(extension);
+        Bytes::from(args)
+    };
+    let input_deposit_cell = {
+        let cell = build_rollup_locked_cell(
+            &rollup_type_script.hash(),
+            &deposit_script_type_hash,
+            DEPOSIT_CAPACITY,
+            with_extension(deposit_args.as_bytes(), &deposit_extension),
+        );
+        let out_point = ctx.insert_cell(cell, Bytes::default());
+        CellInput::new_builder().previous_output(out_point).build()
+    };
+    // submit a new block
+    let block_result = {
+        let mem_pool = chain.mem_pool().lock();
+        construct_block_with_txs(&chain, &mem_pool, deposit_requests, txs).unwrap()
+    };
+    assert!(block_result.unused_transactions.is_empty());
+    let output_custodian_cell = {
+        let args = CustodianLockArgs::new_builder()
+            .deposit_lock_args(deposit_args)
+            .deposit_block_hash(Pack::pack(&block_result.block.hash()))
+            .deposit_block_number(Pack::pack(&1))
+            .build();
+        build_rollup_locked_cell(
+            &rollup_type_script.hash(),
+            &custodian_script_type_hash,
+            DEPOSIT_CAPACITY,
+            with_extension(args.as_bytes(), &deposit_extension),
+        )
+    };
+    let witness = {
+        let rollup_action = RollupAction::new_builder()
+            .set(RollupActionUnion::RollupSubmitBlock(
+                RollupSubmitBlock::new_builder()
+                    .block(block_result.block.clone())
+                    .build(),
+            ))
+            .build();
+        ckb_types::packed::WitnessArgs::new_builder()
+            .output_type(CKBPack::pack(&Some(encode_action(&rollup_action))))
+            .build()
+    };
+    let mut tx = build_simple_tx_with_out_point(
+        &mut ctx.inner,
+        (rollup_cell.clone(), global_state.as_bytes()),
+        input_out_point,
+        (rollup_cell, block_result.global_state.as_bytes()),
+    )
+    .as_advanced_builder()
+    .input(input_stake_cell)
+    .output(output_stake_cell)
+    .output_data(CKBPack::pack(&Bytes::default()))
+    .input(input_deposit_cell)
+    .output(output_custodian_cell)
+    .output_data(CKBPack::pack(&Bytes::default()));
+    // revert a batch deposit from the custodian cell to the deposit cell
+    if let Some((custodian_extension, reverted_deposit_extension)) = reverted_batch {
+        let input_reverted_custodian_cell = {
+            let args = CustodianLockArgs::new_builder()
+                .deposit_block_hash(Pack::pack(&[42u8; 32]))
+                .deposit_block_number(Pack::pack(&2))
+                .build();
+            let cell = build_rollup_locked_cell(
+                &rollup_type_script.hash(),
+                &custodian_script_type_hash,
+                DEPOSIT_CAPACITY,
+                with_extension(args.as_bytes(), &custodian_extension),
+            );
+            let out_point = ctx.insert_cell(cell, Bytes::default());
+            CellInput::new_builder().previous_output(out_point).build()
+        };
+        let output_reverted_deposit_cell = build_rollup_locked_cell(
+            &rollup_type_script.hash(),
+            &deposit_script_type_hash,
+            DEPOSIT_CAPACITY,
+            with_extension(
+                DepositLockArgs::default().as_bytes(),
+                &reverted_deposit_extension,
+            ),
+        );
+        tx = tx
+            .input(input_reverted_custodian_cell)
+            .output(output_reverted_deposit_cell)
+            .output_data(CKBPack::pack(&Bytes::default()));
+    }
+    let tx = tx
+        .cell_dep(ctx.stake_lock_dep.clone())
+        .cell_dep(ctx.deposit_lock_dep.clone())
+        .cell_dep(ctx.custodian_lock_dep.clone())
+        .cell_dep(ctx.always_success_dep.clone())
+        .cell_dep(ctx.state_validator_dep.clone())
+        .cell_dep(ctx.rollup_config_dep.clone())
+        .witness(CKBPack::pack(&witness.as_bytes()))
+        .build();
+    ctx.verify_tx(tx)
+}
+
+#[test]
+fn test_deposit_to_eoa() {
+    submit_block_with_deposit(|_rollup_type_hash, _l2_sudt_script_type_hash| {
+        eoa_script(b"depositor")
+    })
+    .expect("return success");
+}
+
+#[test]
+fn test_deposit_to_contract() {
+    // the CKB simple UDT is an existing contract account
+    submit_block_with_deposit(ckb_sudt_script).expect("return success");
+}
+
+#[test]
+fn test_deposit_to_nonexistent_contract() {
+    // contract accounts can't be created by deposits
+    let err = submit_block_with_deposit(|rollup_type_hash, l2_sudt_script_type_hash| {
+        let mut args = rollup_type_hash.to_vec();
+        args.extend_from_slice(&[42u8; 32]);
+        Script::new_builder()
+            .code_hash(Pack::pack(l2_sudt_script_type_hash))
+            .hash_type(ScriptHashType::Type.into())
+            .args(Pack::pack(&Bytes::from(args)))
+            .build()
+    })
+    .unwrap_err();
+    let expected_err = ScriptError::ValidationFailure(rust::ACCOUNT_NOT_FOUND).input_type_script(0);
+    assert_error_eq!(err, expected_err);
+}
+
+#[test]
+fn test_deposit_to_unknown_script() {
+    let err = submit_block_with_deposit(|_rollup_type_hash, _l2_sudt_script_type_hash| {
+        Script::new_builder()
+            .code_hash(Pack::pack(&UNKNOWN_TYPE_HASH))
+            .hash_type(ScriptHashType::Type.into())
+            .args(Pack::pack(&Bytes::from(b"depositor".to_vec())))
+            .build()
+    })
+    .unwrap_err();
+    let expected_err =
+        ScriptError::ValidationFailure(rust::UNKNOWN_EOA_SCRIPT).input_type_script(0);
+    assert_error_eq!(err, expected_err);
+}
+
+#[test]
+fn test_batch_deposit() {
+    let requests = batch_deposit_requests(&[120 * CKB, 80 * CKB]);
+    submit_block_with_deposit_cells(
+        |_rollup_type_hash, _l2_sudt_script_type_hash| {
+            (
+                DepositLockArgs::default(),
+                batch_extension(&requests),
+                requests.clone(),
+            )
+        },
+        None,
+        |config| config.clone(),
+    )
+    .expect("return success");
+}
+
+/// Submit a block of a batch deposit to EOAs, the block is encoded compressed
+fn submit_compressed_block_with_batch_deposit(
+    encode_action: fn(&RollupAction) -> Bytes,
+) -> Result<ckb_types::core::Cycle, ckb_error::Error> {
+    let requests = batch_deposit_requests(&[10 * CKB; 20]);
+    submit_block_with_deposit_txs(
+        |_rollup_type_hash, _l2_sudt_script_type_hash| {
+            (
+                DepositLockArgs::default(),
+                batch_extension(&requests),
+                requests.clone(),
+            )
+        },
+        None,
+        |config| config.clone(),
+        Vec::new(),
+        encode_action,
+    )
+}
+
+#[test]
+fn test_batch_deposit_in_compressed_block() {
+    submit_compressed_block_with_batch_deposit(|action| {
+        let compressed = compress_rollup_action(action);
+        println!(
+            "rollup action size: {}, compressed: {}",
+            action.as_slice().len(),
+            compressed.len()
+        );
+        assert!(compressed.len() < action.as_slice().len());
+        compressed
+    })
+    .expect("return success");
+    // a malformed token
+    let err = submit_compressed_block_with_batch_deposit(|action| {
+        let mut compressed = compress_rollup_action(action).to_vec();
+        compressed.push(0xff);
+        compressed.into()
+    })
+    .unwrap_err();
+    let expected_err = ScriptError::ValidationFailure(rust::ENCODING).input_type_script(0);
+    assert_error_eq!(err, expected_err);
+    // the uncompressed reverted block hashes don't match the block
+    let err = submit_compressed_block_with_batch_deposit(|action| {
+        let compressed = compress_rollup_action(action);
+        // replace the empty Byte32Vec
+        let mut hashes = 1u32.to_le_bytes().to_vec();
+        hashes.extend_from_slice(&[1u8; 32]);
+        compressed_action(&hashes, &compressed[8..])
+    })
+    .unwrap_err();
+    assert_error_eq!(err, expected_err);
+}
+
+#[test]
+fn test_compressed_block_size_limits() {
+    let expected_err = ScriptError::ValidationFailure(rust::ENCODING).input_type_script(0);
+    // the largest compressed data and decoded data fit the heap, the script doesn't abort
+    // on the allocation and rejects the decoded data, which isn't a valid RollupSubmitBlock
+    let err = submit_compressed_block_with_batch_deposit(|_action| {
+        compressed_action(
+            &0u32.to_le_bytes(),
+            &compressed_padding(MAX_COMPRESSED_SIZE, MAX_DECOMPRESSED_SIZE),
+        )
+    })
+    .unwrap_err();
+    assert_error_eq!(err, expected_err);
+    // above the limits
+    let err = submit_compressed_block_with_batch_deposit(|_action| {
+        compressed_action(
+            &0u32.to_le_bytes(),
+            &compressed_padding(MAX_COMPRESSED_SIZE + 1, MAX_DECOMPRESSED_SIZE),
+        )
+    })
+    .unwrap_err();
+    assert_error_eq!(err, expected_err);
+    let err = submit_compressed_block_with_batch_deposit(|_action| {
+        compressed_action(
+            &0u32.to_le_bytes(),
+            &compressed_padding(MAX_COMPRESSED_SIZE, MAX_DECOMPRESSED_SIZE + 1),
+        )
+    })
+    .unwrap_err();
+    assert_error_eq!(err, expected_err);
+}
+
+#[test]
+fn test_batch_deposit_mismatch_cell_value() {
+    // the entries sum up to 199 CKB
+    let requests = batch_deposit_requests(&[120 * CKB, 79 * CKB]);
+    let err = submit_block_with_deposit_cells(
+        |_rollup_type_hash, _l2_sudt_script_type_hash| {
+            (
+                DepositLockArgs::default(),
+                batch_extension(&requests),
+                requests.clone(),
+            )
+        },
+        None,
+        |config| config.clone(),
+    )
+    .unwrap_err();
+    let expected_err =
+        ScriptError::ValidationFailure(rust::INVALID_DEPOSIT_CELL).input_type_script(0);
+    assert_error_eq!(err, expected_err);
+}
+
+#[test]
+fn test_revert_batch_deposit() {
+    let requests = batch_deposit_requests(&[120 * CKB, 80 * CKB]);
+    let build_deposit = |_rollup_type_hash: &[u8; 32], _l2_sudt_script_type_hash: &[u8; 32]| {
+        (
+            DepositLockArgs::new_builder()
+                .layer2_lock(eoa_script(b"depositor"))
+                .build(),
+            Bytes::default(),
+            vec![DepositRequest::new_builder()
+                .capacity(Pack::pack(&DEPOSIT_CAPACITY))
+                .script(eoa_script(b"depositor"))
+                .build()],
+        )
+    };
+    let extension = batch_extension(&requests);
+    submit_block_with_deposit_cells(
+        build_deposit,
+        Some((extension.clone(), extension.clone())),
+        |config| config.clone(),
+    )
+    .expect("return success");
+    // the reverted deposit cell must keep the entries
+    let reverted_extension = batch_extension(&batch_deposit_requests(&[DEPOSIT_CAPACITY]));
+    let err = submit_block_with_deposit_cells(
+        build_deposit,
+        Some((extension, reverted_extension)),
+        |config| config.clone(),
+    )
+    .unwrap_err();
+    let expected_err =
+        ScriptError::ValidationFailure(rust::INVALID_CUSTODIAN_CELL).input_type_script(0);
+    assert_error_eq!(err, expected_err);
+}
+
+#[test]
+fn test_min_deposit_capacity() {
+    // the deposit creates a new account
+    submit_block_with_limited_deposit(
+        |_rollup_type_hash, _l2_sudt_script_type_hash| eoa_script(b"depositor"),
+        |config| with_deposit_limits(config, DEPOSIT_CAPACITY, &[]),
+    )
+    .expect("return success");
+    let err = submit_block_with_limited_deposit(
+        |_rollup_type_hash, _l2_sudt_script_type_hash| eoa_script(b"depositor"),
+        |config| with_deposit_limits(config, DEPOSIT_CAPACITY + 1, &[]),
+    )
+    .unwrap_err();
+    let expected_err =
+        ScriptError::ValidationFailure(rust::INVALID_DEPOSIT_CELL).input_type_script(0);
+    assert_error_eq!(err, expected_err);
+    // the minimal capacity doesn't apply to existing accounts
+    submit_block_with_limited_deposit(ckb_sudt_script, |config| {
+        with_deposit_limits(config, DEPOSIT_CAPACITY + 1, &[])
+    })
+    .expect("return success");
+}
+
+#[test]
+fn test_deposit_caps() {
+    submit_block_with_limited_deposit(
+        |_rollup_type_hash, _l2_sudt_script_type_hash| eoa_script(b"depositor"),
+        |config| {
+            with_deposit_limits(
+                config,
+                0,
+                &[(CKB_SUDT_SCRIPT_ARGS, DEPOSIT_CAPACITY.into())],
+            )
+        },
+    )
+    .expect("return success");
+    let err = submit_block_with_limited_deposit(
+        |_rollup_type_hash, _l2_sudt_script_type_hash| eoa_script(b"depositor"),
+        |config| {
+            with_deposit_limits(
+                config,
+                0,
+                &[(CKB_SUDT_SCRIPT_ARGS, (DEPOSIT_CAPACITY - 1).into())],
+            )
+        },
+    )
+    .unwrap_err();
+    let expected_err =
+        ScriptError::ValidationFailure(rust::INVALID_DEPOSIT_CELL).input_type_script(0);
+    assert_error_eq!(err, expected_err);
+}
+
+#[test]
+fn test_deposit_fee_exceeds_deposit() {
+    let err = submit_block_with_deposit_fee(CKB_SUDT_ACCOUNT_ID, DEPOSIT_CAPACITY as u128 + 1)
+        .unwrap_err();
+    let expected_err =
+        ScriptError::ValidationFailure(rust::INVALID_DEPOSIT_CELL).input_type_script(0);
+    assert_error_eq!(err, expected_err);
+}
+
+#[test]
+fn test_deposit_fee_in_other_sudt() {
+    // the deposit carries CKB only
+    let err = submit_block_with_deposit_fee(42, 1).unwrap_err();
+    let expected_err =
+        ScriptError::ValidationFailure(rust::INVALID_DEPOSIT_CELL).input_type_script(0);
+    assert_error_eq!(err, expected_err);
+}
+
+#[test]
+fn test_deposit_call() {
+    let account_script = eoa_script(b"depositor");
+    let args = query_ckb_args(&account_script);
+    let call_tx = deposit_call_tx(FIRST_DEPOSIT_ACCOUNT_ID, CKB_SUDT_ACCOUNT_ID, &args);
+    submit_block_with_deposit_call(
+        |_rollup_type_hash, _l2_sudt_script_type_hash| account_script.clone(),
+        CKB_SUDT_ACCOUNT_ID,
+        &args,
+        vec![call_tx],
+    )
+    .expect("return success");
+}
+
+#[test]
+fn test_deposit_call_by_existing_account() {
+    // the CKB simple UDT exists before the deposit, the deposit can't send a call as it
+    let err = submit_block_with_deposit_call(ckb_sudt_script, CKB_SUDT_ACCOUNT_ID, &[], vec![])
+        .unwrap_err();
+    let expected_err =
+        ScriptError::ValidationFailure(rust::INVALID_DEPOSIT_CELL).input_type_script(0);
+    assert_error_eq!(err, expected_err);
+}
+
+#[test]
+fn test_deposit_call_mismatch_transactions() {
+    let account_script = eoa_script(b"depositor");
+    let args = query_ckb_args(&account_script);
+    let expected_err = ScriptError::ValidationFailure(rust::INVALID_BLOCK).input_type_script(0);
+    // the deposit call is missing
+    let err = submit_block_with_deposit_call(
+        |_rollup_type_hash, _l2_sudt_script_type_hash| account_script.clone(),
+        CKB_SUDT_ACCOUNT_ID,
+        &args,
+        vec![],
+    )
+    .unwrap_err();
+    assert_error_eq!(err, expected_err.clone());
+    // the transaction doesn't match the deposit call
+    let other_args = query_ckb_args(&eoa_script(b"other"));
+    let call_tx = deposit_call_tx(FIRST_DEPOSIT_ACCOUNT_ID, CKB_SUDT_ACCOUNT_ID, &other_args);
+    let err = submit_block_with_deposit_call(
+        |_rollup_type_hash, _l2_sudt_script_type_hash| account_script.clone(),
+        CKB_SUDT_ACCOUNT_ID,
+        &args,
+        vec![call_tx],
+    )
+    .unwrap_err();
+    assert_error_eq!(err, expected_err);
+}
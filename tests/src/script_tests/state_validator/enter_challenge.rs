@@ -1,3 +1,4 @@
+use crate::script_tests::utils::error_codes::rust;
 use crate::script_tests::utils::layer1::build_simple_tx_with_out_point;
 use crate::script_tests::utils::layer1::random_out_point;
 use crate::script_tests::utils::rollup::{
@@ -27,8 +28,6 @@ use gw_types::{
     },
 };
 
-const INVALID_CHALLENGE_TARGET_ERROR: i8 = 34;
-
 #[test]
 fn test_enter_challenge() {
     let input_out_point = random_out_point();
@@ -428,6 +427,6 @@ fn test_enter_challenge_finalized_block() {
 
     let err = ctx.verify_tx(tx).unwrap_err();
     let expected_err =
-        ScriptError::ValidationFailure(INVALID_CHALLENGE_TARGET_ERROR).input_type_script(0);
+        ScriptError::ValidationFailure(rust::INVALID_CHALLENGE_TARGET).input_type_script(0);
     assert_error_eq!(err, expected_err);
 }
@@ -1,4 +1,11 @@
+mod bench;
 mod cancel_challenge;
+mod compaction;
+mod dao;
+mod deposit;
 mod enter_challenge;
 mod revert;
+mod solvency;
+mod stake;
 mod submit_block;
+mod token_cells;
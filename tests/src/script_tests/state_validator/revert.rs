@@ -1,11 +1,14 @@
+use crate::script_tests::utils::error_codes::rust;
 use crate::script_tests::utils::layer1::build_simple_tx_with_out_point;
 use crate::script_tests::utils::layer1::{always_success_script, random_out_point};
 use crate::script_tests::utils::rollup::{
     build_always_success_cell, build_rollup_locked_cell, build_type_id_script,
-    calculate_state_validator_type_id, CellContext, CellContextParam,
+    calculate_state_validator_type_id, with_slash_schedule, CellContext, CellContextParam,
 };
 use crate::testing_tool::chain::{apply_block_result, construct_block, setup_chain};
 use crate::testing_tool::programs::{ALWAYS_SUCCESS_CODE_HASH, STATE_VALIDATOR_CODE_HASH};
+use ckb_error::assert_error_eq;
+use ckb_script::ScriptError;
 use ckb_types::{
     packed::{CellInput, CellOutput},
     prelude::{Pack as CKBPack, Unpack as CKBUnpack},
@@ -26,8 +29,104 @@ use gw_types::{
 };
 use gw_types::{packed::StakeLockArgs, prelude::*};
 
+const STAKE_CAPACITY: u64 = 10000_00000000u64;
+/// Slashing schedule: (slash_capacity, slash_rate, slash_per_reverted_block)
+type SlashSchedule = (u64, u32, bool);
+
 #[test]
 fn test_revert() {
+    // forfeit the whole stake
+    revert_with_slash_schedule(None, STAKE_CAPACITY, None).expect("return success");
+}
+
+#[test]
+fn test_revert_with_slash_schedule() {
+    // slash 1000 CKB + 10% of the stake
+    let schedule = Some((1000_00000000u64, 1000u32, false));
+    revert_with_slash_schedule(schedule, 2000_00000000u64, Some(8000_00000000u64))
+        .expect("return success");
+    // scaled by the number of reverted blocks
+    let schedule = Some((1000_00000000u64, 1000u32, true));
+    revert_with_slash_schedule(schedule, 2000_00000000u64, Some(8000_00000000u64))
+        .expect("return success");
+    // more than the stake
+    let schedule = Some((20000_00000000u64, 0u32, false));
+    revert_with_slash_schedule(schedule, STAKE_CAPACITY, None).expect("return success");
+}
+
+#[test]
+fn test_revert_without_returning_stake() {
+    let expected_err =
+        ScriptError::ValidationFailure(rust::INVALID_STAKE_CELL).input_type_script(0);
+    let schedule = Some((1000_00000000u64, 1000u32, false));
+    let err =
+        revert_with_slash_schedule(schedule, 2000_00000000u64, Some(7999_00000000u64)).unwrap_err();
+    assert_error_eq!(err, expected_err);
+    let err = revert_with_slash_schedule(schedule, 2000_00000000u64, None).unwrap_err();
+    assert_error_eq!(err, expected_err);
+    // the whole stake is forfeited
+    let err = revert_with_slash_schedule(None, STAKE_CAPACITY, Some(1)).unwrap_err();
+    assert_error_eq!(err, expected_err);
+}
+
+#[test]
+fn test_revert_with_insufficient_slashed_rewards() {
+    // the challenger is rewarded from the slashed capacity
+    let schedule = Some((1000_00000000u64, 1000u32, false));
+    let err =
+        revert_with_slash_schedule(schedule, 1000_00000000u64, Some(9000_00000000u64)).unwrap_err();
+    let expected_err =
+        ScriptError::ValidationFailure(rust::INVALID_CHALLENGE_REWARD).input_type_script(0);
+    assert_error_eq!(err, expected_err);
+}
+
+#[test]
+fn test_revert_with_delegated_stake() {
+    // only the producer's own stake is slashed, the delegated stake cells aren't accepted
+    let schedule = Some((1000_00000000u64, 1000u32, false));
+    let err = revert_with_delegated_stake(
+        schedule,
+        2000_00000000u64,
+        Some(8000_00000000u64),
+        &[(5000_00000000u64, Some(5000_00000000u64))],
+    )
+    .unwrap_err();
+    let expected_err =
+        ScriptError::ValidationFailure(rust::INVALID_STAKE_CELL).input_type_script(0);
+    assert_error_eq!(err, expected_err);
+}
+
+/// Revert the challenged block,
+/// `slashed_capacity` is split into the reward and the burn,
+/// `returned_capacity` is returned to an output stake cell
+fn revert_with_slash_schedule(
+    slash_schedule: Option<SlashSchedule>,
+    slashed_capacity: u64,
+    returned_capacity: Option<u64>,
+) -> Result<ckb_types::core::Cycle, ckb_error::Error> {
+    revert_with_delegated_stake(slash_schedule, slashed_capacity, returned_capacity, &[])
+}
+
+/// Build the lock args of a stake cell delegated to the producer of the challenged block
+fn delegated_stake_lock_args(delegator_index: usize) -> Bytes {
+    let lock_args = StakeLockArgs::new_builder()
+        .owner_lock_hash(Pack::pack(&[delegator_index as u8 + 1; 32]))
+        .build();
+    let mut args = lock_args.as_slice().to_vec();
+    // the producer's stake cell is owned by the default lock hash
+    args.extend_from_slice(&[0u8; 32]);
+    args.into()
+}
+
+/// Revert the challenged block with the delegated stake cells,
+/// `delegated_stakes` are the (input capacity, returned capacity) of the delegated stake cells,
+/// `slashed_capacity` is the slashed capacity of all the stake cells
+fn revert_with_delegated_stake(
+    slash_schedule: Option<SlashSchedule>,
+    slashed_capacity: u64,
+    returned_capacity: Option<u64>,
+    delegated_stakes: &[(u64, Option<u64>)],
+) -> Result<ckb_types::core::Cycle, ckb_error::Error> {
     let input_out_point = random_out_point();
     let type_id = calculate_state_validator_type_id(input_out_point.clone());
     let rollup_type_script = {
@@ -52,13 +151,24 @@ fn test_revert() {
     let challenge_lock_type = build_type_id_script(b"challenge_lock_type_id");
     let challenge_script_type_hash: [u8; 32] = challenge_lock_type.calc_script_hash().unpack();
     let finality_blocks = 10;
-    let rollup_config = RollupConfig::new_builder()
-        .stake_script_type_hash(Pack::pack(&stake_script_type_hash))
-        .challenge_script_type_hash(Pack::pack(&challenge_script_type_hash))
-        .reward_burn_rate(50u8.into())
-        .burn_lock_hash(Pack::pack(&reward_burn_lock_hash))
-        .finality_blocks(Pack::pack(&finality_blocks))
-        .build();
+    let rollup_config = {
+        let config = RollupConfig::new_builder()
+            .stake_script_type_hash(Pack::pack(&stake_script_type_hash))
+            .challenge_script_type_hash(Pack::pack(&challenge_script_type_hash))
+            .reward_burn_rate(50u8.into())
+            .burn_lock_hash(Pack::pack(&reward_burn_lock_hash))
+            .finality_blocks(Pack::pack(&finality_blocks))
+            .build();
+        match slash_schedule {
+            Some((slash_capacity, slash_rate, slash_per_reverted_block)) => with_slash_schedule(
+                &config,
+                slash_capacity,
+                slash_rate,
+                slash_per_reverted_block,
+            ),
+            None => config,
+        }
+    };
     // setup chain
     let mut chain = setup_chain(rollup_type_script.clone(), rollup_config.clone());
     // create a rollup cell
@@ -153,7 +263,7 @@ fn test_revert() {
         ..Default::default()
     };
     let mut ctx = CellContext::new(&rollup_config, param);
-    let stake_capacity = 10000_00000000u64;
+    let stake_capacity = STAKE_CAPACITY;
     let input_stake_cell = {
         let cell = build_rollup_locked_cell(
             &rollup_type_script.hash(),
@@ -164,6 +274,20 @@ fn test_revert() {
         let out_point = ctx.insert_cell(cell, Bytes::default());
         CellInput::new_builder().previous_output(out_point).build()
     };
+    let input_delegated_stake_cells: Vec<_> = delegated_stakes
+        .iter()
+        .enumerate()
+        .map(|(index, (capacity, _))| {
+            let cell = build_rollup_locked_cell(
+                &rollup_type_script.hash(),
+                &stake_script_type_hash,
+                *capacity,
+                delegated_stake_lock_args(index),
+            );
+            let out_point = ctx.insert_cell(cell, Bytes::default());
+            CellInput::new_builder().previous_output(out_point).build()
+        })
+        .collect();
     let challenge_capacity = 10000_00000000u64;
     let challenged_block = chain.local_state().tip().clone();
     let input_challenge_cell = {
@@ -197,9 +321,9 @@ fn test_revert() {
             .build()
     };
     let burn_rate: u8 = rollup_config.reward_burn_rate().into();
-    let reward_capacity: u64 = stake_capacity * burn_rate as u64 / 100;
+    let reward_capacity: u64 = slashed_capacity * burn_rate as u64 / 100;
     let received_capacity: u64 = reward_capacity + challenge_capacity;
-    let burned_capacity: u64 = stake_capacity - reward_capacity;
+    let burned_capacity: u64 = slashed_capacity - reward_capacity;
     let receive_cell = CellOutput::new_builder()
         .capacity(CKBPack::pack(&received_capacity))
         .lock(reward_receive_lock)
@@ -291,14 +415,40 @@ fn test_revert() {
     .output(receive_cell)
     .output_data(Default::default())
     .output(reward_burned_cell)
-    .output_data(Default::default())
-    .cell_dep(ctx.challenge_lock_dep.clone())
-    .cell_dep(ctx.stake_lock_dep.clone())
-    .cell_dep(ctx.always_success_dep.clone())
-    .cell_dep(ctx.state_validator_dep.clone())
-    .cell_dep(ctx.rollup_config_dep.clone())
-    .witness(CKBPack::pack(&witness.as_bytes()))
-    .witness(CKBPack::pack(&Bytes::new()))
-    .build();
-    ctx.verify_tx(tx).expect("return success");
+    .output_data(Default::default());
+    // return the remaining stake
+    let tx = match returned_capacity {
+        Some(capacity) => tx
+            .output(build_rollup_locked_cell(
+                &rollup_type_script.hash(),
+                &stake_script_type_hash,
+                capacity,
+                StakeLockArgs::default().as_bytes(),
+            ))
+            .output_data(Default::default()),
+        None => tx,
+    };
+    let mut tx = tx.inputs(input_delegated_stake_cells);
+    for (index, (_, returned_capacity)) in delegated_stakes.iter().enumerate() {
+        if let Some(capacity) = returned_capacity {
+            tx = tx
+                .output(build_rollup_locked_cell(
+                    &rollup_type_script.hash(),
+                    &stake_script_type_hash,
+                    *capacity,
+                    delegated_stake_lock_args(index),
+                ))
+                .output_data(Default::default());
+        }
+    }
+    let tx = tx
+        .cell_dep(ctx.challenge_lock_dep.clone())
+        .cell_dep(ctx.stake_lock_dep.clone())
+        .cell_dep(ctx.always_success_dep.clone())
+        .cell_dep(ctx.state_validator_dep.clone())
+        .cell_dep(ctx.rollup_config_dep.clone())
+        .witness(CKBPack::pack(&witness.as_bytes()))
+        .witness(CKBPack::pack(&Bytes::new()))
+        .build();
+    ctx.verify_tx(tx)
 }
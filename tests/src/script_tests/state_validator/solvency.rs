@@ -0,0 +1,294 @@
+use crate::script_tests::utils::error_codes::rust;
+use crate::script_tests::utils::layer1::{build_simple_tx_with_out_point, random_out_point};
+use crate::script_tests::utils::rollup::{
+    build_always_success_cell, build_rollup_locked_cell, build_type_id_script,
+    calculate_state_validator_type_id, CellContext, CellContextParam,
+};
+use crate::testing_tool::chain::{construct_block, setup_chain};
+use crate::testing_tool::programs::{ALWAYS_SUCCESS_CODE_HASH, STATE_VALIDATOR_CODE_HASH};
+use ckb_error::assert_error_eq;
+use ckb_script::ScriptError;
+use ckb_types::{
+    packed::CellInput,
+    prelude::{Pack as CKBPack, Unpack},
+};
+use gw_common::CKB_SUDT_SCRIPT_ARGS;
+use gw_types::prelude::*;
+use gw_types::{
+    bytes::Bytes,
+    core::ScriptHashType,
+    packed::{
+        CustodianLockArgs, DepositLockArgs, RollupAction, RollupActionUnion, RollupConfig,
+        RollupSubmitBlock, Script, StakeLockArgs, WithdrawalLockArgs,
+    },
+};
+
+const CKB: u128 = 100000000;
+
+/// Encode the custodian assets accumulator appended to the GlobalState
+fn custodian_assets(assets: &[([u8; 32], u128)]) -> Bytes {
+    let mut data = (assets.len() as u32).to_le_bytes().to_vec();
+    for (sudt_script_hash, amount) in assets {
+        data.extend_from_slice(sudt_script_hash);
+        data.extend_from_slice(&amount.to_le_bytes());
+    }
+    data.into()
+}
+
+fn rollup_cell_data(global_state: Bytes, assets: &Option<Bytes>) -> Bytes {
+    let mut data = global_state.to_vec();
+    if let Some(assets) = assets {
+        data.extend_from_slice(assets);
+    }
+    data.into()
+}
+
+/// Submit a block which reverts a deposit of 200 CKB and a withdrawal of 130 CKB,
+/// the custodian cells lose 70 CKB
+fn submit_block_with_custodian_assets(
+    prev_assets: Option<Bytes>,
+    post_assets: Option<Bytes>,
+) -> Result<ckb_types::core::Cycle, ckb_error::Error> {
+    let capacity = 1000_00000000u64;
+    let input_out_point = random_out_point();
+    let type_id = calculate_state_validator_type_id(input_out_point.clone());
+    let rollup_type_script = {
+        Script::new_builder()
+            .code_hash(Pack::pack(&*STATE_VALIDATOR_CODE_HASH))
+            .hash_type(ScriptHashType::Data.into())
+            .args(Pack::pack(&Bytes::from(type_id.to_vec())))
+            .build()
+    };
+    // rollup lock & config
+    let stake_lock_type = build_type_id_script(b"stake_lock_type_id");
+    let stake_script_type_hash: [u8; 32] = stake_lock_type.calc_script_hash().unpack();
+    let deposit_lock_type = build_type_id_script(b"deposit_lock_type_id");
+    let deposit_script_type_hash: [u8; 32] = deposit_lock_type.calc_script_hash().unpack();
+    let custodian_lock_type = build_type_id_script(b"custodian_lock_type_id");
+    let custodian_script_type_hash: [u8; 32] = custodian_lock_type.calc_script_hash().unpack();
+    let withdrawal_lock_type = build_type_id_script(b"withdrawal_lock_type_id");
+    let withdrawal_script_type_hash: [u8; 32] = withdrawal_lock_type.calc_script_hash().unpack();
+    let rollup_config = RollupConfig::new_builder()
+        .stake_script_type_hash(Pack::pack(&stake_script_type_hash))
+        .deposit_script_type_hash(Pack::pack(&deposit_script_type_hash))
+        .custodian_script_type_hash(Pack::pack(&custodian_script_type_hash))
+        .withdrawal_script_type_hash(Pack::pack(&withdrawal_script_type_hash))
+        .build();
+    // setup chain
+    let chain = setup_chain(rollup_type_script.clone(), rollup_config.clone());
+    // deploy scripts
+    let param = CellContextParam {
+        stake_lock_type,
+        deposit_lock_type,
+        custodian_lock_type,
+        withdrawal_lock_type,
+        ..Default::default()
+    };
+    let mut ctx = CellContext::new(&rollup_config, param);
+    let stake_capacity = 10000_00000000u64;
+    let input_stake_cell = {
+        let cell = build_rollup_locked_cell(
+            &rollup_type_script.hash(),
+            &stake_script_type_hash,
+            stake_capacity,
+            StakeLockArgs::default().as_bytes(),
+        );
+        let out_point = ctx.insert_cell(cell, Bytes::default());
+        CellInput::new_builder().previous_output(out_point).build()
+    };
+    let output_stake_cell = {
+        let lock_args = StakeLockArgs::new_builder()
+            .stake_block_number(Pack::pack(&1))
+            .build();
+        build_rollup_locked_cell(
+            &rollup_type_script.hash(),
+            &stake_script_type_hash,
+            stake_capacity,
+            lock_args.as_bytes(),
+        )
+    };
+    // create a rollup cell
+    let rollup_cell = build_always_success_cell(
+        capacity,
+        Some(ckb_types::packed::Script::new_unchecked(
+            rollup_type_script.as_bytes(),
+        )),
+    );
+    let global_state = chain.local_state().last_global_state();
+    let initial_rollup_cell_data = rollup_cell_data(global_state.as_bytes(), &prev_assets);
+    // reverted deposit: unfinalized custodian cell -> deposit cell
+    let reverted_deposit_capacity: u64 = 200_00000000u64;
+    let deposit_args = DepositLockArgs::new_builder()
+        .owner_lock_hash(Pack::pack(&[0u8; 32]))
+        .layer2_lock(
+            Script::new_builder()
+                .code_hash(Pack::pack(&ALWAYS_SUCCESS_CODE_HASH.clone()))
+                .hash_type(ScriptHashType::Data.into())
+                .args(Pack::pack(&Bytes::from(b"sender".to_vec())))
+                .build(),
+        )
+        .cancel_timeout(Pack::pack(&0))
+        .build();
+    let revert_block_hash = [42u8; 32];
+    let revert_block_number = 2u64;
+    let input_reverted_custodian_cell = {
+        let args = CustodianLockArgs::new_builder()
+            .deposit_lock_args(deposit_args.clone())
+            .deposit_block_hash(Pack::pack(&revert_block_hash))
+            .deposit_block_number(Pack::pack(&revert_block_number))
+            .build();
+        let cell = build_rollup_locked_cell(
+            &rollup_type_script.hash(),
+            &custodian_script_type_hash,
+            reverted_deposit_capacity,
+            args.as_bytes(),
+        );
+        let out_point = ctx.insert_cell(cell, Bytes::new());
+        CellInput::new_builder().previous_output(out_point).build()
+    };
+    let output_reverted_deposit_cell = build_rollup_locked_cell(
+        &rollup_type_script.hash(),
+        &deposit_script_type_hash,
+        reverted_deposit_capacity,
+        deposit_args.as_bytes(),
+    );
+    // reverted withdrawal: withdrawal cell -> finalized custodian cell
+    let reverted_withdrawal_capacity: u64 = 130_00000000u64;
+    let input_reverted_withdrawal_cell = {
+        let args = WithdrawalLockArgs::new_builder()
+            .withdrawal_block_hash(Pack::pack(&revert_block_hash))
+            .withdrawal_block_number(Pack::pack(&revert_block_number))
+            .build();
+        let cell = build_rollup_locked_cell(
+            &rollup_type_script.hash(),
+            &withdrawal_script_type_hash,
+            reverted_withdrawal_capacity,
+            args.as_bytes(),
+        );
+        let out_point = ctx.insert_cell(cell, Bytes::new());
+        CellInput::new_builder().previous_output(out_point).build()
+    };
+    let output_reverted_custodian_cell = {
+        let args = CustodianLockArgs::new_builder()
+            .deposit_block_hash(Pack::pack(&[0u8; 32]))
+            .deposit_block_number(Pack::pack(&0))
+            .build();
+        build_rollup_locked_cell(
+            &rollup_type_script.hash(),
+            &custodian_script_type_hash,
+            reverted_withdrawal_capacity,
+            args.as_bytes(),
+        )
+    };
+    // submit a new block
+    let block_result = {
+        let mem_pool = chain.mem_pool().lock();
+        construct_block(&chain, &mem_pool, Vec::default()).unwrap()
+    };
+    let post_rollup_cell_data =
+        rollup_cell_data(block_result.global_state.as_bytes(), &post_assets);
+    let witness = {
+        let rollup_action = RollupAction::new_builder()
+            .set(RollupActionUnion::RollupSubmitBlock(
+                RollupSubmitBlock::new_builder()
+                    .block(block_result.block.clone())
+                    .build(),
+            ))
+            .build();
+        ckb_types::packed::WitnessArgs::new_builder()
+            .output_type(CKBPack::pack(&Some(rollup_action.as_bytes())))
+            .build()
+    };
+    let tx = build_simple_tx_with_out_point(
+        &mut ctx.inner,
+        (rollup_cell.clone(), initial_rollup_cell_data),
+        input_out_point,
+        (rollup_cell, post_rollup_cell_data),
+    )
+    .as_advanced_builder()
+    .input(input_stake_cell)
+    .output(output_stake_cell)
+    .output_data(CKBPack::pack(&Bytes::default()))
+    .input(input_reverted_custodian_cell)
+    .output(output_reverted_deposit_cell)
+    .output_data(CKBPack::pack(&Bytes::default()))
+    .input(input_reverted_withdrawal_cell)
+    .output(output_reverted_custodian_cell)
+    .output_data(CKBPack::pack(&Bytes::default()))
+    .cell_dep(ctx.stake_lock_dep.clone())
+    .cell_dep(ctx.deposit_lock_dep.clone())
+    .cell_dep(ctx.custodian_lock_dep.clone())
+    .cell_dep(ctx.withdrawal_lock_dep.clone())
+    .cell_dep(ctx.always_success_dep.clone())
+    .cell_dep(ctx.state_validator_dep.clone())
+    .cell_dep(ctx.rollup_config_dep.clone())
+    .witness(CKBPack::pack(&witness.as_bytes()))
+    .build();
+    ctx.verify_tx(tx)
+}
+
+#[test]
+fn test_update_custodian_assets() {
+    let sudt_script_hash = [1u8; 32];
+    let prev_assets =
+        custodian_assets(&[(CKB_SUDT_SCRIPT_ARGS, 5000 * CKB), (sudt_script_hash, 42)]);
+    let post_assets =
+        custodian_assets(&[(CKB_SUDT_SCRIPT_ARGS, 4930 * CKB), (sudt_script_hash, 42)]);
+    submit_block_with_custodian_assets(Some(prev_assets), Some(post_assets))
+        .expect("return success");
+    // the accumulator is optional
+    submit_block_with_custodian_assets(None, None).expect("return success");
+}
+
+#[test]
+fn test_mismatch_custodian_assets() {
+    let expected_err =
+        ScriptError::ValidationFailure(rust::INVALID_CUSTODIAN_ASSETS).input_type_script(0);
+    let prev_assets = custodian_assets(&[(CKB_SUDT_SCRIPT_ARGS, 5000 * CKB)]);
+    // wrong amount
+    let post_assets = custodian_assets(&[(CKB_SUDT_SCRIPT_ARGS, 5000 * CKB)]);
+    let err = submit_block_with_custodian_assets(Some(prev_assets.clone()), Some(post_assets))
+        .unwrap_err();
+    assert_error_eq!(err, expected_err);
+    // drop the accumulator
+    let err = submit_block_with_custodian_assets(Some(prev_assets), None).unwrap_err();
+    assert_error_eq!(err, expected_err);
+    // add the accumulator to an existing rollup
+    let post_assets = custodian_assets(&[(CKB_SUDT_SCRIPT_ARGS, 4930 * CKB)]);
+    let err = submit_block_with_custodian_assets(None, Some(post_assets)).unwrap_err();
+    assert_error_eq!(err, expected_err);
+}
+
+#[test]
+fn test_insufficient_custodian_assets() {
+    // the custodian cells lose more assets than the accumulator records
+    let prev_assets = custodian_assets(&[(CKB_SUDT_SCRIPT_ARGS, 50 * CKB)]);
+    let post_assets = custodian_assets(&[]);
+    let err = submit_block_with_custodian_assets(Some(prev_assets), Some(post_assets)).unwrap_err();
+    let expected_err =
+        ScriptError::ValidationFailure(rust::INVALID_CUSTODIAN_ASSETS).input_type_script(0);
+    assert_error_eq!(err, expected_err);
+}
+
+#[test]
+fn test_malformed_custodian_assets() {
+    let expected_err =
+        ScriptError::ValidationFailure(rust::INVALID_CUSTODIAN_ASSETS).input_type_script(0);
+    let post_assets = custodian_assets(&[(CKB_SUDT_SCRIPT_ARGS, 4930 * CKB)]);
+    // unsorted
+    let prev_assets = custodian_assets(&[([1u8; 32], 42), (CKB_SUDT_SCRIPT_ARGS, 5000 * CKB)]);
+    let err = submit_block_with_custodian_assets(Some(prev_assets), Some(post_assets.clone()))
+        .unwrap_err();
+    assert_error_eq!(err, expected_err);
+    // zero amount
+    let prev_assets = custodian_assets(&[(CKB_SUDT_SCRIPT_ARGS, 5000 * CKB), ([1u8; 32], 0)]);
+    let err = submit_block_with_custodian_assets(Some(prev_assets), Some(post_assets.clone()))
+        .unwrap_err();
+    assert_error_eq!(err, expected_err);
+    // truncated
+    let prev_assets = custodian_assets(&[(CKB_SUDT_SCRIPT_ARGS, 5000 * CKB)]);
+    let prev_assets = prev_assets.slice(..prev_assets.len() - 1);
+    let err = submit_block_with_custodian_assets(Some(prev_assets), Some(post_assets)).unwrap_err();
+    let expected_err = ScriptError::ValidationFailure(rust::ENCODING).input_type_script(0);
+    assert_error_eq!(err, expected_err);
+}
@@ -0,0 +1,503 @@
+use crate::script_tests::utils::error_codes::rust;
+use crate::script_tests::utils::layer1::{build_simple_tx_with_out_point, random_out_point};
+use crate::script_tests::utils::rollup::{
+    build_always_success_cell, build_rollup_locked_cell, build_type_id_script,
+    calculate_state_validator_type_id, with_stake_rates, with_stake_token, CellContext,
+    CellContextParam,
+};
+use crate::testing_tool::chain::{construct_block, setup_chain};
+use crate::testing_tool::programs::STATE_VALIDATOR_CODE_HASH;
+use ckb_error::assert_error_eq;
+use ckb_script::ScriptError;
+use ckb_types::{
+    packed::CellInput,
+    prelude::{Pack as CKBPack, Unpack},
+};
+use gw_common::CKB_SUDT_SCRIPT_ARGS;
+use gw_types::prelude::*;
+use gw_types::{
+    bytes::Bytes,
+    core::ScriptHashType,
+    packed::{
+        RollupAction, RollupActionUnion, RollupConfig, RollupSubmitBlock, Script, StakeLockArgs,
+    },
+};
+
+const CKB: u64 = 100000000;
+const REQUIRED_STAKING_CAPACITY: u64 = 1000 * CKB;
+/// 10% of the custodian CKB
+const STAKE_TVL_RATE: u32 = 1000;
+/// stake_cell_owner_lock_hash of the submitted block
+const PRODUCER_LOCK_HASH: [u8; 32] = [0u8; 32];
+const DELEGATOR_LOCK_HASH: [u8; 32] = [7u8; 32];
+const REQUIRED_STAKING_SUDT_AMOUNT: u128 = 500;
+/// The trailing lock args byte of a delegated stake cell which requested unbonding
+const STAKE_UNBONDING_FLAG: u8 = 1;
+
+/// Encode the custodian assets accumulator which only records CKB
+fn ckb_custodian_assets(amount: u64) -> Bytes {
+    let mut data = 1u32.to_le_bytes().to_vec();
+    data.extend_from_slice(&CKB_SUDT_SCRIPT_ARGS);
+    data.extend_from_slice(&u128::from(amount).to_le_bytes());
+    data.into()
+}
+
+/// Build a stake cell of the delegator, which is delegated to the block producer,
+/// an `unbonding` cell has requested unbonding by the delegator
+fn build_delegated_stake_cell(
+    rollup_type_hash: &[u8; 32],
+    stake_script_type_hash: &[u8; 32],
+    capacity: u64,
+    stake_block_number: u64,
+    unbonding: bool,
+) -> ckb_types::packed::CellOutput {
+    let lock_args = StakeLockArgs::new_builder()
+        .owner_lock_hash(Pack::pack(&DELEGATOR_LOCK_HASH))
+        .stake_block_number(Pack::pack(&stake_block_number))
+        .build();
+    let mut args = lock_args.as_slice().to_vec();
+    args.extend_from_slice(&PRODUCER_LOCK_HASH);
+    if unbonding {
+        args.push(STAKE_UNBONDING_FLAG);
+    }
+    build_rollup_locked_cell(
+        rollup_type_hash,
+        stake_script_type_hash,
+        capacity,
+        args.into(),
+    )
+}
+
+/// Submit an empty block with the stake cell of `stake_capacity`,
+/// `custodian_assets` is appended to both rollup cells
+fn submit_block_with_stake(
+    stake_tvl_rate: u32,
+    stake_capacity: u64,
+    custodian_assets: Option<Bytes>,
+) -> Result<ckb_types::core::Cycle, ckb_error::Error> {
+    submit_block_with_delegated_stake(stake_tvl_rate, stake_capacity, custodian_assets, &[], &[])
+}
+
+/// Submit an empty block with the producer's stake cell and the delegated stake cells
+fn submit_block_with_delegated_stake(
+    stake_tvl_rate: u32,
+    stake_capacity: u64,
+    custodian_assets: Option<Bytes>,
+    input_delegated_capacities: &[u64],
+    output_delegated_capacities: &[u64],
+) -> Result<ckb_types::core::Cycle, ckb_error::Error> {
+    submit_block_with_delegated_cells(
+        stake_tvl_rate,
+        stake_capacity,
+        custodian_assets,
+        input_delegated_capacities,
+        output_delegated_capacities,
+        false,
+    )
+}
+
+/// Submit an empty block with the producer's stake cell and the delegated stake cells,
+/// the delegated stake cells are `unbonding` ones
+fn submit_block_with_delegated_cells(
+    stake_tvl_rate: u32,
+    stake_capacity: u64,
+    custodian_assets: Option<Bytes>,
+    input_delegated_capacities: &[u64],
+    output_delegated_capacities: &[u64],
+    unbonding: bool,
+) -> Result<ckb_types::core::Cycle, ckb_error::Error> {
+    let capacity = 1000 * CKB;
+    let input_out_point = random_out_point();
+    let type_id = calculate_state_validator_type_id(input_out_point.clone());
+    let rollup_type_script = {
+        Script::new_builder()
+            .code_hash(Pack::pack(&*STATE_VALIDATOR_CODE_HASH))
+            .hash_type(ScriptHashType::Data.into())
+            .args(Pack::pack(&Bytes::from(type_id.to_vec())))
+            .build()
+    };
+    // rollup lock & config
+    let stake_lock_type = build_type_id_script(b"stake_lock_type_id");
+    let stake_script_type_hash: [u8; 32] = stake_lock_type.calc_script_hash().unpack();
+    let rollup_config = {
+        let config = RollupConfig::new_builder()
+            .stake_script_type_hash(Pack::pack(&stake_script_type_hash))
+            .required_staking_capacity(Pack::pack(&REQUIRED_STAKING_CAPACITY))
+            .build();
+        with_stake_rates(&config, stake_tvl_rate, 0)
+    };
+    // setup chain
+    let chain = setup_chain(rollup_type_script.clone(), rollup_config.clone());
+    // deploy scripts
+    let param = CellContextParam {
+        stake_lock_type,
+        ..Default::default()
+    };
+    let mut ctx = CellContext::new(&rollup_config, param);
+    let output_stake_cell = {
+        let lock_args = StakeLockArgs::new_builder()
+            .stake_block_number(Pack::pack(&1))
+            .build();
+        build_rollup_locked_cell(
+            &rollup_type_script.hash(),
+            &stake_script_type_hash,
+            stake_capacity,
+            lock_args.as_bytes(),
+        )
+    };
+    // create a rollup cell
+    let rollup_cell = build_always_success_cell(
+        capacity,
+        Some(ckb_types::packed::Script::new_unchecked(
+            rollup_type_script.as_bytes(),
+        )),
+    );
+    let rollup_cell_data = |global_state: Bytes| {
+        let mut data = global_state.to_vec();
+        if let Some(assets) = &custodian_assets {
+            data.extend_from_slice(assets);
+        }
+        Bytes::from(data)
+    };
+    let global_state = chain.local_state().last_global_state();
+    let initial_rollup_cell_data = rollup_cell_data(global_state.as_bytes());
+    // submit a new block
+    let block_result = {
+        let mem_pool = chain.mem_pool().lock();
+        construct_block(&chain, &mem_pool, Vec::default()).unwrap()
+    };
+    let post_rollup_cell_data = rollup_cell_data(block_result.global_state.as_bytes());
+    let witness = {
+        let rollup_action = RollupAction::new_builder()
+            .set(RollupActionUnion::RollupSubmitBlock(
+                RollupSubmitBlock::new_builder()
+                    .block(block_result.block.clone())
+                    .build(),
+            ))
+            .build();
+        ckb_types::packed::WitnessArgs::new_builder()
+            .output_type(CKBPack::pack(&Some(rollup_action.as_bytes())))
+            .build()
+    };
+    let input_stake_cell = {
+        let cell = build_rollup_locked_cell(
+            &rollup_type_script.hash(),
+            &stake_script_type_hash,
+            stake_capacity,
+            StakeLockArgs::default().as_bytes(),
+        );
+        let out_point = ctx.insert_cell(cell, Bytes::default());
+        CellInput::new_builder().previous_output(out_point).build()
+    };
+    let input_delegated_stake_cells: Vec<_> = input_delegated_capacities
+        .iter()
+        .map(|capacity| {
+            let cell = build_delegated_stake_cell(
+                &rollup_type_script.hash(),
+                &stake_script_type_hash,
+                *capacity,
+                0,
+                unbonding,
+            );
+            let out_point = ctx.insert_cell(cell, Bytes::default());
+            CellInput::new_builder().previous_output(out_point).build()
+        })
+        .collect();
+    let mut tx = build_simple_tx_with_out_point(
+        &mut ctx.inner,
+        (rollup_cell.clone(), initial_rollup_cell_data),
+        input_out_point,
+        (rollup_cell, post_rollup_cell_data),
+    )
+    .as_advanced_builder()
+    .input(input_stake_cell)
+    .output(output_stake_cell)
+    .output_data(CKBPack::pack(&Bytes::default()))
+    .inputs(input_delegated_stake_cells);
+    for capacity in output_delegated_capacities {
+        let cell = build_delegated_stake_cell(
+            &rollup_type_script.hash(),
+            &stake_script_type_hash,
+            *capacity,
+            1,
+            unbonding,
+        );
+        tx = tx
+            .output(cell)
+            .output_data(CKBPack::pack(&Bytes::default()));
+    }
+    let tx = tx
+        .cell_dep(ctx.stake_lock_dep.clone())
+        .cell_dep(ctx.always_success_dep.clone())
+        .cell_dep(ctx.state_validator_dep.clone())
+        .cell_dep(ctx.rollup_config_dep.clone())
+        .witness(CKBPack::pack(&witness.as_bytes()))
+        .build();
+    ctx.verify_tx(tx)
+}
+
+#[test]
+fn test_stake_proportional_to_tvl() {
+    // required: 1000 CKB + 10% of 100000 CKB
+    let tvl = Some(ckb_custodian_assets(100000 * CKB));
+    submit_block_with_stake(STAKE_TVL_RATE, 11000 * CKB, tvl.clone()).expect("return success");
+    let err = submit_block_with_stake(STAKE_TVL_RATE, 10999 * CKB, tvl).unwrap_err();
+    let expected_err =
+        ScriptError::ValidationFailure(rust::INVALID_STAKE_CELL).input_type_script(0);
+    assert_error_eq!(err, expected_err);
+}
+
+#[test]
+fn test_stake_without_tvl_rate() {
+    // the static required staking capacity
+    let tvl = Some(ckb_custodian_assets(100000 * CKB));
+    submit_block_with_stake(0, REQUIRED_STAKING_CAPACITY, tvl).expect("return success");
+    submit_block_with_stake(0, REQUIRED_STAKING_CAPACITY, None).expect("return success");
+    let err = submit_block_with_stake(0, REQUIRED_STAKING_CAPACITY - 1, None).unwrap_err();
+    let expected_err =
+        ScriptError::ValidationFailure(rust::INVALID_STAKE_CELL).input_type_script(0);
+    assert_error_eq!(err, expected_err);
+}
+
+#[test]
+fn test_stake_tvl_rate_requires_custodian_assets() {
+    let err = submit_block_with_stake(STAKE_TVL_RATE, 11000 * CKB, None).unwrap_err();
+    let expected_err =
+        ScriptError::ValidationFailure(rust::INVALID_CUSTODIAN_ASSETS).input_type_script(0);
+    assert_error_eq!(err, expected_err);
+}
+
+#[test]
+fn test_delegated_stake() {
+    // required: 1000 CKB + 10% of 100000 CKB, 10000 CKB is delegated
+    let tvl = Some(ckb_custodian_assets(100000 * CKB));
+    submit_block_with_delegated_stake(
+        STAKE_TVL_RATE,
+        1000 * CKB,
+        tvl.clone(),
+        &[4000 * CKB],
+        &[4000 * CKB, 6000 * CKB],
+    )
+    .expect("return success");
+    // merge delegated stake cells
+    submit_block_with_delegated_stake(
+        STAKE_TVL_RATE,
+        1000 * CKB,
+        tvl.clone(),
+        &[4000 * CKB, 6000 * CKB],
+        &[10000 * CKB],
+    )
+    .expect("return success");
+    // insufficient
+    let err = submit_block_with_delegated_stake(
+        STAKE_TVL_RATE,
+        1000 * CKB,
+        tvl,
+        &[4000 * CKB],
+        &[4000 * CKB, 5999 * CKB],
+    )
+    .unwrap_err();
+    let expected_err =
+        ScriptError::ValidationFailure(rust::INVALID_STAKE_CELL).input_type_script(0);
+    assert_error_eq!(err, expected_err);
+}
+
+#[test]
+fn test_delegated_stake_decreases() {
+    let err = submit_block_with_delegated_stake(
+        0,
+        REQUIRED_STAKING_CAPACITY,
+        None,
+        &[4000 * CKB],
+        &[3999 * CKB],
+    )
+    .unwrap_err();
+    let expected_err =
+        ScriptError::ValidationFailure(rust::INVALID_STAKE_CELL).input_type_script(0);
+    assert_error_eq!(err, expected_err);
+}
+
+#[test]
+fn test_unbonding_delegated_stake() {
+    // the producer can't re-stamp the delegated stake cell which requested unbonding
+    let err = submit_block_with_delegated_cells(
+        0,
+        REQUIRED_STAKING_CAPACITY,
+        None,
+        &[4000 * CKB],
+        &[4000 * CKB],
+        true,
+    )
+    .unwrap_err();
+    let expected_err =
+        ScriptError::ValidationFailure(rust::INVALID_STAKE_CELL).input_type_script(0);
+    assert_error_eq!(err, expected_err);
+}
+
+/// Submit an empty block with a stake cell which holds `input_amount` of sUDT,
+/// the output stake cell holds `output_amount`
+fn submit_block_with_token_stake(
+    stake_token_configured: bool,
+    input_amount: u128,
+    output_amount: u128,
+) -> Result<ckb_types::core::Cycle, ckb_error::Error> {
+    let capacity = 1000 * CKB;
+    let input_out_point = random_out_point();
+    let type_id = calculate_state_validator_type_id(input_out_point.clone());
+    let rollup_type_script = {
+        Script::new_builder()
+            .code_hash(Pack::pack(&*STATE_VALIDATOR_CODE_HASH))
+            .hash_type(ScriptHashType::Data.into())
+            .args(Pack::pack(&Bytes::from(type_id.to_vec())))
+            .build()
+    };
+    // rollup lock & config
+    let stake_lock_type = build_type_id_script(b"stake_lock_type_id");
+    let stake_script_type_hash: [u8; 32] = stake_lock_type.calc_script_hash().unpack();
+    let l1_sudt_type = build_type_id_script(b"l1_sudt_type_id");
+    let l1_sudt_script_type_hash: [u8; 32] = l1_sudt_type.calc_script_hash().unpack();
+    let sudt_script = ckb_types::packed::Script::new_builder()
+        .code_hash(CKBPack::pack(&l1_sudt_script_type_hash))
+        .hash_type(ScriptHashType::Type.into())
+        .args(CKBPack::pack(&Bytes::from(b"stake token".to_vec())))
+        .build();
+    let sudt_script_hash: [u8; 32] = sudt_script.calc_script_hash().unpack();
+    let rollup_config = {
+        let config = RollupConfig::new_builder()
+            .stake_script_type_hash(Pack::pack(&stake_script_type_hash))
+            .l1_sudt_script_type_hash(Pack::pack(&l1_sudt_script_type_hash))
+            .required_staking_capacity(Pack::pack(&REQUIRED_STAKING_CAPACITY))
+            .build();
+        if stake_token_configured {
+            with_stake_token(&config, &sudt_script_hash, REQUIRED_STAKING_SUDT_AMOUNT)
+        } else {
+            config
+        }
+    };
+    // setup chain
+    let chain = setup_chain(rollup_type_script.clone(), rollup_config.clone());
+    // deploy scripts
+    let param = CellContextParam {
+        stake_lock_type,
+        l1_sudt_type,
+        ..Default::default()
+    };
+    let mut ctx = CellContext::new(&rollup_config, param);
+    let build_stake_cell = |stake_block_number: u64| {
+        let lock_args = StakeLockArgs::new_builder()
+            .stake_block_number(Pack::pack(&stake_block_number))
+            .build();
+        build_rollup_locked_cell(
+            &rollup_type_script.hash(),
+            &stake_script_type_hash,
+            REQUIRED_STAKING_CAPACITY,
+            lock_args.as_bytes(),
+        )
+        .as_builder()
+        .type_(CKBPack::pack(&Some(sudt_script.clone())))
+        .build()
+    };
+    // create a rollup cell
+    let rollup_cell = build_always_success_cell(
+        capacity,
+        Some(ckb_types::packed::Script::new_unchecked(
+            rollup_type_script.as_bytes(),
+        )),
+    );
+    let global_state = chain.local_state().last_global_state();
+    // submit a new block
+    let block_result = {
+        let mem_pool = chain.mem_pool().lock();
+        construct_block(&chain, &mem_pool, Vec::default()).unwrap()
+    };
+    let witness = {
+        let rollup_action = RollupAction::new_builder()
+            .set(RollupActionUnion::RollupSubmitBlock(
+                RollupSubmitBlock::new_builder()
+                    .block(block_result.block.clone())
+                    .build(),
+            ))
+            .build();
+        ckb_types::packed::WitnessArgs::new_builder()
+            .output_type(CKBPack::pack(&Some(rollup_action.as_bytes())))
+            .build()
+    };
+    let input_stake_cell = {
+        let data = Bytes::from(input_amount.to_le_bytes().to_vec());
+        let out_point = ctx.insert_cell(build_stake_cell(0), data);
+        CellInput::new_builder().previous_output(out_point).build()
+    };
+    let tx = build_simple_tx_with_out_point(
+        &mut ctx.inner,
+        (rollup_cell.clone(), global_state.as_bytes()),
+        input_out_point,
+        (rollup_cell, block_result.global_state.as_bytes()),
+    )
+    .as_advanced_builder()
+    .input(input_stake_cell)
+    .output(build_stake_cell(1))
+    .output_data(CKBPack::pack(&Bytes::from(
+        output_amount.to_le_bytes().to_vec(),
+    )))
+    .cell_dep(ctx.stake_lock_dep.clone())
+    .cell_dep(ctx.l1_sudt_dep.clone())
+    .cell_dep(ctx.always_success_dep.clone())
+    .cell_dep(ctx.state_validator_dep.clone())
+    .cell_dep(ctx.rollup_config_dep.clone())
+    .witness(CKBPack::pack(&witness.as_bytes()))
+    .build();
+    ctx.verify_tx(tx)
+}
+
+#[test]
+fn test_token_stake() {
+    submit_block_with_token_stake(
+        true,
+        REQUIRED_STAKING_SUDT_AMOUNT,
+        REQUIRED_STAKING_SUDT_AMOUNT,
+    )
+    .expect("return success");
+    // top up the stake token
+    submit_block_with_token_stake(
+        true,
+        REQUIRED_STAKING_SUDT_AMOUNT,
+        REQUIRED_STAKING_SUDT_AMOUNT + 100,
+    )
+    .expect("return success");
+    // insufficient
+    let err = submit_block_with_token_stake(
+        true,
+        REQUIRED_STAKING_SUDT_AMOUNT - 1,
+        REQUIRED_STAKING_SUDT_AMOUNT - 1,
+    )
+    .unwrap_err();
+    let expected_err =
+        ScriptError::ValidationFailure(rust::INVALID_STAKE_CELL).input_type_script(0);
+    assert_error_eq!(err, expected_err);
+}
+
+#[test]
+fn test_token_stake_decreases() {
+    let err = submit_block_with_token_stake(
+        true,
+        REQUIRED_STAKING_SUDT_AMOUNT + 100,
+        REQUIRED_STAKING_SUDT_AMOUNT,
+    )
+    .unwrap_err();
+    let expected_err =
+        ScriptError::ValidationFailure(rust::INVALID_STAKE_CELL).input_type_script(0);
+    assert_error_eq!(err, expected_err);
+}
+
+#[test]
+fn test_token_stake_not_configured() {
+    let err = submit_block_with_token_stake(
+        false,
+        REQUIRED_STAKING_SUDT_AMOUNT,
+        REQUIRED_STAKING_SUDT_AMOUNT,
+    )
+    .unwrap_err();
+    let expected_err =
+        ScriptError::ValidationFailure(rust::INVALID_STAKE_CELL).input_type_script(0);
+    assert_error_eq!(err, expected_err);
+}
@@ -0,0 +1,270 @@
+use crate::script_tests::utils::error_codes::rust;
+use crate::script_tests::utils::layer1::{build_simple_tx_with_out_point, random_out_point};
+use crate::script_tests::utils::rollup::{
+    build_always_success_cell, build_rollup_locked_cell, build_type_id_script,
+    calculate_state_validator_type_id, with_allowed_token_type_hashes, CellContext,
+    CellContextParam,
+};
+use crate::testing_tool::chain::{construct_block, setup_chain};
+use crate::testing_tool::programs::STATE_VALIDATOR_CODE_HASH;
+use ckb_error::assert_error_eq;
+use ckb_script::ScriptError;
+use ckb_types::{
+    packed::CellInput,
+    prelude::{Pack as CKBPack, Unpack},
+};
+use gw_types::prelude::*;
+use gw_types::{
+    bytes::Bytes,
+    core::ScriptHashType,
+    packed::{
+        CustodianLockArgs, RollupAction, RollupActionUnion, RollupConfig, RollupSubmitBlock,
+        Script, StakeLockArgs,
+    },
+};
+
+const CUSTODIAN_CAPACITY: u64 = 300_00000000u64;
+
+fn l1_sudt_type() -> ckb_types::packed::Script {
+    build_type_id_script(b"l1_sudt_type_id")
+}
+
+fn l1_xudt_type() -> ckb_types::packed::Script {
+    build_type_id_script(b"l1_xudt_type_id")
+}
+
+fn token_data(amount: u128, extension: &[u8]) -> Bytes {
+    let mut data = amount.to_le_bytes().to_vec();
+    data.extend_from_slice(extension);
+    data.into()
+}
+
+/// Submit a block which merges finalized custodian cells of a L1 token
+fn merge_token_custodian_cells(
+    allowed_token_type_hashes: &[[u8; 32]],
+    token_code_hash: [u8; 32],
+    inputs_data: Vec<Bytes>,
+    output_data: Bytes,
+) -> Result<ckb_types::core::Cycle, ckb_error::Error> {
+    let capacity = 1000_00000000u64;
+    let input_out_point = random_out_point();
+    let type_id = calculate_state_validator_type_id(input_out_point.clone());
+    let rollup_type_script = {
+        Script::new_builder()
+            .code_hash(Pack::pack(&*STATE_VALIDATOR_CODE_HASH))
+            .hash_type(ScriptHashType::Data.into())
+            .args(Pack::pack(&Bytes::from(type_id.to_vec())))
+            .build()
+    };
+    // rollup lock & config
+    let stake_lock_type = build_type_id_script(b"stake_lock_type_id");
+    let stake_script_type_hash: [u8; 32] = stake_lock_type.calc_script_hash().unpack();
+    let custodian_lock_type = build_type_id_script(b"custodian_lock_type_id");
+    let custodian_script_type_hash: [u8; 32] = custodian_lock_type.calc_script_hash().unpack();
+    let l1_sudt_script_type_hash: [u8; 32] = l1_sudt_type().calc_script_hash().unpack();
+    let rollup_config = {
+        let config = RollupConfig::new_builder()
+            .stake_script_type_hash(Pack::pack(&stake_script_type_hash))
+            .custodian_script_type_hash(Pack::pack(&custodian_script_type_hash))
+            .l1_sudt_script_type_hash(Pack::pack(&l1_sudt_script_type_hash))
+            .build();
+        with_allowed_token_type_hashes(&config, allowed_token_type_hashes)
+    };
+    // setup chain
+    let chain = setup_chain(rollup_type_script.clone(), rollup_config.clone());
+    // deploy scripts
+    let param = CellContextParam {
+        stake_lock_type: stake_lock_type.clone(),
+        custodian_lock_type: custodian_lock_type.clone(),
+        l1_sudt_type: l1_sudt_type(),
+        l1_xudt_type: l1_xudt_type(),
+        ..Default::default()
+    };
+    let mut ctx = CellContext::new(&rollup_config, param);
+    let stake_capacity = 10000_00000000u64;
+    let input_stake_cell = {
+        let cell = build_rollup_locked_cell(
+            &rollup_type_script.hash(),
+            &stake_script_type_hash,
+            stake_capacity,
+            StakeLockArgs::default().as_bytes(),
+        );
+        let out_point = ctx.insert_cell(cell, Bytes::default());
+        CellInput::new_builder().previous_output(out_point).build()
+    };
+    let output_stake_cell = {
+        let lock_args = StakeLockArgs::new_builder()
+            .stake_block_number(Pack::pack(&1))
+            .build();
+        build_rollup_locked_cell(
+            &rollup_type_script.hash(),
+            &stake_script_type_hash,
+            stake_capacity,
+            lock_args.as_bytes(),
+        )
+    };
+    // create a rollup cell
+    let rollup_cell = build_always_success_cell(
+        capacity,
+        Some(ckb_types::packed::Script::new_unchecked(
+            rollup_type_script.as_bytes(),
+        )),
+    );
+    let global_state = chain.local_state().last_global_state();
+    let initial_rollup_cell_data = global_state.as_bytes();
+    // finalized custodian cells of the token
+    let token_type_script = ckb_types::packed::Script::new_builder()
+        .code_hash(CKBPack::pack(&token_code_hash))
+        .hash_type(ScriptHashType::Type.into())
+        .args(CKBPack::pack(&Bytes::from(b"token owner".to_vec())))
+        .build();
+    let build_custodian_cell = |capacity: u64| {
+        let args = CustodianLockArgs::new_builder()
+            .deposit_block_hash(Pack::pack(&[0u8; 32]))
+            .deposit_block_number(Pack::pack(&0))
+            .build();
+        build_rollup_locked_cell(
+            &rollup_type_script.hash(),
+            &custodian_script_type_hash,
+            capacity,
+            args.as_bytes(),
+        )
+        .as_builder()
+        .type_(CKBPack::pack(&Some(token_type_script.clone())))
+        .build()
+    };
+    let output_capacity = CUSTODIAN_CAPACITY * inputs_data.len() as u64;
+    let input_custodian_cells: Vec<_> = inputs_data
+        .into_iter()
+        .map(|data| {
+            let out_point = ctx.insert_cell(build_custodian_cell(CUSTODIAN_CAPACITY), data);
+            CellInput::new_builder().previous_output(out_point).build()
+        })
+        .collect();
+    // submit a new block
+    let block_result = {
+        let mem_pool = chain.mem_pool().lock();
+        construct_block(&chain, &mem_pool, Vec::default()).unwrap()
+    };
+    let rollup_cell_data = block_result.global_state.as_bytes();
+    let witness = {
+        let rollup_action = RollupAction::new_builder()
+            .set(RollupActionUnion::RollupSubmitBlock(
+                RollupSubmitBlock::new_builder()
+                    .block(block_result.block.clone())
+                    .build(),
+            ))
+            .build();
+        ckb_types::packed::WitnessArgs::new_builder()
+            .output_type(CKBPack::pack(&Some(rollup_action.as_bytes())))
+            .build()
+    };
+    let tx = build_simple_tx_with_out_point(
+        &mut ctx.inner,
+        (rollup_cell.clone(), initial_rollup_cell_data),
+        input_out_point,
+        (rollup_cell, rollup_cell_data),
+    )
+    .as_advanced_builder()
+    .input(input_stake_cell)
+    .output(output_stake_cell)
+    .output_data(CKBPack::pack(&Bytes::default()))
+    .inputs(input_custodian_cells)
+    .output(build_custodian_cell(output_capacity))
+    .output_data(CKBPack::pack(&output_data))
+    .cell_dep(ctx.stake_lock_dep.clone())
+    .cell_dep(ctx.custodian_lock_dep.clone())
+    .cell_dep(ctx.l1_sudt_dep.clone())
+    .cell_dep(ctx.l1_xudt_dep.clone())
+    .cell_dep(ctx.always_success_dep.clone())
+    .cell_dep(ctx.state_validator_dep.clone())
+    .cell_dep(ctx.rollup_config_dep.clone())
+    .witness(CKBPack::pack(&witness.as_bytes()))
+    .build();
+    ctx.verify_tx(tx)
+}
+
+#[test]
+fn test_sudt_custodian_cells() {
+    let sudt_code_hash: [u8; 32] = l1_sudt_type().calc_script_hash().unpack();
+    // sUDT data may have extra bytes after the amount
+    merge_token_custodian_cells(
+        &[],
+        sudt_code_hash,
+        vec![token_data(100, &[]), token_data(50, &[0u8; 4])],
+        token_data(150, &[]),
+    )
+    .expect("return success");
+}
+
+#[test]
+fn test_xudt_custodian_cells() {
+    let xudt_code_hash: [u8; 32] = l1_xudt_type().calc_script_hash().unpack();
+    merge_token_custodian_cells(
+        &[xudt_code_hash],
+        xudt_code_hash,
+        vec![
+            token_data(100, b"xudt extension data"),
+            token_data(50, b"xudt extension data"),
+        ],
+        token_data(150, b"xudt extension data"),
+    )
+    .expect("return success");
+    // the amounts must still match
+    let err = merge_token_custodian_cells(
+        &[xudt_code_hash],
+        xudt_code_hash,
+        vec![
+            token_data(100, b"xudt extension data"),
+            token_data(50, b"xudt extension data"),
+        ],
+        token_data(151, b"xudt extension data"),
+    )
+    .unwrap_err();
+    let expected_err = ScriptError::ValidationFailure(rust::INSUFFICIENT_INPUT_FINALIZED_ASSETS)
+        .input_type_script(0);
+    assert_error_eq!(err, expected_err);
+}
+
+#[test]
+fn test_token_not_allowed_by_config() {
+    let xudt_code_hash: [u8; 32] = l1_xudt_type().calc_script_hash().unpack();
+    let err = merge_token_custodian_cells(
+        &[],
+        xudt_code_hash,
+        vec![token_data(100, &[]), token_data(50, &[])],
+        token_data(150, &[]),
+    )
+    .unwrap_err();
+    let expected_err = ScriptError::ValidationFailure(rust::INVALID_SUDT_CELL).input_type_script(0);
+    assert_error_eq!(err, expected_err);
+}
+
+#[test]
+fn test_malformed_token_cells() {
+    let sudt_code_hash: [u8; 32] = l1_sudt_type().calc_script_hash().unpack();
+    let xudt_code_hash: [u8; 32] = l1_xudt_type().calc_script_hash().unpack();
+    let expected_err = ScriptError::ValidationFailure(rust::INVALID_SUDT_CELL).input_type_script(0);
+    // data shorter than the amount
+    for code_hash in [sudt_code_hash, xudt_code_hash].iter() {
+        for short_data in [Bytes::new(), Bytes::from(vec![1u8; 15])].iter() {
+            let err = merge_token_custodian_cells(
+                &[xudt_code_hash],
+                *code_hash,
+                vec![token_data(100, &[]), short_data.clone()],
+                token_data(100, &[]),
+            )
+            .unwrap_err();
+            assert_error_eq!(err, expected_err.clone());
+        }
+    }
+    // malformed output token cell
+    let err = merge_token_custodian_cells(
+        &[xudt_code_hash],
+        xudt_code_hash,
+        vec![token_data(100, &[])],
+        Bytes::from(vec![1u8; 8]),
+    )
+    .unwrap_err();
+    assert_error_eq!(err, expected_err);
+}
@@ -0,0 +1,140 @@
+//! Encoder of the compressed rollup witness, see `gw_utils::compression`
+
+use gw_types::{
+    bytes::Bytes,
+    packed::{RollupAction, RollupActionUnion},
+    prelude::*,
+};
+use std::collections::HashMap;
+
+/// RollupAction item id of the compressed RollupSubmitBlock
+pub const COMPRESSED_SUBMIT_BLOCK_ITEM_ID: u32 = 6;
+/// Max size of the compressed data
+pub const MAX_COMPRESSED_SIZE: usize = 256 * 1024;
+/// Max size of the decoded data
+pub const MAX_DECOMPRESSED_SIZE: usize = 256 * 1024;
+const MAX_DICTIONARY_WORDS: usize = 8192;
+const WORD_SIZE: usize = 32;
+const TAG_LITERAL: u8 = 0;
+const TAG_WORD: u8 = 1;
+const TAG_ZEROS: u8 = 2;
+const TAG_U32: u8 = 3;
+
+fn push_varint(buf: &mut Vec<u8>, mut value: u32) {
+    while value >= 0x80 {
+        buf.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+fn flush_literal(tokens: &mut Vec<u8>, literal: &mut Vec<u8>) {
+    if !literal.is_empty() {
+        tokens.push(TAG_LITERAL);
+        push_varint(tokens, literal.len() as u32);
+        tokens.append(literal);
+    }
+}
+
+/// Compress the data, the 32 bytes words which occur more than once are put in the dictionary
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    let mut occurrences: HashMap<&[u8], usize> = HashMap::new();
+    for word in data.windows(WORD_SIZE) {
+        *occurrences.entry(word).or_default() += 1;
+    }
+    let mut dictionary: Vec<&[u8]> = Vec::new();
+    let mut indices: HashMap<&[u8], u32> = HashMap::new();
+    let mut tokens = Vec::new();
+    let mut literal = Vec::new();
+    let mut i = 0;
+    while i < data.len() {
+        let rest = &data[i..];
+        // zeros
+        let zeros = rest.iter().take_while(|b| **b == 0).count();
+        if zeros >= 4 {
+            flush_literal(&mut tokens, &mut literal);
+            tokens.push(TAG_ZEROS);
+            push_varint(&mut tokens, zeros as u32);
+            i += zeros;
+            continue;
+        }
+        // repeated words
+        if rest.len() >= WORD_SIZE {
+            let word = &rest[..WORD_SIZE];
+            let index = match indices.get(word) {
+                Some(index) => Some(*index),
+                None if occurrences[word] > 1 && dictionary.len() < MAX_DICTIONARY_WORDS => {
+                    dictionary.push(word);
+                    let index = dictionary.len() as u32 - 1;
+                    indices.insert(word, index);
+                    Some(index)
+                }
+                None => None,
+            };
+            if let Some(index) = index {
+                flush_literal(&mut tokens, &mut literal);
+                tokens.push(TAG_WORD);
+                push_varint(&mut tokens, index);
+                i += WORD_SIZE;
+                continue;
+            }
+        }
+        // small numbers, e.g. ids and molecule offsets
+        if rest.len() >= 4 && rest[0] != 0 && rest[1..4] == [0u8; 3] && rest[0] < 0x80 {
+            flush_literal(&mut tokens, &mut literal);
+            tokens.push(TAG_U32);
+            push_varint(&mut tokens, rest[0].into());
+            i += 4;
+            continue;
+        }
+        literal.push(rest[0]);
+        i += 1;
+    }
+    flush_literal(&mut tokens, &mut literal);
+
+    let mut compressed = (data.len() as u32).to_le_bytes().to_vec();
+    compressed.extend_from_slice(&(dictionary.len() as u32).to_le_bytes());
+    for word in dictionary {
+        compressed.extend_from_slice(word);
+    }
+    compressed.extend(tokens);
+    compressed
+}
+
+/// Encode the compressed item with the uncompressed reverted block hashes (a Byte32Vec)
+pub fn compressed_action(reverted_block_hashes: &[u8], compressed: &[u8]) -> Bytes {
+    let mut data = COMPRESSED_SUBMIT_BLOCK_ITEM_ID.to_le_bytes().to_vec();
+    data.extend_from_slice(reverted_block_hashes);
+    data.extend_from_slice(compressed);
+    data.into()
+}
+
+/// Encode the RollupSubmitBlock action compressed
+pub fn compress_rollup_action(action: &RollupAction) -> Bytes {
+    let args = match action.to_enum() {
+        RollupActionUnion::RollupSubmitBlock(args) => args,
+        _ => panic!("not a RollupSubmitBlock"),
+    };
+    compressed_action(
+        args.reverted_block_hashes().as_slice(),
+        &compress(args.as_slice()),
+    )
+}
+
+/// Compressed data of `size` bytes which decodes to `decoded_size` bytes,
+/// a literal token takes the space and the zeros token fills the rest
+pub fn compressed_padding(size: usize, decoded_size: usize) -> Vec<u8> {
+    let mut literal_size = size;
+    loop {
+        let mut data = (decoded_size as u32).to_le_bytes().to_vec();
+        data.extend_from_slice(&0u32.to_le_bytes());
+        let mut literal = vec![1u8; literal_size];
+        flush_literal(&mut data, &mut literal);
+        data.push(TAG_ZEROS);
+        push_varint(&mut data, (decoded_size - literal_size) as u32);
+        if data.len() == size {
+            return data;
+        }
+        literal_size = literal_size + size - data.len();
+    }
+}
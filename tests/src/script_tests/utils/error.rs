@@ -0,0 +1,59 @@
+//! Decode the exit codes of the scripts with the registry `errors.toml`
+
+use super::error_codes::{ErrorCode, ERROR_CODES};
+
+const VALIDATION_FAILURE: &str = "ValidationFailure(";
+
+/// Find the registered error of an exit code
+pub fn decode_exit_code(code: i8) -> Option<&'static ErrorCode> {
+    ERROR_CODES.iter().find(|error| error.code == code)
+}
+
+/// Exit code of the script which fails the verification
+pub fn exit_code(err: &ckb_error::Error) -> Option<i8> {
+    // ckb-error doesn't expose the inner ScriptError, parse it from the message
+    let msg = err.to_string();
+    let start = msg.find(VALIDATION_FAILURE)? + VALIDATION_FAILURE.len();
+    let end = start + msg[start..].find(')')?;
+    msg[start..end].parse().ok()
+}
+
+/// Append the name of the exit code to the error message
+pub fn describe_error(err: &ckb_error::Error) -> String {
+    match exit_code(err).and_then(decode_exit_code) {
+        Some(error) => format!(
+            "{} {}::{}: {}",
+            err, error.script, error.name, error.description
+        ),
+        None => err.to_string(),
+    }
+}
+
+#[test]
+fn test_decode_error() {
+    use super::error_codes::{c, rust};
+    use ckb_script::ScriptError;
+
+    let err: ckb_error::Error = ScriptError::ValidationFailure(rust::WRONG_SIGNATURE)
+        .input_lock_script(0)
+        .into();
+    assert_eq!(exit_code(&err), Some(rust::WRONG_SIGNATURE));
+    let error = decode_exit_code(rust::WRONG_SIGNATURE).unwrap();
+    assert_eq!((error.script, error.name), ("rust", "WrongSignature"));
+    assert!(describe_error(&err)
+        .ends_with("rust::WrongSignature: raise from signature verification script"));
+
+    let err: ckb_error::Error = ScriptError::ValidationFailure(c::GW_FATAL_SMT_VERIFY)
+        .input_type_script(1)
+        .into();
+    let error = decode_exit_code(exit_code(&err).unwrap()).unwrap();
+    assert_eq!((error.script, error.name), ("c", "GW_FATAL_SMT_VERIFY"));
+
+    // codes of the backend VM aren't registered
+    let err: ckb_error::Error = ScriptError::ValidationFailure(-1)
+        .input_type_script(0)
+        .into();
+    assert_eq!(exit_code(&err), Some(-1));
+    assert!(decode_exit_code(-1).is_none());
+    assert_eq!(describe_error(&err), err.to_string());
+}
@@ -0,0 +1,539 @@
+//! Error codes of the scripts
+//!
+//! Generated from `errors.toml` by `tools/gen-errors.py`, don't edit.
+
+#![allow(dead_code)]
+
+pub struct ErrorCode {
+    pub script: &'static str,
+    pub code: i8,
+    pub name: &'static str,
+    pub description: &'static str,
+}
+
+pub mod rust {
+    pub const INDEX_OUT_OF_BOUND: i8 = 1;
+    pub const ITEM_MISSING: i8 = 2;
+    pub const LENGTH_NOT_ENOUGH: i8 = 3;
+    pub const ENCODING: i8 = 4;
+    pub const INVALID_ARGS: i8 = 5;
+    pub const INVALID_SINCE: i8 = 6;
+    pub const INVALID_OUTPUT: i8 = 7;
+    pub const OWNER_CELL_NOT_FOUND: i8 = 8;
+    pub const ROLLUP_CELL_NOT_FOUND: i8 = 9;
+    pub const ROLLUP_CONFIG_NOT_FOUND: i8 = 10;
+    pub const PROOF_NOT_FOUND: i8 = 11;
+    pub const ACCOUNT_NOT_FOUND: i8 = 12;
+    pub const MERKLE_PROOF: i8 = 13;
+    pub const AMOUNT_OVERFLOW: i8 = 14;
+    pub const INVALID_SHORT_ADDRESS: i8 = 15;
+    pub const INSUFFICIENT_AMOUNT: i8 = 16;
+    pub const INSUFFICIENT_INPUT_FINALIZED_ASSETS: i8 = 17;
+    pub const INSUFFICIENT_OUTPUT_FINALIZED_ASSETS: i8 = 18;
+    pub const NOT_FOR_SELL: i8 = 19;
+    pub const SMT_KEY_MISSING: i8 = 20;
+    pub const INVALID_STATE_CHECKPOINT: i8 = 21;
+    pub const INVALID_BLOCK: i8 = 22;
+    pub const INVALID_STATUS: i8 = 23;
+    pub const INVALID_STAKE_CELL_UNLOCK: i8 = 24;
+    pub const INVALID_POST_GLOBAL_STATE: i8 = 25;
+    pub const INVALID_CHALLENGE_CELL: i8 = 26;
+    pub const INVALID_STAKE_CELL: i8 = 27;
+    pub const INVALID_DEPOSIT_CELL: i8 = 28;
+    pub const INVALID_WITHDRAWAL_CELL: i8 = 29;
+    pub const INVALID_CUSTODIAN_CELL: i8 = 30;
+    pub const INVALID_REVERTED_BLOCKS: i8 = 31;
+    pub const INVALID_CHALLENGE_REWARD: i8 = 32;
+    pub const INVALID_SUDT_CELL: i8 = 33;
+    pub const INVALID_CHALLENGE_TARGET: i8 = 34;
+    pub const INVALID_WITHDRAWAL_REQUEST: i8 = 35;
+    pub const UNKNOWN_EOA_SCRIPT: i8 = 36;
+    pub const UNKNOWN_CONTRACT_SCRIPT: i8 = 37;
+    pub const SCRIPT_NOT_FOUND: i8 = 38;
+    pub const ACCOUNT_LOCK_CELL_NOT_FOUND: i8 = 39;
+    pub const ACCOUNT_SCRIPT_CELL_NOT_FOUND: i8 = 40;
+    pub const INVALID_TYPE_ID: i8 = 41;
+    pub const UNEXPECTED_TX_NONCE: i8 = 42;
+    pub const WRONG_SIGNATURE: i8 = 43;
+    pub const INSUFFICIENT_KV_STATE_CAPACITY: i8 = 44;
+    pub const SMT_PROOF_MALFORMED: i8 = 45;
+    pub const SMT_ROOT_MISMATCH: i8 = 46;
+    pub const INVALID_DAO_WITHDRAWAL: i8 = 47;
+    pub const INVALID_CUSTODIAN_ASSETS: i8 = 48;
+}
+
+pub mod c {
+    pub const GW_FATAL_BUFFER_OVERFLOW: i8 = 50;
+    pub const GW_FATAL_INVALID_CONTEXT: i8 = 51;
+    pub const GW_FATAL_INVALID_DATA: i8 = 52;
+    pub const GW_FATAL_MISMATCH_RETURN_DATA: i8 = 53;
+    pub const GW_FATAL_UNKNOWN_ARGS: i8 = 54;
+    pub const GW_FATAL_INVALID_SUDT_SCRIPT: i8 = 55;
+    pub const GW_FATAL_INVALID_CHECK_POINT: i8 = 56;
+    pub const GW_FATAL_DATA_CELL_NOT_FOUND: i8 = 60;
+    pub const GW_FATAL_STATE_KEY_NOT_FOUND: i8 = 61;
+    pub const GW_FATAL_SIGNATURE_CELL_NOT_FOUND: i8 = 62;
+    pub const GW_FATAL_SCRIPT_NOT_FOUND: i8 = 63;
+    pub const GW_FATAL_SMT_VERIFY: i8 = 70;
+    pub const GW_FATAL_SMT_FETCH: i8 = 71;
+    pub const GW_FATAL_SMT_STORE: i8 = 72;
+    pub const GW_FATAL_SMT_CALCULATE_ROOT: i8 = 73;
+    pub const GW_ERROR_DUPLICATED_SCRIPT_HASH: i8 = 80;
+    pub const GW_ERROR_UNKNOWN_SCRIPT_CODE_HASH: i8 = 81;
+    pub const GW_ERROR_INVALID_ACCOUNT_SCRIPT: i8 = 82;
+    pub const GW_ERROR_NOT_FOUND: i8 = 83;
+    pub const GW_ERROR_RECOVER: i8 = 84;
+    pub const GW_ERROR_ACCOUNT_NOT_EXISTS: i8 = 85;
+    pub const GW_SUDT_ERROR_INSUFFICIENT_BALANCE: i8 = 92;
+    pub const GW_SUDT_ERROR_AMOUNT_OVERFLOW: i8 = 93;
+    pub const GW_SUDT_ERROR_TO_ADDR: i8 = 94;
+    pub const GW_SUDT_ERROR_ACCOUNT_NOT_EXISTS: i8 = 95;
+    pub const GW_SUDT_ERROR_SHORT_ADDR_LEN: i8 = 96;
+}
+
+pub const ERROR_CODES: &[ErrorCode] = &[
+    ErrorCode {
+        script: "rust",
+        code: 1,
+        name: "IndexOutOfBound",
+        description: "syscall: the index of the cell, input or witness is out of bound",
+    },
+    ErrorCode {
+        script: "rust",
+        code: 2,
+        name: "ItemMissing",
+        description: "syscall: the loaded field is missing",
+    },
+    ErrorCode {
+        script: "rust",
+        code: 3,
+        name: "LengthNotEnough",
+        description: "syscall: the buffer is shorter than the loaded data",
+    },
+    ErrorCode {
+        script: "rust",
+        code: 4,
+        name: "Encoding",
+        description: "data is not a valid molecule structure",
+    },
+    ErrorCode {
+        script: "rust",
+        code: 5,
+        name: "InvalidArgs",
+        description: "the script args are invalid",
+    },
+    ErrorCode {
+        script: "rust",
+        code: 6,
+        name: "InvalidSince",
+        description: "the since of the input doesn't satisfy the lock",
+    },
+    ErrorCode {
+        script: "rust",
+        code: 7,
+        name: "InvalidOutput",
+        description: "the output cells don't match the unlock condition",
+    },
+    ErrorCode {
+        script: "rust",
+        code: 8,
+        name: "OwnerCellNotFound",
+        description: "no input cell is locked by the owner lock",
+    },
+    ErrorCode {
+        script: "rust",
+        code: 9,
+        name: "RollupCellNotFound",
+        description: "the rollup cell is missing",
+    },
+    ErrorCode {
+        script: "rust",
+        code: 10,
+        name: "RollupConfigNotFound",
+        description: "the rollup config cell is missing from the cell deps",
+    },
+    ErrorCode {
+        script: "rust",
+        code: 11,
+        name: "ProofNotFound",
+        description: "a required merkle proof is missing",
+    },
+    ErrorCode {
+        script: "rust",
+        code: 12,
+        name: "AccountNotFound",
+        description: "the layer2 account doesn't exist",
+    },
+    ErrorCode {
+        script: "rust",
+        code: 13,
+        name: "MerkleProof",
+        description: "failed to verify a merkle proof",
+    },
+    ErrorCode {
+        script: "rust",
+        code: 14,
+        name: "AmountOverflow",
+        description: "the token amount overflows",
+    },
+    ErrorCode {
+        script: "rust",
+        code: 15,
+        name: "InvalidShortAddress",
+        description: "the short address doesn't match a script hash",
+    },
+    ErrorCode {
+        script: "rust",
+        code: 16,
+        name: "InsufficientAmount",
+        description: "the token amount is insufficient",
+    },
+    ErrorCode {
+        script: "rust",
+        code: 17,
+        name: "InsufficientInputFinalizedAssets",
+        description: "the finalized custodian assets of the inputs are insufficient",
+    },
+    ErrorCode {
+        script: "rust",
+        code: 18,
+        name: "InsufficientOutputFinalizedAssets",
+        description: "the finalized custodian assets of the outputs are insufficient",
+    },
+    ErrorCode {
+        script: "rust",
+        code: 19,
+        name: "NotForSell",
+        description: "the withdrawal cell isn't for sell",
+    },
+    ErrorCode {
+        script: "rust",
+        code: 20,
+        name: "SMTKeyMissing",
+        description: "the key is missing from the sparse merkle tree",
+    },
+    ErrorCode {
+        script: "rust",
+        code: 21,
+        name: "InvalidStateCheckpoint",
+        description: "the state checkpoint doesn't match the state",
+    },
+    ErrorCode {
+        script: "rust",
+        code: 22,
+        name: "InvalidBlock",
+        description: "the layer2 block is invalid",
+    },
+    ErrorCode {
+        script: "rust",
+        code: 23,
+        name: "InvalidStatus",
+        description: "the rollup status doesn't allow the action",
+    },
+    ErrorCode {
+        script: "rust",
+        code: 24,
+        name: "InvalidStakeCellUnlock",
+        description: "the stake cell can't be unlocked yet",
+    },
+    ErrorCode {
+        script: "rust",
+        code: 25,
+        name: "InvalidPostGlobalState",
+        description: "the post global state doesn't match the expected one",
+    },
+    ErrorCode {
+        script: "rust",
+        code: 26,
+        name: "InvalidChallengeCell",
+        description: "the challenge cell is invalid",
+    },
+    ErrorCode {
+        script: "rust",
+        code: 27,
+        name: "InvalidStakeCell",
+        description: "the stake cell is invalid",
+    },
+    ErrorCode {
+        script: "rust",
+        code: 28,
+        name: "InvalidDepositCell",
+        description: "the deposit cell is invalid",
+    },
+    ErrorCode {
+        script: "rust",
+        code: 29,
+        name: "InvalidWithdrawalCell",
+        description: "the withdrawal cell is invalid",
+    },
+    ErrorCode {
+        script: "rust",
+        code: 30,
+        name: "InvalidCustodianCell",
+        description: "the custodian cell is invalid",
+    },
+    ErrorCode {
+        script: "rust",
+        code: 31,
+        name: "InvalidRevertedBlocks",
+        description: "the reverted blocks are invalid",
+    },
+    ErrorCode {
+        script: "rust",
+        code: 32,
+        name: "InvalidChallengeReward",
+        description: "the challenge reward is invalid",
+    },
+    ErrorCode {
+        script: "rust",
+        code: 33,
+        name: "InvalidSUDTCell",
+        description: "the sUDT cell is invalid",
+    },
+    ErrorCode {
+        script: "rust",
+        code: 34,
+        name: "InvalidChallengeTarget",
+        description: "the challenge target doesn't exist in the block",
+    },
+    ErrorCode {
+        script: "rust",
+        code: 35,
+        name: "InvalidWithdrawalRequest",
+        description: "the withdrawal request is invalid",
+    },
+    ErrorCode {
+        script: "rust",
+        code: 36,
+        name: "UnknownEOAScript",
+        description: "the EOA lock script isn't allowed by the rollup config",
+    },
+    ErrorCode {
+        script: "rust",
+        code: 37,
+        name: "UnknownContractScript",
+        description: "the contract script isn't allowed by the rollup config",
+    },
+    ErrorCode {
+        script: "rust",
+        code: 38,
+        name: "ScriptNotFound",
+        description: "the layer2 script is missing",
+    },
+    ErrorCode {
+        script: "rust",
+        code: 39,
+        name: "AccountLockCellNotFound",
+        description: "the cell of the account lock is missing from the cell deps",
+    },
+    ErrorCode {
+        script: "rust",
+        code: 40,
+        name: "AccountScriptCellNotFound",
+        description: "the cell of the account script is missing from the cell deps",
+    },
+    ErrorCode {
+        script: "rust",
+        code: 41,
+        name: "InvalidTypeID",
+        description: "the type id of the cell is invalid",
+    },
+    ErrorCode {
+        script: "rust",
+        code: 42,
+        name: "UnexpectedTxNonce",
+        description: "the nonce of the layer2 transaction doesn't match the account",
+    },
+    ErrorCode {
+        script: "rust",
+        code: 43,
+        name: "WrongSignature",
+        description: "raise from signature verification script",
+    },
+    ErrorCode {
+        script: "rust",
+        code: 44,
+        name: "InsufficientKVStateCapacity",
+        description: "the kv state buffer is full",
+    },
+    ErrorCode {
+        script: "rust",
+        code: 45,
+        name: "SMTProofMalformed",
+        description: "the proof of a sparse merkle tree is malformed",
+    },
+    ErrorCode {
+        script: "rust",
+        code: 46,
+        name: "SMTRootMismatch",
+        description: "the root calculated from a sparse merkle tree isn't the expected root",
+    },
+    ErrorCode {
+        script: "rust",
+        code: 47,
+        name: "InvalidDAOWithdrawal",
+        description: "the DAO withdrawal of the custodian cells is invalid",
+    },
+    ErrorCode {
+        script: "rust",
+        code: 48,
+        name: "InvalidCustodianAssets",
+        description: "the custodian assets accumulator of the rollup cell is invalid",
+    },
+    ErrorCode {
+        script: "c",
+        code: 50,
+        name: "GW_FATAL_BUFFER_OVERFLOW",
+        description: "the buffer is too small for the data",
+    },
+    ErrorCode {
+        script: "c",
+        code: 51,
+        name: "GW_FATAL_INVALID_CONTEXT",
+        description: "the validation context is invalid",
+    },
+    ErrorCode {
+        script: "c",
+        code: 52,
+        name: "GW_FATAL_INVALID_DATA",
+        description: "the data is not a valid molecule structure",
+    },
+    ErrorCode {
+        script: "c",
+        code: 53,
+        name: "GW_FATAL_MISMATCH_RETURN_DATA",
+        description: "the return data doesn't match the block",
+    },
+    ErrorCode {
+        script: "c",
+        code: 54,
+        name: "GW_FATAL_UNKNOWN_ARGS",
+        description: "the args of the layer2 transaction are unknown",
+    },
+    ErrorCode {
+        script: "c",
+        code: 55,
+        name: "GW_FATAL_INVALID_SUDT_SCRIPT",
+        description: "the sUDT script is invalid",
+    },
+    ErrorCode {
+        script: "c",
+        code: 56,
+        name: "GW_FATAL_INVALID_CHECK_POINT",
+        description: "the state checkpoint doesn't match the state",
+    },
+    ErrorCode {
+        script: "c",
+        code: 60,
+        name: "GW_FATAL_DATA_CELL_NOT_FOUND",
+        description: "the data cell is missing",
+    },
+    ErrorCode {
+        script: "c",
+        code: 61,
+        name: "GW_FATAL_STATE_KEY_NOT_FOUND",
+        description: "the state key is missing from the kv state",
+    },
+    ErrorCode {
+        script: "c",
+        code: 62,
+        name: "GW_FATAL_SIGNATURE_CELL_NOT_FOUND",
+        description: "the signature cell is missing",
+    },
+    ErrorCode {
+        script: "c",
+        code: 63,
+        name: "GW_FATAL_SCRIPT_NOT_FOUND",
+        description: "the account script is missing",
+    },
+    ErrorCode {
+        script: "c",
+        code: 70,
+        name: "GW_FATAL_SMT_VERIFY",
+        description: "failed to verify the kv state proof",
+    },
+    ErrorCode {
+        script: "c",
+        code: 71,
+        name: "GW_FATAL_SMT_FETCH",
+        description: "failed to fetch a key from the kv state",
+    },
+    ErrorCode {
+        script: "c",
+        code: 72,
+        name: "GW_FATAL_SMT_STORE",
+        description: "failed to store a key into the kv state",
+    },
+    ErrorCode {
+        script: "c",
+        code: 73,
+        name: "GW_FATAL_SMT_CALCULATE_ROOT",
+        description: "failed to calculate the root of the kv state",
+    },
+    ErrorCode {
+        script: "c",
+        code: 80,
+        name: "GW_ERROR_DUPLICATED_SCRIPT_HASH",
+        description: "the account script hash already exists",
+    },
+    ErrorCode {
+        script: "c",
+        code: 81,
+        name: "GW_ERROR_UNKNOWN_SCRIPT_CODE_HASH",
+        description: "the code hash of the account script is unknown",
+    },
+    ErrorCode {
+        script: "c",
+        code: 82,
+        name: "GW_ERROR_INVALID_ACCOUNT_SCRIPT",
+        description: "the account script is invalid",
+    },
+    ErrorCode {
+        script: "c",
+        code: 83,
+        name: "GW_ERROR_NOT_FOUND",
+        description: "the requested data is missing",
+    },
+    ErrorCode {
+        script: "c",
+        code: 84,
+        name: "GW_ERROR_RECOVER",
+        description: "failed to recover the account script hash from the signature",
+    },
+    ErrorCode {
+        script: "c",
+        code: 85,
+        name: "GW_ERROR_ACCOUNT_NOT_EXISTS",
+        description: "the account doesn't exist",
+    },
+    ErrorCode {
+        script: "c",
+        code: 92,
+        name: "GW_SUDT_ERROR_INSUFFICIENT_BALANCE",
+        description: "the sender's balance is insufficient",
+    },
+    ErrorCode {
+        script: "c",
+        code: 93,
+        name: "GW_SUDT_ERROR_AMOUNT_OVERFLOW",
+        description: "the receiver's balance overflows",
+    },
+    ErrorCode {
+        script: "c",
+        code: 94,
+        name: "GW_SUDT_ERROR_TO_ADDR",
+        description: "the receiver address is invalid",
+    },
+    ErrorCode {
+        script: "c",
+        code: 95,
+        name: "GW_SUDT_ERROR_ACCOUNT_NOT_EXISTS",
+        description: "the sUDT account doesn't exist",
+    },
+    ErrorCode {
+        script: "c",
+        code: 96,
+        name: "GW_SUDT_ERROR_SHORT_ADDR_LEN",
+        description: "the length of the short address is invalid",
+    },
+];
@@ -1,2 +1,6 @@
+pub mod compression;
+pub mod error;
+pub mod error_codes;
 pub mod layer1;
 pub mod rollup;
+pub mod trace;
@@ -1,6 +1,7 @@
 use crate::script_tests::utils::layer1::{
     always_success_script, build_resolved_tx, random_out_point, DummyDataLoader, MAX_CYCLES,
 };
+use crate::script_tests::utils::trace::TraceReport;
 use crate::testing_tool::programs::{
     ALWAYS_SUCCESS_CODE_HASH, ALWAYS_SUCCESS_PROGRAM, CHALLENGE_LOCK_PROGRAM,
     ETH_ACCOUNT_LOCK_PROGRAM, SECP256K1_DATA, STATE_VALIDATOR_PROGRAM,
@@ -11,7 +12,13 @@ use ckb_types::{
     prelude::Pack as CKBPack,
 };
 use gw_common::blake2b::new_blake2b;
-use gw_types::{bytes::Bytes, core::ScriptHashType, packed::RollupConfig, prelude::*};
+use gw_types::{
+    bytes::Bytes,
+    core::ScriptHashType,
+    packed::{Byte32Vec, RollupConfig},
+    prelude::*,
+};
+use std::cell::RefCell;
 
 pub struct CellContextParam {
     pub stake_lock_type: ckb_types::packed::Script,
@@ -23,6 +30,9 @@ pub struct CellContextParam {
     pub always_success_type: ckb_types::packed::Script,
     pub eoa_lock_type: ckb_types::packed::Script,
     pub eth_lock_type: ckb_types::packed::Script,
+    pub l1_sudt_type: ckb_types::packed::Script,
+    pub l1_xudt_type: ckb_types::packed::Script,
+    pub dao_type: ckb_types::packed::Script,
 }
 
 impl Default for CellContextParam {
@@ -37,6 +47,9 @@ impl Default for CellContextParam {
             always_success_type: random_type_id_script(),
             eoa_lock_type: random_type_id_script(),
             eth_lock_type: random_type_id_script(),
+            l1_sudt_type: random_type_id_script(),
+            l1_xudt_type: random_type_id_script(),
+            dao_type: random_type_id_script(),
         }
     }
 }
@@ -56,6 +69,12 @@ pub struct CellContext {
     pub eoa_lock_dep: CellDep,
     /// Eth account lock
     pub eth_lock_dep: CellDep,
+    /// L1 sUDT (always success)
+    pub l1_sudt_dep: CellDep,
+    /// L1 xUDT (always success)
+    pub l1_xudt_dep: CellDep,
+    /// Nervos DAO (always success)
+    pub dao_dep: CellDep,
     pub secp256k1_data_dep: CellDep,
 }
 
@@ -165,6 +184,48 @@ impl CellContext {
             );
             CellDep::new_builder().out_point(l2_sudt_out_point).build()
         };
+        let l1_sudt_dep = {
+            let out_point = random_out_point();
+            data_loader.cells.insert(
+                out_point.clone(),
+                (
+                    CellOutput::new_builder()
+                        .capacity(CKBPack::pack(&(ALWAYS_SUCCESS_PROGRAM.len() as u64)))
+                        .type_(CKBPack::pack(&Some(param.l1_sudt_type.clone())))
+                        .build(),
+                    ALWAYS_SUCCESS_PROGRAM.clone(),
+                ),
+            );
+            CellDep::new_builder().out_point(out_point).build()
+        };
+        let l1_xudt_dep = {
+            let out_point = random_out_point();
+            data_loader.cells.insert(
+                out_point.clone(),
+                (
+                    CellOutput::new_builder()
+                        .capacity(CKBPack::pack(&(ALWAYS_SUCCESS_PROGRAM.len() as u64)))
+                        .type_(CKBPack::pack(&Some(param.l1_xudt_type.clone())))
+                        .build(),
+                    ALWAYS_SUCCESS_PROGRAM.clone(),
+                ),
+            );
+            CellDep::new_builder().out_point(out_point).build()
+        };
+        let dao_dep = {
+            let out_point = random_out_point();
+            data_loader.cells.insert(
+                out_point.clone(),
+                (
+                    CellOutput::new_builder()
+                        .capacity(CKBPack::pack(&(ALWAYS_SUCCESS_PROGRAM.len() as u64)))
+                        .type_(CKBPack::pack(&Some(param.dao_type.clone())))
+                        .build(),
+                    ALWAYS_SUCCESS_PROGRAM.clone(),
+                ),
+            );
+            CellDep::new_builder().out_point(out_point).build()
+        };
         let stake_lock_dep = {
             let stake_out_point = random_out_point();
             data_loader.cells.insert(
@@ -248,6 +309,9 @@ impl CellContext {
             l2_sudt_dep,
             eoa_lock_dep,
             eth_lock_dep,
+            l1_sudt_dep,
+            l1_xudt_dep,
+            dao_dep,
             secp256k1_data_dep,
         }
     }
@@ -267,9 +331,17 @@ impl CellContext {
         tx: ckb_types::core::TransactionView,
     ) -> Result<ckb_types::core::Cycle, ckb_error::Error> {
         let resolved_tx = build_resolved_tx(&self.inner, &tx);
+        let trace = RefCell::new(TraceReport::default());
         let mut verifier = TransactionScriptsVerifier::new(&resolved_tx, &self.inner);
-        verifier.set_debug_printer(|_script, msg| println!("[script debug] {}", msg));
-        verifier.verify(MAX_CYCLES)
+        verifier.set_debug_printer(|script, msg| {
+            if !trace.borrow_mut().record(script, msg) {
+                println!("[script debug] {}", msg);
+            }
+        });
+        verifier.verify(MAX_CYCLES).map_err(|err| {
+            println!("{}", trace.borrow().failure_report(&err));
+            err
+        })
     }
 }
 
@@ -285,6 +357,162 @@ fn random_type_id_script() -> ckb_types::packed::Script {
     build_type_id_script(&random_bytes)
 }
 
+/// Append extension fields to the rollup config
+pub fn extend_rollup_config(rollup_config: &RollupConfig, fields: &[Bytes]) -> RollupConfig {
+    const NUMBER_SIZE: usize = 4;
+    let read_number = |data: &[u8], offset: usize| {
+        let mut buf = [0u8; NUMBER_SIZE];
+        buf.copy_from_slice(&data[offset..offset + NUMBER_SIZE]);
+        u32::from_le_bytes(buf) as usize
+    };
+    let data = rollup_config.as_slice();
+    let header_size = read_number(data, NUMBER_SIZE);
+    let extra_header_size = NUMBER_SIZE * fields.len();
+    let total_size = data.len() + extra_header_size + fields.iter().map(|f| f.len()).sum::<usize>();
+    // shift the offsets by the new offsets
+    let mut header = vec![total_size];
+    for i in 1..header_size / NUMBER_SIZE {
+        header.push(read_number(data, NUMBER_SIZE * i) + extra_header_size);
+    }
+    let mut offset = data.len() + extra_header_size;
+    for field in fields {
+        header.push(offset);
+        offset += field.len();
+    }
+    let mut extended = Vec::with_capacity(total_size);
+    for number in header {
+        extended.extend_from_slice(&(number as u32).to_le_bytes());
+    }
+    extended.extend_from_slice(&data[header_size..]);
+    for field in fields {
+        extended.extend_from_slice(field);
+    }
+    RollupConfig::new_unchecked(extended.into())
+}
+
+fn build_byte32_vec(hashes: &[[u8; 32]]) -> Bytes {
+    Byte32Vec::new_builder()
+        .set(hashes.iter().map(|hash| hash.pack()).collect())
+        .build()
+        .as_bytes()
+}
+
+/// Append the `allowed_token_type_hashes: Byte32Vec` extension field to the rollup config
+pub fn with_allowed_token_type_hashes(
+    rollup_config: &RollupConfig,
+    token_type_hashes: &[[u8; 32]],
+) -> RollupConfig {
+    extend_rollup_config(rollup_config, &[build_byte32_vec(token_type_hashes)])
+}
+
+/// Append the `dao_script_type_hash` and `dao_beneficiary_lock_hash` extension fields
+/// (after an empty `allowed_token_type_hashes`) to the rollup config
+pub fn with_dao_config(
+    rollup_config: &RollupConfig,
+    dao_script_type_hash: &[u8; 32],
+    dao_beneficiary_lock_hash: &[u8; 32],
+) -> RollupConfig {
+    extend_rollup_config(
+        rollup_config,
+        &[
+            build_byte32_vec(&[]),
+            Bytes::from(dao_script_type_hash.to_vec()),
+            Bytes::from(dao_beneficiary_lock_hash.to_vec()),
+        ],
+    )
+}
+
+/// Append the `stake_tvl_rate` and `stake_withdrawal_rate` extension fields
+/// (after empty token and DAO fields) to the rollup config
+pub fn with_stake_rates(
+    rollup_config: &RollupConfig,
+    stake_tvl_rate: u32,
+    stake_withdrawal_rate: u32,
+) -> RollupConfig {
+    extend_rollup_config(
+        rollup_config,
+        &[
+            build_byte32_vec(&[]),
+            Bytes::default(),
+            Bytes::default(),
+            Bytes::from(stake_tvl_rate.to_le_bytes().to_vec()),
+            Bytes::from(stake_withdrawal_rate.to_le_bytes().to_vec()),
+        ],
+    )
+}
+
+/// Append the `stake_unbonding_blocks` extension field
+/// (after empty token, DAO and stake rate fields) to the rollup config
+pub fn with_stake_unbonding_blocks(
+    rollup_config: &RollupConfig,
+    stake_unbonding_blocks: u64,
+) -> RollupConfig {
+    let mut fields = vec![build_byte32_vec(&[])];
+    fields.resize(5, Bytes::default());
+    fields.push(Bytes::from(stake_unbonding_blocks.to_le_bytes().to_vec()));
+    extend_rollup_config(rollup_config, &fields)
+}
+
+/// Append the `slash_capacity`, `slash_rate` and `slash_per_reverted_block` extension fields
+/// (after empty token, DAO and stake fields) to the rollup config
+pub fn with_slash_schedule(
+    rollup_config: &RollupConfig,
+    slash_capacity: u64,
+    slash_rate: u32,
+    slash_per_reverted_block: bool,
+) -> RollupConfig {
+    extend_rollup_config(
+        rollup_config,
+        &[
+            build_byte32_vec(&[]),
+            Bytes::default(),
+            Bytes::default(),
+            Bytes::default(),
+            Bytes::default(),
+            Bytes::default(),
+            Bytes::from(slash_capacity.to_le_bytes().to_vec()),
+            Bytes::from(slash_rate.to_le_bytes().to_vec()),
+            Bytes::from(vec![slash_per_reverted_block as u8]),
+        ],
+    )
+}
+
+/// Append the `stake_sudt_script_hash` and `required_staking_sudt_amount` extension fields
+/// (after empty token, DAO, stake and slashing fields) to the rollup config
+pub fn with_stake_token(
+    rollup_config: &RollupConfig,
+    stake_sudt_script_hash: &[u8; 32],
+    required_staking_sudt_amount: u128,
+) -> RollupConfig {
+    let mut fields = vec![build_byte32_vec(&[])];
+    fields.resize(9, Bytes::default());
+    fields.push(Bytes::from(stake_sudt_script_hash.to_vec()));
+    fields.push(Bytes::from(
+        required_staking_sudt_amount.to_le_bytes().to_vec(),
+    ));
+    extend_rollup_config(rollup_config, &fields)
+}
+
+/// Append the `min_deposit_capacity` and `deposit_caps` extension fields
+/// (after empty token, DAO, stake, slashing and deposit sUDT fields) to the rollup config
+pub fn with_deposit_limits(
+    rollup_config: &RollupConfig,
+    min_deposit_capacity: u64,
+    deposit_caps: &[([u8; 32], u128)],
+) -> RollupConfig {
+    let mut fields = vec![build_byte32_vec(&[])];
+    fields.resize(12, Bytes::default());
+    fields.push(Bytes::from(min_deposit_capacity.to_le_bytes().to_vec()));
+    fields.push(Bytes::default());
+    let mut caps = (deposit_caps.len() as u32).to_le_bytes().to_vec();
+    for (sudt_script_hash, amount) in deposit_caps {
+        caps.extend_from_slice(sudt_script_hash);
+        caps.extend_from_slice(&amount.to_le_bytes());
+    }
+    fields.push(Bytes::from(caps));
+    extend_rollup_config(rollup_config, &fields)
+}
+
 pub fn build_rollup_locked_cell(
     rollup_type_script_hash: &[u8; 32],
     script_type_hash: &[u8; 32],
@@ -0,0 +1,134 @@
+//! Parse the checkpoints recorded by `gw_utils::trace!` into a failure report
+
+use super::error::describe_error;
+use ckb_types::packed::Byte32;
+use std::fmt::Write;
+
+const TRACE_PREFIX: &str = "gw-trace";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checkpoint {
+    pub step: String,
+    pub fields: Vec<(String, String)>,
+}
+
+impl Checkpoint {
+    pub fn field(&self, key: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(k, _v)| k == key)
+            .map(|(_k, v)| v.as_str())
+    }
+}
+
+/// Parse a debug message `gw-trace <step> [<key>=<value> ...]`
+pub fn parse_checkpoint(msg: &str) -> Option<Checkpoint> {
+    let mut parts = msg.split_whitespace();
+    if parts.next()? != TRACE_PREFIX {
+        return None;
+    }
+    let step = parts.next()?.to_string();
+    let fields = parts
+        .map(|field| {
+            let mut kv = field.splitn(2, '=');
+            let key = kv.next().unwrap_or_default().to_string();
+            let value = kv.next().unwrap_or_default().to_string();
+            (key, value)
+        })
+        .collect();
+    Some(Checkpoint { step, fields })
+}
+
+/// Checkpoints of the scripts in the order of recording
+#[derive(Debug, Default)]
+pub struct TraceReport {
+    pub checkpoints: Vec<(Byte32, Checkpoint)>,
+}
+
+impl TraceReport {
+    /// Record the debug message, return false if it isn't a checkpoint
+    pub fn record(&mut self, script_hash: &Byte32, msg: &str) -> bool {
+        match parse_checkpoint(msg) {
+            Some(checkpoint) => {
+                self.checkpoints.push((script_hash.clone(), checkpoint));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The last checkpoint of the script
+    pub fn last_checkpoint(&self, script_hash: &Byte32) -> Option<&Checkpoint> {
+        self.checkpoints
+            .iter()
+            .rev()
+            .find(|(hash, _checkpoint)| hash == script_hash)
+            .map(|(_hash, checkpoint)| checkpoint)
+    }
+
+    /// Describe the error and the checkpoints of every script,
+    /// the last checkpoint of the failed script is where it failed
+    pub fn failure_report(&self, err: &ckb_error::Error) -> String {
+        let mut report = String::new();
+        writeln!(report, "verification failed: {}", describe_error(err)).unwrap();
+        let mut script_hashes: Vec<&Byte32> = Vec::new();
+        for (script_hash, _checkpoint) in &self.checkpoints {
+            if !script_hashes.contains(&script_hash) {
+                script_hashes.push(script_hash);
+            }
+        }
+        for script_hash in script_hashes {
+            writeln!(report, "script {}:", script_hash).unwrap();
+            for (hash, checkpoint) in &self.checkpoints {
+                if hash != script_hash {
+                    continue;
+                }
+                write!(report, "  {}", checkpoint.step).unwrap();
+                for (key, value) in &checkpoint.fields {
+                    write!(report, " {}={}", key, value).unwrap();
+                }
+                writeln!(report).unwrap();
+            }
+        }
+        report
+    }
+}
+
+#[test]
+fn test_parse_checkpoint() {
+    assert_eq!(
+        parse_checkpoint("gw-trace submit_block.withdrawal_cell index=2"),
+        Some(Checkpoint {
+            step: "submit_block.withdrawal_cell".to_string(),
+            fields: vec![("index".to_string(), "2".to_string())],
+        })
+    );
+    let checkpoint =
+        parse_checkpoint("gw-trace enter_challenge.target target_type=01 target_index=0").unwrap();
+    assert_eq!(checkpoint.field("target_type"), Some("01"));
+    assert_eq!(checkpoint.field("target_index"), Some("0"));
+    assert_eq!(checkpoint.field("index"), None);
+    assert_eq!(parse_checkpoint("gw-trace"), None);
+    assert_eq!(parse_checkpoint("eth_address [0, 1]"), None);
+}
+
+#[test]
+fn test_trace_report() {
+    use ckb_types::prelude::*;
+
+    let validator: Byte32 = [1u8; 32].pack();
+    let lock: Byte32 = [2u8; 32].pack();
+    let mut report = TraceReport::default();
+    assert!(report.record(&validator, "gw-trace state_validator.submit_block"));
+    assert!(report.record(&lock, "gw-trace stake_lock.unlock_by_rollup"));
+    assert!(!report.record(&validator, "some debug output"));
+    assert!(report.record(&validator, "gw-trace submit_block.deposit_cell index=3"));
+    assert_eq!(report.checkpoints.len(), 3);
+    let last = report.last_checkpoint(&validator).unwrap();
+    assert_eq!(last.step, "submit_block.deposit_cell");
+    assert_eq!(last.field("index"), Some("3"));
+    assert_eq!(
+        report.last_checkpoint(&lock).unwrap().step,
+        "stake_lock.unlock_by_rollup"
+    );
+}
@@ -0,0 +1,197 @@
+//! Differential tests of the C and the pure-Rust sparse merkle tree of ckb-smt
+
+use ckb_smt::{c_smt, error::SMTError, rust_smt};
+use gw_common::{sparse_merkle_tree::default_store::DefaultStore, H256};
+use rand::{thread_rng, Rng};
+
+const TEST_ROUNDS: usize = 100;
+
+fn random_pairs(rng: &mut impl Rng, count: usize) -> Vec<([u8; 32], [u8; 32])> {
+    (0..count)
+        .map(|_| {
+            let key: [u8; 32] = rng.gen();
+            // zero values are leaves of zero hash
+            let value: [u8; 32] = if rng.gen_bool(0.1) {
+                [0u8; 32]
+            } else {
+                rng.gen()
+            };
+            (key, value)
+        })
+        .collect()
+}
+
+/// Compile a proof of the pairs with the Rust SMT
+fn build_proof(pairs: &[([u8; 32], [u8; 32])]) -> ([u8; 32], Vec<u8>) {
+    let mut tree: gw_common::smt::SMT<DefaultStore<H256>> = Default::default();
+    // insert some unrelated leaves so the proof contains siblings
+    for (k, v) in random_pairs(&mut thread_rng(), 16) {
+        tree.update(k.into(), v.into()).unwrap();
+    }
+    for (k, v) in pairs {
+        tree.update((*k).into(), (*v).into()).unwrap();
+    }
+    let keys: Vec<H256> = pairs.iter().map(|(k, _v)| (*k).into()).collect();
+    let leaves: Vec<(H256, H256)> = pairs
+        .iter()
+        .map(|(k, v)| ((*k).into(), (*v).into()))
+        .collect();
+    let proof = tree.merkle_proof(keys).unwrap().compile(leaves).unwrap().0;
+    let root: [u8; 32] = (*tree.root()).into();
+    (root, proof)
+}
+
+fn build_trees<'a>(
+    capacity: usize,
+    pairs: &[([u8; 32], [u8; 32])],
+) -> (c_smt::Tree<'a>, rust_smt::Tree<'a>) {
+    let mut c_tree = c_smt::Tree::with_capacity(capacity);
+    let mut rust_tree = rust_smt::Tree::with_capacity(capacity);
+    for (k, v) in pairs {
+        assert_eq!(c_tree.update(k, v), rust_tree.update(k, v));
+    }
+    (c_tree, rust_tree)
+}
+
+#[test]
+fn test_smt_update_and_get() {
+    let mut rng = thread_rng();
+    for _ in 0..TEST_ROUNDS {
+        let count = rng.gen_range(1..64);
+        let mut pairs = random_pairs(&mut rng, count);
+        // update some keys again
+        let updated: Vec<_> = pairs
+            .iter()
+            .take(count / 2)
+            .map(|(k, _v)| (*k, rng.gen()))
+            .collect();
+        pairs.extend(updated);
+        // a small capacity forces overwriting and exhausting the buffer
+        let capacity = rng.gen_range(0..pairs.len() + 1);
+        let (mut c_tree, mut rust_tree) = build_trees(capacity, &pairs);
+        assert_eq!(c_tree.is_empty(), rust_tree.is_empty());
+        assert_eq!(c_tree.len(), rust_tree.len());
+        assert_eq!(c_tree.capacity(), capacity);
+        assert_eq!(rust_tree.capacity(), capacity);
+        for (k, _v) in &pairs {
+            assert_eq!(c_tree.get(k), rust_tree.get(k));
+        }
+        let missing_key: [u8; 32] = rng.gen();
+        assert_eq!(c_tree.get(&missing_key), rust_tree.get(&missing_key));
+        c_tree.normalize();
+        rust_tree.normalize();
+        for (k, _v) in &pairs {
+            assert_eq!(c_tree.get(k), rust_tree.get(k));
+        }
+    }
+}
+
+#[test]
+fn test_smt_remove_and_iter() {
+    let mut rng = thread_rng();
+    for _ in 0..TEST_ROUNDS {
+        let count = rng.gen_range(1..64);
+        let mut pairs = random_pairs(&mut rng, count);
+        let updated: Vec<_> = pairs
+            .iter()
+            .take(count / 2)
+            .map(|(k, _v)| (*k, rng.gen()))
+            .collect();
+        pairs.extend(updated);
+        let (mut c_tree, mut rust_tree) = build_trees(pairs.len(), &pairs);
+        assert_eq!(c_tree.len(), pairs.len());
+        assert!(c_tree.iter().eq(rust_tree.iter()));
+
+        let (removed_key, _v) = pairs[rng.gen_range(0..pairs.len())];
+        let c_value = c_tree.remove(&removed_key);
+        assert!(c_value.is_ok());
+        assert_eq!(c_value, rust_tree.remove(&removed_key));
+        assert_eq!(c_tree.get(&removed_key), Err(SMTError::KeyMissing));
+        assert_eq!(rust_tree.get(&removed_key), Err(SMTError::KeyMissing));
+        assert_eq!(c_tree.remove(&removed_key), Err(SMTError::KeyMissing));
+        assert_eq!(rust_tree.remove(&removed_key), Err(SMTError::KeyMissing));
+        let expected_pairs: Vec<_> = pairs.iter().filter(|(k, _v)| k != &removed_key).collect();
+        assert_eq!(c_tree.len(), expected_pairs.len());
+        assert!(c_tree.iter().eq(expected_pairs.iter().map(|(k, v)| (k, v))));
+        assert!(c_tree.iter().eq(rust_tree.iter()));
+
+        // the removed slots can be used again
+        assert_eq!(c_tree.update(&removed_key, &[1u8; 32]), Ok(()));
+        assert_eq!(rust_tree.update(&removed_key, &[1u8; 32]), Ok(()));
+        c_tree.normalize();
+        rust_tree.normalize();
+        assert!(c_tree.iter().eq(rust_tree.iter()));
+    }
+}
+
+#[test]
+fn test_smt_verify_proofs() {
+    let mut rng = thread_rng();
+    for _ in 0..TEST_ROUNDS {
+        let count = rng.gen_range(1..32);
+        let pairs = random_pairs(&mut rng, count);
+        let (root, proof) = build_proof(&pairs);
+        let (mut c_tree, mut rust_tree) = build_trees(pairs.len(), &pairs);
+        c_tree.normalize();
+        rust_tree.normalize();
+        let c_root = c_tree.calculate_root(&proof);
+        assert_eq!(c_root, Ok(root));
+        assert_eq!(c_root, rust_tree.calculate_root(&proof));
+        assert_eq!(c_tree.verify(&root, &proof), Ok(()));
+        assert_eq!(rust_tree.verify(&root, &proof), Ok(()));
+        // wrong root
+        let wrong_root: [u8; 32] = rng.gen();
+        assert_eq!(
+            c_tree.verify(&wrong_root, &proof),
+            Err(SMTError::RootMismatch)
+        );
+        assert_eq!(
+            rust_tree.verify(&wrong_root, &proof),
+            Err(SMTError::RootMismatch)
+        );
+    }
+}
+
+#[test]
+fn test_smt_malformed_proofs() {
+    let mut rng = thread_rng();
+    for _ in 0..TEST_ROUNDS {
+        let count = rng.gen_range(1..8);
+        let pairs = random_pairs(&mut rng, count);
+        let (_root, mut proof) = build_proof(&pairs);
+        // corrupt, truncate or extend the proof
+        match rng.gen_range(0..3) {
+            0 => {
+                let index = rng.gen_range(0..proof.len());
+                proof[index] = rng.gen();
+            }
+            1 => {
+                let len = rng.gen_range(0..proof.len());
+                proof.truncate(len);
+            }
+            _ => {
+                let extra: [u8; 8] = rng.gen();
+                proof.extend_from_slice(&extra);
+            }
+        }
+        let (mut c_tree, mut rust_tree) = build_trees(pairs.len(), &pairs);
+        c_tree.normalize();
+        rust_tree.normalize();
+        assert_eq!(
+            c_tree.calculate_root(&proof),
+            rust_tree.calculate_root(&proof)
+        );
+    }
+    // random bytes as proof
+    for _ in 0..TEST_ROUNDS {
+        let pairs = random_pairs(&mut rng, 2);
+        let proof: Vec<u8> = (0..rng.gen_range(0..128)).map(|_| rng.gen()).collect();
+        let (mut c_tree, mut rust_tree) = build_trees(pairs.len(), &pairs);
+        c_tree.normalize();
+        rust_tree.normalize();
+        assert_eq!(
+            c_tree.calculate_root(&proof),
+            rust_tree.calculate_root(&proof)
+        );
+    }
+}
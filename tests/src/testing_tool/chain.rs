@@ -17,8 +17,9 @@ use gw_mem_pool::pool::MemPool;
 use gw_store::Store;
 use gw_types::{
     packed::{
-        CellOutput, DepositRequest, L2BlockCommittedInfo, RawTransaction, RollupAction,
-        RollupActionUnion, RollupConfig, RollupSubmitBlock, Script, Transaction, WitnessArgs,
+        CellOutput, DepositRequest, L2BlockCommittedInfo, L2Transaction, RawTransaction,
+        RollupAction, RollupActionUnion, RollupConfig, RollupSubmitBlock, Script, Transaction,
+        WitnessArgs,
     },
     prelude::*,
 };
@@ -164,6 +165,17 @@ pub fn construct_block(
     chain: &Chain,
     mem_pool: &MemPool,
     deposit_requests: Vec<DepositRequest>,
+) -> anyhow::Result<ProduceBlockResult> {
+    construct_block_with_txs(chain, mem_pool, deposit_requests, Vec::new())
+}
+
+/// Construct a block which executes `txs` before the transactions of the mem pool,
+/// the txs aren't checked by the mem pool, e.g. the deposit calls
+pub fn construct_block_with_txs(
+    chain: &Chain,
+    mem_pool: &MemPool,
+    deposit_requests: Vec<DepositRequest>,
+    mut txs: Vec<L2Transaction>,
 ) -> anyhow::Result<ProduceBlockResult> {
     let block_producer_id = 0u32;
     let timestamp = 0;
@@ -173,7 +185,6 @@ pub fn construct_block(
     let generator = chain.generator();
     let parent_block = chain.store().get_tip_block().unwrap();
     let rollup_config_hash = chain.rollup_config_hash().clone().into();
-    let mut txs = Vec::new();
     let mut available_custodians = AvailableCustodians::default();
     // initialize with some withdraw-able capacity
     available_custodians.capacity += 10000 * (100000000);
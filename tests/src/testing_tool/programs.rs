@@ -8,9 +8,13 @@ const SCRIPT_DIR: &'static str = "../build/debug";
 const CHALLENGE_LOCK_PATH: &'static str = "challenge-lock";
 const STATE_VALIDATOR: &'static str = "state-validator";
 const ALWAYS_SUCCESS_PATH: &'static str = "always-success";
+const DEPOSIT_LOCK_PATH: &'static str = "deposit-lock";
+const STAKE_LOCK_PATH: &'static str = "stake-lock";
 const SECP256K1_DATA_PATH: &'static str = "../c/deps/ckb-production-scripts/build/secp256k1_data";
 const C_SCRIPTS_DIR: &'static str = "../../godwoken-scripts/c/build";
 const META_CONTRACT_BIN_NAME: &'static str = "meta-contract-validator";
+/// The default MAX KV pairs in a tx of the scripts, see c/gw_def.h
+pub const DEFAULT_MAX_KV_PAIRS: usize = 1024;
 // account locks
 const ETH_LOCK_PATH: &'static str = "eth-account-lock";
 const TRON_LOCK_PATH: &'static str = "tron-account-lock";
@@ -64,6 +68,38 @@ lazy_static! {
         hasher.finalize(&mut buf);
         buf
     };
+    pub static ref DEPOSIT_LOCK_PROGRAM: Bytes = {
+        let mut buf = Vec::new();
+        let mut path = PathBuf::new();
+        path.push(&SCRIPT_DIR);
+        path.push(&DEPOSIT_LOCK_PATH);
+        let mut f = fs::File::open(&path).expect("load program");
+        f.read_to_end(&mut buf).expect("read program");
+        Bytes::from(buf.to_vec())
+    };
+    pub static ref DEPOSIT_LOCK_CODE_HASH: [u8; 32] = {
+        let mut buf = [0u8; 32];
+        let mut hasher = new_blake2b();
+        hasher.update(&DEPOSIT_LOCK_PROGRAM);
+        hasher.finalize(&mut buf);
+        buf
+    };
+    pub static ref STAKE_LOCK_PROGRAM: Bytes = {
+        let mut buf = Vec::new();
+        let mut path = PathBuf::new();
+        path.push(&SCRIPT_DIR);
+        path.push(&STAKE_LOCK_PATH);
+        let mut f = fs::File::open(&path).expect("load program");
+        f.read_to_end(&mut buf).expect("read program");
+        Bytes::from(buf.to_vec())
+    };
+    pub static ref STAKE_LOCK_CODE_HASH: [u8; 32] = {
+        let mut buf = [0u8; 32];
+        let mut hasher = new_blake2b();
+        hasher.update(&STAKE_LOCK_PROGRAM);
+        hasher.finalize(&mut buf);
+        buf
+    };
     pub static ref ETH_ACCOUNT_LOCK_PROGRAM: Bytes = {
         let mut buf = Vec::new();
         let mut path = PathBuf::new();
@@ -125,4 +161,10 @@ lazy_static! {
         hasher.finalize(&mut buf);
         buf
     };
+    /// MAX KV pairs in a tx of the scripts, set `GW_MAX_KV_PAIRS` to the value
+    /// which the scripts are built with
+    pub static ref MAX_KV_PAIRS: usize = match std::env::var("GW_MAX_KV_PAIRS") {
+        Ok(value) => value.parse().expect("GW_MAX_KV_PAIRS must be a positive integer"),
+        Err(_) => DEFAULT_MAX_KV_PAIRS,
+    };
 }
@@ -0,0 +1,198 @@
+#!/usr/bin/env python3
+"""Generate the error codes of the scripts from `errors.toml`
+
+Usage:
+    python3 tools/gen-errors.py          # regenerate the files
+    python3 tools/gen-errors.py --check  # fail if a generated file is outdated
+
+Requires Python 3.11 for `tomllib`, or the `tomli` package on older versions.
+"""
+
+import re
+import sys
+from pathlib import Path
+
+try:
+    import tomllib
+except ModuleNotFoundError:
+    import tomli as tomllib
+
+ROOT = Path(__file__).resolve().parent.parent
+REGISTRY = ROOT / "errors.toml"
+RUST_ERRORS = ROOT / "contracts/gw-utils/src/error/codes.rs"
+C_ERRORS = ROOT / "c/gw_errors.h"
+TEST_ERRORS = ROOT / "tests/src/script_tests/utils/error_codes.rs"
+
+GENERATED_NOTICE = "Generated from `errors.toml` by `tools/gen-errors.py`, don't edit."
+
+
+def script_ranges(value):
+    """A script owns a range `[start, end]` or a list of ranges"""
+    if value and isinstance(value[0], list):
+        return [tuple(r) for r in value]
+    return [tuple(value)]
+
+
+def load_registry():
+    with open(REGISTRY, "rb") as f:
+        registry = tomllib.load(f)
+    ranges = {
+        script: script_ranges(value) for script, value in registry["ranges"].items()
+    }
+    errors = registry["errors"]
+
+    sorted_ranges = sorted(
+        ((script, r) for script, rs in ranges.items() for r in rs),
+        key=lambda item: item[1][0],
+    )
+    for script, (start, end) in sorted_ranges:
+        if not 1 <= start <= end <= 127:
+            sys.exit(f"range of {script} must be inside 1 ~ 127")
+    for (script_a, (_, end_a)), (script_b, (start_b, _)) in zip(
+        sorted_ranges, sorted_ranges[1:]
+    ):
+        if end_a >= start_b:
+            sys.exit(f"range of {script_a} overlaps with {script_b}")
+
+    codes = {}
+    names = set()
+    for error in errors:
+        script, code, name = error["script"], error["code"], error["name"]
+        if script not in ranges:
+            sys.exit(f"{name}: unknown script {script}")
+        if not any(start <= code <= end for start, end in ranges[script]):
+            sys.exit(f"{name}: code {code} is outside the range of {script}")
+        if code in codes:
+            sys.exit(f"{name}: code {code} is already used by {codes[code]}")
+        if (script, name) in names:
+            sys.exit(f"{name}: duplicated name")
+        codes[code] = name
+        names.add((script, name))
+    return errors
+
+
+def script_errors(errors, script):
+    return sorted(
+        (error for error in errors if error["script"] == script),
+        key=lambda error: error["code"],
+    )
+
+
+def screaming_case(name):
+    return re.sub(r"(?<=[a-z0-9])(?=[A-Z])|(?<=[A-Z])(?=[A-Z][a-z])", "_", name).upper()
+
+
+def rust_string(s):
+    return '"' + s.replace("\\", "\\\\").replace('"', '\\"') + '"'
+
+
+def gen_rust(errors):
+    lines = [
+        "//! Error codes of the Rust scripts",
+        "//!",
+        f"//! {GENERATED_NOTICE}",
+        "",
+        "/// Error",
+        "#[repr(i8)]",
+        "pub enum Error {",
+    ]
+    for error in script_errors(errors, "rust"):
+        lines.append(f"    /// {error['description']}")
+        lines.append(f"    {error['name']} = {error['code']},")
+    lines.append("}")
+    return "\n".join(lines) + "\n"
+
+
+def gen_c(errors):
+    lines = [
+        "#ifndef GW_ERRORS_H_",
+        "#define GW_ERRORS_H_",
+        "",
+        "/* Godwoken Errors",
+        "   The exit code of CKB-VM is -128 ~ 127",
+        "   The 1 ~ 127 is used by Godwoken runtime(validator_utils.h &",
+        "   generator_utils.h). (To avoid conflict with backend we actually using 50 ~",
+        "   111) The Backend VM layer such as Polyjuice uses -1 ~ -128",
+        "",
+        "   In Godwoken runtime, we seperate errors into Fatal & Errors,",
+        "   Fatals represents errors that shouldn't be recovered by user programs,",
+        "    typically caused by lack of validation context.",
+        "   Errors represents the syscall errors caused by the user input.",
+        "",
+        f"   {GENERATED_NOTICE}",
+        " */",
+    ]
+    section = None
+    for error in script_errors(errors, "c"):
+        if error.get("section") != section:
+            section = error.get("section")
+            lines.append("")
+            if section:
+                lines.append(f"/* {section} */")
+        lines.append(f"/* {error['description']} */")
+        lines.append(f"#define {error['name']} {error['code']}")
+    lines.append("")
+    lines.append("#endif")
+    return "\n".join(lines) + "\n"
+
+
+def gen_tests(errors):
+    lines = [
+        "//! Error codes of the scripts",
+        "//!",
+        f"//! {GENERATED_NOTICE}",
+        "",
+        "#![allow(dead_code)]",
+        "",
+        "pub struct ErrorCode {",
+        "    pub script: &'static str,",
+        "    pub code: i8,",
+        "    pub name: &'static str,",
+        "    pub description: &'static str,",
+        "}",
+    ]
+    for script in ("rust", "c"):
+        lines.append("")
+        lines.append(f"pub mod {script} {{")
+        for error in script_errors(errors, script):
+            lines.append(
+                f"    pub const {screaming_case(error['name'])}: i8 = {error['code']};"
+            )
+        lines.append("}")
+    lines.append("")
+    lines.append("pub const ERROR_CODES: &[ErrorCode] = &[")
+    for error in sorted(errors, key=lambda error: error["code"]):
+        lines.append("    ErrorCode {")
+        lines.append(f"        script: {rust_string(error['script'])},")
+        lines.append(f"        code: {error['code']},")
+        lines.append(f"        name: {rust_string(error['name'])},")
+        lines.append(f"        description: {rust_string(error['description'])},")
+        lines.append("    },")
+    lines.append("];")
+    return "\n".join(lines) + "\n"
+
+
+def main():
+    check = "--check" in sys.argv[1:]
+    errors = load_registry()
+    outdated = False
+    for path, content in (
+        (RUST_ERRORS, gen_rust(errors)),
+        (C_ERRORS, gen_c(errors)),
+        (TEST_ERRORS, gen_tests(errors)),
+    ):
+        if path.exists() and path.read_text() == content:
+            continue
+        if check:
+            print(f"{path.relative_to(ROOT)} is outdated")
+            outdated = True
+        else:
+            path.parent.mkdir(parents=True, exist_ok=True)
+            path.write_text(content)
+            print(f"generate {path.relative_to(ROOT)}")
+    if outdated:
+        sys.exit(1)
+
+
+if __name__ == "__main__":
+    main()